@@ -1,4 +1,4 @@
-use axum::extract::rejection::JsonRejection;
+use axum::extract::rejection::{BytesRejection, JsonRejection};
 use sqlx::Error as SqlxError;
 use thiserror::Error;
 
@@ -8,8 +8,42 @@ pub enum Error {
     DatabaseQueryError(#[from] SqlxError),
     #[error("AddressBook not found")]
     AddressBookNotFound,
+    #[error("Contact not found")]
+    ContactNotFound,
+    #[error("Group not found")]
+    GroupNotFound,
+    #[error("Custom field not found")]
+    FieldNotFound,
+    #[error("Photo not found")]
+    PhotoNotFound,
+    #[error("Webhook not found")]
+    WebhookNotFound,
+    #[error("Webhook delivery not found")]
+    WebhookDeliveryNotFound,
+    #[error("Precondition failed")]
+    PreconditionFailed,
+    #[error("Unsupported media type")]
+    UnsupportedMediaType,
+    #[error("Payload too large: {0}")]
+    PayloadTooLarge(String),
+    #[error("Invalid request body: {0}")]
+    InvalidBody(String),
+    #[error("Bad request: {0}")]
+    BadRequest(String),
+    #[error("Method not allowed")]
+    MethodNotAllowed,
+    #[error("Invalid sync token")]
+    InvalidSyncToken,
+    #[error("Validation failed: {0}")]
+    ValidationError(String),
+    #[error("Conflict: {0}")]
+    Conflict(String),
+    #[error("Storage error: {0}")]
+    StorageError(#[from] std::io::Error),
     //#[error("Missing parameters")]
     //MissingParameters,
     #[error("Ivalid json string")]
     JsonDeserilizationError(#[from] JsonRejection),
+    #[error("Request body could not be read")]
+    BodyRejection(#[from] BytesRejection),
 }
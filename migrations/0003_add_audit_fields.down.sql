@@ -0,0 +1,22 @@
+DROP INDEX IF EXISTS contacts_address_book_id_updated_at_idx;
+DROP INDEX IF EXISTS address_books_updated_at_idx;
+
+DROP TRIGGER IF EXISTS contacts_set_updated_at ON contacts;
+DROP TRIGGER IF EXISTS address_books_set_updated_at ON address_books;
+DROP FUNCTION IF EXISTS set_updated_at();
+
+ALTER TABLE contacts
+    DROP COLUMN updated_by,
+    DROP COLUMN created_by,
+    DROP COLUMN updated_at,
+    ALTER COLUMN created_at DROP NOT NULL,
+    ALTER COLUMN created_at SET DEFAULT CURRENT_TIMESTAMP,
+    ALTER COLUMN created_at TYPE TIMESTAMP USING created_at AT TIME ZONE 'UTC';
+
+ALTER TABLE address_books
+    DROP COLUMN updated_by,
+    DROP COLUMN created_by,
+    DROP COLUMN updated_at,
+    ALTER COLUMN created_at DROP NOT NULL,
+    ALTER COLUMN created_at SET DEFAULT CURRENT_TIMESTAMP,
+    ALTER COLUMN created_at TYPE TIMESTAMP USING created_at AT TIME ZONE 'UTC';
@@ -0,0 +1,10 @@
+DELETE FROM contacts WHERE deleted_at IS NOT NULL;
+DELETE FROM address_books WHERE deleted_at IS NOT NULL;
+
+DROP INDEX IF EXISTS contacts_deleted_at_idx;
+DROP INDEX IF EXISTS address_books_deleted_at_idx;
+DROP INDEX IF EXISTS address_books_address_book_name_live_idx;
+ALTER TABLE address_books ADD CONSTRAINT address_books_address_book_name_key UNIQUE (address_book_name);
+
+ALTER TABLE contacts DROP COLUMN deleted_at;
+ALTER TABLE address_books DROP COLUMN deleted_at;
@@ -0,0 +1,11 @@
+ALTER TABLE address_books ADD COLUMN deleted_at TIMESTAMPTZ;
+ALTER TABLE contacts ADD COLUMN deleted_at TIMESTAMPTZ;
+
+-- Names only need to be unique among live address books, so a deleted book
+-- does not block creating a new one with the same name.
+ALTER TABLE address_books DROP CONSTRAINT IF EXISTS address_books_address_book_name_key;
+CREATE UNIQUE INDEX address_books_address_book_name_live_idx
+    ON address_books (address_book_name) WHERE deleted_at IS NULL;
+
+CREATE INDEX address_books_deleted_at_idx ON address_books (deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX contacts_deleted_at_idx ON contacts (deleted_at) WHERE deleted_at IS NOT NULL;
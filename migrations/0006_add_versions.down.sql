@@ -0,0 +1,9 @@
+DROP TRIGGER IF EXISTS contacts_bump_address_book_version ON contacts;
+DROP FUNCTION IF EXISTS bump_address_book_version();
+
+DROP TRIGGER IF EXISTS contacts_bump_version ON contacts;
+DROP TRIGGER IF EXISTS address_books_bump_version ON address_books;
+DROP FUNCTION IF EXISTS bump_version();
+
+ALTER TABLE contacts DROP COLUMN version;
+ALTER TABLE address_books DROP COLUMN version;
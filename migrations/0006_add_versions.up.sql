@@ -0,0 +1,33 @@
+ALTER TABLE address_books ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE contacts ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+
+CREATE OR REPLACE FUNCTION bump_version() RETURNS TRIGGER AS $$
+BEGIN
+    NEW.version = OLD.version + 1;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER address_books_bump_version
+    BEFORE UPDATE ON address_books
+    FOR EACH ROW EXECUTE FUNCTION bump_version();
+
+CREATE TRIGGER contacts_bump_version
+    BEFORE UPDATE ON contacts
+    FOR EACH ROW EXECUTE FUNCTION bump_version();
+
+-- A book's representation embeds its contacts, so any contact write also
+-- moves the owning book(s) to a new version.
+CREATE OR REPLACE FUNCTION bump_address_book_version() RETURNS TRIGGER AS $$
+BEGIN
+    UPDATE address_books SET version = version + 1 WHERE id = NEW.address_book_id;
+    IF TG_OP = 'UPDATE' AND OLD.address_book_id IS DISTINCT FROM NEW.address_book_id THEN
+        UPDATE address_books SET version = version + 1 WHERE id = OLD.address_book_id;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER contacts_bump_address_book_version
+    AFTER INSERT OR UPDATE ON contacts
+    FOR EACH ROW EXECUTE FUNCTION bump_address_book_version();
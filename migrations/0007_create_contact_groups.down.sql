@@ -0,0 +1,3 @@
+DROP FUNCTION IF EXISTS contact_group_names(INTEGER);
+DROP TABLE IF EXISTS contact_group_members;
+DROP TABLE IF EXISTS contact_groups;
@@ -0,0 +1,36 @@
+CREATE TABLE IF NOT EXISTS contact_groups (
+    id SERIAL PRIMARY KEY,
+    address_book_id INTEGER NOT NULL REFERENCES address_books(id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    created_by VARCHAR(255),
+    updated_by VARCHAR(255),
+    version INTEGER NOT NULL DEFAULT 1,
+    UNIQUE (address_book_id, name)
+);
+
+CREATE TRIGGER contact_groups_set_updated_at
+    BEFORE UPDATE ON contact_groups
+    FOR EACH ROW EXECUTE FUNCTION set_updated_at();
+
+CREATE TRIGGER contact_groups_bump_version
+    BEFORE UPDATE ON contact_groups
+    FOR EACH ROW EXECUTE FUNCTION bump_version();
+
+CREATE TABLE IF NOT EXISTS contact_group_members (
+    group_id INTEGER NOT NULL REFERENCES contact_groups(id) ON DELETE CASCADE,
+    contact_id INTEGER NOT NULL REFERENCES contacts(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (group_id, contact_id)
+);
+
+CREATE INDEX contact_group_members_contact_id_idx ON contact_group_members (contact_id);
+
+-- Group names are embedded in every contact representation.
+CREATE OR REPLACE FUNCTION contact_group_names(contact_id INTEGER) RETURNS TEXT[] AS $$
+    SELECT COALESCE(array_agg(g.name::TEXT ORDER BY g.name), '{}')
+    FROM contact_group_members AS m
+    JOIN contact_groups AS g ON g.id = m.group_id
+    WHERE m.contact_id = $1
+$$ LANGUAGE sql STABLE;
@@ -0,0 +1,26 @@
+CREATE TABLE IF NOT EXISTS custom_fields (
+    id SERIAL PRIMARY KEY,
+    address_book_id INTEGER NOT NULL REFERENCES address_books(id) ON DELETE CASCADE,
+    name VARCHAR(64) NOT NULL,
+    field_type VARCHAR(16) NOT NULL
+        CHECK (field_type IN ('text', 'number', 'date', 'bool', 'enum')),
+    required BOOLEAN NOT NULL DEFAULT false,
+    options TEXT[] NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    created_by VARCHAR(255),
+    updated_by VARCHAR(255),
+    version INTEGER NOT NULL DEFAULT 1,
+    UNIQUE (address_book_id, name)
+);
+
+CREATE TRIGGER custom_fields_set_updated_at
+    BEFORE UPDATE ON custom_fields
+    FOR EACH ROW EXECUTE FUNCTION set_updated_at();
+
+CREATE TRIGGER custom_fields_bump_version
+    BEFORE UPDATE ON custom_fields
+    FOR EACH ROW EXECUTE FUNCTION bump_version();
+
+-- Values keyed by field name, validated against the definitions on every write.
+ALTER TABLE contacts ADD COLUMN custom_fields JSONB NOT NULL DEFAULT '{}';
@@ -0,0 +1,17 @@
+DROP INDEX IF EXISTS contacts_address_book_id_sort_key_idx;
+ALTER TABLE contacts
+    DROP COLUMN sort_key,
+    DROP COLUMN display_name,
+    DROP COLUMN notes,
+    DROP COLUMN anniversary,
+    DROP COLUMN birthday,
+    DROP COLUMN job_title,
+    DROP COLUMN organization,
+    DROP COLUMN nickname,
+    DROP COLUMN name_suffix,
+    DROP COLUMN family_name,
+    DROP COLUMN middle_name,
+    DROP COLUMN given_name,
+    DROP COLUMN name_prefix;
+DROP FUNCTION IF EXISTS contact_sort_key(TEXT, TEXT, TEXT, TEXT, TEXT, TEXT);
+DROP FUNCTION IF EXISTS contact_display_name(TEXT, TEXT, TEXT, TEXT, TEXT, TEXT);
@@ -0,0 +1,47 @@
+ALTER TABLE contacts
+    ADD COLUMN name_prefix VARCHAR(255),
+    ADD COLUMN given_name VARCHAR(255),
+    ADD COLUMN middle_name VARCHAR(255),
+    ADD COLUMN family_name VARCHAR(255),
+    ADD COLUMN name_suffix VARCHAR(255),
+    ADD COLUMN nickname VARCHAR(255),
+    ADD COLUMN organization VARCHAR(255),
+    ADD COLUMN job_title VARCHAR(255),
+    ADD COLUMN birthday DATE,
+    ADD COLUMN anniversary DATE,
+    ADD COLUMN notes TEXT;
+
+-- The structured name parts joined by spaces, or the free-form name when there are none.
+CREATE OR REPLACE FUNCTION contact_display_name(
+    prefix TEXT, given TEXT, middle TEXT, family TEXT, suffix TEXT, name TEXT
+) RETURNS TEXT AS $$
+    SELECT COALESCE(
+        NULLIF(array_to_string(ARRAY[
+            NULLIF(btrim(prefix), ''), NULLIF(btrim(given), ''), NULLIF(btrim(middle), ''),
+            NULLIF(btrim(family), ''), NULLIF(btrim(suffix), '')
+        ], ' '), ''),
+        name
+    )
+$$ LANGUAGE sql IMMUTABLE;
+
+-- "family given middle" lowercased, falling back to the display name without any of them.
+CREATE OR REPLACE FUNCTION contact_sort_key(
+    prefix TEXT, given TEXT, middle TEXT, family TEXT, suffix TEXT, name TEXT
+) RETURNS TEXT AS $$
+    SELECT lower(COALESCE(
+        NULLIF(array_to_string(ARRAY[
+            NULLIF(btrim(family), ''), NULLIF(btrim(given), ''), NULLIF(btrim(middle), '')
+        ], ' '), ''),
+        contact_display_name(prefix, given, middle, family, suffix, name)
+    ))
+$$ LANGUAGE sql IMMUTABLE;
+
+ALTER TABLE contacts
+    ADD COLUMN display_name TEXT GENERATED ALWAYS AS (contact_display_name(
+        name_prefix, given_name, middle_name, family_name, name_suffix, name
+    )) STORED,
+    ADD COLUMN sort_key TEXT GENERATED ALWAYS AS (contact_sort_key(
+        name_prefix, given_name, middle_name, family_name, name_suffix, name
+    )) STORED;
+
+CREATE INDEX contacts_address_book_id_sort_key_idx ON contacts (address_book_id, sort_key, id);
@@ -0,0 +1,2 @@
+DROP FUNCTION IF EXISTS contact_has_photo(INTEGER);
+DROP TABLE IF EXISTS contact_photos;
@@ -0,0 +1,25 @@
+-- Photo metadata; the image bytes themselves live in the blob store.
+CREATE TABLE IF NOT EXISTS contact_photos (
+    contact_id INTEGER PRIMARY KEY REFERENCES contacts(id) ON DELETE CASCADE,
+    content_type VARCHAR(32) NOT NULL,
+    width INTEGER NOT NULL,
+    height INTEGER NOT NULL,
+    byte_size INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    created_by VARCHAR(255),
+    updated_by VARCHAR(255),
+    version INTEGER NOT NULL DEFAULT 1
+);
+
+CREATE TRIGGER contact_photos_set_updated_at
+    BEFORE UPDATE ON contact_photos
+    FOR EACH ROW EXECUTE FUNCTION set_updated_at();
+
+CREATE TRIGGER contact_photos_bump_version
+    BEFORE UPDATE ON contact_photos
+    FOR EACH ROW EXECUTE FUNCTION bump_version();
+
+CREATE OR REPLACE FUNCTION contact_has_photo(contact_id INTEGER) RETURNS BOOLEAN AS $$
+    SELECT EXISTS (SELECT 1 FROM contact_photos AS p WHERE p.contact_id = $1)
+$$ LANGUAGE sql STABLE;
@@ -0,0 +1,7 @@
+DROP TRIGGER IF EXISTS contacts_record_change ON contacts;
+DROP FUNCTION IF EXISTS record_contact_change();
+DROP TABLE IF EXISTS contact_changes;
+DROP INDEX IF EXISTS contacts_address_book_id_dav_name_idx;
+ALTER TABLE contacts
+    DROP COLUMN dav_name,
+    DROP COLUMN uid;
@@ -0,0 +1,38 @@
+-- CardDAV resource names and vCard UIDs. Contacts without a name are served as "<id>.vcf".
+ALTER TABLE contacts
+    ADD COLUMN uid VARCHAR(255),
+    ADD COLUMN dav_name VARCHAR(255);
+
+CREATE UNIQUE INDEX contacts_address_book_id_dav_name_idx
+    ON contacts (address_book_id, dav_name)
+    WHERE dav_name IS NOT NULL AND deleted_at IS NULL;
+
+-- Every write to a contact, recorded against each book it touched, so that sync clients
+-- can ask for what changed after a given sequence number.
+CREATE TABLE IF NOT EXISTS contact_changes (
+    seq BIGSERIAL PRIMARY KEY,
+    address_book_id INTEGER NOT NULL,
+    contact_id INTEGER NOT NULL,
+    resource_name VARCHAR(255) NOT NULL,
+    changed_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX contact_changes_address_book_id_seq_idx ON contact_changes (address_book_id, seq);
+
+CREATE OR REPLACE FUNCTION record_contact_change() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP <> 'INSERT' AND (TG_OP = 'DELETE' OR OLD.address_book_id <> NEW.address_book_id) THEN
+        INSERT INTO contact_changes (address_book_id, contact_id, resource_name)
+        VALUES (OLD.address_book_id, OLD.id, COALESCE(OLD.dav_name, OLD.id || '.vcf'));
+    END IF;
+    IF TG_OP <> 'DELETE' THEN
+        INSERT INTO contact_changes (address_book_id, contact_id, resource_name)
+        VALUES (NEW.address_book_id, NEW.id, COALESCE(NEW.dav_name, NEW.id || '.vcf'));
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER contacts_record_change
+    AFTER INSERT OR UPDATE OR DELETE ON contacts
+    FOR EACH ROW EXECUTE FUNCTION record_contact_change();
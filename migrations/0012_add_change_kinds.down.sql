@@ -0,0 +1,17 @@
+CREATE OR REPLACE FUNCTION record_contact_change() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP <> 'INSERT' AND (TG_OP = 'DELETE' OR OLD.address_book_id <> NEW.address_book_id) THEN
+        INSERT INTO contact_changes (address_book_id, contact_id, resource_name)
+        VALUES (OLD.address_book_id, OLD.id, COALESCE(OLD.dav_name, OLD.id || '.vcf'));
+    END IF;
+    IF TG_OP <> 'DELETE' THEN
+        INSERT INTO contact_changes (address_book_id, contact_id, resource_name)
+        VALUES (NEW.address_book_id, NEW.id, COALESCE(NEW.dav_name, NEW.id || '.vcf'));
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TABLE IF EXISTS contact_change_horizons;
+DROP INDEX IF EXISTS contact_changes_changed_at_idx;
+ALTER TABLE contact_changes DROP COLUMN kind;
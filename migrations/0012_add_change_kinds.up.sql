@@ -0,0 +1,32 @@
+-- What each change did to the contact as seen from the book: 'create' when it appeared there
+-- (inserted, moved in or restored), 'delete' when it left and 'update' otherwise.
+ALTER TABLE contact_changes ADD COLUMN kind VARCHAR(16) NOT NULL DEFAULT 'update';
+
+CREATE INDEX contact_changes_changed_at_idx ON contact_changes (changed_at);
+
+-- Old changes are pruned; tokens before a book's horizon can no longer be served.
+CREATE TABLE IF NOT EXISTS contact_change_horizons (
+    address_book_id INTEGER PRIMARY KEY,
+    pruned_through BIGINT NOT NULL
+);
+
+CREATE OR REPLACE FUNCTION record_contact_change() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP <> 'INSERT' AND (TG_OP = 'DELETE' OR OLD.address_book_id <> NEW.address_book_id) THEN
+        INSERT INTO contact_changes (address_book_id, contact_id, resource_name, kind)
+        VALUES (OLD.address_book_id, OLD.id, COALESCE(OLD.dav_name, OLD.id || '.vcf'), 'delete');
+    END IF;
+    IF TG_OP <> 'DELETE' THEN
+        INSERT INTO contact_changes (address_book_id, contact_id, resource_name, kind)
+        VALUES (NEW.address_book_id, NEW.id, COALESCE(NEW.dav_name, NEW.id || '.vcf'),
+            CASE
+                WHEN TG_OP = 'INSERT' OR OLD.address_book_id <> NEW.address_book_id
+                    THEN CASE WHEN NEW.deleted_at IS NULL THEN 'create' ELSE 'update' END
+                WHEN OLD.deleted_at IS NULL AND NEW.deleted_at IS NOT NULL THEN 'delete'
+                WHEN OLD.deleted_at IS NOT NULL AND NEW.deleted_at IS NULL THEN 'create'
+                ELSE 'update'
+            END);
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
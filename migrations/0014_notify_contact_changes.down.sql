@@ -0,0 +1,2 @@
+DROP TRIGGER IF EXISTS contact_changes_notify ON contact_changes;
+DROP FUNCTION IF EXISTS notify_contact_change();
@@ -0,0 +1,12 @@
+-- Wakes up the event streams of a book whenever one of its contacts changes. The payload is
+-- the book id; listeners read the changes themselves from contact_changes.
+CREATE OR REPLACE FUNCTION notify_contact_change() RETURNS TRIGGER AS $$
+BEGIN
+    PERFORM pg_notify('contact_changes', NEW.address_book_id::TEXT);
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER contact_changes_notify
+    AFTER INSERT ON contact_changes
+    FOR EACH ROW EXECUTE FUNCTION notify_contact_change();
@@ -0,0 +1,42 @@
+-- Token buckets of the rate limiter when RATE_LIMIT_STORE is `postgres`, shared by every
+-- instance. Keys name the route class and the client, e.g. `write:ip:203.0.113.7`.
+CREATE TABLE IF NOT EXISTS rate_limit_buckets (
+    key VARCHAR(128) PRIMARY KEY,
+    tokens DOUBLE PRECISION NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX rate_limit_buckets_updated_at_idx ON rate_limit_buckets (updated_at);
+
+-- Refills the bucket for the time since it was last used, at `capacity` tokens per
+-- `window_secs`, then takes a token if a whole one is left. A missing bucket starts out full.
+-- The row lock makes concurrent requests for one bucket take turns.
+CREATE OR REPLACE FUNCTION take_rate_limit_token(
+    bucket VARCHAR,
+    capacity DOUBLE PRECISION,
+    window_secs DOUBLE PRECISION,
+    at TIMESTAMPTZ,
+    OUT granted BOOLEAN,
+    OUT remaining DOUBLE PRECISION
+) AS $$
+DECLARE
+    stored rate_limit_buckets%ROWTYPE;
+BEGIN
+    INSERT INTO rate_limit_buckets (key, tokens, updated_at)
+    VALUES (bucket, capacity, at)
+    ON CONFLICT (key) DO NOTHING;
+
+    SELECT * INTO stored FROM rate_limit_buckets WHERE key = bucket FOR UPDATE;
+    remaining := LEAST(capacity, stored.tokens
+        + GREATEST(0, EXTRACT(EPOCH FROM at - stored.updated_at))::DOUBLE PRECISION
+            * capacity / window_secs);
+    granted := remaining >= 1;
+    IF granted THEN
+        remaining := remaining - 1;
+    END IF;
+
+    UPDATE rate_limit_buckets
+    SET tokens = remaining, updated_at = GREATEST(stored.updated_at, at)
+    WHERE key = bucket;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,3 @@
+-- Photos uploaded since the upgrade are left under prefixes the old code does not read.
+DROP FUNCTION IF EXISTS contact_photo_blob_prefix(INTEGER);
+ALTER TABLE contact_photos DROP COLUMN IF EXISTS blob_prefix;
@@ -0,0 +1,9 @@
+-- Each upload is stored under its own prefix, so renditions of the photo in use are never
+-- overwritten by an upload that fails or loses a race. Existing photos keep their old keys.
+ALTER TABLE contact_photos ADD COLUMN blob_prefix VARCHAR(255);
+UPDATE contact_photos SET blob_prefix = 'contacts/' || contact_id || '/photo';
+ALTER TABLE contact_photos ALTER COLUMN blob_prefix SET NOT NULL;
+
+CREATE OR REPLACE FUNCTION contact_photo_blob_prefix(contact_id INTEGER) RETURNS VARCHAR AS $$
+    SELECT p.blob_prefix FROM contact_photos AS p WHERE p.contact_id = $1
+$$ LANGUAGE sql STABLE;
@@ -0,0 +1,10 @@
+SELECT sequence_contact_changes();
+DROP FUNCTION IF EXISTS sequence_contact_changes();
+
+DROP INDEX IF EXISTS contact_changes_unsequenced_idx;
+DROP INDEX IF EXISTS contact_changes_seq_idx;
+ALTER TABLE contact_changes DROP COLUMN id;
+ALTER TABLE contact_changes
+    ALTER COLUMN seq SET NOT NULL,
+    ALTER COLUMN seq SET DEFAULT nextval('contact_changes_seq_seq');
+ALTER TABLE contact_changes ADD PRIMARY KEY (seq);
@@ -0,0 +1,23 @@
+-- Sequence numbers drawn while a change is written become visible in commit order, not in
+-- the order they were drawn, so a token could pass a change that committed later. Changes are
+-- now recorded without one and numbered by sequence_contact_changes() once committed, one
+-- caller at a time, so every number handed out is above the ones already visible.
+ALTER TABLE contact_changes DROP CONSTRAINT contact_changes_pkey;
+ALTER TABLE contact_changes ALTER COLUMN seq DROP DEFAULT, ALTER COLUMN seq DROP NOT NULL;
+ALTER TABLE contact_changes ADD COLUMN id BIGSERIAL PRIMARY KEY;
+
+CREATE UNIQUE INDEX contact_changes_seq_idx ON contact_changes (seq);
+CREATE INDEX contact_changes_unsequenced_idx ON contact_changes (id) WHERE seq IS NULL;
+
+CREATE OR REPLACE FUNCTION sequence_contact_changes() RETURNS VOID AS $$
+BEGIN
+    -- Held until the caller commits, so the next caller only numbers changes after these.
+    PERFORM pg_advisory_xact_lock(hashtext('sequence_contact_changes'));
+    UPDATE contact_changes AS cc SET seq = pending.seq
+    FROM (
+        SELECT id, nextval('contact_changes_seq_seq') AS seq
+        FROM (SELECT id FROM contact_changes WHERE seq IS NULL ORDER BY id) AS unsequenced
+    ) AS pending
+    WHERE cc.id = pending.id;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS audit_events_source_address_book_id_idx;
+ALTER TABLE audit_events DROP COLUMN IF EXISTS source_address_book_id;
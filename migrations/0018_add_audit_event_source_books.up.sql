@@ -0,0 +1,11 @@
+-- A contact moved out of a book stays in that book's history. The book it left gets its own
+-- indexed column, so the history is read from indexes rather than from the recorded changes.
+ALTER TABLE audit_events ADD COLUMN source_address_book_id INTEGER;
+
+ALTER TABLE audit_events DISABLE TRIGGER audit_events_append_only;
+UPDATE audit_events SET source_address_book_id = (changes->'before'->>'address_book_id')::INTEGER
+WHERE action = 'move' AND changes->'before' ? 'address_book_id';
+ALTER TABLE audit_events ENABLE TRIGGER audit_events_append_only;
+
+CREATE INDEX audit_events_source_address_book_id_idx ON audit_events (source_address_book_id, id)
+    WHERE source_address_book_id IS NOT NULL;
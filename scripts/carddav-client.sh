@@ -0,0 +1,107 @@
+#!/bin/sh
+# A minimal CardDAV client for trying the server by hand, built on curl.
+#
+#   scripts/carddav-client.sh discover
+#   scripts/carddav-client.sh books
+#   scripts/carddav-client.sh list <book>
+#   scripts/carddav-client.sh get <book> <name.vcf>
+#   scripts/carddav-client.sh put <book> <name.vcf> <file.vcf> [etag]
+#   scripts/carddav-client.sh delete <book> <name.vcf> [etag]
+#   scripts/carddav-client.sh multiget <book> <name.vcf>...
+#   scripts/carddav-client.sh query <book> <property> <text>
+#   scripts/carddav-client.sh sync <book> [sync-token]
+#
+# The server defaults to http://localhost:8000; set CARDDAV_URL to change it.
+set -eu
+
+base=${CARDDAV_URL:-http://localhost:8000}
+
+dav() {
+    method=$1
+    path=$2
+    shift 2
+    curl -sS -X "$method" -H 'Content-Type: application/xml; charset=utf-8' "$@" "$base$path"
+    echo
+}
+
+usage() {
+    sed -n '4,12p' "$0" | sed 's/^# *//' >&2
+    exit 2
+}
+
+[ $# -ge 1 ] || usage
+command=$1
+shift
+
+case $command in
+discover)
+    curl -sS -o /dev/null -w '%{http_code} -> %{redirect_url}\n' "$base/.well-known/carddav"
+    dav PROPFIND /dav/ -H 'Depth: 0' --data '<?xml version="1.0"?>
+<d:propfind xmlns:d="DAV:" xmlns:card="urn:ietf:params:xml:ns:carddav">
+  <d:prop><d:current-user-principal/><card:addressbook-home-set/></d:prop>
+</d:propfind>'
+    ;;
+books)
+    dav PROPFIND /dav/addressbooks/ -H 'Depth: 1' --data '<?xml version="1.0"?>
+<d:propfind xmlns:d="DAV:" xmlns:cs="http://calendarserver.org/ns/">
+  <d:prop><d:resourcetype/><d:displayname/><cs:getctag/><d:sync-token/></d:prop>
+</d:propfind>'
+    ;;
+list)
+    [ $# -eq 1 ] || usage
+    dav PROPFIND "/dav/addressbooks/$1/" -H 'Depth: 1' --data '<?xml version="1.0"?>
+<d:propfind xmlns:d="DAV:"><d:prop><d:getetag/><d:getcontenttype/></d:prop></d:propfind>'
+    ;;
+get)
+    [ $# -eq 2 ] || usage
+    curl -sS -i "$base/dav/addressbooks/$1/$2"
+    ;;
+put)
+    [ $# -ge 3 ] || usage
+    if [ $# -ge 4 ]; then precondition="If-Match: $4"; else precondition='If-None-Match: *'; fi
+    curl -sS -i -X PUT -H 'Content-Type: text/vcard; charset=utf-8' -H "$precondition" \
+        --data-binary "@$3" "$base/dav/addressbooks/$1/$2"
+    ;;
+delete)
+    [ $# -ge 2 ] || usage
+    if [ $# -ge 3 ]; then
+        curl -sS -i -X DELETE -H "If-Match: $3" "$base/dav/addressbooks/$1/$2"
+    else
+        curl -sS -i -X DELETE "$base/dav/addressbooks/$1/$2"
+    fi
+    ;;
+multiget)
+    [ $# -ge 2 ] || usage
+    book=$1
+    shift
+    hrefs=
+    for name in "$@"; do
+        hrefs="$hrefs<d:href>/dav/addressbooks/$book/$name</d:href>"
+    done
+    dav REPORT "/dav/addressbooks/$book/" -H 'Depth: 1' --data "<?xml version=\"1.0\"?>
+<card:addressbook-multiget xmlns:d=\"DAV:\" xmlns:card=\"urn:ietf:params:xml:ns:carddav\">
+  <d:prop><d:getetag/><card:address-data/></d:prop>$hrefs
+</card:addressbook-multiget>"
+    ;;
+query)
+    [ $# -eq 3 ] || usage
+    dav REPORT "/dav/addressbooks/$1/" -H 'Depth: 1' --data "<?xml version=\"1.0\"?>
+<card:addressbook-query xmlns:d=\"DAV:\" xmlns:card=\"urn:ietf:params:xml:ns:carddav\">
+  <d:prop><d:getetag/><card:address-data/></d:prop>
+  <card:filter><card:prop-filter name=\"$2\">
+    <card:text-match match-type=\"contains\">$3</card:text-match>
+  </card:prop-filter></card:filter>
+</card:addressbook-query>"
+    ;;
+sync)
+    [ $# -ge 1 ] || usage
+    dav REPORT "/dav/addressbooks/$1/" --data "<?xml version=\"1.0\"?>
+<d:sync-collection xmlns:d=\"DAV:\">
+  <d:sync-token>${2:-}</d:sync-token><d:sync-level>1</d:sync-level>
+  <d:prop><d:getetag/></d:prop>
+</d:sync-collection>"
+    ;;
+*)
+    usage
+    ;;
+esac
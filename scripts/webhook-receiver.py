@@ -0,0 +1,54 @@
+#!/usr/bin/env python3
+"""A local webhook receiver for trying deliveries by hand.
+
+    scripts/webhook-receiver.py <secret> [port] [failures]
+
+Listens on http://localhost:<port>/ (default 9000), checks each delivery's
+Webhook-Signature against <secret> and prints the event. The first <failures>
+deliveries (default 0) are answered with 500 to exercise retries.
+"""
+import hashlib
+import hmac
+import json
+import sys
+from http.server import BaseHTTPRequestHandler, HTTPServer
+
+if len(sys.argv) < 2:
+    sys.exit(__doc__)
+secret = sys.argv[1].encode()
+port = int(sys.argv[2]) if len(sys.argv) > 2 else 9000
+failures = int(sys.argv[3]) if len(sys.argv) > 3 else 0
+
+
+class Receiver(BaseHTTPRequestHandler):
+    def do_POST(self):
+        global failures
+        body = self.rfile.read(int(self.headers.get("Content-Length", 0)))
+        timestamp = self.headers.get("Webhook-Timestamp", "")
+        expected = "sha256=" + hmac.new(
+            secret, timestamp.encode() + b"." + body, hashlib.sha256
+        ).hexdigest()
+        valid = hmac.compare_digest(expected, self.headers.get("Webhook-Signature", ""))
+
+        event = json.loads(body)
+        print(
+            f"{self.headers.get('Webhook-Id')} {event['type']} "
+            f"signature={'ok' if valid else 'INVALID'} data={json.dumps(event['data'])}",
+            flush=True,
+        )
+
+        if not valid:
+            status = 401
+        elif failures > 0:
+            failures -= 1
+            status = 500
+        else:
+            status = 204
+        self.send_response(status)
+        self.end_headers()
+
+    def log_message(self, format, *args):
+        pass
+
+
+HTTPServer(("localhost", port), Receiver).serve_forever()
@@ -0,0 +1,100 @@
+use sqlx::migrate::{Migrate, Migrator};
+use sqlx::PgPool;
+use std::collections::HashSet;
+
+static MIGRATOR: Migrator = sqlx::migrate!();
+
+const USAGE: &str = "usage: migrate <up|down|status>";
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let command = std::env::args().nth(1).ok_or(USAGE)?;
+    let database_url = std::env::var("DATABASE_URL").map_err(|_| "DATABASE_URL must be set")?;
+    let pool = PgPool::connect(&database_url).await?;
+
+    match command.as_str() {
+        "up" => MIGRATOR.run(&pool).await?,
+        "down" => revert_last(&pool).await?,
+        "status" => {
+            for (version, description, applied) in status(&pool).await? {
+                let state = if applied { "applied" } else { "pending" };
+                println!("{:04} {:<8} {}", version, state, description);
+            }
+        }
+        _ => return Err(USAGE.into()),
+    }
+
+    Ok(())
+}
+
+async fn applied_versions(pool: &PgPool) -> Result<HashSet<i64>, sqlx::migrate::MigrateError> {
+    let mut conn = pool.acquire().await?;
+    conn.ensure_migrations_table().await?;
+
+    Ok(conn
+        .list_applied_migrations()
+        .await?
+        .into_iter()
+        .map(|migration| migration.version)
+        .collect())
+}
+
+async fn status(pool: &PgPool) -> Result<Vec<(i64, String, bool)>, sqlx::migrate::MigrateError> {
+    let applied = applied_versions(pool).await?;
+
+    Ok(MIGRATOR
+        .iter()
+        .filter(|migration| migration.migration_type.is_up_migration())
+        .map(|migration| {
+            (
+                migration.version,
+                migration.description.to_string(),
+                applied.contains(&migration.version),
+            )
+        })
+        .collect())
+}
+
+/// Reverts the most recently applied migration only.
+async fn revert_last(pool: &PgPool) -> Result<(), sqlx::migrate::MigrateError> {
+    let applied = applied_versions(pool).await?;
+
+    let Some(latest) = applied.iter().max().copied() else {
+        return Ok(());
+    };
+    let target = applied
+        .iter()
+        .filter(|version| **version < latest)
+        .max()
+        .copied()
+        .unwrap_or(0);
+
+    MIGRATOR.undo(pool, target).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Needs a throwaway database: DATABASE_URL=postgres://... cargo test -- --ignored
+    #[tokio::test]
+    #[ignore]
+    async fn test_migrations_apply_and_revert() {
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url).await.unwrap();
+
+        MIGRATOR.run(&pool).await.unwrap();
+        assert!(status(&pool).await.unwrap().iter().all(|(_, _, applied)| *applied));
+
+        MIGRATOR.undo(&pool, 0).await.unwrap();
+        assert!(status(&pool).await.unwrap().iter().all(|(_, _, applied)| !*applied));
+
+        MIGRATOR.run(&pool).await.unwrap();
+        revert_last(&pool).await.unwrap();
+        let status = status(&pool).await.unwrap();
+        assert!(!status.last().unwrap().2);
+        assert!(status[..status.len() - 1].iter().all(|(_, _, applied)| *applied));
+
+        MIGRATOR.undo(&pool, 0).await.unwrap();
+    }
+}
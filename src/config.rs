@@ -0,0 +1,268 @@
+use ipnet::IpNet;
+use shuttle_runtime::SecretStore;
+use std::collections::HashSet;
+use std::net::IpAddr;
+use std::time::Duration;
+
+use crate::types::limits::RequestLimits;
+use crate::types::rate_limit::{RateLimit, RateLimits};
+use crate::types::webhook::WebhookTargets;
+
+const DEFAULT_TRASH_RETENTION_DAYS: i64 = 30;
+const DEFAULT_CHANGE_RETENTION_DAYS: i64 = 30;
+const DEFAULT_PHOTO_STORAGE_DIR: &str = "photos";
+const DEFAULT_RATE_LIMIT_READ_PER_MINUTE: u32 = 600;
+const DEFAULT_RATE_LIMIT_WRITE_PER_MINUTE: u32 = 120;
+const DEFAULT_RATE_LIMIT_IMPORT_PER_MINUTE: u32 = 10;
+const DEFAULT_MAX_BODY_BYTES: usize = 2 * 1024 * 1024;
+const DEFAULT_MAX_IMPORT_BODY_BYTES: usize = 25 * 1024 * 1024;
+const DEFAULT_REQUEST_TIMEOUT_SECS: u64 = 30;
+const DEFAULT_IMPORT_TIMEOUT_SECS: u64 = 120;
+const DEFAULT_STATEMENT_TIMEOUT_SECS: u64 = 20;
+const DEFAULT_LOG_FILTER: &str = "info";
+
+/// How log lines are written to stdout.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogFormat {
+    /// One JSON object per line, for log collectors.
+    Json,
+    /// Human-readable lines, for local runs.
+    Text,
+}
+
+/// Runtime settings read from the Shuttle secret store (`Secrets.toml`).
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// How long soft-deleted books and contacts stay restorable before being purged.
+    pub trash_retention: chrono::Duration,
+    /// How long contact changes are kept for incremental sync. Older tokens get a full resync.
+    pub change_retention: chrono::Duration,
+    /// Directory where contact photos are stored.
+    pub photo_storage_dir: String,
+    /// Requests per minute allowed to each client, by route class.
+    pub rate_limits: RateLimits,
+    /// Whether the rate limiter keeps its buckets in Postgres, to share them between instances.
+    pub shared_rate_limits: bool,
+    /// API keys that get rate limit quotas of their own.
+    pub api_keys: HashSet<String>,
+    /// Proxies in front of the service that append to `X-Forwarded-For`. Without any, the
+    /// header is ignored.
+    pub trusted_proxy_hops: usize,
+    /// Largest request bodies and longest requests accepted.
+    pub request_limits: RequestLimits,
+    /// How long a single database statement may run before Postgres cancels it.
+    pub statement_timeout: Duration,
+    pub log_format: LogFormat,
+    /// `tracing_subscriber::EnvFilter` directives selecting what is logged, such as `info` or
+    /// `info,addressbook_service=debug`.
+    pub log_filter: String,
+    /// Base URL of an OTLP/HTTP collector to export traces to. Traces are not exported without it.
+    pub otlp_endpoint: Option<String>,
+    /// Internal networks webhooks may still deliver to.
+    pub webhook_targets: WebhookTargets,
+    /// Why settings that were set but refused fell back to their defaults, to be logged once
+    /// logging is set up.
+    pub ignored_settings: Vec<String>,
+}
+
+impl Config {
+    pub fn from_secrets(secrets: &SecretStore) -> Self {
+        let mut ignored_settings = Vec::new();
+        let trash_retention = retention(
+            secrets,
+            "TRASH_RETENTION_DAYS",
+            DEFAULT_TRASH_RETENTION_DAYS,
+            &mut ignored_settings,
+        );
+        let change_retention = retention(
+            secrets,
+            "CHANGE_RETENTION_DAYS",
+            DEFAULT_CHANGE_RETENTION_DAYS,
+            &mut ignored_settings,
+        );
+
+        let photo_storage_dir = secrets
+            .get("PHOTO_STORAGE_DIR")
+            .unwrap_or_else(|| String::from(DEFAULT_PHOTO_STORAGE_DIR));
+
+        let rate_limits = RateLimits {
+            read: rate_limit(
+                secrets,
+                "RATE_LIMIT_READ_PER_MINUTE",
+                DEFAULT_RATE_LIMIT_READ_PER_MINUTE,
+            ),
+            write: rate_limit(
+                secrets,
+                "RATE_LIMIT_WRITE_PER_MINUTE",
+                DEFAULT_RATE_LIMIT_WRITE_PER_MINUTE,
+            ),
+            import: rate_limit(
+                secrets,
+                "RATE_LIMIT_IMPORT_PER_MINUTE",
+                DEFAULT_RATE_LIMIT_IMPORT_PER_MINUTE,
+            ),
+        };
+
+        let shared_rate_limits = secrets
+            .get("RATE_LIMIT_STORE")
+            .is_some_and(|store| store.eq_ignore_ascii_case("postgres"));
+
+        let api_keys = secrets
+            .get("API_KEYS")
+            .map(|keys| {
+                keys.split(',')
+                    .map(str::trim)
+                    .filter(|key| !key.is_empty())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+        let trusted_proxy_hops = setting(secrets, "TRUSTED_PROXY_HOPS", 0);
+
+        let request_limits = RequestLimits {
+            body_bytes: setting(secrets, "MAX_BODY_BYTES", DEFAULT_MAX_BODY_BYTES),
+            import_body_bytes: setting(
+                secrets,
+                "MAX_IMPORT_BODY_BYTES",
+                DEFAULT_MAX_IMPORT_BODY_BYTES,
+            ),
+            timeout: Duration::from_secs(setting(
+                secrets,
+                "REQUEST_TIMEOUT_SECS",
+                DEFAULT_REQUEST_TIMEOUT_SECS,
+            )),
+            import_timeout: Duration::from_secs(setting(
+                secrets,
+                "IMPORT_TIMEOUT_SECS",
+                DEFAULT_IMPORT_TIMEOUT_SECS,
+            )),
+        };
+
+        let statement_timeout = Duration::from_secs(setting(
+            secrets,
+            "STATEMENT_TIMEOUT_SECS",
+            DEFAULT_STATEMENT_TIMEOUT_SECS,
+        ));
+
+        let log_format = match secrets.get("LOG_FORMAT") {
+            Some(format) if format.eq_ignore_ascii_case("text") => LogFormat::Text,
+            _ => LogFormat::Json,
+        };
+
+        let log_filter = secrets
+            .get("LOG_FILTER")
+            .unwrap_or_else(|| String::from(DEFAULT_LOG_FILTER));
+
+        let otlp_endpoint = secrets
+            .get("OTLP_ENDPOINT")
+            .filter(|endpoint| !endpoint.is_empty());
+
+        // Networks are written like `10.0.0.0/8`; a single address like `10.0.0.5` also works.
+        let webhook_targets = WebhookTargets {
+            allowed: secrets
+                .get("WEBHOOK_ALLOWED_NETWORKS")
+                .map(|networks| {
+                    networks
+                        .split(',')
+                        .map(str::trim)
+                        .filter_map(|network| {
+                            network
+                                .parse()
+                                .or_else(|_| network.parse::<IpAddr>().map(IpNet::from))
+                                .ok()
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+        };
+
+        Self {
+            trash_retention,
+            change_retention,
+            photo_storage_dir,
+            rate_limits,
+            shared_rate_limits,
+            api_keys,
+            trusted_proxy_hops,
+            request_limits,
+            statement_timeout,
+            log_format,
+            log_filter,
+            otlp_endpoint,
+            webhook_targets,
+            ignored_settings,
+        }
+    }
+}
+
+/// The number under `name`, or `default` when it is unset or not a number.
+fn setting<T: std::str::FromStr>(secrets: &SecretStore, name: &str, default: T) -> T {
+    secrets
+        .get(name)
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+/// The number of days under `name` as a duration, or `default_days` when unset. Values the
+/// purge jobs cannot use are refused with a message in `ignored`: zero or less would purge at
+/// once, and more days than a date can go back would panic.
+fn retention(
+    secrets: &SecretStore,
+    name: &str,
+    default_days: i64,
+    ignored: &mut Vec<String>,
+) -> chrono::Duration {
+    let default = chrono::Duration::days(default_days);
+    let Some(value) = secrets.get(name) else {
+        return default;
+    };
+    match retention_days(&value) {
+        Some(retention) => retention,
+        None => {
+            ignored.push(format!(
+                "{name} must be a whole number of days above zero, not {value:?}; \
+                 using {default_days}"
+            ));
+            default
+        }
+    }
+}
+
+/// `value` as a positive number of days that can be subtracted from now.
+fn retention_days(value: &str) -> Option<chrono::Duration> {
+    value
+        .trim()
+        .parse::<i64>()
+        .ok()
+        .filter(|days| *days > 0)
+        .and_then(chrono::Duration::try_days)
+        .filter(|retention| chrono::Utc::now().checked_sub_signed(*retention).is_some())
+}
+
+/// The limit under `name`, or `default` when unset. Zero turns the limit off.
+fn rate_limit(secrets: &SecretStore, name: &str, default: u32) -> Option<RateLimit> {
+    let per_minute = setting(secrets, name, default);
+    (per_minute > 0).then_some(RateLimit { per_minute })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retention_days_must_be_positive_and_in_range() {
+        assert_eq!(retention_days("7"), Some(chrono::Duration::days(7)));
+        assert_eq!(retention_days(" 30 "), Some(chrono::Duration::days(30)));
+        for value in [
+            "0",
+            "-1",
+            "1.5",
+            "seven",
+            "",
+            "9223372036854775807",
+            "106751991167",
+        ] {
+            assert_eq!(retention_days(value), None, "{value:?}");
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use std::time::Duration;
+
+use metrics_exporter_prometheus::PrometheusHandle;
+use sqlx::postgres::PgListener;
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::repositories::blob_store::LocalBlobStore;
+use crate::repositories::change_feed::{ChangeFeed, CHANGE_CHANNEL};
+use crate::repositories::contact_repo::ContactRepository;
+use crate::repositories::metrics_repo::MetricsRepository;
+use crate::repositories::rate_limit_store::IRateLimitStore;
+use crate::repositories::trash_repo::TrashRepository;
+use crate::repositories::webhook_repo::WebhookRepository;
+use crate::repositories::webhook_sender::HttpWebhookSender;
+use crate::services::contact_service::ContactService;
+use crate::services::metrics_service::MetricsService;
+use crate::services::rate_limit_service::RateLimitService;
+use crate::services::trash_service::TrashService;
+use crate::services::webhook_service::WebhookService;
+
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const DISPATCH_INTERVAL: Duration = Duration::from_secs(5);
+const BUCKET_PRUNE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+const COUNT_INTERVAL: Duration = Duration::from_secs(60);
+const METRICS_UPKEEP_INTERVAL: Duration = Duration::from_secs(5);
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Periodically hard-deletes trash older than `retention`, with the photos stored in
+/// `blob_store`. Runs for the lifetime of the service.
+pub async fn purge_trash(pool: PgPool, blob_store: LocalBlobStore, retention: chrono::Duration) {
+    let mut interval = tokio::time::interval(PURGE_INTERVAL);
+    loop {
+        interval.tick().await;
+        let repo = TrashRepository::new(pool.clone());
+        // A failed run is retried on the next tick.
+        if let Err(e) = TrashService::purge_expired(repo, blob_store.clone(), retention).await {
+            tracing::warn!(error = %e, "purging trash failed");
+        }
+    }
+}
+
+/// Periodically prunes the contact change log back to `retention`. Runs for the lifetime of
+/// the service.
+pub async fn prune_changes(pool: PgPool, retention: chrono::Duration) {
+    let mut interval = tokio::time::interval(PURGE_INTERVAL);
+    loop {
+        interval.tick().await;
+        let repo = ContactRepository::new(pool.clone());
+        // A failed run is retried on the next tick.
+        if let Err(e) = ContactService::prune_changes(repo, retention).await {
+            tracing::warn!(error = %e, "pruning contact changes failed");
+        }
+    }
+}
+
+/// Periodically drops rate limit buckets that have filled up again, so clients seen once do
+/// not stay in the store. Runs for the lifetime of the service.
+pub async fn prune_rate_limits(store: Arc<dyn IRateLimitStore + Send + Sync>) {
+    let mut interval = tokio::time::interval(BUCKET_PRUNE_INTERVAL);
+    loop {
+        interval.tick().await;
+        // A failed run is retried on the next tick.
+        if let Err(e) = RateLimitService::prune_idle(store.as_ref()).await {
+            tracing::warn!(error = %e, "pruning rate limit buckets failed");
+        }
+    }
+}
+
+/// Delivers due webhook events every few seconds. Runs for the lifetime of the service.
+pub async fn dispatch_webhooks(pool: PgPool, sender: HttpWebhookSender) {
+    let mut interval = tokio::time::interval(DISPATCH_INTERVAL);
+    loop {
+        interval.tick().await;
+        let repo = WebhookRepository::new(pool.clone());
+        // Unrecorded attempts are retried once their claim lapses.
+        if let Err(e) = WebhookService::dispatch_due(repo, &sender).await {
+            tracing::warn!(error = %e, "dispatching webhooks failed");
+        }
+    }
+}
+
+/// Relays change notifications from Postgres to the open event streams. Runs for the lifetime
+/// of the service.
+pub async fn relay_changes(pool: PgPool, feed: ChangeFeed) {
+    loop {
+        if let Ok(mut listener) = PgListener::connect_with(&pool).await {
+            if listener.listen(CHANGE_CHANNEL).await.is_ok() {
+                while let Ok(notification) = listener.recv().await {
+                    if let Ok(address_book_id) = notification.payload().parse() {
+                        feed.publish(address_book_id);
+                    }
+                }
+            }
+        }
+        // Streams also poll, so notices missed until the listener is back only delay them.
+        tracing::warn!("listening for contact changes stopped, reconnecting");
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+/// Refreshes the book and contact gauges every minute. Runs for the lifetime of the service.
+pub async fn count_entities(pool: PgPool) {
+    let mut interval = tokio::time::interval(COUNT_INTERVAL);
+    loop {
+        interval.tick().await;
+        let repo = MetricsRepository::new(pool.clone());
+        // The gauges keep their last value until the next tick.
+        if let Err(e) = MetricsService::refresh_counts(repo).await {
+            tracing::warn!(error = %e, "counting books and contacts failed");
+        }
+    }
+}
+
+/// Runs the metrics recorder's periodic housekeeping. Runs for the lifetime of the service.
+pub async fn metrics_upkeep(handle: PrometheusHandle) {
+    let mut interval = tokio::time::interval(METRICS_UPKEEP_INTERVAL);
+    loop {
+        interval.tick().await;
+        handle.run_upkeep();
+    }
+}
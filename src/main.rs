@@ -1,32 +1,298 @@
+mod config;
+mod jobs;
+mod monitoring;
 mod repositories;
 mod routes;
+mod server;
 mod services;
+mod telemetry;
 mod types;
 
 use axum::{
-    routing::{delete, get, post, put},
+    extract::DefaultBodyLimit,
+    middleware,
+    routing::{any, delete, get, patch, post, put},
     Router,
 };
+use config::Config;
+use repositories::blob_store::LocalBlobStore;
+use repositories::change_feed::ChangeFeed;
+use repositories::pool::with_statement_timeout;
+use repositories::rate_limit_store::{IRateLimitStore, MemoryRateLimitStore, PgRateLimitStore};
+use repositories::webhook_sender::HttpWebhookSender;
 use routes::address_book::*;
+use routes::{
+    audit, contact, dav, event, field, graphql, group, limits, metrics, openapi, photo, rate_limit,
+    trash, webhook,
+};
+use shuttle_runtime::SecretStore;
 use sqlx::PgPool;
+use std::sync::Arc;
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
+use tower_http::trace::TraceLayer;
+use types::photo::MAX_PHOTO_BYTES;
+use types::rate_limit::RateLimiter;
 use types::AppState;
 
 #[shuttle_runtime::main]
-async fn main(#[shuttle_shared_db::Postgres] pool: PgPool) -> shuttle_axum::ShuttleAxum {
+async fn main(
+    #[shuttle_shared_db::Postgres] pool: PgPool,
+    #[shuttle_runtime::Secrets] secrets: SecretStore,
+) -> Result<server::Server, shuttle_runtime::Error> {
+    let config = Config::from_secrets(&secrets);
+    let metrics = monitoring::install().expect("Failed to install the metrics recorder");
+    let telemetry = telemetry::init(&config);
+    for problem in &config.ignored_settings {
+        tracing::error!("{problem}");
+    }
+
     sqlx::migrate!()
         .run(&pool)
         .await
         .expect("Faild to run migrations");
 
-    let state = AppState { pool };
+    // Migrations may legitimately run long, so only the connections used afterwards are bounded.
+    let pool = with_statement_timeout(pool, config.statement_timeout).await;
+    let blob_store = LocalBlobStore::new(config.photo_storage_dir);
+    tokio::spawn(jobs::purge_trash(
+        pool.clone(),
+        blob_store.clone(),
+        config.trash_retention,
+    ));
+    tokio::spawn(jobs::prune_changes(pool.clone(), config.change_retention));
+    let webhook_sender = HttpWebhookSender::new(config.webhook_targets);
+    tokio::spawn(jobs::dispatch_webhooks(
+        pool.clone(),
+        webhook_sender.clone(),
+    ));
+    let change_feed = ChangeFeed::new();
+    tokio::spawn(jobs::relay_changes(pool.clone(), change_feed.clone()));
+    tokio::spawn(jobs::count_entities(pool.clone()));
+    tokio::spawn(jobs::metrics_upkeep(metrics.clone()));
+
+    let rate_limit_store: Arc<dyn IRateLimitStore + Send + Sync> = if config.shared_rate_limits {
+        Arc::new(PgRateLimitStore::new(pool.clone()))
+    } else {
+        Arc::new(MemoryRateLimitStore::new())
+    };
+    tokio::spawn(jobs::prune_rate_limits(rate_limit_store.clone()));
+
+    let state = AppState {
+        pool,
+        blob_store,
+        change_feed,
+        rate_limiter: RateLimiter {
+            store: rate_limit_store,
+            limits: config.rate_limits,
+            api_keys: Arc::new(config.api_keys),
+            trusted_proxy_hops: config.trusted_proxy_hops,
+        },
+        limits: config.request_limits,
+        metrics,
+        webhook_sender,
+    };
 
-    let router = Router::new()
+    Ok(server::Server {
+        router: app(state),
+        telemetry,
+    })
+}
+
+fn app(state: AppState) -> Router {
+    let import_body_limit = DefaultBodyLimit::max(state.limits.import_body_bytes);
+    Router::new()
         .route("/api/addressbooks", get(index))
         .route("/api/addressbooks", post(create_address_book))
-        //.route("/api/addressbooks/:id", put(update))
+        .route("/api/addressbooks/:id", put(update))
         .route("/api/addressbooks/:id", delete(delete_address_book))
         .route("/api/addressbooks/:id", get(show))
-        .with_state(state);
+        .route("/api/addressbooks/:id/restore", post(restore))
+        .route("/api/addressbooks/:id/stats", get(stats))
+        .route("/api/addressbooks/:id/changes", get(contact::changes))
+        .route("/api/addressbooks/:id/events", get(event::stream))
+        .route(
+            "/api/addressbooks/:id/history",
+            get(audit::address_book_history),
+        )
+        .route("/api/addressbooks/:id/contacts", get(contact::index))
+        .route(
+            "/api/addressbooks/:id/contacts",
+            post(contact::create_contact),
+        )
+        .route(
+            "/api/addressbooks/:id/contacts/bulk",
+            post(contact::bulk).layer(import_body_limit.clone()),
+        )
+        .route(
+            "/api/addressbooks/:id/contacts/:contact_id",
+            get(contact::show),
+        )
+        .route(
+            "/api/addressbooks/:id/contacts/:contact_id",
+            put(contact::update),
+        )
+        .route(
+            "/api/addressbooks/:id/contacts/:contact_id",
+            patch(contact::patch),
+        )
+        .route(
+            "/api/addressbooks/:id/contacts/:contact_id",
+            delete(contact::delete_contact),
+        )
+        .route(
+            "/api/addressbooks/:id/contacts/:contact_id/restore",
+            post(contact::restore),
+        )
+        .route(
+            "/api/addressbooks/:id/contacts/:contact_id/history",
+            get(audit::contact_history),
+        )
+        .route(
+            "/api/addressbooks/:id/contacts/:contact_id/photo",
+            get(photo::show),
+        )
+        .route(
+            "/api/addressbooks/:id/contacts/:contact_id/photo",
+            put(photo::update).layer(DefaultBodyLimit::max(MAX_PHOTO_BYTES)),
+        )
+        .route(
+            "/api/addressbooks/:id/contacts/:contact_id/photo",
+            delete(photo::delete_photo),
+        )
+        .route(
+            "/api/addressbooks/:id/contacts/export",
+            get(contact::export),
+        )
+        .route(
+            "/api/addressbooks/:id/contacts/import",
+            post(contact::import).layer(import_body_limit),
+        )
+        .route("/api/addressbooks/:id/fields", get(field::index))
+        .route("/api/addressbooks/:id/fields", post(field::create_field))
+        .route("/api/addressbooks/:id/fields/:field_id", get(field::show))
+        .route("/api/addressbooks/:id/fields/:field_id", put(field::update))
+        .route(
+            "/api/addressbooks/:id/fields/:field_id",
+            delete(field::delete_field),
+        )
+        .route("/api/addressbooks/:id/groups", get(group::index))
+        .route("/api/addressbooks/:id/groups", post(group::create_group))
+        .route("/api/addressbooks/:id/groups/:group_id", get(group::show))
+        .route("/api/addressbooks/:id/groups/:group_id", put(group::update))
+        .route(
+            "/api/addressbooks/:id/groups/:group_id",
+            delete(group::delete_group),
+        )
+        .route(
+            "/api/addressbooks/:id/groups/:group_id/contacts/:contact_id",
+            put(group::add_member),
+        )
+        .route(
+            "/api/addressbooks/:id/groups/:group_id/contacts/:contact_id",
+            delete(group::remove_member),
+        )
+        .route("/api/addressbooks/:id/webhooks", get(webhook::index))
+        .route(
+            "/api/addressbooks/:id/webhooks",
+            post(webhook::create_webhook),
+        )
+        .route(
+            "/api/addressbooks/:id/webhooks/:webhook_id",
+            get(webhook::show),
+        )
+        .route(
+            "/api/addressbooks/:id/webhooks/:webhook_id",
+            put(webhook::update),
+        )
+        .route(
+            "/api/addressbooks/:id/webhooks/:webhook_id",
+            delete(webhook::delete_webhook),
+        )
+        .route(
+            "/api/addressbooks/:id/webhooks/:webhook_id/deliveries",
+            get(webhook::deliveries),
+        )
+        .route(
+            "/api/addressbooks/:id/webhooks/:webhook_id/deliveries/:delivery_id",
+            get(webhook::delivery),
+        )
+        .route(
+            "/api/addressbooks/:id/webhooks/:webhook_id/deliveries/:delivery_id/redeliver",
+            post(webhook::redeliver),
+        )
+        .route("/api/contacts/move", post(contact::move_contacts))
+        .route(
+            "/api/contacts/:contact_id/move",
+            post(contact::move_contact),
+        )
+        .route(
+            "/api/contacts/:contact_id/copy",
+            post(contact::copy_contact),
+        )
+        .route("/api/trash", get(trash::index))
+        .route("/metrics", get(metrics::scrape))
+        .route("/graphql", post(graphql::execute))
+        .route("/.well-known/carddav", any(dav::well_known))
+        .route("/dav", any(dav::principal))
+        .route("/dav/", any(dav::principal))
+        .route("/dav/addressbooks", any(dav::home))
+        .route("/dav/addressbooks/", any(dav::home))
+        .route("/dav/addressbooks/:id", any(dav::address_book))
+        .route("/dav/addressbooks/:id/", any(dav::address_book))
+        .route("/dav/addressbooks/:id/:resource", any(dav::card))
+        .merge(openapi::docs())
+        .layer(DefaultBodyLimit::max(state.limits.body_bytes))
+        .layer(middleware::from_fn_with_state(
+            state.limits,
+            limits::timeout,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.rate_limiter.clone(),
+            rate_limit::limit,
+        ))
+        .layer(middleware::from_fn(monitoring::track_requests))
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(telemetry::request_span)
+                .on_response(telemetry::record_response),
+        )
+        .layer(PropagateRequestIdLayer::x_request_id())
+        .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+        .layer(middleware::from_fn(telemetry::check_request_id))
+        .with_state(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use metrics_exporter_prometheus::PrometheusBuilder;
+    use std::collections::HashSet;
+    use std::time::Duration;
+    use types::limits::RequestLimits;
+    use types::rate_limit::RateLimits;
+    use types::webhook::WebhookTargets;
 
-    Ok(router.into())
+    /// Application state for router tests: no rate limits, the default request limits and a
+    /// metrics recorder of its own.
+    pub(crate) fn state(pool: PgPool) -> AppState {
+        AppState {
+            pool,
+            blob_store: LocalBlobStore::new(std::env::temp_dir().join("addressbook-photos")),
+            change_feed: ChangeFeed::new(),
+            rate_limiter: RateLimiter {
+                store: Arc::new(MemoryRateLimitStore::new()),
+                limits: RateLimits::default(),
+                api_keys: Arc::new(HashSet::new()),
+                trusted_proxy_hops: 0,
+            },
+            limits: RequestLimits {
+                body_bytes: 2 * 1024 * 1024,
+                import_body_bytes: 25 * 1024 * 1024,
+                timeout: Duration::from_secs(30),
+                import_timeout: Duration::from_secs(120),
+            },
+            metrics: PrometheusBuilder::new().build_recorder().handle(),
+            webhook_sender: HttpWebhookSender::new(WebhookTargets::default()),
+        }
+    }
 }
@@ -0,0 +1,256 @@
+use axum::extract::{MatchedPath, Request};
+use axum::http::Method;
+use axum::middleware::Next;
+use axum::response::Response;
+use metrics::{counter, describe_gauge, describe_histogram, gauge, histogram, Unit};
+use metrics_exporter_prometheus::{BuildError, Matcher, PrometheusBuilder, PrometheusHandle};
+use sqlx::PgPool;
+use std::time::Instant;
+use tracing::span::{Attributes, Id};
+use tracing::{Metadata, Subscriber};
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+use crate::types::stats::EntityCounts;
+
+/// Histogram buckets for every `*_seconds` metric, from a millisecond to half a minute.
+const LATENCY_BUCKETS: [f64; 14] = [
+    0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+
+/// Module path prefix of the repositories whose calls are timed.
+const REPOSITORIES: &str = concat!(env!("CARGO_CRATE_NAME"), "::repositories::");
+
+/// Installs the global metrics recorder. Its handle renders the `/metrics` page.
+pub fn install() -> Result<PrometheusHandle, BuildError> {
+    let handle = PrometheusBuilder::new()
+        .set_buckets_for_metric(Matcher::Suffix(String::from("_seconds")), &LATENCY_BUCKETS)?
+        .install_recorder()?;
+
+    describe_histogram!(
+        "http_request_duration_seconds",
+        Unit::Seconds,
+        "Time until the response started, by method, route and status."
+    );
+    describe_histogram!(
+        "db_query_duration_seconds",
+        Unit::Seconds,
+        "Duration of repository calls, by repository and method."
+    );
+    describe_gauge!(
+        "db_pool_connections",
+        "Open database connections, by state (idle or in_use)."
+    );
+    describe_gauge!(
+        "db_pool_max_connections",
+        "Size limit of the database pool."
+    );
+    describe_gauge!("address_books", "Address books that are not in the trash.");
+    describe_gauge!("contacts", "Contacts that are not in the trash.");
+    Ok(handle)
+}
+
+/// The `method` label of a request. Methods other than the standard ones, WebDAV's
+/// included, share `other`, so clients cannot add label values by inventing methods.
+fn method_label(method: &Method) -> &'static str {
+    match *method {
+        Method::GET => "GET",
+        Method::HEAD => "HEAD",
+        Method::POST => "POST",
+        Method::PUT => "PUT",
+        Method::PATCH => "PATCH",
+        Method::DELETE => "DELETE",
+        Method::OPTIONS => "OPTIONS",
+        _ => "other",
+    }
+}
+
+/// Counts and times every request by method, route and status. Requests matching no route
+/// are counted under `unmatched`, so made-up paths cannot add label values.
+pub async fn track_requests(request: Request, next: Next) -> Response {
+    let method = method_label(request.method());
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| String::from("unmatched"));
+    let started = Instant::now();
+
+    let response = next.run(request).await;
+
+    let labels = [
+        ("method", method.to_string()),
+        ("route", route),
+        ("status", response.status().as_u16().to_string()),
+    ];
+    counter!("http_requests_total", &labels).increment(1);
+    histogram!("http_request_duration_seconds", &labels).record(started.elapsed().as_secs_f64());
+    response
+}
+
+/// Publishes how many connections of the pool are open and in use.
+pub fn record_pool(pool: &PgPool) {
+    let size = pool.size() as f64;
+    let idle = pool.num_idle() as f64;
+    gauge!("db_pool_connections", "state" => "idle").set(idle);
+    gauge!("db_pool_connections", "state" => "in_use").set(size - idle);
+    gauge!("db_pool_max_connections").set(pool.options().get_max_connections() as f64);
+}
+
+/// Publishes the book and contact counts as gauges.
+pub fn record_counts(counts: &EntityCounts) {
+    gauge!("address_books").set(counts.address_books as f64);
+    gauge!("contacts").set(counts.contacts as f64);
+}
+
+/// Times repository calls from the spans their methods open, recording them as
+/// `db_query_duration_seconds`.
+pub struct QueryMetricsLayer;
+
+struct CallStarted {
+    repository: &'static str,
+    at: Instant,
+}
+
+/// The repository module a span was opened in, such as `contact_repo`.
+fn repository(metadata: &Metadata<'static>) -> Option<&'static str> {
+    metadata
+        .target()
+        .strip_prefix(REPOSITORIES)
+        .filter(|module| module.ends_with("_repo"))
+}
+
+impl<S> Layer<S> for QueryMetricsLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if let (Some(repository), Some(span)) = (repository(attrs.metadata()), ctx.span(id)) {
+            span.extensions_mut().insert(CallStarted {
+                repository,
+                at: Instant::now(),
+            });
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let extensions = span.extensions();
+        if let Some(started) = extensions.get::<CallStarted>() {
+            histogram!(
+                "db_query_duration_seconds",
+                "repository" => started.repository,
+                "method" => span.name(),
+            )
+            .record(started.at.elapsed().as_secs_f64());
+        }
+    }
+}
+
+/// The target filter that keeps repository spans enabled for `QueryMetricsLayer`, whatever
+/// the log filter.
+pub fn repository_spans() -> tracing_subscriber::filter::Targets {
+    tracing_subscriber::filter::Targets::new()
+        .with_target(REPOSITORIES.trim_end_matches("::"), tracing::Level::INFO)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::metrics_repo::{IMetricsRepository, MetricsRepository};
+    use axum::body::Body;
+    use axum::routing::get;
+    use axum::{middleware, Router};
+    use sqlx::postgres::PgPoolOptions;
+    use tower::ServiceExt;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    /// Runs `test` on this thread with a fresh recorder and returns the page it renders.
+    /// The recorder is local to the thread, so tests running alongside do not mix in.
+    fn render<F: std::future::Future<Output = ()>>(test: F) -> String {
+        let recorder = PrometheusBuilder::new().build_recorder();
+        let handle = recorder.handle();
+        metrics::with_local_recorder(&recorder, || {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap()
+                .block_on(test)
+        });
+        handle.render()
+    }
+
+    #[test]
+    fn test_method_label_is_other_for_unknown_methods() {
+        assert_eq!(method_label(&Method::GET), "GET");
+        assert_eq!(method_label(&Method::DELETE), "DELETE");
+        for method in ["PROPFIND", "REPORT", "MKCOL", "BREW"] {
+            assert_eq!(method_label(&method.parse().unwrap()), "other", "{method}");
+        }
+    }
+
+    #[test]
+    fn test_track_requests_labels() {
+        let page = render(async {
+            let app = Router::new()
+                .route("/books/:id", get(|| async { "book" }))
+                .layer(middleware::from_fn(track_requests));
+            for (method, uri) in [
+                ("GET", "/books/1"),
+                ("GET", "/books/2"),
+                ("PROPFIND", "/books/1"),
+                ("GET", "/made-up/path"),
+            ] {
+                let request = axum::http::Request::builder()
+                    .method(method)
+                    .uri(uri)
+                    .body(Body::empty())
+                    .unwrap();
+                app.clone().oneshot(request).await.unwrap();
+            }
+        });
+
+        for line in [
+            r#"http_requests_total{method="GET",route="/books/:id",status="200"} 2"#,
+            r#"http_requests_total{method="other",route="/books/:id",status="405"} 1"#,
+            r#"http_requests_total{method="GET",route="unmatched",status="404"} 1"#,
+            r#"http_request_duration_seconds_count{method="GET",route="/books/:id",status="200"} 2"#,
+        ] {
+            assert!(
+                page.lines().any(|l| l == line),
+                "{line} missing from:\n{page}"
+            );
+        }
+        assert!(!page.contains("PROPFIND"));
+        assert!(!page.contains("/books/1"));
+    }
+
+    #[test]
+    fn test_repository_calls_are_timed() {
+        let subscriber =
+            tracing_subscriber::registry().with(QueryMetricsLayer.with_filter(repository_spans()));
+        let page = tracing::subscriber::with_default(subscriber, || {
+            render(async {
+                let pool = PgPoolOptions::new()
+                    .acquire_timeout(std::time::Duration::from_millis(100))
+                    .connect_lazy("postgres://127.0.0.1:9/unused")
+                    .unwrap();
+                // Failed calls are timed too.
+                let repo = MetricsRepository::new(pool);
+                assert!(repo.count_entities().await.is_err());
+                // Spans outside the repositories are not.
+                tracing::info_span!("count_entities").in_scope(|| {});
+            })
+        });
+
+        let line = r#"db_query_duration_seconds_count{repository="metrics_repo",method="count_entities"} 1"#;
+        assert!(
+            page.lines().any(|l| l == line),
+            "{line} missing from:\n{page}"
+        );
+        assert_eq!(page.matches("db_query_duration_seconds_count").count(), 1);
+    }
+}
@@ -1,9 +1,17 @@
-use crate::types::address_book::{AddressBook, AddressBookId};
+use crate::repositories::audit_repo::record_event;
+use crate::repositories::contact_repo::{details_from_row, ensure_locale};
+use crate::types::address_book::{self, AddressBook, AddressBookId, AddressBookView};
+use crate::types::audit::{AuditAction, NewAuditEvent};
 use crate::types::contact::{Contact, ContactId};
+use crate::types::precondition::IfMatch;
+use crate::types::stats::{AddressBookStats, StatsBucket};
+use crate::types::ListFilter;
 use async_trait::async_trait;
 
+use serde_json::{Map, Value};
 use sqlx::postgres::PgRow;
-use sqlx::Row;
+use sqlx::types::Json;
+use sqlx::{PgConnection, Row};
 
 #[cfg(test)]
 use mockall::{predicate::*, *};
@@ -15,13 +23,27 @@ pub trait IAddressBookRepository {
         &self,
         limit: Option<i32>,
         offset: i32,
+        filter: ListFilter,
+        view: AddressBookView,
     ) -> Result<Vec<AddressBook>, handle_errors::Error>;
 
-    async fn get_address_book_by_id(&self, id: i32) -> Result<AddressBook, handle_errors::Error>;
+    async fn get_address_book_by_id(
+        &self,
+        id: i32,
+        view: AddressBookView,
+    ) -> Result<AddressBook, handle_errors::Error>;
+
+    /// The live books among `ids`, ordered by id. Unknown and deleted ids are left out.
+    async fn get_address_books_by_ids(
+        &self,
+        ids: Vec<i32>,
+        view: AddressBookView,
+    ) -> Result<Vec<AddressBook>, handle_errors::Error>;
 
     async fn create_address_book(
         &self,
         address_book_name: String,
+        actor: Option<String>,
     ) -> Result<AddressBook, handle_errors::Error>;
 
     async fn find_address_book_by_name(
@@ -29,13 +51,32 @@ pub trait IAddressBookRepository {
         name: String,
     ) -> Result<AddressBook, handle_errors::Error>;
 
-    async fn delete_address_book(&self, id: i32) -> Result<(), handle_errors::Error>;
+    async fn delete_address_book(
+        &self,
+        id: i32,
+        actor: Option<String>,
+        if_match: IfMatch,
+    ) -> Result<(), handle_errors::Error>;
+
+    async fn restore_address_book(
+        &self,
+        id: i32,
+        actor: Option<String>,
+    ) -> Result<AddressBook, handle_errors::Error>;
 
     async fn update_address_book(
         &self,
         id: i32,
         address_book: &str,
+        actor: Option<String>,
+        if_match: IfMatch,
     ) -> Result<AddressBook, handle_errors::Error>;
+
+    /// Aggregates over the book's live contacts, computed in the database.
+    async fn get_address_book_stats(
+        &self,
+        id: i32,
+    ) -> Result<AddressBookStats, handle_errors::Error>;
 }
 
 pub struct AddressBookRepository {
@@ -48,93 +89,196 @@ impl AddressBookRepository {
     }
 }
 
+const ADDRESS_BOOK_COLUMNS: &str = "ab.id AS address_book_id, ab.address_book_name,
+    ab.created_at, ab.updated_at, ab.created_by, ab.updated_by, ab.deleted_at, ab.version,
+    ab.contact_count";
+
+const EMBEDDED_CONTACT_COLUMNS: &str =
+    "c.id AS contact_id, c.name, c.address, c.phone_number, c.email,
+    c.name_prefix, c.given_name, c.middle_name, c.family_name, c.name_suffix, c.nickname,
+    c.organization, c.job_title, c.birthday, c.anniversary, c.notes, c.display_name, c.sort_key,
+    c.created_at AS contact_created_at, c.updated_at AS contact_updated_at,
+    c.created_by AS contact_created_by, c.updated_by AS contact_updated_by,
+    c.deleted_at AS contact_deleted_at, c.version AS contact_version,
+    contact_group_names(c.id) AS contact_groups, contact_has_photo(c.id) AS contact_has_photo,
+    c.custom_fields AS contact_custom_fields";
+
+pub(crate) const BOOK_COLUMNS: &str = "id AS address_book_id, address_book_name,
+    created_at, updated_at, created_by, updated_by, deleted_at, version";
+
+pub(crate) fn address_book_from_row(row: &PgRow) -> AddressBook {
+    AddressBook {
+        id: AddressBookId(row.get("address_book_id")),
+        address_book_name: row.get("address_book_name"),
+        contacts: None,
+        contact_count: None,
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+        created_by: row.get("created_by"),
+        updated_by: row.get("updated_by"),
+        deleted_at: row.get("deleted_at"),
+        version: row.get("version"),
+    }
+}
+
+fn bucket_from_row(row: &PgRow) -> StatsBucket {
+    StatsBucket {
+        name: row.get("name"),
+        count: row.get("count"),
+    }
+}
+
+/// Locks a book row for the rest of the transaction, returning its current state.
+async fn lock_address_book(
+    conn: &mut PgConnection,
+    id: i32,
+    deleted: bool,
+) -> Result<Option<AddressBook>, sqlx::Error> {
+    let q = format!(
+        "SELECT {BOOK_COLUMNS} FROM address_books
+         WHERE id = $1 AND (deleted_at IS NOT NULL) = $2 FOR UPDATE"
+    );
+    sqlx::query(&q)
+        .bind(id)
+        .bind(deleted)
+        .map(|row: PgRow| address_book_from_row(&row))
+        .fetch_optional(conn)
+        .await
+}
+
+/// The select list, joins and contact ordering that read books the way `view` asks.
+/// Queries select from a subquery aliased `ab` that adds a `contact_count` column.
+fn view_sql(view: &AddressBookView) -> (String, &'static str, &'static str) {
+    if view.contacts {
+        (
+            format!("{ADDRESS_BOOK_COLUMNS}, {EMBEDDED_CONTACT_COLUMNS}"),
+            "LEFT JOIN contacts AS c ON ab.id = c.address_book_id AND c.deleted_at IS NULL",
+            ", c.id",
+        )
+    } else {
+        (String::from(ADDRESS_BOOK_COLUMNS), "", "")
+    }
+}
+
+/// Counts a book's live contacts when the view asks for it, for the `ab` subquery.
+fn contact_count_sql(view: &AddressBookView) -> &'static str {
+    if view.contact_count {
+        "(SELECT count(*) FROM contacts AS n
+          WHERE n.address_book_id = ab.id AND n.deleted_at IS NULL) AS contact_count"
+    } else {
+        "NULL::bigint AS contact_count"
+    }
+}
+
+/// Folds address book rows, LEFT JOINed with their contacts if the view embeds them, into
+/// one `AddressBook` per book. Rows must be ordered so that all rows of a book are adjacent.
+fn group_address_books(rows: Vec<PgRow>, view: &AddressBookView) -> Vec<AddressBook> {
+    let mut address_books: Vec<AddressBook> = vec![];
+    for row in rows {
+        let address_book_id = AddressBookId(row.get("address_book_id"));
+        if address_books.last().map(|a| &a.id) != Some(&address_book_id) {
+            let mut address_book = address_book_from_row(&row);
+            address_book.contact_count = row.get("contact_count");
+            if view.contacts {
+                address_book.contacts = Some(vec![]);
+            }
+            address_books.push(address_book);
+        }
+        if !view.contacts {
+            continue;
+        }
+
+        let contact_id: Option<i32> = row.get("contact_id");
+        let contacts = address_books
+            .last_mut()
+            .and_then(|address_book| address_book.contacts.as_mut());
+        if let (Some(contact_id), Some(contacts)) = (contact_id, contacts) {
+            contacts.push(Contact {
+                id: ContactId(contact_id),
+                name: row.get("name"),
+                address: row.get("address"),
+                phone_number: row.get("phone_number"),
+                email: row.get("email"),
+                details: details_from_row(&row),
+                display_name: row.get("display_name"),
+                sort_key: row.get("sort_key"),
+                address_book_id,
+                created_at: row.get("contact_created_at"),
+                updated_at: row.get("contact_updated_at"),
+                created_by: row.get("contact_created_by"),
+                updated_by: row.get("contact_updated_by"),
+                deleted_at: row.get("contact_deleted_at"),
+                version: row.get("contact_version"),
+                groups: row.get("contact_groups"),
+                has_photo: row.get("contact_has_photo"),
+                custom_fields: row
+                    .get::<Json<Map<String, Value>>, _>("contact_custom_fields")
+                    .0,
+            });
+        }
+    }
+    address_books
+}
+
 #[async_trait]
 impl IAddressBookRepository for AddressBookRepository {
+    #[tracing::instrument(skip_all)]
     async fn get_all_address_books(
         &self,
         limit: Option<i32>,
         offset: i32,
+        filter: ListFilter,
+        view: AddressBookView,
     ) -> Result<Vec<AddressBook>, handle_errors::Error> {
-        let q = r#"
-            SELECT ab.id AS address_book_id, ab.name AS address_book_name,
-            c.id AS contact_id, c.name AS contact_name, c.phone_number c.email
-            FROM address_books AS ab
-            LEFT JOIN contacts AS c ON ab.id = c.address_book_id LIMIT $1 OFFSET $2
-         "#;
-
-        match sqlx::query(q)
+        let order_by = filter.order_by("ab", &address_book::SORT_FIELDS)?;
+        let mut conn = self.pool.acquire().await?;
+        ensure_locale(&mut conn, filter.locale.as_deref()).await?;
+
+        let (columns, join, contact_order) = view_sql(&view);
+        let contact_count = contact_count_sql(&view);
+        let q = format!(
+            "SELECT {columns}
+             FROM (SELECT *, {contact_count} FROM address_books AS ab
+                   WHERE ab.deleted_at IS NULL
+                   AND ($3::timestamptz IS NULL OR ab.updated_at >= $3)
+                   ORDER BY {order_by} LIMIT $1 OFFSET $2) AS ab
+             {join}
+             ORDER BY {order_by}{contact_order}"
+        );
+
+        match sqlx::query(&q)
             .bind(limit)
             .bind(offset)
-            .map(|row: PgRow| {
-                let mut contacts = vec![];
-                let address_book_id: AddressBookId = AddressBookId(row.get("address_book_id"));
-                let address_book_name: String = row.get("address_book_name");
-                let contact_id: ContactId = ContactId(row.get("contact_id"));
-                let contact_name: String = row.get("contact_name");
-                let email: Option<String> = row.get("email");
-                let address = row.get("address");
-                let phone_number: Option<String> = row.get("phone_number");
-                let contact = Contact {
-                    id: contact_id,
-                    name: contact_name,
-                    address,
-                    phone_number,
-                    email,
-                    address_book_id: address_book_id.clone(),
-                };
-                contacts.push(contact);
-
-                AddressBook {
-                    id: address_book_id,
-                    address_book_name,
-                    contacts,
-                }
-            })
-            .fetch_all(&self.pool)
+            .bind(filter.updated_since)
+            .fetch_all(&mut *conn)
             .await
         {
-            Ok(address_books) => Ok(address_books),
+            Ok(rows) => Ok(group_address_books(rows, &view)),
             Err(e) => Err(handle_errors::Error::DatabaseQueryError(e)),
         }
     }
 
+    #[tracing::instrument(skip_all, fields(%address_book_id))]
     async fn get_address_book_by_id(
         &self,
         address_book_id: i32,
+        view: AddressBookView,
     ) -> Result<AddressBook, handle_errors::Error> {
-        let q = "SELECT a.id AS address_book_id, a.address_book_name, c.id AS contact_id, c.name, c.address, c.phone_number, c.email
-                    FROM address_books AS a
-                    LEFT JOIN contacts AS c ON a.id = c.address_book_id
-                    WHERE a.id = $1";
-        match sqlx::query(q)
+        let (columns, join, contact_order) = view_sql(&view);
+        let contact_count = contact_count_sql(&view);
+        let q = format!(
+            "SELECT {columns}
+             FROM (SELECT *, {contact_count} FROM address_books AS ab
+                   WHERE ab.id = $1 AND ab.deleted_at IS NULL) AS ab
+             {join}
+             ORDER BY ab.id{contact_order}"
+        );
+        match sqlx::query(&q)
             .bind(address_book_id)
-            .map(|row: PgRow| {
-                let mut contacts = vec![];
-                let address_book_id = AddressBookId(row.get("address_book_id"));
-                let address_book_name = row.get("address_book_name");
-                let contact_id = ContactId(row.get("contact_id"));
-                let name = row.get("name");
-                let address = row.get("address");
-                let phone_number = row.get("phone_number");
-                let email = row.get("email");
-                contacts.push(Contact {
-                    id: contact_id,
-                    name,
-                    address,
-                    phone_number,
-                    email,
-                    address_book_id: address_book_id.clone(),
-                });
-                AddressBook {
-                    id: address_book_id,
-                    address_book_name,
-                    contacts,
-                }
-            })
-            .fetch_optional(&self.pool)
+            .fetch_all(&self.pool)
             .await
         {
-            Ok(address_book) => match address_book {
+            Ok(rows) => match group_address_books(rows, &view).pop() {
                 Some(address_book) => Ok(address_book),
                 None => Err(handle_errors::Error::AddressBookNotFound),
             },
@@ -142,66 +286,70 @@ impl IAddressBookRepository for AddressBookRepository {
         }
     }
 
+    #[tracing::instrument(skip_all)]
+    async fn get_address_books_by_ids(
+        &self,
+        ids: Vec<i32>,
+        view: AddressBookView,
+    ) -> Result<Vec<AddressBook>, handle_errors::Error> {
+        let (columns, join, contact_order) = view_sql(&view);
+        let contact_count = contact_count_sql(&view);
+        let q = format!(
+            "SELECT {columns}
+             FROM (SELECT *, {contact_count} FROM address_books AS ab
+                   WHERE ab.id = ANY($1) AND ab.deleted_at IS NULL) AS ab
+             {join}
+             ORDER BY ab.id{contact_order}"
+        );
+        match sqlx::query(&q).bind(ids).fetch_all(&self.pool).await {
+            Ok(rows) => Ok(group_address_books(rows, &view)),
+            Err(e) => Err(handle_errors::Error::DatabaseQueryError(e)),
+        }
+    }
+
+    #[tracing::instrument(skip_all)]
     async fn create_address_book(
         &self,
         address_book_name: String,
+        actor: Option<String>,
     ) -> Result<AddressBook, handle_errors::Error> {
-        let q = "INSERT INTO address_books (address_book_name)
-                       VALUES ($1) RETURNING id, address_book_name";
-        match sqlx::query(q)
+        let mut tx = self.pool.begin().await?;
+
+        let q = format!(
+            "INSERT INTO address_books (address_book_name, created_by, updated_by)
+             VALUES ($1, $2, $2) RETURNING {BOOK_COLUMNS}"
+        );
+        let address_book = sqlx::query(&q)
             .bind(address_book_name)
-            .map(|row: PgRow| AddressBook {
-                id: AddressBookId(row.get("id")),
-                address_book_name: row.get("address_book_name"),
-                contacts: vec![],
-            })
-            .fetch_one(&self.pool)
-            .await
-        {
-            Ok(address_book) => Ok(address_book),
-            Err(e) => Err(handle_errors::Error::DatabaseQueryError(e)),
-        }
+            .bind(&actor)
+            .map(|row: PgRow| address_book_from_row(&row))
+            .fetch_one(&mut *tx)
+            .await?;
+
+        let event = NewAuditEvent::address_book(AuditAction::Create, actor, None, &address_book);
+        record_event(&mut tx, event).await?;
+        tx.commit().await?;
+
+        Ok(address_book)
     }
 
+    #[tracing::instrument(skip_all)]
     async fn find_address_book_by_name(
         &self,
         name: String,
     ) -> Result<AddressBook, handle_errors::Error> {
-        let q = "SELECT a.id AS address_book_id, a.address_book_name, c.id AS contact_id, c.name, c.address, c.phone_number, c.email
-                        FROM address_books AS a
-                        LEFT JOIN contacts AS c ON a.id = c.address_book_id
-                        WHERE a.address_book_name = $1";
-        match sqlx::query(q)
-            .bind(name)
-            .map(|row: PgRow| {
-                let mut contacts = vec![];
-                let address_book_id = AddressBookId(row.get("address_book_id"));
-                let address_book_name = row.get("address_book_name");
-                let contact_id = row.get("contact_id");
-                let name = row.get("name");
-                let address = row.get("address");
-                let phone_number = row.get("phone_number");
-                let email = row.get("email");
-                let contact = Contact {
-                    id: ContactId(contact_id),
-                    name,
-                    address,
-                    phone_number,
-                    email,
-                    address_book_id: address_book_id.clone(),
-                };
-                contacts.push(contact);
-
-                AddressBook {
-                    id: address_book_id,
-                    address_book_name,
-                    contacts,
-                }
-            })
-            .fetch_optional(&self.pool)
-            .await
-        {
-            Ok(address_book) => match address_book {
+        let view = AddressBookView::default();
+        let (columns, join, contact_order) = view_sql(&view);
+        let contact_count = contact_count_sql(&view);
+        let q = format!(
+            "SELECT {columns}
+             FROM (SELECT *, {contact_count} FROM address_books AS ab
+                   WHERE ab.address_book_name = $1 AND ab.deleted_at IS NULL) AS ab
+             {join}
+             ORDER BY ab.id{contact_order}"
+        );
+        match sqlx::query(&q).bind(name).fetch_all(&self.pool).await {
+            Ok(rows) => match group_address_books(rows, &view).pop() {
                 Some(address_book) => Ok(address_book),
                 None => Err(handle_errors::Error::AddressBookNotFound),
             },
@@ -209,33 +357,334 @@ impl IAddressBookRepository for AddressBookRepository {
         }
     }
 
-    async fn delete_address_book(&self, id: i32) -> Result<(), handle_errors::Error> {
-        let q = "DELETE FROM address_books WHERE id = $1";
-        match sqlx::query(q).bind(id).execute(&self.pool).await {
-            Ok(_) => Ok(()),
-            Err(e) => Err(handle_errors::Error::DatabaseQueryError(e)),
+    #[tracing::instrument(skip_all, fields(address_book_id = %id))]
+    async fn delete_address_book(
+        &self,
+        id: i32,
+        actor: Option<String>,
+        if_match: IfMatch,
+    ) -> Result<(), handle_errors::Error> {
+        let mut tx = self.pool.begin().await?;
+        let Some(before) = lock_address_book(&mut tx, id, false).await? else {
+            return Err(handle_errors::Error::AddressBookNotFound);
+        };
+        if !if_match.matches(&before.etag()) {
+            return Err(handle_errors::Error::PreconditionFailed);
         }
+
+        let q = format!(
+            "UPDATE address_books SET deleted_at = now(), updated_by = $2
+             WHERE id = $1 RETURNING {BOOK_COLUMNS}"
+        );
+        let address_book = sqlx::query(&q)
+            .bind(id)
+            .bind(&actor)
+            .map(|row: PgRow| address_book_from_row(&row))
+            .fetch_one(&mut *tx)
+            .await?;
+
+        let event =
+            NewAuditEvent::address_book(AuditAction::Delete, actor, Some(&before), &address_book);
+        record_event(&mut tx, event).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all, fields(address_book_id = %id))]
+    async fn restore_address_book(
+        &self,
+        id: i32,
+        actor: Option<String>,
+    ) -> Result<AddressBook, handle_errors::Error> {
+        let mut tx = self.pool.begin().await?;
+        let Some(before) = lock_address_book(&mut tx, id, true).await? else {
+            return Err(handle_errors::Error::AddressBookNotFound);
+        };
+
+        let q = format!(
+            "UPDATE address_books SET deleted_at = NULL, updated_by = $2
+             WHERE id = $1 RETURNING {BOOK_COLUMNS}"
+        );
+        let address_book = sqlx::query(&q)
+            .bind(id)
+            .bind(&actor)
+            .map(|row: PgRow| address_book_from_row(&row))
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|e| match &e {
+                // The name has been given to another book since this one was deleted.
+                sqlx::Error::Database(db) if db.is_unique_violation() => {
+                    handle_errors::Error::Conflict(format!(
+                        "an address book named \"{}\" already exists",
+                        before.address_book_name
+                    ))
+                }
+                _ => handle_errors::Error::DatabaseQueryError(e),
+            })?;
+
+        let event =
+            NewAuditEvent::address_book(AuditAction::Restore, actor, Some(&before), &address_book);
+        record_event(&mut tx, event).await?;
+        tx.commit().await?;
+
+        Ok(address_book)
     }
 
+    #[tracing::instrument(skip_all, fields(address_book_id = %id))]
     async fn update_address_book(
         &self,
         id: i32,
         address_book_name: &str,
+        actor: Option<String>,
+        if_match: IfMatch,
     ) -> Result<AddressBook, handle_errors::Error> {
-        let q = "UPDATE address_books SET address_book_name = $1 WHERE id = $2 RETURNING id, address_book_name";
-        match sqlx::query(q)
+        let mut tx = self.pool.begin().await?;
+        let Some(before) = lock_address_book(&mut tx, id, false).await? else {
+            return Err(handle_errors::Error::AddressBookNotFound);
+        };
+        if !if_match.matches(&before.etag()) {
+            return Err(handle_errors::Error::PreconditionFailed);
+        }
+
+        let q = format!(
+            "UPDATE address_books SET address_book_name = $1, updated_by = $3
+             WHERE id = $2 RETURNING {BOOK_COLUMNS}"
+        );
+        let address_book = sqlx::query(&q)
             .bind(address_book_name)
             .bind(id)
-            .map(|row: PgRow| AddressBook {
-                id: AddressBookId(row.get("id")),
-                address_book_name: row.get("address_book_name"),
-                contacts: vec![],
-            })
-            .fetch_one(&self.pool)
+            .bind(&actor)
+            .map(|row: PgRow| address_book_from_row(&row))
+            .fetch_one(&mut *tx)
+            .await?;
+
+        let event =
+            NewAuditEvent::address_book(AuditAction::Update, actor, Some(&before), &address_book);
+        record_event(&mut tx, event).await?;
+        tx.commit().await?;
+
+        Ok(address_book)
+    }
+
+    #[tracing::instrument(skip_all, fields(address_book_id = %id))]
+    async fn get_address_book_stats(
+        &self,
+        id: i32,
+    ) -> Result<AddressBookStats, handle_errors::Error> {
+        let mut conn = self.pool.acquire().await?;
+
+        // A contact counts as a duplicate when an older contact has the same display name,
+        // email or phone number digits, compared case-insensitively.
+        let q = r"WITH live AS (
+                 SELECT c.*, regexp_replace(c.phone_number, '\D', '', 'g') AS phone_digits
+                 FROM contacts AS c WHERE c.address_book_id = $1 AND c.deleted_at IS NULL
+             ), ranked AS (
+                 SELECT live.*,
+                 row_number() OVER (PARTITION BY lower(display_name) ORDER BY id) AS name_rank,
+                 row_number() OVER (PARTITION BY lower(email) ORDER BY id) AS email_rank,
+                 row_number() OVER (PARTITION BY phone_digits ORDER BY id) AS phone_rank
+                 FROM live
+             )
+             SELECT ab.id AS address_book_id, count(r.id) AS contact_count,
+             count(r.id) FILTER (WHERE r.email <> '') AS with_email,
+             count(r.id) FILTER (WHERE r.phone_number <> '') AS with_phone_number,
+             count(r.id) FILTER (WHERE r.name_rank > 1
+                 OR (r.email <> '' AND r.email_rank > 1)
+                 OR (r.phone_digits <> '' AND r.phone_rank > 1)) AS duplicates,
+             GREATEST(ab.updated_at, max(r.updated_at)) AS last_updated_at
+             FROM address_books AS ab LEFT JOIN ranked AS r ON true
+             WHERE ab.id = $1 AND ab.deleted_at IS NULL
+             GROUP BY ab.id";
+        let Some(row) = sqlx::query(q).bind(id).fetch_optional(&mut *conn).await? else {
+            return Err(handle_errors::Error::AddressBookNotFound);
+        };
+
+        // Postal addresses end with the country, so the last comma- or line-separated part
+        // is taken as one. Single-part addresses have no country.
+        let q = r"SELECT NULLIF(btrim(substring(address FROM '[,\n]([^,\n]*)$')), '') AS name,
+                 count(*) AS count
+             FROM contacts WHERE address_book_id = $1 AND deleted_at IS NULL
+             GROUP BY 1 ORDER BY count DESC, name NULLS LAST";
+        let by_country = sqlx::query(q)
+            .bind(id)
+            .map(|row: PgRow| bucket_from_row(&row))
+            .fetch_all(&mut *conn)
+            .await?;
+
+        let q = "SELECT g.name, count(c.id) AS count FROM contact_groups AS g
+             LEFT JOIN contact_group_members AS m ON m.group_id = g.id
+             LEFT JOIN contacts AS c ON c.id = m.contact_id AND c.deleted_at IS NULL
+             WHERE g.address_book_id = $1
+             GROUP BY g.id ORDER BY count DESC, g.name";
+        let by_group = sqlx::query(q)
+            .bind(id)
+            .map(|row: PgRow| bucket_from_row(&row))
+            .fetch_all(&mut *conn)
+            .await?;
+
+        let contact_count: i64 = row.get("contact_count");
+        let with_email: i64 = row.get("with_email");
+        let with_phone_number: i64 = row.get("with_phone_number");
+        Ok(AddressBookStats {
+            address_book_id: AddressBookId(row.get("address_book_id")),
+            contact_count,
+            with_email,
+            without_email: contact_count - with_email,
+            with_phone_number,
+            without_phone_number: contact_count - with_phone_number,
+            duplicates: row.get("duplicates"),
+            last_updated_at: row.get("last_updated_at"),
+            by_country,
+            by_group,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::contact_repo::{ContactRepository, IContactRepository};
+    use crate::repositories::group_repo::{GroupRepository, IGroupRepository};
+    use crate::types::contact::NewContact;
+
+    // Needs a database server: DATABASE_URL=postgres://... cargo test -- --ignored
+    #[sqlx::test]
+    #[ignore]
+    async fn test_restore_refuses_name_taken_since(pool: sqlx::PgPool) {
+        let repo = AddressBookRepository::new(pool);
+        let old = repo
+            .create_address_book(String::from("Friends"), None)
             .await
-        {
-            Ok(address_book) => Ok(address_book),
-            Err(e) => Err(handle_errors::Error::DatabaseQueryError(e)),
+            .unwrap();
+        repo.delete_address_book(old.id.0, None, IfMatch::default())
+            .await
+            .unwrap();
+        repo.create_address_book(String::from("Friends"), None)
+            .await
+            .unwrap();
+
+        let result = repo.restore_address_book(old.id.0, None).await;
+        assert!(matches!(result, Err(handle_errors::Error::Conflict(_))));
+    }
+
+    fn bucket(name: Option<&str>, count: i64) -> StatsBucket {
+        StatsBucket {
+            name: name.map(String::from),
+            count,
         }
     }
+
+    #[sqlx::test]
+    #[ignore]
+    async fn test_stats_count_live_contacts_of_the_book(pool: sqlx::PgPool) {
+        let repo = AddressBookRepository::new(pool.clone());
+        let contacts = ContactRepository::new(pool.clone());
+        let groups = GroupRepository::new(pool.clone());
+        let book = repo
+            .create_address_book(String::from("Friends"), None)
+            .await
+            .unwrap();
+        let other = repo
+            .create_address_book(String::from("Work"), None)
+            .await
+            .unwrap();
+        let add = |book: i32, name: &str, address: &str, email: &str, phone: &str| {
+            let contact = NewContact {
+                name: String::from(name),
+                address: String::from(address),
+                email: Some(String::from(email)).filter(|email| !email.is_empty()),
+                phone_number: Some(String::from(phone)).filter(|phone| !phone.is_empty()),
+                ..Default::default()
+            };
+            let contacts = &contacts;
+            async move {
+                contacts
+                    .add_contact_to_address_book(book, contact, None)
+                    .await
+                    .unwrap()
+            }
+        };
+
+        let ann = add(
+            book.id.0,
+            "Ann",
+            "1 Main St, Springfield, USA",
+            "ann@example.com",
+            "+1 555 0100",
+        )
+        .await;
+        // Same name as Ann, in other letters.
+        add(book.id.0, "ANN", "Unter den Linden 1\nGermany", "", "").await;
+        // Same email and phone digits as Ann.
+        add(
+            book.id.0,
+            "Bob",
+            "2 High St, USA",
+            "Ann@Example.com",
+            "+1 (555) 0100",
+        )
+        .await;
+        add(book.id.0, "Cy", "Nowhere", "", "+1 555 0199").await;
+        let gone = add(book.id.0, "Dee", "3 Low St, France", "dee@example.com", "").await;
+        add(
+            other.id.0,
+            "Eve",
+            "4 Side St, France",
+            "eve@example.com",
+            "",
+        )
+        .await;
+
+        let members = groups
+            .create_group(book.id.0, String::from("Close"), None)
+            .await
+            .unwrap();
+        groups
+            .create_group(book.id.0, String::from("Empty"), None)
+            .await
+            .unwrap();
+        groups
+            .add_member(book.id.0, members.id.0, ann.id.0, None)
+            .await
+            .unwrap();
+        groups
+            .add_member(book.id.0, members.id.0, gone.id.0, None)
+            .await
+            .unwrap();
+        contacts
+            .delete_contact(gone.id.0, book.id.0, None, IfMatch::default())
+            .await
+            .unwrap();
+
+        let stats = repo.get_address_book_stats(book.id.0).await.unwrap();
+        assert_eq!(stats.contact_count, 4);
+        assert_eq!((stats.with_email, stats.without_email), (2, 2));
+        assert_eq!(
+            (stats.with_phone_number, stats.without_phone_number),
+            (3, 1)
+        );
+        assert_eq!(stats.duplicates, 2);
+        assert_eq!(
+            stats.by_country,
+            [
+                bucket(Some("USA"), 2),
+                bucket(Some("Germany"), 1),
+                bucket(None, 1)
+            ]
+        );
+        assert_eq!(
+            stats.by_group,
+            [bucket(Some("Close"), 1), bucket(Some("Empty"), 0)]
+        );
+
+        repo.delete_address_book(other.id.0, None, IfMatch::default())
+            .await
+            .unwrap();
+        let result = repo.get_address_book_stats(other.id.0).await;
+        assert!(matches!(
+            result,
+            Err(handle_errors::Error::AddressBookNotFound)
+        ));
+    }
 }
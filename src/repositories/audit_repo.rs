@@ -0,0 +1,226 @@
+use crate::repositories::contact_repo::ensure_live_address_book;
+use crate::repositories::webhook_repo::enqueue_webhooks;
+use crate::types::address_book::AddressBookId;
+use crate::types::audit::{AuditEvent, EntityType, NewAuditEvent};
+use async_trait::async_trait;
+
+use sqlx::postgres::PgRow;
+use sqlx::{PgConnection, PgPool, Row};
+
+#[cfg(test)]
+use mockall::{predicate::*, *};
+
+#[async_trait]
+#[cfg_attr(test, automock)]
+pub trait IAuditRepository {
+    /// Events for a live book itself and every contact in it, including contacts moved out
+    /// of it, newest first.
+    async fn get_address_book_history(
+        &self,
+        address_book_id: i32,
+        limit: Option<i32>,
+        offset: i32,
+    ) -> Result<Vec<AuditEvent>, handle_errors::Error>;
+
+    async fn get_contact_history(
+        &self,
+        address_book_id: i32,
+        contact_id: i32,
+        limit: Option<i32>,
+        offset: i32,
+    ) -> Result<Vec<AuditEvent>, handle_errors::Error>;
+}
+
+pub struct AuditRepository {
+    pool: PgPool,
+}
+
+impl AuditRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+/// Appends an audit event on the caller's connection, so it commits or rolls back
+/// together with the mutation it describes. The event is also queued for any webhook
+/// subscribed to it.
+pub(crate) async fn record_event(
+    conn: &mut PgConnection,
+    event: NewAuditEvent,
+) -> Result<(), sqlx::Error> {
+    let q = "INSERT INTO audit_events
+             (entity_type, entity_id, address_book_id, source_address_book_id, action, actor,
+              changes)
+             VALUES ($1, $2, $3, $4, $5, $6, $7)";
+    sqlx::query(q)
+        .bind(event.entity_type.as_str())
+        .bind(event.entity_id)
+        .bind(event.address_book_id)
+        .bind(event.source_address_book_id)
+        .bind(event.action.as_str())
+        .bind(&event.actor)
+        .bind(&event.changes)
+        .execute(&mut *conn)
+        .await?;
+    enqueue_webhooks(conn, &event).await
+}
+
+fn audit_event_from_row(row: PgRow) -> AuditEvent {
+    AuditEvent {
+        id: row.get("id"),
+        entity_type: row.get("entity_type"),
+        entity_id: row.get("entity_id"),
+        address_book_id: AddressBookId(row.get("address_book_id")),
+        action: row.get("action"),
+        actor: row.get("actor"),
+        changes: row.get("changes"),
+        created_at: row.get("created_at"),
+    }
+}
+
+#[async_trait]
+impl IAuditRepository for AuditRepository {
+    #[tracing::instrument(skip_all, fields(%address_book_id))]
+    async fn get_address_book_history(
+        &self,
+        address_book_id: i32,
+        limit: Option<i32>,
+        offset: i32,
+    ) -> Result<Vec<AuditEvent>, handle_errors::Error> {
+        let mut conn = self.pool.acquire().await?;
+        ensure_live_address_book(&mut conn, address_book_id).await?;
+
+        // Contacts moved out of the book are recorded against their new book.
+        let q = "SELECT * FROM audit_events
+                 WHERE address_book_id = $1 OR source_address_book_id = $1
+                 ORDER BY id DESC LIMIT $2 OFFSET $3";
+        match sqlx::query(q)
+            .bind(address_book_id)
+            .bind(limit)
+            .bind(offset)
+            .map(audit_event_from_row)
+            .fetch_all(&mut *conn)
+            .await
+        {
+            Ok(events) => Ok(events),
+            Err(e) => Err(handle_errors::Error::DatabaseQueryError(e)),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, %contact_id))]
+    async fn get_contact_history(
+        &self,
+        address_book_id: i32,
+        contact_id: i32,
+        limit: Option<i32>,
+        offset: i32,
+    ) -> Result<Vec<AuditEvent>, handle_errors::Error> {
+        // The full history follows a contact across moves, from any book it has been in.
+        let q = "SELECT * FROM audit_events
+                 WHERE entity_type = $1 AND entity_id = $2
+                 AND EXISTS (SELECT 1 FROM audit_events AS e
+                             WHERE e.entity_type = $1 AND e.entity_id = $2
+                             AND e.address_book_id = $3)
+                 ORDER BY id DESC LIMIT $4 OFFSET $5";
+        match sqlx::query(q)
+            .bind(EntityType::Contact.as_str())
+            .bind(contact_id)
+            .bind(address_book_id)
+            .bind(limit)
+            .bind(offset)
+            .map(audit_event_from_row)
+            .fetch_all(&self.pool)
+            .await
+        {
+            Ok(events) => Ok(events),
+            Err(e) => Err(handle_errors::Error::DatabaseQueryError(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::address_book_repo::{AddressBookRepository, IAddressBookRepository};
+    use crate::repositories::contact_repo::{ContactRepository, IContactRepository};
+    use crate::types::contact::{NewContact, OnConflict};
+    use crate::types::precondition::IfMatch;
+
+    // Needs a database server: DATABASE_URL=postgres://... cargo test -- --ignored
+    #[sqlx::test]
+    #[ignore]
+    async fn test_book_history_keeps_contacts_moved_out(pool: PgPool) {
+        let books = AddressBookRepository::new(pool.clone());
+        let home = books
+            .create_address_book(String::from("Home"), None)
+            .await
+            .unwrap();
+        let work = books
+            .create_address_book(String::from("Work"), None)
+            .await
+            .unwrap();
+        let contacts = ContactRepository::new(pool.clone());
+        let contact = NewContact {
+            name: String::from("Ann"),
+            address: String::from("1 Main St"),
+            ..Default::default()
+        };
+        let ann = contacts
+            .add_contact_to_address_book(home.id.0, contact, None)
+            .await
+            .unwrap();
+        contacts
+            .move_contacts(
+                vec![ann.id.0],
+                work.id.0,
+                OnConflict::Allow,
+                None,
+                IfMatch::default(),
+            )
+            .await
+            .unwrap();
+
+        let repo = AuditRepository::new(pool.clone());
+        let actions = |events: Vec<AuditEvent>| {
+            events
+                .into_iter()
+                .map(|event| (event.entity_type, event.action))
+                .collect::<Vec<_>>()
+        };
+        let history = repo
+            .get_address_book_history(home.id.0, None, 0)
+            .await
+            .unwrap();
+        assert_eq!(
+            actions(history),
+            [
+                (String::from("contact"), String::from("move")),
+                (String::from("contact"), String::from("create")),
+                (String::from("address_book"), String::from("create")),
+            ]
+        );
+        let history = repo
+            .get_address_book_history(work.id.0, None, 0)
+            .await
+            .unwrap();
+        assert_eq!(
+            actions(history),
+            [
+                (String::from("contact"), String::from("move")),
+                (String::from("address_book"), String::from("create")),
+            ]
+        );
+
+        books
+            .delete_address_book(home.id.0, None, IfMatch::default())
+            .await
+            .unwrap();
+        for id in [home.id.0, work.id.0 + 1] {
+            let result = repo.get_address_book_history(id, None, 0).await;
+            assert!(matches!(
+                result,
+                Err(handle_errors::Error::AddressBookNotFound)
+            ));
+        }
+    }
+}
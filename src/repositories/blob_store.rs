@@ -0,0 +1,108 @@
+use async_trait::async_trait;
+use std::io::ErrorKind;
+use std::path::{Component, Path, PathBuf};
+
+#[cfg(test)]
+use mockall::{predicate::*, *};
+
+/// Storage for binary objects such as contact photos, addressed by `/`-separated keys.
+#[async_trait]
+#[cfg_attr(test, automock)]
+pub trait IBlobStore {
+    /// Stores `data` under `key`, replacing any previous object.
+    async fn put(&self, key: &str, data: Vec<u8>) -> Result<(), handle_errors::Error>;
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, handle_errors::Error>;
+
+    /// Removes the object under `key`. Removing a missing object is not an error.
+    async fn delete(&self, key: &str) -> Result<(), handle_errors::Error>;
+}
+
+/// Keeps each object in a file below `root`.
+#[derive(Debug, Clone)]
+pub struct LocalBlobStore {
+    root: PathBuf,
+}
+
+impl LocalBlobStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Maps a key to a file below the root, refusing keys that would escape it.
+    fn path(&self, key: &str) -> Result<PathBuf, handle_errors::Error> {
+        let relative = Path::new(key);
+        let safe = !key.is_empty()
+            && relative
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)));
+        if !safe {
+            return Err(handle_errors::Error::StorageError(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid blob key {key:?}"),
+            )));
+        }
+        Ok(self.root.join(relative))
+    }
+}
+
+#[async_trait]
+impl IBlobStore for LocalBlobStore {
+    #[tracing::instrument(skip_all)]
+    async fn put(&self, key: &str, data: Vec<u8>) -> Result<(), handle_errors::Error> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        // Write to a sibling file first so readers never see a partial object.
+        let partial = path.with_extension("partial");
+        tokio::fs::write(&partial, data).await?;
+        tokio::fs::rename(&partial, &path).await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, handle_errors::Error> {
+        match tokio::fs::read(self.path(key)?).await {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(handle_errors::Error::StorageError(e)),
+        }
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn delete(&self, key: &str) -> Result<(), handle_errors::Error> {
+        match tokio::fs::remove_file(self.path(key)?).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(handle_errors::Error::StorageError(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_local_blob_store_round_trip() {
+        let root = std::env::temp_dir().join(format!("blob-store-test-{}", std::process::id()));
+        let store = LocalBlobStore::new(&root);
+
+        store
+            .put("contacts/1/original", vec![1, 2, 3])
+            .await
+            .unwrap();
+        assert_eq!(
+            store.get("contacts/1/original").await.unwrap(),
+            Some(vec![1, 2, 3])
+        );
+
+        store.delete("contacts/1/original").await.unwrap();
+        store.delete("contacts/1/original").await.unwrap();
+        assert_eq!(store.get("contacts/1/original").await.unwrap(), None);
+        assert!(store.get("../outside").await.is_err());
+
+        tokio::fs::remove_dir_all(root).await.unwrap();
+    }
+}
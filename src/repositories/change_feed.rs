@@ -0,0 +1,30 @@
+use tokio::sync::broadcast;
+
+/// The Postgres channel contact changes are announced on, with the book id as payload.
+pub const CHANGE_CHANNEL: &str = "contact_changes";
+
+/// Notices a slow stream may leave unread before it starts missing them.
+const CAPACITY: usize = 1024;
+
+/// Relays notifications about contact changes to the open event streams. A notice only says
+/// which book changed; each stream reads the changes themselves.
+#[derive(Debug, Clone)]
+pub struct ChangeFeed {
+    sender: broadcast::Sender<i32>,
+}
+
+impl ChangeFeed {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CAPACITY);
+        Self { sender }
+    }
+
+    pub fn publish(&self, address_book_id: i32) {
+        // Nobody listening is not an error.
+        let _ = self.sender.send(address_book_id);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<i32> {
+        self.sender.subscribe()
+    }
+}
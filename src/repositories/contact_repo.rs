@@ -1,26 +1,58 @@
+use crate::repositories::audit_repo::record_event;
+use crate::repositories::field_repo::load_fields;
 use crate::types::address_book::AddressBookId;
-use crate::types::contact::{Contact, ContactId};
+use crate::types::audit::{AuditAction, NewAuditEvent};
+use crate::types::bulk::{self, BulkItemResult, BulkOperation};
+use crate::types::changes::{ChangeKind, ChangeLog, ChangePosition, ContactEvent};
+use crate::types::contact::{
+    self, merge_custom_fields, Contact, ContactDetails, ContactFilter, ContactId, ContactPatch,
+    NewContact, OnConflict,
+};
+use crate::types::custom_field::{self, CustomField};
+use crate::types::precondition::IfMatch;
+use crate::types::ListFilter;
 
 use async_trait::async_trait;
+use axum::http::StatusCode;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde_json::{Map, Value};
 use sqlx::postgres::PgRow;
-use sqlx::PgPool;
-use sqlx::Row;
+use sqlx::types::Json;
+use sqlx::{Acquire, PgConnection, PgPool, Postgres, QueryBuilder, Row, Transaction};
+
+#[cfg(test)]
+use mockall::{predicate::*, *};
+
 #[async_trait]
+#[cfg_attr(test, automock)]
 pub trait IContactRepository {
     async fn get_address_book_contacts(
         &self,
         address_book_id: i32,
         limit: Option<i32>,
         offset: i32,
+        filter: ListFilter,
+        contact_filter: ContactFilter,
+    ) -> Result<Vec<Contact>, handle_errors::Error>;
+
+    /// The same page of contacts from each book, as `get_address_book_contacts` would list
+    /// it, ordered by book. With `search`, only contacts whose names, email, phone number or
+    /// organization contain it, ignoring case.
+    async fn get_contacts_of_address_books(
+        &self,
+        address_book_ids: Vec<i32>,
+        limit: Option<i32>,
+        offset: i32,
+        filter: ListFilter,
+        contact_filter: ContactFilter,
+        search: Option<String>,
     ) -> Result<Vec<Contact>, handle_errors::Error>;
 
     async fn add_contact_to_address_book(
         &self,
-        name: &str,
-        address: &str,
-        phone_number: Option<String>,
-        email: Option<String>,
         address_book_id: i32,
+        contact: NewContact,
+        actor: Option<String>,
     ) -> Result<Contact, handle_errors::Error>;
 
     async fn get_contact_by_id(
@@ -32,19 +64,105 @@ pub trait IContactRepository {
     async fn update_contact(
         &self,
         id: i32,
-        name: &str,
-        address: &str,
-        phone_number: Option<String>,
-        email: Option<String>,
         address_book_id: i32,
+        contact: NewContact,
+        actor: Option<String>,
+        if_match: IfMatch,
+    ) -> Result<Contact, handle_errors::Error>;
+
+    /// Applies only the fields present in `patch`, leaving every other column untouched.
+    async fn patch_contact(
+        &self,
+        id: i32,
+        address_book_id: i32,
+        patch: ContactPatch,
+        actor: Option<String>,
+        if_match: IfMatch,
     ) -> Result<Contact, handle_errors::Error>;
 
     async fn delete_contact(
         &self,
         id: i32,
         address_book_id: i32,
+        actor: Option<String>,
+        if_match: IfMatch,
     ) -> Result<(), handle_errors::Error>;
 
+    async fn restore_contact(
+        &self,
+        id: i32,
+        address_book_id: i32,
+        actor: Option<String>,
+    ) -> Result<Contact, handle_errors::Error>;
+
+    /// Moves live contacts into another live book in one transaction, returning every
+    /// contact in its final state. `if_match` is checked against each contact. There are no
+    /// per-book permissions: both books only have to be live.
+    async fn move_contacts(
+        &self,
+        ids: Vec<i32>,
+        target_address_book_id: i32,
+        on_conflict: OnConflict,
+        actor: Option<String>,
+        if_match: IfMatch,
+    ) -> Result<Vec<Contact>, handle_errors::Error>;
+
+    async fn copy_contact(
+        &self,
+        id: i32,
+        target_address_book_id: i32,
+        on_conflict: OnConflict,
+        actor: Option<String>,
+    ) -> Result<Contact, handle_errors::Error>;
+
+    /// Applies a batch of operations in one transaction. When `atomic` the first failing
+    /// operation rolls back the whole batch; otherwise each one succeeds or fails on its own.
+    async fn bulk_contacts(
+        &self,
+        address_book_id: i32,
+        operations: Vec<BulkOperation>,
+        actor: Option<String>,
+        atomic: bool,
+    ) -> Result<Vec<BulkItemResult>, handle_errors::Error>;
+
+    /// Creates all of `contacts` in one transaction, or none of them.
+    async fn import_contacts(
+        &self,
+        address_book_id: i32,
+        contacts: Vec<NewContact>,
+        actor: Option<String>,
+    ) -> Result<Vec<Contact>, handle_errors::Error>;
+
+    /// Reads a live book's change log after `since` in one snapshot: the first change to each
+    /// contact and the live contacts among them, or every live contact when the log no longer
+    /// covers `since`.
+    async fn get_contact_changes(
+        &self,
+        address_book_id: i32,
+        since: Option<i64>,
+    ) -> Result<ChangeLog, handle_errors::Error>;
+
+    /// Where a live book's change log stands.
+    async fn get_change_position(
+        &self,
+        address_book_id: i32,
+    ) -> Result<ChangePosition, handle_errors::Error>;
+
+    /// Up to `limit` changes to a live book's contacts after `after`, oldest first.
+    async fn get_contact_events(
+        &self,
+        address_book_id: i32,
+        after: i64,
+        limit: i64,
+    ) -> Result<Vec<ContactEvent>, handle_errors::Error>;
+
+    /// Forgets changes recorded before `changed_before`, moving each book's horizon past them,
+    /// and returns the number of changes removed.
+    async fn prune_contact_changes(
+        &self,
+        changed_before: DateTime<Utc>,
+    ) -> Result<u64, handle_errors::Error>;
+
     //async fn find_contact_by_name(
     //  &self,
     //name: &str,
@@ -62,28 +180,572 @@ impl ContactRepository {
     }
 }
 
+/// Every contact column plus the names of the groups it belongs to and whether it has a photo.
+pub(crate) const CONTACT_COLUMNS: &str =
+    "*, contact_group_names(id) AS groups, contact_has_photo(id) AS has_photo";
+
+/// Conditions of a contact listing over `contacts AS c`: live contacts of live books, then
+/// `$4` updated since, `$5` group name and `$6` custom field values.
+/// Number fields are compared as numbers, so `42` finds a stored `42.0`.
+const CONTACT_LISTING_CONDITIONS: &str = "c.deleted_at IS NULL
+     AND ($4::timestamptz IS NULL OR c.updated_at >= $4)
+     AND EXISTS (SELECT 1 FROM address_books AS ab
+                 WHERE ab.id = c.address_book_id AND ab.deleted_at IS NULL)
+     AND ($5::text IS NULL OR EXISTS (
+         SELECT 1 FROM contact_group_members AS m
+         JOIN contact_groups AS g ON g.id = m.group_id
+         WHERE m.contact_id = c.id AND g.name = $5))
+     AND NOT EXISTS (SELECT 1 FROM jsonb_each_text($6) AS f WHERE CASE
+         WHEN jsonb_typeof(c.custom_fields -> f.key) = 'number'
+             AND f.value ~ '^[-+]?([0-9]+[.]?[0-9]*|[.][0-9]+)([eE][-+]?[0-9]+)?$'
+             THEN (c.custom_fields ->> f.key)::numeric <> f.value::numeric
+         ELSE c.custom_fields ->> f.key IS DISTINCT FROM f.value END)";
+
+/// An `ILIKE` pattern matching `text` anywhere in a value, or `None` for blank text.
+fn contains_pattern(text: Option<&str>) -> Option<String> {
+    let text = text.map(str::trim).filter(|text| !text.is_empty())?;
+    let escaped = text
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    Some(format!("%{escaped}%"))
+}
+
+/// Where a book's change log stands: `sync_seq` is the latest change to its contacts, counting
+/// pruned ones, or 0 before the first; changes up to `pruned_through` are gone. `book` is the
+/// SQL expression for the book's id.
+pub(crate) fn sync_position_columns(book: &str) -> String {
+    let horizon = format!(
+        "(SELECT pruned_through FROM contact_change_horizons AS h WHERE h.address_book_id = {book})"
+    );
+    format!(
+        "COALESCE(GREATEST((SELECT max(seq) FROM contact_changes AS cc
+             WHERE cc.address_book_id = {book}), {horizon}), 0) AS sync_seq,
+         COALESCE({horizon}, 0) AS pruned_through"
+    )
+}
+
+/// Numbers the changes committed since the last call. Readers of the change log call this
+/// first, in its own transaction, so that what they read has a position no change committing
+/// later can fall below.
+pub(crate) async fn sequence_changes(pool: &PgPool) -> Result<(), sqlx::Error> {
+    sqlx::query("SELECT sequence_contact_changes()")
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+async fn change_position(
+    conn: &mut PgConnection,
+    address_book_id: i32,
+) -> Result<ChangePosition, sqlx::Error> {
+    let q = format!("SELECT {}", sync_position_columns("$1"));
+    sqlx::query(&q)
+        .bind(address_book_id)
+        .map(|row: PgRow| ChangePosition {
+            current: row.get("sync_seq"),
+            pruned_through: row.get("pruned_through"),
+        })
+        .fetch_one(conn)
+        .await
+}
+
+/// The live contacts of a book among `ids`, ordered by id.
+async fn live_contacts(
+    conn: &mut PgConnection,
+    address_book_id: i32,
+    ids: Option<Vec<i32>>,
+) -> Result<Vec<Contact>, sqlx::Error> {
+    let q = format!(
+        "SELECT {CONTACT_COLUMNS} FROM contacts
+         WHERE address_book_id = $1 AND deleted_at IS NULL
+         AND ($2::integer[] IS NULL OR id = ANY($2))
+         ORDER BY id"
+    );
+    sqlx::query(&q)
+        .bind(address_book_id)
+        .bind(ids)
+        .map(contact_from_row)
+        .fetch_all(conn)
+        .await
+}
+
+pub(crate) fn contact_from_row(row: PgRow) -> Contact {
+    Contact {
+        id: ContactId(row.get("id")),
+        name: row.get("name"),
+        address: row.get("address"),
+        phone_number: row.get("phone_number"),
+        email: row.get("email"),
+        details: details_from_row(&row),
+        display_name: row.get("display_name"),
+        sort_key: row.get("sort_key"),
+        address_book_id: AddressBookId(row.get("address_book_id")),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+        created_by: row.get("created_by"),
+        updated_by: row.get("updated_by"),
+        deleted_at: row.get("deleted_at"),
+        version: row.get("version"),
+        groups: row.get("groups"),
+        has_photo: row.get("has_photo"),
+        custom_fields: row.get::<Json<Map<String, Value>>, _>("custom_fields").0,
+    }
+}
+
+pub(crate) fn details_from_row(row: &PgRow) -> ContactDetails {
+    ContactDetails {
+        name_prefix: row.get("name_prefix"),
+        given_name: row.get("given_name"),
+        middle_name: row.get("middle_name"),
+        family_name: row.get("family_name"),
+        name_suffix: row.get("name_suffix"),
+        nickname: row.get("nickname"),
+        organization: row.get("organization"),
+        job_title: row.get("job_title"),
+        birthday: row.get("birthday"),
+        anniversary: row.get("anniversary"),
+        notes: row.get("notes"),
+    }
+}
+
+/// Checks custom field values against the book's definitions.
+fn validate_custom_fields(
+    definitions: &[CustomField],
+    values: &Map<String, Value>,
+) -> Result<Map<String, Value>, handle_errors::Error> {
+    custom_field::validate(definitions, values).map_err(handle_errors::Error::ValidationError)
+}
+
+/// Keeps only the values the target book defines, for contacts leaving their book.
+fn carry_custom_fields(
+    definitions: &[CustomField],
+    values: &Map<String, Value>,
+) -> Result<Map<String, Value>, handle_errors::Error> {
+    let carried = values
+        .iter()
+        .filter(|(name, _)| definitions.iter().any(|d| &d.name == *name))
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+    validate_custom_fields(definitions, &carried)
+}
+
+/// Locks a contact row of a live book for the rest of the transaction, returning its current
+/// state. Contacts of a book in the trash are left alone, like the book itself.
+pub(crate) async fn lock_contact(
+    conn: &mut PgConnection,
+    id: i32,
+    address_book_id: i32,
+    deleted: bool,
+) -> Result<Option<Contact>, sqlx::Error> {
+    let q = "SELECT c.*, contact_group_names(c.id) AS groups,
+             contact_has_photo(c.id) AS has_photo FROM contacts AS c
+             JOIN address_books AS ab ON ab.id = c.address_book_id AND ab.deleted_at IS NULL
+             WHERE c.id = $1 AND c.address_book_id = $2 AND (c.deleted_at IS NOT NULL) = $3
+             FOR UPDATE OF c";
+    sqlx::query(q)
+        .bind(id)
+        .bind(address_book_id)
+        .bind(deleted)
+        .map(contact_from_row)
+        .fetch_optional(conn)
+        .await
+}
+
+/// Gives a contact a new version after a change to data embedded in its representation.
+pub(crate) async fn touch_contact(
+    conn: &mut PgConnection,
+    contact_id: i32,
+    actor: &Option<String>,
+) -> Result<Contact, sqlx::Error> {
+    let q =
+        format!("UPDATE contacts SET updated_by = $2 WHERE id = $1 RETURNING {CONTACT_COLUMNS}");
+    sqlx::query(&q)
+        .bind(contact_id)
+        .bind(actor)
+        .map(contact_from_row)
+        .fetch_one(conn)
+        .await
+}
+
+/// Locks a live contact in a live book by id alone, for requests not scoped to a book.
+async fn lock_live_contact(
+    conn: &mut PgConnection,
+    id: i32,
+) -> Result<Option<Contact>, sqlx::Error> {
+    let q = "SELECT c.*, contact_group_names(c.id) AS groups,
+             contact_has_photo(c.id) AS has_photo FROM contacts AS c
+             JOIN address_books AS ab ON ab.id = c.address_book_id AND ab.deleted_at IS NULL
+             WHERE c.id = $1 AND c.deleted_at IS NULL
+             FOR UPDATE OF c";
+    sqlx::query(q)
+        .bind(id)
+        .map(contact_from_row)
+        .fetch_optional(conn)
+        .await
+}
+
+async fn find_contact_by_name(
+    conn: &mut PgConnection,
+    name: &str,
+    address_book_id: i32,
+) -> Result<Option<Contact>, sqlx::Error> {
+    let q = format!(
+        "SELECT {CONTACT_COLUMNS} FROM contacts
+         WHERE name = $1 AND address_book_id = $2 AND deleted_at IS NULL
+         ORDER BY id LIMIT 1"
+    );
+    sqlx::query(&q)
+        .bind(name)
+        .bind(address_book_id)
+        .map(contact_from_row)
+        .fetch_optional(conn)
+        .await
+}
+
+/// Picks the name a contact gets in the target book, or `None` when it should be skipped.
+async fn resolve_name(
+    conn: &mut PgConnection,
+    name: &str,
+    address_book_id: i32,
+    on_conflict: OnConflict,
+) -> Result<Option<String>, handle_errors::Error> {
+    if on_conflict == OnConflict::Allow
+        || find_contact_by_name(conn, name, address_book_id)
+            .await?
+            .is_none()
+    {
+        return Ok(Some(name.to_string()));
+    }
+
+    match on_conflict {
+        OnConflict::Skip => Ok(None),
+        OnConflict::Rename => {
+            let mut suffix = 2;
+            loop {
+                let candidate = format!("{name} ({suffix})");
+                if find_contact_by_name(conn, &candidate, address_book_id)
+                    .await?
+                    .is_none()
+                {
+                    return Ok(Some(candidate));
+                }
+                suffix += 1;
+            }
+        }
+        _ => Err(handle_errors::Error::Conflict(format!(
+            "a contact named \"{name}\" already exists in address book {address_book_id}"
+        ))),
+    }
+}
+
+/// Fails with a validation error unless the database has an ICU collation for `locale`.
+pub(crate) async fn ensure_locale(
+    conn: &mut PgConnection,
+    locale: Option<&str>,
+) -> Result<(), handle_errors::Error> {
+    let Some(locale) = locale else {
+        return Ok(());
+    };
+    let q = "SELECT 1 FROM pg_collation WHERE collname = $1 || '-x-icu'";
+    match sqlx::query(q).bind(locale).fetch_optional(conn).await? {
+        Some(_) => Ok(()),
+        None => Err(handle_errors::Error::ValidationError(format!(
+            "locale \"{locale}\" is not supported"
+        ))),
+    }
+}
+
+pub(crate) async fn ensure_live_address_book(
+    conn: &mut PgConnection,
+    address_book_id: i32,
+) -> Result<(), handle_errors::Error> {
+    let q = "SELECT id FROM address_books WHERE id = $1 AND deleted_at IS NULL";
+    match sqlx::query(q)
+        .bind(address_book_id)
+        .fetch_optional(conn)
+        .await?
+    {
+        Some(_) => Ok(()),
+        None => Err(handle_errors::Error::AddressBookNotFound),
+    }
+}
+
+/// Inserts `contacts` with a single multi-row statement and returns them in input order.
+pub(crate) async fn insert_contacts(
+    conn: &mut PgConnection,
+    address_book_id: i32,
+    contacts: &[NewContact],
+    actor: &Option<String>,
+) -> Result<Vec<Contact>, handle_errors::Error> {
+    insert_named_contacts(conn, address_book_id, contacts, &[], actor).await
+}
+
+/// Like `insert_contacts`, also giving the leading contacts the CardDAV resource names and
+/// UIDs in `names`. A name taken in the book fails with `Conflict`.
+pub(crate) async fn insert_named_contacts(
+    conn: &mut PgConnection,
+    address_book_id: i32,
+    contacts: &[NewContact],
+    names: &[(String, Option<String>)],
+    actor: &Option<String>,
+) -> Result<Vec<Contact>, handle_errors::Error> {
+    let definitions = load_fields(conn, address_book_id).await?;
+    let custom_fields = contacts
+        .iter()
+        .map(|c| validate_custom_fields(&definitions, &c.custom_fields).map(Json))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let column = |f: fn(&NewContact) -> Option<String>| contacts.iter().map(f).collect::<Vec<_>>();
+    let date = |f: fn(&NewContact) -> Option<NaiveDate>| contacts.iter().map(f).collect::<Vec<_>>();
+
+    let q = format!(
+        "INSERT INTO contacts
+         (name, address, phone_number, email, name_prefix, given_name, middle_name,
+          family_name, name_suffix, nickname, organization, job_title, birthday, anniversary,
+          notes, custom_fields, dav_name, uid, address_book_id, created_by, updated_by)
+         SELECT name, address, phone_number, email, name_prefix, given_name, middle_name,
+          family_name, name_suffix, nickname, organization, job_title, birthday, anniversary,
+          notes, custom_fields, dav_name, uid, $1, $2, $2
+         FROM UNNEST($3::varchar[], $4::varchar[], $5::varchar[], $6::varchar[],
+          $7::varchar[], $8::varchar[], $9::varchar[], $10::varchar[], $11::varchar[],
+          $12::varchar[], $13::varchar[], $14::varchar[], $15::date[], $16::date[],
+          $17::text[], $18::jsonb[], $19::varchar[], $20::varchar[])
+         WITH ORDINALITY AS t(name, address, phone_number, email, name_prefix, given_name,
+          middle_name, family_name, name_suffix, nickname, organization, job_title, birthday,
+          anniversary, notes, custom_fields, dav_name, uid, position)
+         ORDER BY position
+         RETURNING {CONTACT_COLUMNS}"
+    );
+    let mut inserted = sqlx::query(&q)
+        .bind(address_book_id)
+        .bind(actor)
+        .bind(contacts.iter().map(|c| c.name.clone()).collect::<Vec<_>>())
+        .bind(
+            contacts
+                .iter()
+                .map(|c| c.address.clone())
+                .collect::<Vec<_>>(),
+        )
+        .bind(column(|c| c.phone_number.clone()))
+        .bind(column(|c| c.email.clone()))
+        .bind(column(|c| c.details.name_prefix.clone()))
+        .bind(column(|c| c.details.given_name.clone()))
+        .bind(column(|c| c.details.middle_name.clone()))
+        .bind(column(|c| c.details.family_name.clone()))
+        .bind(column(|c| c.details.name_suffix.clone()))
+        .bind(column(|c| c.details.nickname.clone()))
+        .bind(column(|c| c.details.organization.clone()))
+        .bind(column(|c| c.details.job_title.clone()))
+        .bind(date(|c| c.details.birthday))
+        .bind(date(|c| c.details.anniversary))
+        .bind(column(|c| c.details.notes.clone()))
+        .bind(custom_fields)
+        // UNNEST pads the shorter name arrays with NULLs.
+        .bind(
+            names
+                .iter()
+                .map(|(name, _)| name.clone())
+                .collect::<Vec<_>>(),
+        )
+        .bind(names.iter().map(|(_, uid)| uid.clone()).collect::<Vec<_>>())
+        .map(contact_from_row)
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| match &e {
+            // Only resource names are unique among contacts.
+            sqlx::Error::Database(db) if db.is_unique_violation() => {
+                handle_errors::Error::Conflict(String::from(
+                    "the resource name is already taken in this address book",
+                ))
+            }
+            _ => handle_errors::Error::DatabaseQueryError(e),
+        })?;
+    // Ids are drawn in insertion order, which follows the input order.
+    inserted.sort_by_key(|contact| contact.id.0);
+
+    for contact in &inserted {
+        let event = NewAuditEvent::contact(AuditAction::Create, actor.clone(), None, contact);
+        record_event(conn, event).await?;
+    }
+    Ok(inserted)
+}
+
+/// Overwrites a contact with `contact`, also replacing its vCard UID when `uid` is given.
+pub(crate) async fn replace_contact(
+    conn: &mut PgConnection,
+    id: i32,
+    address_book_id: i32,
+    contact: &NewContact,
+    uid: Option<&str>,
+    actor: Option<String>,
+    if_match: &IfMatch,
+) -> Result<Contact, handle_errors::Error> {
+    let Some(before) = lock_contact(conn, id, address_book_id, false).await? else {
+        return Err(handle_errors::Error::ContactNotFound);
+    };
+    if !if_match.matches(&before.etag()) {
+        return Err(handle_errors::Error::PreconditionFailed);
+    }
+    let definitions = load_fields(conn, address_book_id).await?;
+    let custom_fields = validate_custom_fields(&definitions, &contact.custom_fields)?;
+
+    let details = &contact.details;
+    let q = format!(
+        "UPDATE contacts SET
+         name = $1, address = $2, phone_number = $3, email = $4, custom_fields = $7,
+         name_prefix = $8, given_name = $9, middle_name = $10, family_name = $11,
+         name_suffix = $12, nickname = $13, organization = $14, job_title = $15,
+         birthday = $16, anniversary = $17, notes = $18, uid = COALESCE($19, uid),
+         updated_by = $6
+         WHERE id = $5
+         RETURNING {CONTACT_COLUMNS}"
+    );
+    let after = sqlx::query(&q)
+        .bind(&contact.name)
+        .bind(&contact.address)
+        .bind(&contact.phone_number)
+        .bind(&contact.email)
+        .bind(id)
+        .bind(&actor)
+        .bind(Json(custom_fields))
+        .bind(&details.name_prefix)
+        .bind(&details.given_name)
+        .bind(&details.middle_name)
+        .bind(&details.family_name)
+        .bind(&details.name_suffix)
+        .bind(&details.nickname)
+        .bind(&details.organization)
+        .bind(&details.job_title)
+        .bind(details.birthday)
+        .bind(details.anniversary)
+        .bind(&details.notes)
+        .bind(uid)
+        .map(contact_from_row)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    let event = NewAuditEvent::contact(AuditAction::Update, actor, Some(&before), &after);
+    record_event(conn, event).await?;
+
+    Ok(after)
+}
+
+pub(crate) async fn soft_delete_contact(
+    conn: &mut PgConnection,
+    id: i32,
+    address_book_id: i32,
+    actor: Option<String>,
+    if_match: &IfMatch,
+) -> Result<Contact, handle_errors::Error> {
+    let Some(before) = lock_contact(conn, id, address_book_id, false).await? else {
+        return Err(handle_errors::Error::ContactNotFound);
+    };
+    if !if_match.matches(&before.etag()) {
+        return Err(handle_errors::Error::PreconditionFailed);
+    }
+
+    let q = format!(
+        "UPDATE contacts SET deleted_at = now(), updated_by = $2
+         WHERE id = $1 RETURNING {CONTACT_COLUMNS}"
+    );
+    let contact = sqlx::query(&q)
+        .bind(id)
+        .bind(&actor)
+        .map(contact_from_row)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    let event = NewAuditEvent::contact(AuditAction::Delete, actor, Some(&before), &contact);
+    record_event(conn, event).await?;
+
+    Ok(contact)
+}
+
+/// Inserts a run of consecutive bulk creates with one statement, pushing a result for each.
+/// If the statement fails they are retried one at a time so that the offending operations
+/// can be reported individually. When `atomic`, the first of those is returned instead.
+async fn bulk_create(
+    tx: &mut Transaction<'_, Postgres>,
+    address_book_id: i32,
+    creates: &[(usize, NewContact)],
+    actor: &Option<String>,
+    atomic: bool,
+    results: &mut Vec<BulkItemResult>,
+) -> Result<Option<(usize, handle_errors::Error)>, handle_errors::Error> {
+    let contacts: Vec<NewContact> = creates.iter().map(|(_, c)| c.clone()).collect();
+    let mut savepoint = tx.begin().await?;
+    let outcome = insert_contacts(&mut savepoint, address_book_id, &contacts, actor).await;
+    if let Ok(inserted) = settle(savepoint, outcome).await? {
+        results.extend(creates.iter().zip(inserted).map(|((index, _), contact)| {
+            BulkItemResult::succeeded(*index, StatusCode::CREATED, Some(contact))
+        }));
+        return Ok(None);
+    }
+
+    for (index, contact) in creates {
+        let mut savepoint = tx.begin().await?;
+        let outcome = insert_contacts(
+            &mut savepoint,
+            address_book_id,
+            std::slice::from_ref(contact),
+            actor,
+        )
+        .await;
+        match settle(savepoint, outcome).await? {
+            Ok(inserted) => results.push(BulkItemResult::succeeded(
+                *index,
+                StatusCode::CREATED,
+                inserted.into_iter().next(),
+            )),
+            Err(e) if atomic => return Ok(Some((*index, e))),
+            Err(e) => results.push(BulkItemResult::failed(*index, &e)),
+        }
+    }
+    Ok(None)
+}
+
+/// Releases `savepoint` if `outcome` succeeded and rolls it back otherwise.
+async fn settle<T>(
+    savepoint: Transaction<'_, Postgres>,
+    outcome: Result<T, handle_errors::Error>,
+) -> Result<Result<T, handle_errors::Error>, sqlx::Error> {
+    match outcome {
+        Ok(value) => savepoint.commit().await.map(|_| Ok(value)),
+        Err(e) => savepoint.rollback().await.map(|_| Err(e)),
+    }
+}
+
 #[async_trait]
 impl IContactRepository for ContactRepository {
+    #[tracing::instrument(skip_all, fields(%address_book_id))]
     async fn get_address_book_contacts(
         &self,
         address_book_id: i32,
         limit: Option<i32>,
         offset: i32,
+        filter: ListFilter,
+        contact_filter: ContactFilter,
     ) -> Result<Vec<Contact>, handle_errors::Error> {
-        let q = "SELECT * FROM contacts WHERE address_book_id = $1 LIMIT $2 OFFSET $3";
-        match sqlx::query(q)
+        let order_by = filter.order_by("c", &contact::SORT_FIELDS)?;
+        let mut conn = self.pool.acquire().await?;
+        ensure_locale(&mut conn, filter.locale.as_deref()).await?;
+
+        let q = format!(
+            "SELECT c.*, contact_group_names(c.id) AS groups,
+             contact_has_photo(c.id) AS has_photo FROM contacts AS c
+             WHERE c.address_book_id = $1 AND {CONTACT_LISTING_CONDITIONS}
+             ORDER BY {order_by} LIMIT $2 OFFSET $3"
+        );
+        match sqlx::query(&q)
             .bind(address_book_id)
             .bind(limit)
             .bind(offset)
-            .map(|row: PgRow| Contact {
-                id: ContactId(row.get("id")),
-                name: row.get("name"),
-                address: row.get("address"),
-                phone_number: row.get("phone_number"),
-                email: row.get("email"),
-                address_book_id: AddressBookId(row.get("address_book_id")),
-            })
-            .fetch_all(&self.pool)
+            .bind(filter.updated_since)
+            .bind(contact_filter.group.clone())
+            .bind(Json(contact_filter.custom_fields()))
+            .map(contact_from_row)
+            .fetch_all(&mut *conn)
             .await
         {
             Ok(contacts) => Ok(contacts),
@@ -91,57 +753,86 @@ impl IContactRepository for ContactRepository {
         }
     }
 
-    async fn add_contact_to_address_book(
+    #[tracing::instrument(skip_all)]
+    async fn get_contacts_of_address_books(
         &self,
-        name: &str,
-        address: &str,
-        phone_number: Option<String>,
-        email: Option<String>,
-        address_book_id: i32,
-    ) -> Result<Contact, handle_errors::Error> {
-        let q = "INSERT INTO address_books 
-                      (name address phone_number email address_book_id)
-                      VALUES ($1 $2 $3 $4 $5)";
-        match sqlx::query(q)
-            .bind(name)
-            .bind(address)
-            .bind(phone_number)
-            .bind(email)
-            .bind(address_book_id)
-            .map(|row: PgRow| Contact {
-                id: ContactId(row.get("id")),
-                name: row.get("name"),
-                address: row.get("address"),
-                phone_number: row.get("phone_number"),
-                email: row.get("email"),
-                address_book_id: AddressBookId(row.get("address_book_id")),
-            })
-            .fetch_one(&self.pool)
+        address_book_ids: Vec<i32>,
+        limit: Option<i32>,
+        offset: i32,
+        filter: ListFilter,
+        contact_filter: ContactFilter,
+        search: Option<String>,
+    ) -> Result<Vec<Contact>, handle_errors::Error> {
+        let order_by = filter.order_by("c", &contact::SORT_FIELDS)?;
+        let mut conn = self.pool.acquire().await?;
+        ensure_locale(&mut conn, filter.locale.as_deref()).await?;
+
+        let q = format!(
+            "SELECT c.*, contact_group_names(c.id) AS groups,
+             contact_has_photo(c.id) AS has_photo
+             FROM (SELECT c.*, row_number() OVER (
+                       PARTITION BY c.address_book_id ORDER BY {order_by}) AS position
+                   FROM contacts AS c
+                   WHERE c.address_book_id = ANY($1) AND {CONTACT_LISTING_CONDITIONS}
+                   AND ($7::text IS NULL OR c.name ILIKE $7 OR c.display_name ILIKE $7
+                        OR c.email ILIKE $7 OR c.phone_number ILIKE $7
+                        OR c.organization ILIKE $7 OR c.nickname ILIKE $7)) AS c
+             WHERE c.position > $3 AND ($2::integer IS NULL OR c.position <= $3 + $2)
+             ORDER BY c.address_book_id, c.position"
+        );
+        match sqlx::query(&q)
+            .bind(address_book_ids)
+            .bind(limit)
+            .bind(offset)
+            .bind(filter.updated_since)
+            .bind(contact_filter.group.clone())
+            .bind(Json(contact_filter.custom_fields()))
+            .bind(contains_pattern(search.as_deref()))
+            .map(contact_from_row)
+            .fetch_all(&mut *conn)
             .await
         {
-            Ok(contact) => Ok(contact),
+            Ok(contacts) => Ok(contacts),
             Err(e) => Err(handle_errors::Error::DatabaseQueryError(e)),
         }
     }
 
+    #[tracing::instrument(skip_all, fields(%address_book_id))]
+    async fn add_contact_to_address_book(
+        &self,
+        address_book_id: i32,
+        contact: NewContact,
+        actor: Option<String>,
+    ) -> Result<Contact, handle_errors::Error> {
+        let mut tx = self.pool.begin().await?;
+        ensure_live_address_book(&mut tx, address_book_id).await?;
+
+        let mut inserted = insert_contacts(
+            &mut tx,
+            address_book_id,
+            std::slice::from_ref(&contact),
+            &actor,
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(inserted.pop().ok_or(sqlx::Error::RowNotFound)?)
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, contact_id = %id))]
     async fn get_contact_by_id(
         &self,
-        _id: i32,
-        _address_book_id: i32,
+        id: i32,
+        address_book_id: i32,
     ) -> Result<Option<Contact>, handle_errors::Error> {
-        let q = "SELECT * FROM contacts
-                             WHERE id = $1 AND address_book_id = $2";
+        let q = "SELECT c.*, contact_group_names(c.id) AS groups,
+                 contact_has_photo(c.id) AS has_photo FROM contacts AS c
+                 JOIN address_books AS ab ON ab.id = c.address_book_id AND ab.deleted_at IS NULL
+                 WHERE c.id = $1 AND c.address_book_id = $2 AND c.deleted_at IS NULL";
         match sqlx::query(q)
-            .bind("id")
-            .bind("address_book_id")
-            .map(|row: PgRow| Contact {
-                id: ContactId(row.get("id")),
-                name: row.get("name"),
-                address: row.get("address"),
-                phone_number: row.get("phone_number"),
-                email: row.get("email"),
-                address_book_id: AddressBookId(row.get("address_book_id")),
-            })
+            .bind(id)
+            .bind(address_book_id)
+            .map(contact_from_row)
             .fetch_optional(&self.pool)
             .await
         {
@@ -150,56 +841,679 @@ impl IContactRepository for ContactRepository {
         }
     }
 
+    #[tracing::instrument(skip_all, fields(%address_book_id, contact_id = %id))]
     async fn delete_contact(
         &self,
         id: i32,
         address_book_id: i32,
+        actor: Option<String>,
+        if_match: IfMatch,
     ) -> Result<(), handle_errors::Error> {
-        let q = "DELETE FROM contacts 
-                                       WHERE id = $1 AND address_book_id = $2";
-        match sqlx::query(q)
+        let mut tx = self.pool.begin().await?;
+        soft_delete_contact(&mut tx, id, address_book_id, actor, &if_match).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, contact_id = %id))]
+    async fn restore_contact(
+        &self,
+        id: i32,
+        address_book_id: i32,
+        actor: Option<String>,
+    ) -> Result<Contact, handle_errors::Error> {
+        let mut tx = self.pool.begin().await?;
+        let Some(before) = lock_contact(&mut tx, id, address_book_id, true).await? else {
+            return Err(handle_errors::Error::ContactNotFound);
+        };
+
+        // A card created under the same CardDAV name in the meantime keeps it; this one falls
+        // back to being served by id.
+        let q = format!(
+            "UPDATE contacts SET deleted_at = NULL, updated_by = $2,
+             dav_name = CASE WHEN EXISTS (
+                 SELECT 1 FROM contacts AS o
+                 WHERE o.address_book_id = contacts.address_book_id
+                 AND o.dav_name = contacts.dav_name AND o.deleted_at IS NULL)
+             THEN NULL ELSE dav_name END
+             WHERE id = $1 RETURNING {CONTACT_COLUMNS}"
+        );
+        let contact = sqlx::query(&q)
             .bind(id)
-            .bind(address_book_id)
-            .execute(&self.pool)
-            .await
-        {
-            Ok(_) => Ok(()),
-            Err(e) => Err(handle_errors::Error::DatabaseQueryError(e)),
-        }
+            .bind(&actor)
+            .map(contact_from_row)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        let event = NewAuditEvent::contact(AuditAction::Restore, actor, Some(&before), &contact);
+        record_event(&mut tx, event).await?;
+        tx.commit().await?;
+
+        Ok(contact)
     }
 
+    #[tracing::instrument(skip_all, fields(%address_book_id, contact_id = %id))]
     async fn update_contact(
         &self,
         id: i32,
-        name: &str,
-        address: &str,
-        phone_number: Option<String>,
-        email: Option<String>,
         address_book_id: i32,
+        contact: NewContact,
+        actor: Option<String>,
+        if_match: IfMatch,
     ) -> Result<Contact, handle_errors::Error> {
-        let q = "UPDATE contacts SET 
-                                     name = $1, address = $2, phone_number = $3, email = $4 
-                                     WHERE id = $5 AND address_book_id = $6";
-        match sqlx::query(q)
-            .bind(name)
-            .bind(address)
-            .bind(phone_number)
-            .bind(email)
-            .bind(id)
+        let mut tx = self.pool.begin().await?;
+        let contact = replace_contact(
+            &mut tx,
+            id,
+            address_book_id,
+            &contact,
+            None,
+            actor,
+            &if_match,
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(contact)
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, contact_id = %id))]
+    async fn patch_contact(
+        &self,
+        id: i32,
+        address_book_id: i32,
+        patch: ContactPatch,
+        actor: Option<String>,
+        if_match: IfMatch,
+    ) -> Result<Contact, handle_errors::Error> {
+        let mut tx = self.pool.begin().await?;
+        let Some(before) = lock_contact(&mut tx, id, address_book_id, false).await? else {
+            return Err(handle_errors::Error::ContactNotFound);
+        };
+
+        if !if_match.matches(&before.etag()) {
+            return Err(handle_errors::Error::PreconditionFailed);
+        }
+        if patch.is_empty() {
+            return Ok(before);
+        }
+        let custom_fields = match patch.custom_fields {
+            Some(_) => {
+                let definitions = load_fields(&mut tx, address_book_id).await?;
+                let merged = merge_custom_fields(&before.custom_fields, patch.custom_fields);
+                Some(validate_custom_fields(&definitions, &merged)?)
+            }
+            None => None,
+        };
+
+        let mut q: QueryBuilder<Postgres> = QueryBuilder::new("UPDATE contacts SET ");
+        let mut columns = q.separated(", ");
+        for (column, value) in [
+            ("name", patch.name),
+            ("address", patch.address),
+            ("phone_number", patch.phone_number),
+            ("email", patch.email),
+            ("name_prefix", patch.name_prefix),
+            ("given_name", patch.given_name),
+            ("middle_name", patch.middle_name),
+            ("family_name", patch.family_name),
+            ("name_suffix", patch.name_suffix),
+            ("nickname", patch.nickname),
+            ("organization", patch.organization),
+            ("job_title", patch.job_title),
+            ("notes", patch.notes),
+        ] {
+            if let Some(value) = value {
+                columns
+                    .push(column)
+                    .push_unseparated(" = ")
+                    .push_bind_unseparated(value);
+            }
+        }
+        for (column, value) in [
+            ("birthday", patch.birthday),
+            ("anniversary", patch.anniversary),
+        ] {
+            if let Some(value) = value {
+                columns
+                    .push(column)
+                    .push_unseparated(" = ")
+                    .push_bind_unseparated(value);
+            }
+        }
+        if let Some(custom_fields) = custom_fields {
+            columns
+                .push("custom_fields = ")
+                .push_bind_unseparated(Json(custom_fields));
+        }
+        columns
+            .push("updated_by = ")
+            .push_bind_unseparated(actor.clone());
+        q.push(" WHERE id = ")
+            .push_bind(id)
+            .push(format!(" RETURNING {CONTACT_COLUMNS}"));
+
+        let contact = q.build().map(contact_from_row).fetch_one(&mut *tx).await?;
+
+        let event = NewAuditEvent::contact(AuditAction::Update, actor, Some(&before), &contact);
+        record_event(&mut tx, event).await?;
+        tx.commit().await?;
+
+        Ok(contact)
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id))]
+    async fn bulk_contacts(
+        &self,
+        address_book_id: i32,
+        operations: Vec<BulkOperation>,
+        actor: Option<String>,
+        atomic: bool,
+    ) -> Result<Vec<BulkItemResult>, handle_errors::Error> {
+        let len = operations.len();
+        let mut results = Vec::with_capacity(len);
+        let mut tx = self.pool.begin().await?;
+        ensure_live_address_book(&mut tx, address_book_id).await?;
+
+        // Operations run in request order. Consecutive creates share one INSERT.
+        let mut creates: Vec<(usize, NewContact)> = Vec::new();
+        let mut operations = operations.into_iter().enumerate().peekable();
+        while let Some((index, operation)) = operations.next() {
+            let (id, contact, tag) = match operation {
+                BulkOperation::Create { contact } => {
+                    creates.push((index, contact));
+                    if matches!(operations.peek(), Some((_, BulkOperation::Create { .. }))) {
+                        continue;
+                    }
+                    let failed = bulk_create(
+                        &mut tx,
+                        address_book_id,
+                        &creates,
+                        &actor,
+                        atomic,
+                        &mut results,
+                    )
+                    .await?;
+                    if let Some((index, e)) = failed {
+                        return Ok(BulkItemResult::rolled_back(len, index, &e));
+                    }
+                    creates.clear();
+                    continue;
+                }
+                BulkOperation::Update {
+                    id,
+                    contact,
+                    if_match,
+                } => (id, Some(contact), if_match),
+                BulkOperation::Delete { id, if_match } => (id, None, if_match),
+            };
+            let if_match = bulk::if_match(tag);
+
+            let mut savepoint = tx.begin().await?;
+            let outcome = match contact {
+                Some(contact) => replace_contact(
+                    &mut savepoint,
+                    id,
+                    address_book_id,
+                    &contact,
+                    None,
+                    actor.clone(),
+                    &if_match,
+                )
+                .await
+                .map(|contact| BulkItemResult::succeeded(index, StatusCode::OK, Some(contact))),
+                None => soft_delete_contact(
+                    &mut savepoint,
+                    id,
+                    address_book_id,
+                    actor.clone(),
+                    &if_match,
+                )
+                .await
+                .map(|_| BulkItemResult::succeeded(index, StatusCode::NO_CONTENT, None)),
+            };
+            match settle(savepoint, outcome).await? {
+                Ok(result) => results.push(result),
+                Err(e) if atomic => return Ok(BulkItemResult::rolled_back(len, index, &e)),
+                Err(e) => results.push(BulkItemResult::failed(index, &e)),
+            }
+        }
+        tx.commit().await?;
+
+        Ok(results)
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id))]
+    async fn import_contacts(
+        &self,
+        address_book_id: i32,
+        contacts: Vec<NewContact>,
+        actor: Option<String>,
+    ) -> Result<Vec<Contact>, handle_errors::Error> {
+        let mut tx = self.pool.begin().await?;
+        ensure_live_address_book(&mut tx, address_book_id).await?;
+        let contacts = insert_contacts(&mut tx, address_book_id, &contacts, &actor).await?;
+        tx.commit().await?;
+
+        Ok(contacts)
+    }
+
+    #[tracing::instrument(skip_all, fields(%target_address_book_id))]
+    async fn move_contacts(
+        &self,
+        ids: Vec<i32>,
+        target_address_book_id: i32,
+        on_conflict: OnConflict,
+        actor: Option<String>,
+        if_match: IfMatch,
+    ) -> Result<Vec<Contact>, handle_errors::Error> {
+        let mut tx = self.pool.begin().await?;
+        ensure_live_address_book(&mut tx, target_address_book_id).await?;
+        let definitions = load_fields(&mut tx, target_address_book_id).await?;
+
+        let mut contacts = Vec::with_capacity(ids.len());
+        for id in ids {
+            let Some(before) = lock_live_contact(&mut tx, id).await? else {
+                return Err(handle_errors::Error::ContactNotFound);
+            };
+            if !if_match.matches(&before.etag()) {
+                return Err(handle_errors::Error::PreconditionFailed);
+            }
+            if before.address_book_id.0 == target_address_book_id {
+                contacts.push(before);
+                continue;
+            }
+            let Some(name) =
+                resolve_name(&mut tx, &before.name, target_address_book_id, on_conflict).await?
+            else {
+                contacts.push(before);
+                continue;
+            };
+            let custom_fields = carry_custom_fields(&definitions, &before.custom_fields)?;
+
+            // Groups belong to a book, so the contact leaves the old book's groups. Its CardDAV
+            // name may be taken in the target book, so it is served there by id.
+            sqlx::query("DELETE FROM contact_group_members WHERE contact_id = $1")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+
+            let q = format!(
+                "UPDATE contacts SET
+                 address_book_id = $2, name = $3, custom_fields = $5, updated_by = $4,
+                 dav_name = NULL
+                 WHERE id = $1 RETURNING {CONTACT_COLUMNS}"
+            );
+            let contact = sqlx::query(&q)
+                .bind(id)
+                .bind(target_address_book_id)
+                .bind(name)
+                .bind(&actor)
+                .bind(Json(custom_fields))
+                .map(contact_from_row)
+                .fetch_one(&mut *tx)
+                .await?;
+
+            let event =
+                NewAuditEvent::contact(AuditAction::Move, actor.clone(), Some(&before), &contact);
+            record_event(&mut tx, event).await?;
+            contacts.push(contact);
+        }
+        tx.commit().await?;
+
+        Ok(contacts)
+    }
+
+    #[tracing::instrument(skip_all, fields(%target_address_book_id, contact_id = %id))]
+    async fn copy_contact(
+        &self,
+        id: i32,
+        target_address_book_id: i32,
+        on_conflict: OnConflict,
+        actor: Option<String>,
+    ) -> Result<Contact, handle_errors::Error> {
+        let mut tx = self.pool.begin().await?;
+        ensure_live_address_book(&mut tx, target_address_book_id).await?;
+        let Some(source) = lock_live_contact(&mut tx, id).await? else {
+            return Err(handle_errors::Error::ContactNotFound);
+        };
+
+        let Some(name) =
+            resolve_name(&mut tx, &source.name, target_address_book_id, on_conflict).await?
+        else {
+            let existing = find_contact_by_name(&mut tx, &source.name, target_address_book_id)
+                .await?
+                .ok_or(handle_errors::Error::ContactNotFound)?;
+            return Ok(existing);
+        };
+        let definitions = load_fields(&mut tx, target_address_book_id).await?;
+        let copy = NewContact {
+            name,
+            address: source.address,
+            phone_number: source.phone_number,
+            email: source.email,
+            details: source.details,
+            custom_fields: carry_custom_fields(&definitions, &source.custom_fields)?,
+        };
+        let mut inserted = insert_contacts(
+            &mut tx,
+            target_address_book_id,
+            std::slice::from_ref(&copy),
+            &actor,
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(inserted.pop().ok_or(sqlx::Error::RowNotFound)?)
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id))]
+    async fn get_contact_changes(
+        &self,
+        address_book_id: i32,
+        since: Option<i64>,
+    ) -> Result<ChangeLog, handle_errors::Error> {
+        sequence_changes(&self.pool).await?;
+        let mut tx = self.pool.begin().await?;
+        // The position and the contacts must come from the same snapshot, or a write landing
+        // between the reads would be reported without being covered by the token, or the
+        // other way round.
+        sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ READ ONLY")
+            .execute(&mut *tx)
+            .await?;
+        ensure_live_address_book(&mut tx, address_book_id).await?;
+
+        let position = change_position(&mut tx, address_book_id).await?;
+        let mut log = ChangeLog {
+            current: position.current,
+            pruned_through: position.pruned_through,
+            changes: Vec::new(),
+            contacts: Vec::new(),
+        };
+
+        let changed_ids = match since {
+            Some(since) if log.covers(Some(since)) => {
+                let q = "SELECT DISTINCT ON (contact_id) contact_id, kind FROM contact_changes
+                         WHERE address_book_id = $1 AND seq > $2 AND seq <= $3
+                         ORDER BY contact_id, seq";
+                log.changes = sqlx::query(q)
+                    .bind(address_book_id)
+                    .bind(since)
+                    .bind(log.current)
+                    .map(|row: PgRow| {
+                        let kind: String = row.get("kind");
+                        (ContactId(row.get("contact_id")), ChangeKind::parse(&kind))
+                    })
+                    .fetch_all(&mut *tx)
+                    .await?;
+                Some(log.changes.iter().map(|(id, _)| id.0).collect::<Vec<i32>>())
+            }
+            _ => None,
+        };
+
+        log.contacts = live_contacts(&mut tx, address_book_id, changed_ids).await?;
+        tx.commit().await?;
+
+        Ok(log)
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id))]
+    async fn get_change_position(
+        &self,
+        address_book_id: i32,
+    ) -> Result<ChangePosition, handle_errors::Error> {
+        sequence_changes(&self.pool).await?;
+        let mut conn = self.pool.acquire().await?;
+        ensure_live_address_book(&mut conn, address_book_id).await?;
+        Ok(change_position(&mut conn, address_book_id).await?)
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id))]
+    async fn get_contact_events(
+        &self,
+        address_book_id: i32,
+        after: i64,
+        limit: i64,
+    ) -> Result<Vec<ContactEvent>, handle_errors::Error> {
+        sequence_changes(&self.pool).await?;
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ READ ONLY")
+            .execute(&mut *tx)
+            .await?;
+        ensure_live_address_book(&mut tx, address_book_id).await?;
+
+        let q = "SELECT seq, contact_id, kind FROM contact_changes
+                 WHERE address_book_id = $1 AND seq > $2
+                 ORDER BY seq LIMIT $3";
+        let mut events = sqlx::query(q)
             .bind(address_book_id)
-            .map(|row: PgRow| Contact {
-                id: ContactId(row.get("id")),
-                name: row.get("name"),
-                address: row.get("address"),
-                phone_number: row.get("phone_number"),
-                email: row.get("email"),
-                address_book_id: AddressBookId(row.get("address_book_id")),
+            .bind(after)
+            .bind(limit)
+            .map(|row: PgRow| {
+                let kind: String = row.get("kind");
+                ContactEvent {
+                    seq: row.get("seq"),
+                    contact_id: ContactId(row.get("contact_id")),
+                    kind: ChangeKind::parse(&kind),
+                    contact: None,
+                }
             })
+            .fetch_all(&mut *tx)
+            .await?;
+
+        let ids = events.iter().map(|event| event.contact_id.0).collect();
+        let contacts = live_contacts(&mut tx, address_book_id, Some(ids)).await?;
+        tx.commit().await?;
+
+        for event in events.iter_mut() {
+            if event.kind != ChangeKind::Delete {
+                event.contact = contacts
+                    .iter()
+                    .find(|contact| contact.id == event.contact_id)
+                    .cloned();
+            }
+        }
+        Ok(events)
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn prune_contact_changes(
+        &self,
+        changed_before: DateTime<Utc>,
+    ) -> Result<u64, handle_errors::Error> {
+        let q = "WITH pruned AS (
+                     DELETE FROM contact_changes WHERE changed_at < $1 AND seq IS NOT NULL
+                     RETURNING address_book_id, seq
+                 ), horizons AS (
+                     INSERT INTO contact_change_horizons (address_book_id, pruned_through)
+                     SELECT address_book_id, max(seq) FROM pruned GROUP BY address_book_id
+                     ON CONFLICT (address_book_id) DO UPDATE SET pruned_through =
+                         GREATEST(contact_change_horizons.pruned_through, EXCLUDED.pruned_through)
+                 )
+                 SELECT count(*) AS pruned FROM pruned";
+        let pruned: i64 = sqlx::query(q)
+            .bind(changed_before)
+            .map(|row: PgRow| row.get("pruned"))
             .fetch_one(&self.pool)
-            .await
-        {
-            Ok(contact) => Ok(contact),
-            Err(e) => Err(handle_errors::Error::DatabaseQueryError(e)),
+            .await?;
+
+        Ok(pruned as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::address_book_repo::{AddressBookRepository, IAddressBookRepository};
+
+    fn named(name: &str) -> NewContact {
+        NewContact {
+            name: String::from(name),
+            address: String::from("1 Main St"),
+            ..Default::default()
         }
     }
+
+    #[test]
+    fn test_contains_pattern_escapes_wildcards() {
+        assert_eq!(
+            contains_pattern(Some(" 100%_off\\ ")),
+            Some(String::from("%100\\%\\_off\\\\%"))
+        );
+        assert_eq!(contains_pattern(Some("  ")), None);
+        assert_eq!(contains_pattern(None), None);
+    }
+
+    // Needs a database server: DATABASE_URL=postgres://... cargo test -- --ignored
+    #[sqlx::test]
+    #[ignore]
+    async fn test_change_log_keeps_changes_committed_out_of_order(pool: PgPool) {
+        let book = AddressBookRepository::new(pool.clone())
+            .create_address_book(String::from("Friends"), None)
+            .await
+            .unwrap();
+        let repo = ContactRepository::new(pool.clone());
+        let ann = repo
+            .add_contact_to_address_book(book.id.0, named("Ann"), None)
+            .await
+            .unwrap();
+        let start = repo.get_contact_changes(book.id.0, None).await.unwrap();
+
+        // The first writer purges Ann before the second adds Bob, but commits after it.
+        // Purging does not lock the book, so neither waits for the other.
+        let mut first = pool.begin().await.unwrap();
+        sqlx::query("DELETE FROM contacts WHERE id = $1")
+            .bind(ann.id.0)
+            .execute(&mut *first)
+            .await
+            .unwrap();
+        let bob = repo
+            .add_contact_to_address_book(book.id.0, named("Bob"), None)
+            .await
+            .unwrap();
+
+        let seen = repo
+            .get_contact_changes(book.id.0, Some(start.current))
+            .await
+            .unwrap();
+        assert_eq!(seen.changes, [(bob.id, ChangeKind::Create)]);
+        first.commit().await.unwrap();
+
+        let rest = repo
+            .get_contact_changes(book.id.0, Some(seen.current))
+            .await
+            .unwrap();
+        assert_eq!(rest.changes, [(ann.id, ChangeKind::Delete)]);
+    }
+
+    #[sqlx::test]
+    #[ignore]
+    async fn test_events_resume_past_changes_committed_out_of_order(pool: PgPool) {
+        let book = AddressBookRepository::new(pool.clone())
+            .create_address_book(String::from("Friends"), None)
+            .await
+            .unwrap();
+        let repo = ContactRepository::new(pool.clone());
+        let ann = repo
+            .add_contact_to_address_book(book.id.0, named("Ann"), None)
+            .await
+            .unwrap();
+        let opened = repo.get_change_position(book.id.0).await.unwrap().current;
+
+        // Purging Ann does not lock the book, so adding Bob commits first.
+        let mut purge = pool.begin().await.unwrap();
+        sqlx::query("DELETE FROM contacts WHERE id = $1")
+            .bind(ann.id.0)
+            .execute(&mut *purge)
+            .await
+            .unwrap();
+        let bob = repo
+            .add_contact_to_address_book(book.id.0, named("Bob"), None)
+            .await
+            .unwrap();
+
+        let events = repo
+            .get_contact_events(book.id.0, opened, 10)
+            .await
+            .unwrap();
+        let seen: Vec<_> = events.iter().map(|e| (e.contact_id.0, e.kind)).collect();
+        assert_eq!(seen, [(bob.id.0, ChangeKind::Create)]);
+        purge.commit().await.unwrap();
+
+        // A client resuming with the last id it got still receives the purge.
+        let last_event_id = events[0].seq;
+        assert!(repo
+            .get_change_position(book.id.0)
+            .await
+            .unwrap()
+            .covers(last_event_id));
+        let events = repo
+            .get_contact_events(book.id.0, last_event_id, 10)
+            .await
+            .unwrap();
+        let seen: Vec<_> = events.iter().map(|e| (e.contact_id.0, e.kind)).collect();
+        assert_eq!(seen, [(ann.id.0, ChangeKind::Delete)]);
+    }
+
+    // Needs a database server: DATABASE_URL=postgres://... cargo test -- --ignored
+    #[sqlx::test]
+    #[ignore]
+    async fn test_bulk_applies_operations_in_request_order(pool: PgPool) {
+        let book = AddressBookRepository::new(pool.clone())
+            .create_address_book(String::from("Friends"), None)
+            .await
+            .unwrap();
+        let repo = ContactRepository::new(pool.clone());
+        let ann = repo
+            .add_contact_to_address_book(book.id.0, named("Ann"), None)
+            .await
+            .unwrap();
+
+        let operations = vec![
+            BulkOperation::Update {
+                id: ann.id.0,
+                contact: named("Anne"),
+                if_match: None,
+            },
+            BulkOperation::Create {
+                contact: named("Bob"),
+            },
+            BulkOperation::Create {
+                contact: named("Cy"),
+            },
+            BulkOperation::Delete {
+                id: ann.id.0,
+                if_match: None,
+            },
+            BulkOperation::Create {
+                contact: named("Dee"),
+            },
+        ];
+        let results = repo
+            .bulk_contacts(book.id.0, operations, None, true)
+            .await
+            .unwrap();
+        let statuses: Vec<(usize, u16)> = results.iter().map(|r| (r.index, r.status)).collect();
+        assert_eq!(statuses, [(0, 200), (1, 201), (2, 201), (3, 204), (4, 201)]);
+
+        let id = |index: usize| results[index].contact.as_ref().unwrap().id.0;
+        let applied: Vec<(String, i32)> = sqlx::query_as(
+            "SELECT action, entity_id FROM audit_events
+             WHERE entity_type = 'contact' ORDER BY id",
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        let expected = [
+            ("create", ann.id.0),
+            ("update", ann.id.0),
+            ("create", id(1)),
+            ("create", id(2)),
+            ("delete", ann.id.0),
+            ("create", id(4)),
+        ]
+        .map(|(action, id)| (String::from(action), id));
+        assert_eq!(applied, expected);
+    }
 }
@@ -0,0 +1,397 @@
+use crate::repositories::address_book_repo::{address_book_from_row, BOOK_COLUMNS};
+use crate::repositories::contact_repo::{
+    contact_from_row, ensure_live_address_book, insert_named_contacts, lock_contact,
+    replace_contact, sequence_changes, soft_delete_contact, sync_position_columns, CONTACT_COLUMNS,
+};
+use crate::types::contact::NewContact;
+use crate::types::dav::{Card, CardCollection};
+use crate::types::precondition::IfMatch;
+use crate::types::vcard::default_uid;
+
+use async_trait::async_trait;
+use sqlx::postgres::PgRow;
+use sqlx::{PgConnection, PgPool, Row};
+
+#[cfg(test)]
+use mockall::{predicate::*, *};
+
+#[async_trait]
+#[cfg_attr(test, automock)]
+pub trait IDavRepository {
+    /// Every live address book, ordered by id.
+    async fn get_collections(&self) -> Result<Vec<CardCollection>, handle_errors::Error>;
+
+    async fn get_collection(
+        &self,
+        address_book_id: i32,
+    ) -> Result<CardCollection, handle_errors::Error>;
+
+    /// The live cards of a live book, ordered by id: all of them, or only those served under
+    /// the given resource names.
+    async fn get_cards(
+        &self,
+        address_book_id: i32,
+        resource_names: Option<Vec<String>>,
+    ) -> Result<Vec<Card>, handle_errors::Error>;
+
+    /// Names of the resources written after change `since`, up to and including `until`.
+    async fn get_changed_resources(
+        &self,
+        address_book_id: i32,
+        since: i64,
+        until: i64,
+    ) -> Result<Vec<String>, handle_errors::Error>;
+
+    async fn create_card(
+        &self,
+        address_book_id: i32,
+        resource_name: String,
+        uid: Option<String>,
+        contact: NewContact,
+        actor: Option<String>,
+    ) -> Result<Card, handle_errors::Error>;
+
+    /// Replaces the card's contact. vCards carry no custom fields, so those are kept.
+    async fn update_card(
+        &self,
+        address_book_id: i32,
+        contact_id: i32,
+        uid: Option<String>,
+        contact: NewContact,
+        actor: Option<String>,
+        if_match: IfMatch,
+    ) -> Result<Card, handle_errors::Error>;
+
+    async fn delete_card(
+        &self,
+        address_book_id: i32,
+        contact_id: i32,
+        actor: Option<String>,
+        if_match: IfMatch,
+    ) -> Result<(), handle_errors::Error>;
+}
+
+pub struct DavRepository {
+    pool: PgPool,
+}
+
+impl DavRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+fn collection_from_row(row: PgRow) -> CardCollection {
+    CardCollection {
+        book: address_book_from_row(&row),
+        sync_seq: row.get("sync_seq"),
+        pruned_through: row.get("pruned_through"),
+    }
+}
+
+/// Where the renditions of a card's photo are stored, selected next to `CONTACT_COLUMNS`.
+const PHOTO_BLOB_PREFIX: &str = "contact_photo_blob_prefix(id) AS photo_blob_prefix";
+
+fn card_from_row(row: PgRow) -> Card {
+    let id: i32 = row.get("id");
+    let resource_name: Option<String> = row.get("dav_name");
+    let uid: Option<String> = row.get("uid");
+    Card {
+        resource_name: resource_name.unwrap_or_else(|| format!("{id}.vcf")),
+        uid: uid.unwrap_or_else(|| default_uid(id)),
+        photo_blob_prefix: row.get("photo_blob_prefix"),
+        contact: contact_from_row(row),
+    }
+}
+
+async fn fetch_card(conn: &mut PgConnection, contact_id: i32) -> Result<Card, sqlx::Error> {
+    let q = format!("SELECT {CONTACT_COLUMNS}, {PHOTO_BLOB_PREFIX} FROM contacts WHERE id = $1");
+    sqlx::query(&q)
+        .bind(contact_id)
+        .map(card_from_row)
+        .fetch_one(conn)
+        .await
+}
+
+#[async_trait]
+impl IDavRepository for DavRepository {
+    #[tracing::instrument(skip_all)]
+    async fn get_collections(&self) -> Result<Vec<CardCollection>, handle_errors::Error> {
+        sequence_changes(&self.pool).await?;
+        let q = format!(
+            "SELECT {BOOK_COLUMNS}, {} FROM address_books
+             WHERE deleted_at IS NULL ORDER BY id",
+            sync_position_columns("address_books.id")
+        );
+        Ok(sqlx::query(&q)
+            .map(collection_from_row)
+            .fetch_all(&self.pool)
+            .await?)
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id))]
+    async fn get_collection(
+        &self,
+        address_book_id: i32,
+    ) -> Result<CardCollection, handle_errors::Error> {
+        sequence_changes(&self.pool).await?;
+        let q = format!(
+            "SELECT {BOOK_COLUMNS}, {} FROM address_books
+             WHERE id = $1 AND deleted_at IS NULL",
+            sync_position_columns("address_books.id")
+        );
+        match sqlx::query(&q)
+            .bind(address_book_id)
+            .map(collection_from_row)
+            .fetch_optional(&self.pool)
+            .await?
+        {
+            Some(collection) => Ok(collection),
+            None => Err(handle_errors::Error::AddressBookNotFound),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id))]
+    async fn get_cards(
+        &self,
+        address_book_id: i32,
+        resource_names: Option<Vec<String>>,
+    ) -> Result<Vec<Card>, handle_errors::Error> {
+        let mut conn = self.pool.acquire().await?;
+        ensure_live_address_book(&mut conn, address_book_id).await?;
+
+        let q = format!(
+            "SELECT {CONTACT_COLUMNS}, {PHOTO_BLOB_PREFIX} FROM contacts
+             WHERE address_book_id = $1 AND deleted_at IS NULL
+             AND ($2::varchar[] IS NULL OR COALESCE(dav_name, id || '.vcf') = ANY($2))
+             ORDER BY id"
+        );
+        Ok(sqlx::query(&q)
+            .bind(address_book_id)
+            .bind(resource_names)
+            .map(card_from_row)
+            .fetch_all(&mut *conn)
+            .await?)
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id))]
+    async fn get_changed_resources(
+        &self,
+        address_book_id: i32,
+        since: i64,
+        until: i64,
+    ) -> Result<Vec<String>, handle_errors::Error> {
+        let q = "SELECT DISTINCT resource_name FROM contact_changes
+                 WHERE address_book_id = $1 AND seq > $2 AND seq <= $3
+                 ORDER BY resource_name";
+        Ok(sqlx::query(q)
+            .bind(address_book_id)
+            .bind(since)
+            .bind(until)
+            .map(|row: PgRow| row.get("resource_name"))
+            .fetch_all(&self.pool)
+            .await?)
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, %resource_name))]
+    async fn create_card(
+        &self,
+        address_book_id: i32,
+        resource_name: String,
+        uid: Option<String>,
+        contact: NewContact,
+        actor: Option<String>,
+    ) -> Result<Card, handle_errors::Error> {
+        let mut tx = self.pool.begin().await?;
+        ensure_live_address_book(&mut tx, address_book_id).await?;
+
+        // Another client may have taken the name since the caller looked, which fails the
+        // insert with `Conflict`.
+        let names = [(resource_name.clone(), uid.clone())];
+        let contact = insert_named_contacts(
+            &mut tx,
+            address_book_id,
+            std::slice::from_ref(&contact),
+            &names,
+            &actor,
+        )
+        .await?
+        .pop()
+        .ok_or(sqlx::Error::RowNotFound)?;
+        tx.commit().await?;
+
+        Ok(Card {
+            resource_name,
+            uid: uid.unwrap_or_else(|| default_uid(contact.id.0)),
+            photo_blob_prefix: None,
+            contact,
+        })
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, %contact_id))]
+    async fn update_card(
+        &self,
+        address_book_id: i32,
+        contact_id: i32,
+        uid: Option<String>,
+        mut contact: NewContact,
+        actor: Option<String>,
+        if_match: IfMatch,
+    ) -> Result<Card, handle_errors::Error> {
+        let mut tx = self.pool.begin().await?;
+        let Some(before) = lock_contact(&mut tx, contact_id, address_book_id, false).await? else {
+            return Err(handle_errors::Error::ContactNotFound);
+        };
+        if !if_match.matches(&before.etag()) {
+            return Err(handle_errors::Error::PreconditionFailed);
+        }
+        contact.custom_fields = before.custom_fields;
+
+        replace_contact(
+            &mut tx,
+            contact_id,
+            address_book_id,
+            &contact,
+            uid.as_deref(),
+            actor,
+            &IfMatch::default(),
+        )
+        .await?;
+        let card = fetch_card(&mut tx, contact_id).await?;
+        tx.commit().await?;
+
+        Ok(card)
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, %contact_id))]
+    async fn delete_card(
+        &self,
+        address_book_id: i32,
+        contact_id: i32,
+        actor: Option<String>,
+        if_match: IfMatch,
+    ) -> Result<(), handle_errors::Error> {
+        let mut tx = self.pool.begin().await?;
+        soft_delete_contact(&mut tx, contact_id, address_book_id, actor, &if_match).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::address_book_repo::{AddressBookRepository, IAddressBookRepository};
+
+    fn ann() -> NewContact {
+        NewContact {
+            name: String::from("Ann"),
+            address: String::from("1 Main St"),
+            ..Default::default()
+        }
+    }
+
+    // Needs a database server: DATABASE_URL=postgres://... cargo test -- --ignored
+    #[sqlx::test]
+    #[ignore]
+    async fn test_card_writes_are_one_version_each(pool: PgPool) {
+        let book = AddressBookRepository::new(pool.clone())
+            .create_address_book(String::from("Friends"), None)
+            .await
+            .unwrap();
+        let repo = DavRepository::new(pool);
+        let name = String::from("ann.vcf");
+
+        let created = repo
+            .create_card(
+                book.id.0,
+                name.clone(),
+                Some(String::from("u1")),
+                ann(),
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!((created.contact.version, created.uid.as_str()), (1, "u1"));
+
+        let updated = repo
+            .update_card(
+                book.id.0,
+                created.contact.id.0,
+                Some(String::from("u2")),
+                ann(),
+                None,
+                IfMatch::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!((updated.contact.version, updated.uid.as_str()), (2, "u2"));
+
+        let stored = repo
+            .get_cards(book.id.0, Some(vec![name]))
+            .await
+            .unwrap()
+            .pop()
+            .unwrap();
+        assert_eq!(stored.etag(), updated.etag());
+    }
+
+    #[sqlx::test]
+    #[ignore]
+    async fn test_create_card_refuses_taken_name(pool: PgPool) {
+        let book = AddressBookRepository::new(pool.clone())
+            .create_address_book(String::from("Friends"), None)
+            .await
+            .unwrap();
+        let repo = DavRepository::new(pool);
+        let name = String::from("ann.vcf");
+        repo.create_card(book.id.0, name.clone(), None, ann(), None)
+            .await
+            .unwrap();
+
+        let result = repo.create_card(book.id.0, name, None, ann(), None).await;
+        assert!(matches!(result, Err(handle_errors::Error::Conflict(_))));
+    }
+
+    #[sqlx::test]
+    #[ignore]
+    async fn test_sync_token_stays_below_uncommitted_changes(pool: PgPool) {
+        let book = AddressBookRepository::new(pool.clone())
+            .create_address_book(String::from("Friends"), None)
+            .await
+            .unwrap();
+        let repo = DavRepository::new(pool.clone());
+        let old = repo
+            .create_card(book.id.0, String::from("old.vcf"), None, ann(), None)
+            .await
+            .unwrap();
+        let start = repo.get_collection(book.id.0).await.unwrap().sync_seq;
+
+        // Purging the old card does not lock the book, so the new card commits first.
+        let mut purge = pool.begin().await.unwrap();
+        sqlx::query("DELETE FROM contacts WHERE id = $1")
+            .bind(old.contact.id.0)
+            .execute(&mut *purge)
+            .await
+            .unwrap();
+        repo.create_card(book.id.0, String::from("new.vcf"), None, ann(), None)
+            .await
+            .unwrap();
+
+        let token = repo.get_collection(book.id.0).await.unwrap().sync_seq;
+        let changed = repo
+            .get_changed_resources(book.id.0, start, token)
+            .await
+            .unwrap();
+        assert_eq!(changed, ["new.vcf"]);
+        purge.commit().await.unwrap();
+
+        let until = repo.get_collection(book.id.0).await.unwrap().sync_seq;
+        let changed = repo
+            .get_changed_resources(book.id.0, token, until)
+            .await
+            .unwrap();
+        assert_eq!(changed, ["old.vcf"]);
+    }
+}
@@ -0,0 +1,496 @@
+use crate::repositories::audit_repo::record_event;
+use crate::repositories::contact_repo::{
+    contact_from_row, ensure_live_address_book, CONTACT_COLUMNS,
+};
+use crate::types::address_book::AddressBookId;
+use crate::types::audit::{AuditAction, NewAuditEvent};
+use crate::types::custom_field::{CustomField, FieldId, FieldType, NewCustomField};
+use crate::types::precondition::IfMatch;
+
+use async_trait::async_trait;
+use sqlx::postgres::PgRow;
+use sqlx::{PgConnection, PgPool, Row};
+
+#[cfg(test)]
+use mockall::{predicate::*, *};
+
+#[async_trait]
+#[cfg_attr(test, automock)]
+pub trait IFieldRepository {
+    async fn get_fields(
+        &self,
+        address_book_id: i32,
+    ) -> Result<Vec<CustomField>, handle_errors::Error>;
+
+    async fn get_field(
+        &self,
+        address_book_id: i32,
+        id: i32,
+    ) -> Result<CustomField, handle_errors::Error>;
+
+    async fn create_field(
+        &self,
+        address_book_id: i32,
+        field: NewCustomField,
+        actor: Option<String>,
+    ) -> Result<CustomField, handle_errors::Error>;
+
+    /// Changes `required` and `options`. A field's name and type are fixed once created, and
+    /// a change that existing contacts would no longer satisfy is refused as a conflict.
+    async fn update_field(
+        &self,
+        address_book_id: i32,
+        id: i32,
+        field: NewCustomField,
+        actor: Option<String>,
+        if_match: IfMatch,
+    ) -> Result<CustomField, handle_errors::Error>;
+
+    /// Deletes the definition along with every contact's value for it, recording an update
+    /// of each contact that had one.
+    async fn delete_field(
+        &self,
+        address_book_id: i32,
+        id: i32,
+        actor: Option<String>,
+        if_match: IfMatch,
+    ) -> Result<(), handle_errors::Error>;
+}
+
+pub struct FieldRepository {
+    pool: PgPool,
+}
+
+impl FieldRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+fn field_from_row(row: PgRow) -> CustomField {
+    let field_type: String = row.get("field_type");
+    CustomField {
+        id: FieldId(row.get("id")),
+        address_book_id: AddressBookId(row.get("address_book_id")),
+        name: row.get("name"),
+        field_type: FieldType::parse(&field_type).unwrap_or(FieldType::Text),
+        required: row.get("required"),
+        options: row.get("options"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+        created_by: row.get("created_by"),
+        updated_by: row.get("updated_by"),
+        version: row.get("version"),
+    }
+}
+
+/// The book's field definitions in creation order, used to validate contact writes.
+pub(crate) async fn load_fields(
+    conn: &mut PgConnection,
+    address_book_id: i32,
+) -> Result<Vec<CustomField>, sqlx::Error> {
+    // Shared locks keep definitions from changing under contacts being validated against them.
+    let q = "SELECT * FROM custom_fields WHERE address_book_id = $1 ORDER BY id FOR SHARE";
+    sqlx::query(q)
+        .bind(address_book_id)
+        .map(field_from_row)
+        .fetch_all(conn)
+        .await
+}
+
+/// Refuses a change to a field's definition that live contacts holding values under the old
+/// one would violate: newly required with contacts lacking a value, or options removed while
+/// contacts still use them.
+async fn ensure_contacts_satisfy(
+    conn: &mut PgConnection,
+    before: &CustomField,
+    field: &NewCustomField,
+) -> Result<(), handle_errors::Error> {
+    if field.required && !before.required {
+        let q = "SELECT count(*) FROM contacts
+                 WHERE address_book_id = $1 AND deleted_at IS NULL AND NOT custom_fields ? $2";
+        let missing: i64 = sqlx::query_scalar(q)
+            .bind(before.address_book_id.0)
+            .bind(&before.name)
+            .fetch_one(&mut *conn)
+            .await?;
+        if missing > 0 {
+            return Err(handle_errors::Error::Conflict(format!(
+                "{missing} contacts have no value for custom field \"{}\"",
+                before.name
+            )));
+        }
+    }
+    if before.field_type == FieldType::Enum {
+        let q = "SELECT count(*) FROM contacts
+                 WHERE address_book_id = $1 AND deleted_at IS NULL AND custom_fields ? $2
+                 AND NOT custom_fields ->> $2 = ANY($3)";
+        let stale: i64 = sqlx::query_scalar(q)
+            .bind(before.address_book_id.0)
+            .bind(&before.name)
+            .bind(&field.options)
+            .fetch_one(&mut *conn)
+            .await?;
+        if stale > 0 {
+            return Err(handle_errors::Error::Conflict(format!(
+                "{stale} contacts use an option of custom field \"{}\" that would be removed",
+                before.name
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Locks a field of a live book for the rest of the transaction.
+async fn lock_field(
+    conn: &mut PgConnection,
+    address_book_id: i32,
+    id: i32,
+) -> Result<Option<CustomField>, sqlx::Error> {
+    let q = "SELECT f.* FROM custom_fields AS f
+             JOIN address_books AS ab ON ab.id = f.address_book_id AND ab.deleted_at IS NULL
+             WHERE f.id = $1 AND f.address_book_id = $2
+             FOR UPDATE OF f";
+    sqlx::query(q)
+        .bind(id)
+        .bind(address_book_id)
+        .map(field_from_row)
+        .fetch_optional(conn)
+        .await
+}
+
+#[async_trait]
+impl IFieldRepository for FieldRepository {
+    #[tracing::instrument(skip_all, fields(%address_book_id))]
+    async fn get_fields(
+        &self,
+        address_book_id: i32,
+    ) -> Result<Vec<CustomField>, handle_errors::Error> {
+        let mut conn = self.pool.acquire().await?;
+        ensure_live_address_book(&mut conn, address_book_id).await?;
+
+        match load_fields(&mut conn, address_book_id).await {
+            Ok(fields) => Ok(fields),
+            Err(e) => Err(handle_errors::Error::DatabaseQueryError(e)),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, field_id = %id))]
+    async fn get_field(
+        &self,
+        address_book_id: i32,
+        id: i32,
+    ) -> Result<CustomField, handle_errors::Error> {
+        let q = "SELECT f.* FROM custom_fields AS f
+                 JOIN address_books AS ab ON ab.id = f.address_book_id AND ab.deleted_at IS NULL
+                 WHERE f.id = $1 AND f.address_book_id = $2";
+        match sqlx::query(q)
+            .bind(id)
+            .bind(address_book_id)
+            .map(field_from_row)
+            .fetch_optional(&self.pool)
+            .await
+        {
+            Ok(Some(field)) => Ok(field),
+            Ok(None) => Err(handle_errors::Error::FieldNotFound),
+            Err(e) => Err(handle_errors::Error::DatabaseQueryError(e)),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id))]
+    async fn create_field(
+        &self,
+        address_book_id: i32,
+        field: NewCustomField,
+        actor: Option<String>,
+    ) -> Result<CustomField, handle_errors::Error> {
+        let mut tx = self.pool.begin().await?;
+        ensure_live_address_book(&mut tx, address_book_id).await?;
+
+        let q = "INSERT INTO custom_fields
+                 (address_book_id, name, field_type, required, options, created_by, updated_by)
+                 VALUES ($1, $2, $3, $4, $5, $6, $6) RETURNING *";
+        let created = sqlx::query(q)
+            .bind(address_book_id)
+            .bind(&field.name)
+            .bind(field.field_type.as_str())
+            .bind(field.required)
+            .bind(&field.options)
+            .bind(&actor)
+            .map(field_from_row)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|e| match &e {
+                sqlx::Error::Database(db) if db.is_unique_violation() => {
+                    handle_errors::Error::Conflict(format!(
+                        "a custom field named \"{}\" already exists in this address book",
+                        field.name
+                    ))
+                }
+                _ => handle_errors::Error::DatabaseQueryError(e),
+            })?;
+
+        let event = NewAuditEvent::custom_field(AuditAction::Create, actor, None, &created);
+        record_event(&mut tx, event).await?;
+        tx.commit().await?;
+
+        Ok(created)
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, field_id = %id))]
+    async fn update_field(
+        &self,
+        address_book_id: i32,
+        id: i32,
+        field: NewCustomField,
+        actor: Option<String>,
+        if_match: IfMatch,
+    ) -> Result<CustomField, handle_errors::Error> {
+        let mut tx = self.pool.begin().await?;
+        let Some(before) = lock_field(&mut tx, address_book_id, id).await? else {
+            return Err(handle_errors::Error::FieldNotFound);
+        };
+        if !if_match.matches(&before.etag()) {
+            return Err(handle_errors::Error::PreconditionFailed);
+        }
+        if field.name != before.name || field.field_type != before.field_type {
+            return Err(handle_errors::Error::ValidationError(String::from(
+                "the name and type of a custom field cannot be changed",
+            )));
+        }
+        ensure_contacts_satisfy(&mut tx, &before, &field).await?;
+
+        let q = "UPDATE custom_fields SET required = $2, options = $3, updated_by = $4
+                 WHERE id = $1 RETURNING *";
+        let updated = sqlx::query(q)
+            .bind(id)
+            .bind(field.required)
+            .bind(&field.options)
+            .bind(&actor)
+            .map(field_from_row)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        let event =
+            NewAuditEvent::custom_field(AuditAction::Update, actor, Some(&before), &updated);
+        record_event(&mut tx, event).await?;
+        tx.commit().await?;
+
+        Ok(updated)
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, field_id = %id))]
+    async fn delete_field(
+        &self,
+        address_book_id: i32,
+        id: i32,
+        actor: Option<String>,
+        if_match: IfMatch,
+    ) -> Result<(), handle_errors::Error> {
+        let mut tx = self.pool.begin().await?;
+        let Some(field) = lock_field(&mut tx, address_book_id, id).await? else {
+            return Err(handle_errors::Error::FieldNotFound);
+        };
+        if !if_match.matches(&field.etag()) {
+            return Err(handle_errors::Error::PreconditionFailed);
+        }
+
+        let q = format!(
+            "SELECT {CONTACT_COLUMNS} FROM contacts
+             WHERE address_book_id = $1 AND custom_fields ? $2 ORDER BY id FOR UPDATE"
+        );
+        let before = sqlx::query(&q)
+            .bind(address_book_id)
+            .bind(&field.name)
+            .map(contact_from_row)
+            .fetch_all(&mut *tx)
+            .await?;
+        let q = format!(
+            "UPDATE contacts SET custom_fields = custom_fields - $2, updated_by = $3
+             WHERE id = $1 RETURNING {CONTACT_COLUMNS}"
+        );
+        for contact in &before {
+            let after = sqlx::query(&q)
+                .bind(contact.id.0)
+                .bind(&field.name)
+                .bind(&actor)
+                .map(contact_from_row)
+                .fetch_one(&mut *tx)
+                .await?;
+            let event =
+                NewAuditEvent::contact(AuditAction::Update, actor.clone(), Some(contact), &after);
+            record_event(&mut tx, event).await?;
+        }
+        sqlx::query("DELETE FROM custom_fields WHERE id = $1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+
+        let event = NewAuditEvent::custom_field(AuditAction::Delete, actor, None, &field);
+        record_event(&mut tx, event).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::address_book_repo::{AddressBookRepository, IAddressBookRepository};
+    use crate::repositories::contact_repo::{ContactRepository, IContactRepository};
+    use crate::types::contact::{ContactFilter, NewContact};
+    use crate::types::ListFilter;
+    use serde_json::{json, Value};
+
+    async fn book_with_contact(pool: &PgPool, field: NewCustomField, value: Value) -> i32 {
+        let book = AddressBookRepository::new(pool.clone())
+            .create_address_book(String::from("Customers"), None)
+            .await
+            .unwrap();
+        let name = field.name.clone();
+        FieldRepository::new(pool.clone())
+            .create_field(book.id.0, field, None)
+            .await
+            .unwrap();
+        let contact = NewContact {
+            name: String::from("Ann"),
+            address: String::from("1 Main St"),
+            custom_fields: json!({ name: value }).as_object().unwrap().clone(),
+            ..Default::default()
+        };
+        let contacts = ContactRepository::new(pool.clone());
+        contacts
+            .add_contact_to_address_book(book.id.0, contact, None)
+            .await
+            .unwrap();
+        let bare = NewContact {
+            name: String::from("Bob"),
+            address: String::from("2 Main St"),
+            ..Default::default()
+        };
+        contacts
+            .add_contact_to_address_book(book.id.0, bare, None)
+            .await
+            .unwrap();
+        book.id.0
+    }
+
+    fn tier(options: &[&str], required: bool) -> NewCustomField {
+        NewCustomField {
+            name: String::from("tier"),
+            field_type: FieldType::Enum,
+            required,
+            options: options.iter().map(|o| o.to_string()).collect(),
+        }
+    }
+
+    // Needs a database server: DATABASE_URL=postgres://... cargo test -- --ignored
+    #[sqlx::test]
+    #[ignore]
+    async fn test_update_field_refuses_changes_contacts_would_violate(pool: PgPool) {
+        let book = book_with_contact(&pool, tier(&["gold", "silver"], false), json!("gold")).await;
+        let repo = FieldRepository::new(pool);
+        let field = repo.get_fields(book).await.unwrap().pop().unwrap();
+
+        let required = repo
+            .update_field(
+                book,
+                field.id.0,
+                tier(&["gold", "silver"], true),
+                None,
+                IfMatch::default(),
+            )
+            .await;
+        assert!(matches!(required, Err(handle_errors::Error::Conflict(_))));
+
+        let narrowed = repo
+            .update_field(
+                book,
+                field.id.0,
+                tier(&["silver"], false),
+                None,
+                IfMatch::default(),
+            )
+            .await;
+        assert!(matches!(narrowed, Err(handle_errors::Error::Conflict(_))));
+
+        let widened = repo
+            .update_field(
+                book,
+                field.id.0,
+                tier(&["gold", "bronze"], false),
+                None,
+                IfMatch::default(),
+            )
+            .await;
+        assert_eq!(widened.unwrap().options, vec!["gold", "bronze"]);
+    }
+
+    #[sqlx::test]
+    #[ignore]
+    async fn test_delete_field_records_contact_updates(pool: PgPool) {
+        let book = book_with_contact(&pool, tier(&["gold"], false), json!("gold")).await;
+        let repo = FieldRepository::new(pool.clone());
+        let field = repo.get_fields(book).await.unwrap().pop().unwrap();
+
+        repo.delete_field(
+            book,
+            field.id.0,
+            Some(String::from("alice")),
+            IfMatch::default(),
+        )
+        .await
+        .unwrap();
+
+        let events: Vec<(String, Option<String>, Value)> = sqlx::query_as(
+            "SELECT action, actor, changes FROM audit_events
+             WHERE entity_type = 'contact' AND action = 'update' ORDER BY id",
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].1.as_deref(), Some("alice"));
+        assert_eq!(
+            events[0].2["before"]["custom_fields"],
+            json!({ "tier": "gold" })
+        );
+        assert_eq!(events[0].2["after"]["custom_fields"], json!({}));
+    }
+
+    #[sqlx::test]
+    #[ignore]
+    async fn test_number_filter_compares_values(pool: PgPool) {
+        let customer_id = NewCustomField {
+            name: String::from("customer_id"),
+            field_type: FieldType::Number,
+            required: false,
+            options: vec![],
+        };
+        let book = book_with_contact(&pool, customer_id, json!(42.0)).await;
+        let contacts = ContactRepository::new(pool);
+
+        for value in ["42", "42.00", "4.2e1"] {
+            let filter = ContactFilter {
+                params: [(String::from("cf.customer_id"), String::from(value))].into(),
+                ..Default::default()
+            };
+            let found = contacts
+                .get_address_book_contacts(book, None, 0, ListFilter::default(), filter)
+                .await
+                .unwrap();
+            assert_eq!(found.len(), 1, "cf.customer_id={value}");
+        }
+
+        let filter = ContactFilter {
+            params: [(String::from("cf.customer_id"), String::from("forty-two"))].into(),
+            ..Default::default()
+        };
+        let found = contacts
+            .get_address_book_contacts(book, None, 0, ListFilter::default(), filter)
+            .await
+            .unwrap();
+        assert!(found.is_empty());
+    }
+}
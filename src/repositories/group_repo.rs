@@ -0,0 +1,342 @@
+use crate::repositories::audit_repo::record_event;
+use crate::repositories::contact_repo::{ensure_live_address_book, lock_contact, touch_contact};
+use crate::types::address_book::AddressBookId;
+use crate::types::audit::{AuditAction, NewAuditEvent};
+use crate::types::contact::Contact;
+use crate::types::group::{ContactGroup, GroupId};
+use crate::types::precondition::IfMatch;
+
+use async_trait::async_trait;
+use sqlx::postgres::PgRow;
+use sqlx::{PgConnection, PgPool, Row};
+
+#[cfg(test)]
+use mockall::{predicate::*, *};
+
+#[async_trait]
+#[cfg_attr(test, automock)]
+pub trait IGroupRepository {
+    async fn get_groups(
+        &self,
+        address_book_id: i32,
+    ) -> Result<Vec<ContactGroup>, handle_errors::Error>;
+
+    async fn get_group(
+        &self,
+        address_book_id: i32,
+        id: i32,
+    ) -> Result<ContactGroup, handle_errors::Error>;
+
+    async fn create_group(
+        &self,
+        address_book_id: i32,
+        name: String,
+        actor: Option<String>,
+    ) -> Result<ContactGroup, handle_errors::Error>;
+
+    async fn update_group(
+        &self,
+        address_book_id: i32,
+        id: i32,
+        name: String,
+        actor: Option<String>,
+        if_match: IfMatch,
+    ) -> Result<ContactGroup, handle_errors::Error>;
+
+    async fn delete_group(
+        &self,
+        address_book_id: i32,
+        id: i32,
+        actor: Option<String>,
+        if_match: IfMatch,
+    ) -> Result<(), handle_errors::Error>;
+
+    /// Adds a contact of the same book to the group. Adding an existing member is a no-op.
+    async fn add_member(
+        &self,
+        address_book_id: i32,
+        id: i32,
+        contact_id: i32,
+        actor: Option<String>,
+    ) -> Result<Contact, handle_errors::Error>;
+
+    async fn remove_member(
+        &self,
+        address_book_id: i32,
+        id: i32,
+        contact_id: i32,
+        actor: Option<String>,
+    ) -> Result<Contact, handle_errors::Error>;
+}
+
+pub struct GroupRepository {
+    pool: PgPool,
+}
+
+impl GroupRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+fn group_from_row(row: PgRow) -> ContactGroup {
+    ContactGroup {
+        id: GroupId(row.get("id")),
+        address_book_id: AddressBookId(row.get("address_book_id")),
+        name: row.get("name"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+        created_by: row.get("created_by"),
+        updated_by: row.get("updated_by"),
+        version: row.get("version"),
+    }
+}
+
+/// Group names are unique within a book.
+fn map_unique_violation(e: sqlx::Error, name: &str) -> handle_errors::Error {
+    match &e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => handle_errors::Error::Conflict(
+            format!("a group named \"{name}\" already exists in this address book"),
+        ),
+        _ => handle_errors::Error::DatabaseQueryError(e),
+    }
+}
+
+/// Locks a group of a live book for the rest of the transaction.
+async fn lock_group(
+    conn: &mut PgConnection,
+    address_book_id: i32,
+    id: i32,
+) -> Result<Option<ContactGroup>, sqlx::Error> {
+    let q = "SELECT g.* FROM contact_groups AS g
+             JOIN address_books AS ab ON ab.id = g.address_book_id AND ab.deleted_at IS NULL
+             WHERE g.id = $1 AND g.address_book_id = $2
+             FOR UPDATE OF g";
+    sqlx::query(q)
+        .bind(id)
+        .bind(address_book_id)
+        .map(group_from_row)
+        .fetch_optional(conn)
+        .await
+}
+
+/// Bumps the version of every member, since their representation embeds the group name.
+async fn touch_members(
+    conn: &mut PgConnection,
+    id: i32,
+    actor: &Option<String>,
+) -> Result<(), sqlx::Error> {
+    let q = "UPDATE contacts SET updated_by = $2
+             WHERE id IN (SELECT contact_id FROM contact_group_members WHERE group_id = $1)";
+    sqlx::query(q).bind(id).bind(actor).execute(conn).await?;
+    Ok(())
+}
+
+#[async_trait]
+impl IGroupRepository for GroupRepository {
+    #[tracing::instrument(skip_all, fields(%address_book_id))]
+    async fn get_groups(
+        &self,
+        address_book_id: i32,
+    ) -> Result<Vec<ContactGroup>, handle_errors::Error> {
+        let mut conn = self.pool.acquire().await?;
+        ensure_live_address_book(&mut conn, address_book_id).await?;
+
+        let q = "SELECT * FROM contact_groups WHERE address_book_id = $1 ORDER BY name, id";
+        match sqlx::query(q)
+            .bind(address_book_id)
+            .map(group_from_row)
+            .fetch_all(&mut *conn)
+            .await
+        {
+            Ok(groups) => Ok(groups),
+            Err(e) => Err(handle_errors::Error::DatabaseQueryError(e)),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, group_id = %id))]
+    async fn get_group(
+        &self,
+        address_book_id: i32,
+        id: i32,
+    ) -> Result<ContactGroup, handle_errors::Error> {
+        let q = "SELECT g.* FROM contact_groups AS g
+                 JOIN address_books AS ab ON ab.id = g.address_book_id AND ab.deleted_at IS NULL
+                 WHERE g.id = $1 AND g.address_book_id = $2";
+        match sqlx::query(q)
+            .bind(id)
+            .bind(address_book_id)
+            .map(group_from_row)
+            .fetch_optional(&self.pool)
+            .await
+        {
+            Ok(Some(group)) => Ok(group),
+            Ok(None) => Err(handle_errors::Error::GroupNotFound),
+            Err(e) => Err(handle_errors::Error::DatabaseQueryError(e)),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id))]
+    async fn create_group(
+        &self,
+        address_book_id: i32,
+        name: String,
+        actor: Option<String>,
+    ) -> Result<ContactGroup, handle_errors::Error> {
+        let mut tx = self.pool.begin().await?;
+        ensure_live_address_book(&mut tx, address_book_id).await?;
+
+        let q = "INSERT INTO contact_groups (address_book_id, name, created_by, updated_by)
+                 VALUES ($1, $2, $3, $3) RETURNING *";
+        let group = sqlx::query(q)
+            .bind(address_book_id)
+            .bind(&name)
+            .bind(&actor)
+            .map(group_from_row)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|e| map_unique_violation(e, &name))?;
+
+        let event = NewAuditEvent::group(AuditAction::Create, actor, None, &group);
+        record_event(&mut tx, event).await?;
+        tx.commit().await?;
+
+        Ok(group)
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, group_id = %id))]
+    async fn update_group(
+        &self,
+        address_book_id: i32,
+        id: i32,
+        name: String,
+        actor: Option<String>,
+        if_match: IfMatch,
+    ) -> Result<ContactGroup, handle_errors::Error> {
+        let mut tx = self.pool.begin().await?;
+        let Some(before) = lock_group(&mut tx, address_book_id, id).await? else {
+            return Err(handle_errors::Error::GroupNotFound);
+        };
+        if !if_match.matches(&before.etag()) {
+            return Err(handle_errors::Error::PreconditionFailed);
+        }
+
+        let q = "UPDATE contact_groups SET name = $2, updated_by = $3
+                 WHERE id = $1 RETURNING *";
+        let group = sqlx::query(q)
+            .bind(id)
+            .bind(&name)
+            .bind(&actor)
+            .map(group_from_row)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|e| map_unique_violation(e, &name))?;
+        if group.name != before.name {
+            touch_members(&mut tx, id, &actor).await?;
+        }
+
+        let event = NewAuditEvent::group(AuditAction::Update, actor, Some(&before), &group);
+        record_event(&mut tx, event).await?;
+        tx.commit().await?;
+
+        Ok(group)
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, group_id = %id))]
+    async fn delete_group(
+        &self,
+        address_book_id: i32,
+        id: i32,
+        actor: Option<String>,
+        if_match: IfMatch,
+    ) -> Result<(), handle_errors::Error> {
+        let mut tx = self.pool.begin().await?;
+        let Some(group) = lock_group(&mut tx, address_book_id, id).await? else {
+            return Err(handle_errors::Error::GroupNotFound);
+        };
+        if !if_match.matches(&group.etag()) {
+            return Err(handle_errors::Error::PreconditionFailed);
+        }
+
+        touch_members(&mut tx, id, &actor).await?;
+        sqlx::query("DELETE FROM contact_groups WHERE id = $1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+
+        let event = NewAuditEvent::group(AuditAction::Delete, actor, None, &group);
+        record_event(&mut tx, event).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, %contact_id, group_id = %id))]
+    async fn add_member(
+        &self,
+        address_book_id: i32,
+        id: i32,
+        contact_id: i32,
+        actor: Option<String>,
+    ) -> Result<Contact, handle_errors::Error> {
+        let mut tx = self.pool.begin().await?;
+        if lock_group(&mut tx, address_book_id, id).await?.is_none() {
+            return Err(handle_errors::Error::GroupNotFound);
+        }
+        let Some(before) = lock_contact(&mut tx, contact_id, address_book_id, false).await? else {
+            return Err(handle_errors::Error::ContactNotFound);
+        };
+
+        let q = "INSERT INTO contact_group_members (group_id, contact_id) VALUES ($1, $2)
+                 ON CONFLICT DO NOTHING";
+        let inserted = sqlx::query(q)
+            .bind(id)
+            .bind(contact_id)
+            .execute(&mut *tx)
+            .await?;
+        if inserted.rows_affected() == 0 {
+            return Ok(before);
+        }
+
+        let contact = touch_contact(&mut tx, contact_id, &actor).await?;
+        let event = NewAuditEvent::contact(AuditAction::Update, actor, Some(&before), &contact);
+        record_event(&mut tx, event).await?;
+        tx.commit().await?;
+
+        Ok(contact)
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, %contact_id, group_id = %id))]
+    async fn remove_member(
+        &self,
+        address_book_id: i32,
+        id: i32,
+        contact_id: i32,
+        actor: Option<String>,
+    ) -> Result<Contact, handle_errors::Error> {
+        let mut tx = self.pool.begin().await?;
+        if lock_group(&mut tx, address_book_id, id).await?.is_none() {
+            return Err(handle_errors::Error::GroupNotFound);
+        }
+        let Some(before) = lock_contact(&mut tx, contact_id, address_book_id, false).await? else {
+            return Err(handle_errors::Error::ContactNotFound);
+        };
+
+        let q = "DELETE FROM contact_group_members WHERE group_id = $1 AND contact_id = $2";
+        let deleted = sqlx::query(q)
+            .bind(id)
+            .bind(contact_id)
+            .execute(&mut *tx)
+            .await?;
+        if deleted.rows_affected() == 0 {
+            return Ok(before);
+        }
+
+        let contact = touch_contact(&mut tx, contact_id, &actor).await?;
+        let event = NewAuditEvent::contact(AuditAction::Update, actor, Some(&before), &contact);
+        record_event(&mut tx, event).await?;
+        tx.commit().await?;
+
+        Ok(contact)
+    }
+}
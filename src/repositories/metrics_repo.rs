@@ -0,0 +1,44 @@
+use crate::types::stats::EntityCounts;
+use async_trait::async_trait;
+use sqlx::{PgPool, Row};
+
+#[cfg(test)]
+use mockall::{predicate::*, *};
+
+#[async_trait]
+#[cfg_attr(test, automock)]
+pub trait IMetricsRepository {
+    /// Live books, and the live contacts of live books.
+    async fn count_entities(&self) -> Result<EntityCounts, handle_errors::Error>;
+}
+
+pub struct MetricsRepository {
+    pool: PgPool,
+}
+
+impl MetricsRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl IMetricsRepository for MetricsRepository {
+    #[tracing::instrument(skip_all)]
+    async fn count_entities(&self) -> Result<EntityCounts, handle_errors::Error> {
+        let row = sqlx::query(
+            "SELECT
+                (SELECT COUNT(*) FROM address_books WHERE deleted_at IS NULL) AS address_books,
+                (SELECT COUNT(*) FROM contacts c
+                 JOIN address_books b ON b.id = c.address_book_id
+                 WHERE c.deleted_at IS NULL AND b.deleted_at IS NULL) AS contacts",
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(EntityCounts {
+            address_books: row.get("address_books"),
+            contacts: row.get("contacts"),
+        })
+    }
+}
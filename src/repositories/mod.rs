@@ -1,2 +1,15 @@
 pub mod address_book_repo;
+pub mod blob_store;
+pub mod change_feed;
 pub mod contact_repo;
+pub mod dav_repo;
+pub mod field_repo;
+pub mod group_repo;
+pub mod metrics_repo;
+pub mod photo_repo;
+pub mod pool;
+pub mod rate_limit_store;
+pub mod trash_repo;
+pub mod audit_repo;
+pub mod webhook_repo;
+pub mod webhook_sender;
@@ -0,0 +1,174 @@
+use crate::repositories::audit_repo::record_event;
+use crate::repositories::contact_repo::{ensure_live_address_book, lock_contact, touch_contact};
+use crate::types::audit::{AuditAction, NewAuditEvent};
+use crate::types::contact::ContactId;
+use crate::types::photo::{NewPhoto, Photo};
+
+use async_trait::async_trait;
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Row};
+
+#[cfg(test)]
+use mockall::{predicate::*, *};
+
+#[async_trait]
+#[cfg_attr(test, automock)]
+pub trait IPhotoRepository {
+    /// Fails with `ContactNotFound` for an unknown contact and `PhotoNotFound` when the
+    /// contact has no photo.
+    async fn get_photo(
+        &self,
+        address_book_id: i32,
+        contact_id: i32,
+    ) -> Result<Photo, handle_errors::Error>;
+
+    /// Records the contact's new photo, returning it along with the photo it replaced.
+    async fn save_photo(
+        &self,
+        address_book_id: i32,
+        contact_id: i32,
+        photo: NewPhoto,
+        actor: Option<String>,
+    ) -> Result<(Photo, Option<Photo>), handle_errors::Error>;
+
+    /// Forgets the contact's photo, returning what was stored.
+    async fn delete_photo(
+        &self,
+        address_book_id: i32,
+        contact_id: i32,
+        actor: Option<String>,
+    ) -> Result<Photo, handle_errors::Error>;
+}
+
+pub struct PhotoRepository {
+    pool: PgPool,
+}
+
+impl PhotoRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+fn photo_from_row(row: PgRow) -> Photo {
+    Photo {
+        contact_id: ContactId(row.get("contact_id")),
+        content_type: row.get("content_type"),
+        width: row.get("width"),
+        height: row.get("height"),
+        byte_size: row.get("byte_size"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+        created_by: row.get("created_by"),
+        updated_by: row.get("updated_by"),
+        version: row.get("version"),
+        blob_prefix: row.get("blob_prefix"),
+    }
+}
+
+#[async_trait]
+impl IPhotoRepository for PhotoRepository {
+    #[tracing::instrument(skip_all, fields(%address_book_id, %contact_id))]
+    async fn get_photo(
+        &self,
+        address_book_id: i32,
+        contact_id: i32,
+    ) -> Result<Photo, handle_errors::Error> {
+        let q = "SELECT c.id AS id, p.* FROM contacts AS c
+                 JOIN address_books AS ab ON ab.id = c.address_book_id AND ab.deleted_at IS NULL
+                 LEFT JOIN contact_photos AS p ON p.contact_id = c.id
+                 WHERE c.id = $1 AND c.address_book_id = $2 AND c.deleted_at IS NULL";
+        let row = sqlx::query(q)
+            .bind(contact_id)
+            .bind(address_book_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        match row {
+            None => Err(handle_errors::Error::ContactNotFound),
+            Some(row) if row.get::<Option<i32>, _>("contact_id").is_none() => {
+                Err(handle_errors::Error::PhotoNotFound)
+            }
+            Some(row) => Ok(photo_from_row(row)),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, %contact_id))]
+    async fn save_photo(
+        &self,
+        address_book_id: i32,
+        contact_id: i32,
+        photo: NewPhoto,
+        actor: Option<String>,
+    ) -> Result<(Photo, Option<Photo>), handle_errors::Error> {
+        let mut tx = self.pool.begin().await?;
+        ensure_live_address_book(&mut tx, address_book_id).await?;
+        let Some(before) = lock_contact(&mut tx, contact_id, address_book_id, false).await? else {
+            return Err(handle_errors::Error::ContactNotFound);
+        };
+
+        let replaced = sqlx::query("SELECT * FROM contact_photos WHERE contact_id = $1")
+            .bind(contact_id)
+            .map(photo_from_row)
+            .fetch_optional(&mut *tx)
+            .await?;
+        let q = "INSERT INTO contact_photos
+                 (contact_id, content_type, width, height, byte_size, blob_prefix,
+                  created_by, updated_by)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $7)
+                 ON CONFLICT (contact_id) DO UPDATE SET
+                 content_type = EXCLUDED.content_type, width = EXCLUDED.width,
+                 height = EXCLUDED.height, byte_size = EXCLUDED.byte_size,
+                 blob_prefix = EXCLUDED.blob_prefix, updated_by = EXCLUDED.updated_by
+                 RETURNING *";
+        let saved = sqlx::query(q)
+            .bind(contact_id)
+            .bind(&photo.content_type)
+            .bind(photo.width)
+            .bind(photo.height)
+            .bind(photo.byte_size())
+            .bind(&photo.blob_prefix)
+            .bind(&actor)
+            .map(photo_from_row)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        let contact = touch_contact(&mut tx, contact_id, &actor).await?;
+        let event = NewAuditEvent::contact(AuditAction::Update, actor, Some(&before), &contact);
+        record_event(&mut tx, event).await?;
+        tx.commit().await?;
+
+        Ok((saved, replaced))
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, %contact_id))]
+    async fn delete_photo(
+        &self,
+        address_book_id: i32,
+        contact_id: i32,
+        actor: Option<String>,
+    ) -> Result<Photo, handle_errors::Error> {
+        let mut tx = self.pool.begin().await?;
+        ensure_live_address_book(&mut tx, address_book_id).await?;
+        let Some(before) = lock_contact(&mut tx, contact_id, address_book_id, false).await? else {
+            return Err(handle_errors::Error::ContactNotFound);
+        };
+
+        let Some(deleted) =
+            sqlx::query("DELETE FROM contact_photos WHERE contact_id = $1 RETURNING *")
+                .bind(contact_id)
+                .map(photo_from_row)
+                .fetch_optional(&mut *tx)
+                .await?
+        else {
+            return Err(handle_errors::Error::PhotoNotFound);
+        };
+
+        let contact = touch_contact(&mut tx, contact_id, &actor).await?;
+        let event = NewAuditEvent::contact(AuditAction::Update, actor, Some(&before), &contact);
+        record_event(&mut tx, event).await?;
+        tx.commit().await?;
+
+        Ok(deleted)
+    }
+}
@@ -0,0 +1,36 @@
+use sqlx::PgPool;
+use std::time::Duration;
+
+/// A pool with the settings of `pool` whose connections cancel any statement that runs longer
+/// than `timeout`, so a slow query fails instead of holding its connection. Zero means no limit.
+/// `pool` is closed, so its connections do not count against the server's limit.
+pub async fn with_statement_timeout(pool: PgPool, timeout: Duration) -> PgPool {
+    let options = (*pool.connect_options())
+        .clone()
+        .options([("statement_timeout", timeout.as_millis())]);
+    let bounded = pool.options().clone().connect_lazy_with(options);
+    pool.close().await;
+    bounded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Needs a database server: DATABASE_URL=postgres://... cargo test -- --ignored
+    #[sqlx::test]
+    #[ignore]
+    async fn test_bounded_pool_replaces_the_original(pool: PgPool) {
+        let bounded = with_statement_timeout(pool.clone(), Duration::from_millis(1500)).await;
+        assert!(pool.is_closed());
+
+        let timeout: String = sqlx::query_scalar("SHOW statement_timeout")
+            .fetch_one(&bounded)
+            .await
+            .unwrap();
+        assert_eq!(timeout, "1500ms");
+        let slow = sqlx::query("SELECT pg_sleep(3)").execute(&bounded).await;
+        assert!(slow.is_err());
+        bounded.close().await;
+    }
+}
@@ -0,0 +1,130 @@
+use crate::types::rate_limit::{Bucket, Decision, RateLimit, REFILL_WINDOW_SECS};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Row};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+#[cfg(test)]
+use mockall::{predicate::*, *};
+
+/// Keeps the token buckets of the rate limiter.
+#[async_trait]
+#[cfg_attr(test, automock)]
+pub trait IRateLimitStore {
+    /// Takes a token from the bucket named `key`, which starts out full.
+    async fn take(
+        &self,
+        key: &str,
+        limit: RateLimit,
+        now: DateTime<Utc>,
+    ) -> Result<Decision, handle_errors::Error>;
+
+    /// Forgets buckets last used before `before`, returning how many were removed.
+    async fn prune(&self, before: DateTime<Utc>) -> Result<u64, handle_errors::Error>;
+}
+
+/// Buckets in process memory. Each instance counts only the requests it serves.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryRateLimitStore {
+    buckets: Arc<Mutex<HashMap<String, Bucket>>>,
+}
+
+impl MemoryRateLimitStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl IRateLimitStore for MemoryRateLimitStore {
+    #[tracing::instrument(skip_all)]
+    async fn take(
+        &self,
+        key: &str,
+        limit: RateLimit,
+        now: DateTime<Utc>,
+    ) -> Result<Decision, handle_errors::Error> {
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        let bucket = buckets
+            .entry(key.to_string())
+            .or_insert_with(|| Bucket::full(limit, now));
+        let allowed = bucket.take(limit, now);
+        Ok(Decision::new(limit, allowed, bucket.tokens))
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn prune(&self, before: DateTime<Utc>) -> Result<u64, handle_errors::Error> {
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        let count = buckets.len();
+        buckets.retain(|_, bucket| bucket.updated_at >= before);
+        Ok((count - buckets.len()) as u64)
+    }
+}
+
+/// Buckets in the `rate_limit_buckets` table, shared by every instance on the database.
+pub struct PgRateLimitStore {
+    pool: PgPool,
+}
+
+impl PgRateLimitStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl IRateLimitStore for PgRateLimitStore {
+    #[tracing::instrument(skip_all)]
+    async fn take(
+        &self,
+        key: &str,
+        limit: RateLimit,
+        now: DateTime<Utc>,
+    ) -> Result<Decision, handle_errors::Error> {
+        let row =
+            sqlx::query("SELECT granted, remaining FROM take_rate_limit_token($1, $2, $3, $4)")
+                .bind(key)
+                .bind(limit.capacity())
+                .bind(REFILL_WINDOW_SECS as f64)
+                .bind(now)
+                .fetch_one(&self.pool)
+                .await?;
+
+        Ok(Decision::new(
+            limit,
+            row.get("granted"),
+            row.get("remaining"),
+        ))
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn prune(&self, before: DateTime<Utc>) -> Result<u64, handle_errors::Error> {
+        let result = sqlx::query("DELETE FROM rate_limit_buckets WHERE updated_at < $1")
+            .bind(before)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[tokio::test]
+    async fn test_memory_store_keeps_buckets_apart() {
+        let store = MemoryRateLimitStore::new();
+        let limit = RateLimit { per_minute: 1 };
+        let now = Utc::now();
+
+        assert!(store.take("read:ip:a", limit, now).await.unwrap().allowed);
+        assert!(!store.take("read:ip:a", limit, now).await.unwrap().allowed);
+        assert!(store.take("read:ip:b", limit, now).await.unwrap().allowed);
+
+        let pruned = store.prune(now + Duration::seconds(1)).await.unwrap();
+        assert_eq!(pruned, 2);
+        assert!(store.take("read:ip:a", limit, now).await.unwrap().allowed);
+    }
+}
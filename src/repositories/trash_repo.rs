@@ -0,0 +1,195 @@
+use crate::repositories::address_book_repo::{address_book_from_row, BOOK_COLUMNS};
+use crate::repositories::audit_repo::record_event;
+use crate::repositories::contact_repo::{contact_from_row, CONTACT_COLUMNS};
+use crate::types::audit::NewAuditEvent;
+use crate::types::trash::{Purged, Trash};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+use sqlx::postgres::PgRow;
+use sqlx::PgPool;
+
+#[cfg(test)]
+use mockall::{predicate::*, *};
+
+#[async_trait]
+#[cfg_attr(test, automock)]
+pub trait ITrashRepository {
+    async fn get_trash(
+        &self,
+        limit: Option<i32>,
+        offset: i32,
+    ) -> Result<Trash, handle_errors::Error>;
+
+    /// Permanently removes books and contacts soft-deleted before `deleted_before`,
+    /// returning the number of rows removed and where the removed photos are stored. Live
+    /// contacts of a purged book go with it and get a `delete` audit event each.
+    async fn purge_deleted(
+        &self,
+        deleted_before: DateTime<Utc>,
+    ) -> Result<Purged, handle_errors::Error>;
+}
+
+pub struct TrashRepository {
+    pool: PgPool,
+}
+
+impl TrashRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl ITrashRepository for TrashRepository {
+    #[tracing::instrument(skip_all)]
+    async fn get_trash(
+        &self,
+        limit: Option<i32>,
+        offset: i32,
+    ) -> Result<Trash, handle_errors::Error> {
+        let books_q = format!(
+            "SELECT {BOOK_COLUMNS} FROM address_books
+             WHERE deleted_at IS NOT NULL
+             ORDER BY deleted_at DESC, id LIMIT $1 OFFSET $2"
+        );
+        let address_books = sqlx::query(&books_q)
+            .bind(limit)
+            .bind(offset)
+            .map(|row: PgRow| address_book_from_row(&row))
+            .fetch_all(&self.pool)
+            .await
+            .map_err(handle_errors::Error::DatabaseQueryError)?;
+
+        let contacts_q = format!(
+            "SELECT {CONTACT_COLUMNS} FROM contacts
+             WHERE deleted_at IS NOT NULL
+             ORDER BY deleted_at DESC, id LIMIT $1 OFFSET $2"
+        );
+        let contacts = sqlx::query(&contacts_q)
+            .bind(limit)
+            .bind(offset)
+            .map(contact_from_row)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(handle_errors::Error::DatabaseQueryError)?;
+
+        Ok(Trash {
+            address_books,
+            contacts,
+        })
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn purge_deleted(
+        &self,
+        deleted_before: DateTime<Utc>,
+    ) -> Result<Purged, handle_errors::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        // Contacts still live in a trashed book were never deleted themselves, so nothing
+        // in the audit trail says they are gone.
+        let orphans_q = format!(
+            "SELECT {CONTACT_COLUMNS} FROM contacts
+             WHERE deleted_at IS NULL
+             AND address_book_id IN (SELECT id FROM address_books WHERE deleted_at < $1)
+             ORDER BY id FOR UPDATE"
+        );
+        let orphans = sqlx::query(&orphans_q)
+            .bind(deleted_before)
+            .map(contact_from_row)
+            .fetch_all(&mut *tx)
+            .await?;
+        for contact in &orphans {
+            record_event(&mut tx, NewAuditEvent::purged_contact(contact)).await?;
+        }
+
+        // Photo rows cascade with their contacts; their blobs are the caller's to remove.
+        let photo_blob_prefixes = sqlx::query_scalar(
+            "SELECT p.blob_prefix FROM contact_photos AS p
+             JOIN contacts AS c ON c.id = p.contact_id
+             WHERE c.deleted_at < $1
+             OR c.address_book_id IN (SELECT id FROM address_books WHERE deleted_at < $1)",
+        )
+        .bind(deleted_before)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let contacts = sqlx::query("DELETE FROM contacts WHERE deleted_at < $1")
+            .bind(deleted_before)
+            .execute(&mut *tx)
+            .await?;
+        let address_books = sqlx::query("DELETE FROM address_books WHERE deleted_at < $1")
+            .bind(deleted_before)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(Purged {
+            rows: orphans.len() as u64 + contacts.rows_affected() + address_books.rows_affected(),
+            photo_blob_prefixes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::address_book_repo::{AddressBookRepository, IAddressBookRepository};
+    use crate::repositories::contact_repo::{ContactRepository, IContactRepository};
+    use crate::types::contact::NewContact;
+    use crate::types::precondition::IfMatch;
+    use chrono::Duration;
+
+    // Needs a database server: DATABASE_URL=postgres://... cargo test -- --ignored
+    #[sqlx::test]
+    #[ignore]
+    async fn test_purge_records_deletion_of_contacts_left_in_book(pool: PgPool) {
+        let books = AddressBookRepository::new(pool.clone());
+        let book = books
+            .create_address_book(String::from("Old"), None)
+            .await
+            .unwrap();
+        let contact = NewContact {
+            name: String::from("Ann"),
+            address: String::from("1 Main St"),
+            ..Default::default()
+        };
+        let contact = ContactRepository::new(pool.clone())
+            .add_contact_to_address_book(book.id.0, contact, None)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO contact_photos
+             (contact_id, content_type, width, height, byte_size, blob_prefix)
+             VALUES ($1, 'image/png', 1, 1, 1, 'contacts/x/photos/y')",
+        )
+        .bind(contact.id.0)
+        .execute(&pool)
+        .await
+        .unwrap();
+        books
+            .delete_address_book(book.id.0, None, IfMatch::default())
+            .await
+            .unwrap();
+
+        let purged = TrashRepository::new(pool.clone())
+            .purge_deleted(Utc::now() + Duration::seconds(1))
+            .await
+            .unwrap();
+        assert_eq!(purged.rows, 2);
+        assert_eq!(purged.photo_blob_prefixes, ["contacts/x/photos/y"]);
+
+        let (action, changes): (String, serde_json::Value) = sqlx::query_as(
+            "SELECT action, changes FROM audit_events
+             WHERE entity_type = 'contact' AND entity_id = $1 ORDER BY id DESC LIMIT 1",
+        )
+        .bind(contact.id.0)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(action, "delete");
+        assert_eq!(changes["before"]["name"], "Ann");
+        assert!(changes["after"].is_null());
+    }
+}
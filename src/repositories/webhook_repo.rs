@@ -0,0 +1,528 @@
+use crate::repositories::contact_repo::ensure_live_address_book;
+use crate::types::address_book::AddressBookId;
+use crate::types::audit::NewAuditEvent;
+use crate::types::precondition::IfMatch;
+use crate::types::webhook::{
+    event_type, DeliveryAttempt, DeliveryOutcome, NewWebhook, PendingDelivery, Webhook,
+    WebhookDelivery, WebhookId,
+};
+
+use async_trait::async_trait;
+use chrono::Duration;
+use serde_json::json;
+use sqlx::postgres::PgRow;
+use sqlx::{PgConnection, PgPool, Row};
+
+#[cfg(test)]
+use mockall::{predicate::*, *};
+
+#[async_trait]
+#[cfg_attr(test, automock)]
+pub trait IWebhookRepository {
+    async fn get_webhooks(
+        &self,
+        address_book_id: i32,
+    ) -> Result<Vec<Webhook>, handle_errors::Error>;
+
+    async fn get_webhook(
+        &self,
+        address_book_id: i32,
+        id: i32,
+    ) -> Result<Webhook, handle_errors::Error>;
+
+    async fn create_webhook(
+        &self,
+        address_book_id: i32,
+        webhook: NewWebhook,
+        actor: Option<String>,
+    ) -> Result<Webhook, handle_errors::Error>;
+
+    async fn update_webhook(
+        &self,
+        address_book_id: i32,
+        id: i32,
+        webhook: NewWebhook,
+        actor: Option<String>,
+        if_match: IfMatch,
+    ) -> Result<Webhook, handle_errors::Error>;
+
+    /// Deletes the subscription along with its deliveries.
+    async fn delete_webhook(
+        &self,
+        address_book_id: i32,
+        id: i32,
+        if_match: IfMatch,
+    ) -> Result<(), handle_errors::Error>;
+
+    /// The subscription's deliveries, newest first.
+    async fn get_deliveries(
+        &self,
+        address_book_id: i32,
+        webhook_id: i32,
+        limit: Option<i32>,
+        offset: i32,
+    ) -> Result<Vec<WebhookDelivery>, handle_errors::Error>;
+
+    async fn get_delivery(
+        &self,
+        address_book_id: i32,
+        webhook_id: i32,
+        id: i64,
+    ) -> Result<WebhookDelivery, handle_errors::Error>;
+
+    /// Makes the delivery due now with a fresh set of attempts, whatever its status.
+    async fn redeliver(
+        &self,
+        address_book_id: i32,
+        webhook_id: i32,
+        id: i64,
+    ) -> Result<WebhookDelivery, handle_errors::Error>;
+
+    /// Claims up to `limit` due deliveries of active subscriptions, oldest first. A claimed
+    /// delivery is not due again until `lease` has passed, so a dispatcher that dies mid-way
+    /// only delays it.
+    async fn claim_due_deliveries(
+        &self,
+        limit: i64,
+        lease: Duration,
+    ) -> Result<Vec<PendingDelivery>, handle_errors::Error>;
+
+    /// Logs an attempt and moves the delivery on according to `outcome`.
+    async fn record_attempt(
+        &self,
+        id: i64,
+        attempt: DeliveryAttempt,
+        outcome: DeliveryOutcome,
+    ) -> Result<(), handle_errors::Error>;
+}
+
+pub struct WebhookRepository {
+    pool: PgPool,
+}
+
+impl WebhookRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+/// Queues `event` for every active subscription that wants it, on the caller's connection so
+/// it commits or rolls back together with the mutation. A contact move is also reported to
+/// the book it left.
+pub(crate) async fn enqueue_webhooks(
+    conn: &mut PgConnection,
+    event: &NewAuditEvent,
+) -> Result<(), sqlx::Error> {
+    let Some(event_type) = event_type(event) else {
+        return Ok(());
+    };
+    let payload = json!({
+        "entity_id": event.entity_id,
+        "address_book_id": event.address_book_id,
+        "actor": event.actor,
+        "changes": event.changes,
+    });
+
+    let q = "INSERT INTO webhook_outbox (subscription_id, event_type, payload)
+             SELECT id, $3, $4 FROM webhook_subscriptions
+             WHERE (address_book_id = $1 OR address_book_id = $2)
+             AND active AND $3 = ANY(event_types)
+             ORDER BY id";
+    sqlx::query(q)
+        .bind(event.address_book_id)
+        .bind(event.source_address_book_id)
+        .bind(event_type)
+        .bind(payload)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+fn webhook_from_row(row: PgRow) -> Webhook {
+    Webhook {
+        id: WebhookId(row.get("id")),
+        address_book_id: AddressBookId(row.get("address_book_id")),
+        url: row.get("url"),
+        event_types: row.get("event_types"),
+        active: row.get("active"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+        created_by: row.get("created_by"),
+        updated_by: row.get("updated_by"),
+        version: row.get("version"),
+    }
+}
+
+fn delivery_from_row(row: PgRow) -> WebhookDelivery {
+    let status: String = row.get("status");
+    WebhookDelivery {
+        id: row.get("id"),
+        webhook_id: WebhookId(row.get("subscription_id")),
+        event_type: row.get("event_type"),
+        payload: row.get("payload"),
+        next_attempt_at: (status == "pending").then(|| row.get("next_attempt_at")),
+        status,
+        attempts: row.get("attempts"),
+        created_at: row.get("created_at"),
+        delivered_at: row.get("delivered_at"),
+        attempt_log: Vec::new(),
+    }
+}
+
+fn attempt_from_row(row: &PgRow) -> DeliveryAttempt {
+    DeliveryAttempt {
+        attempt: row.get("attempt"),
+        attempted_at: row.get("attempted_at"),
+        status_code: row.get("status_code"),
+        error: row.get("error"),
+        response_body: row.get("response_body"),
+        duration_ms: row.get("duration_ms"),
+    }
+}
+
+/// Locks a subscription of a live book for the rest of the transaction.
+async fn lock_webhook(
+    conn: &mut PgConnection,
+    address_book_id: i32,
+    id: i32,
+) -> Result<Option<Webhook>, sqlx::Error> {
+    let q = "SELECT w.* FROM webhook_subscriptions AS w
+             JOIN address_books AS ab ON ab.id = w.address_book_id AND ab.deleted_at IS NULL
+             WHERE w.id = $1 AND w.address_book_id = $2
+             FOR UPDATE OF w";
+    sqlx::query(q)
+        .bind(id)
+        .bind(address_book_id)
+        .map(webhook_from_row)
+        .fetch_optional(conn)
+        .await
+}
+
+async fn ensure_webhook(
+    conn: &mut PgConnection,
+    address_book_id: i32,
+    id: i32,
+) -> Result<(), handle_errors::Error> {
+    let q = "SELECT w.id FROM webhook_subscriptions AS w
+             JOIN address_books AS ab ON ab.id = w.address_book_id AND ab.deleted_at IS NULL
+             WHERE w.id = $1 AND w.address_book_id = $2";
+    match sqlx::query(q)
+        .bind(id)
+        .bind(address_book_id)
+        .fetch_optional(conn)
+        .await?
+    {
+        Some(_) => Ok(()),
+        None => Err(handle_errors::Error::WebhookNotFound),
+    }
+}
+
+/// Fills in the attempt log of each delivery.
+async fn load_attempts(
+    conn: &mut PgConnection,
+    deliveries: &mut [WebhookDelivery],
+) -> Result<(), sqlx::Error> {
+    let ids: Vec<i64> = deliveries.iter().map(|delivery| delivery.id).collect();
+    let q = "SELECT * FROM webhook_delivery_attempts WHERE outbox_id = ANY($1)
+             ORDER BY attempted_at, id";
+    let rows = sqlx::query(q).bind(ids).fetch_all(conn).await?;
+    for row in rows {
+        let outbox_id: i64 = row.get("outbox_id");
+        if let Some(delivery) = deliveries.iter_mut().find(|d| d.id == outbox_id) {
+            delivery.attempt_log.push(attempt_from_row(&row));
+        }
+    }
+    Ok(())
+}
+
+async fn fetch_delivery(
+    conn: &mut PgConnection,
+    webhook_id: i32,
+    id: i64,
+) -> Result<WebhookDelivery, handle_errors::Error> {
+    let q = "SELECT * FROM webhook_outbox WHERE id = $1 AND subscription_id = $2";
+    let Some(delivery) = sqlx::query(q)
+        .bind(id)
+        .bind(webhook_id)
+        .map(delivery_from_row)
+        .fetch_optional(&mut *conn)
+        .await?
+    else {
+        return Err(handle_errors::Error::WebhookDeliveryNotFound);
+    };
+    let mut deliveries = [delivery];
+    load_attempts(conn, &mut deliveries).await?;
+    let [delivery] = deliveries;
+    Ok(delivery)
+}
+
+#[async_trait]
+impl IWebhookRepository for WebhookRepository {
+    #[tracing::instrument(skip_all, fields(%address_book_id))]
+    async fn get_webhooks(
+        &self,
+        address_book_id: i32,
+    ) -> Result<Vec<Webhook>, handle_errors::Error> {
+        let mut conn = self.pool.acquire().await?;
+        ensure_live_address_book(&mut conn, address_book_id).await?;
+
+        let q = "SELECT * FROM webhook_subscriptions WHERE address_book_id = $1 ORDER BY id";
+        Ok(sqlx::query(q)
+            .bind(address_book_id)
+            .map(webhook_from_row)
+            .fetch_all(&mut *conn)
+            .await?)
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, webhook_id = %id))]
+    async fn get_webhook(
+        &self,
+        address_book_id: i32,
+        id: i32,
+    ) -> Result<Webhook, handle_errors::Error> {
+        let q = "SELECT w.* FROM webhook_subscriptions AS w
+                 JOIN address_books AS ab ON ab.id = w.address_book_id AND ab.deleted_at IS NULL
+                 WHERE w.id = $1 AND w.address_book_id = $2";
+        match sqlx::query(q)
+            .bind(id)
+            .bind(address_book_id)
+            .map(webhook_from_row)
+            .fetch_optional(&self.pool)
+            .await?
+        {
+            Some(webhook) => Ok(webhook),
+            None => Err(handle_errors::Error::WebhookNotFound),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id))]
+    async fn create_webhook(
+        &self,
+        address_book_id: i32,
+        webhook: NewWebhook,
+        actor: Option<String>,
+    ) -> Result<Webhook, handle_errors::Error> {
+        let mut tx = self.pool.begin().await?;
+        ensure_live_address_book(&mut tx, address_book_id).await?;
+
+        let q = "INSERT INTO webhook_subscriptions
+                 (address_book_id, url, secret, event_types, active, created_by, updated_by)
+                 VALUES ($1, $2, $3, $4, $5, $6, $6) RETURNING *";
+        let webhook = sqlx::query(q)
+            .bind(address_book_id)
+            .bind(webhook.url)
+            .bind(webhook.secret)
+            .bind(webhook.event_types)
+            .bind(webhook.active)
+            .bind(actor)
+            .map(webhook_from_row)
+            .fetch_one(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(webhook)
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, webhook_id = %id))]
+    async fn update_webhook(
+        &self,
+        address_book_id: i32,
+        id: i32,
+        webhook: NewWebhook,
+        actor: Option<String>,
+        if_match: IfMatch,
+    ) -> Result<Webhook, handle_errors::Error> {
+        let mut tx = self.pool.begin().await?;
+        let Some(before) = lock_webhook(&mut tx, address_book_id, id).await? else {
+            return Err(handle_errors::Error::WebhookNotFound);
+        };
+        if !if_match.matches(&before.etag()) {
+            return Err(handle_errors::Error::PreconditionFailed);
+        }
+
+        let q = "UPDATE webhook_subscriptions
+                 SET url = $2, secret = $3, event_types = $4, active = $5, updated_by = $6
+                 WHERE id = $1 RETURNING *";
+        let webhook = sqlx::query(q)
+            .bind(id)
+            .bind(webhook.url)
+            .bind(webhook.secret)
+            .bind(webhook.event_types)
+            .bind(webhook.active)
+            .bind(actor)
+            .map(webhook_from_row)
+            .fetch_one(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(webhook)
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, webhook_id = %id))]
+    async fn delete_webhook(
+        &self,
+        address_book_id: i32,
+        id: i32,
+        if_match: IfMatch,
+    ) -> Result<(), handle_errors::Error> {
+        let mut tx = self.pool.begin().await?;
+        let Some(before) = lock_webhook(&mut tx, address_book_id, id).await? else {
+            return Err(handle_errors::Error::WebhookNotFound);
+        };
+        if !if_match.matches(&before.etag()) {
+            return Err(handle_errors::Error::PreconditionFailed);
+        }
+
+        sqlx::query("DELETE FROM webhook_subscriptions WHERE id = $1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, %webhook_id))]
+    async fn get_deliveries(
+        &self,
+        address_book_id: i32,
+        webhook_id: i32,
+        limit: Option<i32>,
+        offset: i32,
+    ) -> Result<Vec<WebhookDelivery>, handle_errors::Error> {
+        let mut conn = self.pool.acquire().await?;
+        ensure_webhook(&mut conn, address_book_id, webhook_id).await?;
+
+        let q = "SELECT * FROM webhook_outbox WHERE subscription_id = $1
+                 ORDER BY id DESC LIMIT $2 OFFSET $3";
+        let mut deliveries = sqlx::query(q)
+            .bind(webhook_id)
+            .bind(limit)
+            .bind(offset)
+            .map(delivery_from_row)
+            .fetch_all(&mut *conn)
+            .await?;
+        load_attempts(&mut conn, &mut deliveries).await?;
+
+        Ok(deliveries)
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, %webhook_id, delivery_id = %id))]
+    async fn get_delivery(
+        &self,
+        address_book_id: i32,
+        webhook_id: i32,
+        id: i64,
+    ) -> Result<WebhookDelivery, handle_errors::Error> {
+        let mut conn = self.pool.acquire().await?;
+        ensure_webhook(&mut conn, address_book_id, webhook_id).await?;
+        fetch_delivery(&mut conn, webhook_id, id).await
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, %webhook_id, delivery_id = %id))]
+    async fn redeliver(
+        &self,
+        address_book_id: i32,
+        webhook_id: i32,
+        id: i64,
+    ) -> Result<WebhookDelivery, handle_errors::Error> {
+        let mut tx = self.pool.begin().await?;
+        ensure_webhook(&mut tx, address_book_id, webhook_id).await?;
+
+        let q = "UPDATE webhook_outbox
+                 SET status = 'pending', attempts = 0, next_attempt_at = now()
+                 WHERE id = $1 AND subscription_id = $2";
+        sqlx::query(q)
+            .bind(id)
+            .bind(webhook_id)
+            .execute(&mut *tx)
+            .await?;
+        let delivery = fetch_delivery(&mut tx, webhook_id, id).await?;
+        tx.commit().await?;
+
+        Ok(delivery)
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn claim_due_deliveries(
+        &self,
+        limit: i64,
+        lease: Duration,
+    ) -> Result<Vec<PendingDelivery>, handle_errors::Error> {
+        let q = "WITH due AS (
+                     SELECT o.id FROM webhook_outbox AS o
+                     JOIN webhook_subscriptions AS s ON s.id = o.subscription_id AND s.active
+                     WHERE o.status = 'pending' AND o.next_attempt_at <= now()
+                     ORDER BY o.next_attempt_at, o.id
+                     LIMIT $1
+                     FOR UPDATE OF o SKIP LOCKED
+                 )
+                 UPDATE webhook_outbox AS o
+                 SET next_attempt_at = now() + $2 * interval '1 millisecond'
+                 FROM due, webhook_subscriptions AS s
+                 WHERE o.id = due.id AND s.id = o.subscription_id
+                 RETURNING o.*, s.url, s.secret";
+        let mut deliveries = sqlx::query(q)
+            .bind(limit)
+            .bind(lease.num_milliseconds() as f64)
+            .map(|row: PgRow| PendingDelivery {
+                id: row.get("id"),
+                url: row.get("url"),
+                secret: row.get("secret"),
+                event_type: row.get("event_type"),
+                payload: row.get("payload"),
+                created_at: row.get("created_at"),
+                attempts: row.get("attempts"),
+            })
+            .fetch_all(&self.pool)
+            .await?;
+        deliveries.sort_by_key(|delivery| delivery.id);
+
+        Ok(deliveries)
+    }
+
+    #[tracing::instrument(skip_all, fields(delivery_id = %id))]
+    async fn record_attempt(
+        &self,
+        id: i64,
+        attempt: DeliveryAttempt,
+        outcome: DeliveryOutcome,
+    ) -> Result<(), handle_errors::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let q = "INSERT INTO webhook_delivery_attempts
+                 (outbox_id, attempt, attempted_at, status_code, error, response_body, duration_ms)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7)";
+        sqlx::query(q)
+            .bind(id)
+            .bind(attempt.attempt)
+            .bind(attempt.attempted_at)
+            .bind(attempt.status_code)
+            .bind(attempt.error)
+            .bind(attempt.response_body)
+            .bind(attempt.duration_ms)
+            .execute(&mut *tx)
+            .await?;
+
+        let (status, next_attempt_at) = match outcome {
+            DeliveryOutcome::Delivered => ("delivered", None),
+            DeliveryOutcome::Retry(at) => ("pending", Some(at)),
+            DeliveryOutcome::Failed => ("failed", None),
+        };
+        let q = "UPDATE webhook_outbox
+                 SET status = $2, attempts = $3,
+                     next_attempt_at = COALESCE($4, next_attempt_at),
+                     delivered_at = CASE WHEN $2 = 'delivered' THEN now() END
+                 WHERE id = $1";
+        sqlx::query(q)
+            .bind(id)
+            .bind(status)
+            .bind(attempt.attempt)
+            .bind(next_attempt_at)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+}
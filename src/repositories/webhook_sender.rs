@@ -0,0 +1,195 @@
+use async_trait::async_trait;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::Url;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::types::webhook::{WebhookTargets, MAX_LOGGED_RESPONSE_CHARS};
+
+#[cfg(test)]
+use mockall::{predicate::*, *};
+
+/// How long a receiver has to answer before the attempt counts as failed.
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A receiver's answer to a delivery.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WebhookResponse {
+    pub status: u16,
+    /// The start of the response body, at most `MAX_LOGGED_RESPONSE_CHARS` long.
+    pub body: String,
+}
+
+/// Posts webhook deliveries to their receivers.
+#[async_trait]
+#[cfg_attr(test, automock)]
+pub trait IWebhookSender {
+    /// Fails unless every address the host of `url` resolves to may receive deliveries.
+    async fn check_target(&self, url: &str) -> Result<(), String>;
+
+    /// Posts `body` as JSON to `url`. Any HTTP response is `Ok`, whatever its status; `Err`
+    /// describes why no response arrived.
+    async fn post(
+        &self,
+        url: &str,
+        headers: Vec<(String, String)>,
+        body: String,
+    ) -> Result<WebhookResponse, String>;
+}
+
+#[derive(Debug, Clone)]
+pub struct HttpWebhookSender {
+    client: reqwest::Client,
+    targets: Arc<WebhookTargets>,
+}
+
+impl HttpWebhookSender {
+    pub fn new(targets: WebhookTargets) -> Self {
+        let targets = Arc::new(targets);
+        let client = reqwest::Client::builder()
+            .timeout(DELIVERY_TIMEOUT)
+            // A redirect could carry the signed payload somewhere the subscriber did not name.
+            .redirect(reqwest::redirect::Policy::none())
+            // A proxy would connect on our behalf, past the address checks.
+            .no_proxy()
+            // Checks the addresses actually connected to, so a name cannot pass
+            // `check_target` and then resolve somewhere internal.
+            .dns_resolver(Arc::new(TargetResolver {
+                targets: targets.clone(),
+            }))
+            .build()
+            .expect("the HTTP client configuration is valid");
+        Self { client, targets }
+    }
+}
+
+/// Resolves `host`, failing unless `targets` permits every address it has.
+async fn resolve(
+    host: &str,
+    port: u16,
+    targets: &WebhookTargets,
+) -> Result<Vec<SocketAddr>, String> {
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|e| format!("{host} could not be resolved: {e}"))?
+        .collect();
+    if addrs.is_empty() {
+        return Err(format!("{host} has no addresses"));
+    }
+    match addrs.iter().find(|addr| !targets.permits(addr.ip())) {
+        Some(addr) => Err(format!(
+            "{host} resolves to {}, which is not a public address",
+            addr.ip()
+        )),
+        None => Ok(addrs),
+    }
+}
+
+struct TargetResolver {
+    targets: Arc<WebhookTargets>,
+}
+
+impl Resolve for TargetResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let targets = self.targets.clone();
+        Box::pin(async move {
+            let addrs = resolve(name.as_str(), 0, &targets).await?;
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+#[async_trait]
+impl IWebhookSender for HttpWebhookSender {
+    #[tracing::instrument(skip_all)]
+    async fn check_target(&self, url: &str) -> Result<(), String> {
+        let url = Url::parse(url).map_err(|e| e.to_string())?;
+        let host = url.host_str().ok_or("the url has no host")?;
+        // IP literals are written in brackets in URLs but parsed without.
+        match host.trim_matches(['[', ']']).parse::<IpAddr>() {
+            Ok(ip) if self.targets.permits(ip) => Ok(()),
+            Ok(ip) => Err(format!("{ip} is not a public address")),
+            Err(_) => {
+                let port = url.port_or_known_default().unwrap_or(80);
+                resolve(host, port, &self.targets).await.map(|_| ())
+            }
+        }
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn post(
+        &self,
+        url: &str,
+        headers: Vec<(String, String)>,
+        body: String,
+    ) -> Result<WebhookResponse, String> {
+        // Names are checked again as they are connected to; IP literals only here.
+        self.check_target(url).await?;
+        let mut request = self
+            .client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body);
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+
+        let mut response = request.send().await.map_err(|e| e.to_string())?;
+        let status = response.status().as_u16();
+        // Reads no more of the body than the log keeps; a character is at most four bytes.
+        let mut body = Vec::new();
+        while body.len() < MAX_LOGGED_RESPONSE_CHARS * 4 {
+            match response.chunk().await {
+                Ok(Some(chunk)) => body.extend_from_slice(&chunk),
+                _ => break,
+            }
+        }
+        let body = String::from_utf8_lossy(&body)
+            .chars()
+            .take(MAX_LOGGED_RESPONSE_CHARS)
+            .collect();
+        Ok(WebhookResponse { status, body })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_check_target_refuses_internal_hosts() {
+        let sender = HttpWebhookSender::new(WebhookTargets::default());
+        for url in [
+            "http://127.0.0.1:9000/hook",
+            "http://[::1]/hook",
+            "http://169.254.169.254/latest/meta-data",
+            "http://localhost:9000/hook",
+            "http://[::ffff:7f00:1]/hook",
+            "http://[64:ff9b::a9fe:a9fe]/latest/meta-data",
+            "http://[2002:a00:1::1]/hook",
+            "http://[fec0::1]/hook",
+            "http://[::127.0.0.1]/hook",
+        ] {
+            assert!(sender.check_target(url).await.is_err(), "{url} passed");
+        }
+        assert!(sender.check_target("https://93.184.216.34/").await.is_ok());
+
+        let sender = HttpWebhookSender::new(WebhookTargets {
+            allowed: vec!["127.0.0.0/8".parse().unwrap()],
+        });
+        assert!(sender
+            .check_target("http://127.0.0.1:9000/hook")
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_post_refuses_internal_hosts() {
+        let sender = HttpWebhookSender::new(WebhookTargets::default());
+        let result = sender
+            .post("http://127.0.0.1:9/hook", Vec::new(), String::from("{}"))
+            .await;
+        assert!(result.is_err());
+    }
+}
@@ -2,27 +2,61 @@ use axum::extract::{rejection::JsonRejection, Json, Path, Query, State};
 
 use crate::repositories::address_book_repo::AddressBookRepository;
 use crate::services::address_book_service::AddressBookService;
-use crate::types::address_book::NewAddressBook;
-use crate::types::{ApiError, ApiResponse, AppState, Pagination};
+use crate::types::address_book::{AddressBook, NewAddressBook, ViewParams};
+use crate::types::precondition::{IfMatch, IfNoneMatch};
+use crate::types::stats::AddressBookStats;
+use crate::types::{Actor, ApiError, ApiResponse, AppState, ErrorBody, ListFilter, Pagination};
 
+use super::map_error;
 use handle_errors::Error;
 
+#[utoipa::path(
+    get,
+    path = "/api/addressbooks",
+    tag = "address books",
+    params(Pagination, ListFilter, ViewParams),
+    responses(
+        (status = 200, description = "A page of address books", body = [AddressBook]),
+        (status = 422, description = "Invalid sort key, locale or fields", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all)]
 pub async fn index(
     State(state): State<AppState>,
     Query(params): Query<Pagination>,
+    Query(filter): Query<ListFilter>,
+    Query(view): Query<ViewParams>,
 ) -> Result<ApiResponse, ApiError> {
     let limit = params.limit.unwrap_or(1);
     let offset = params.offset.unwrap_or(0);
+    let view = view.view().map_err(map_error)?;
+    let fields = view.fields.clone();
     let repo = AddressBookRepository::new(state.pool);
 
-    match AddressBookService::get_all_address_books(repo, Some(limit), offset).await {
-        Ok(address_books) => Ok(ApiResponse::JsonDataAddressBookCollection(address_books)),
+    match AddressBookService::get_all_address_books(repo, Some(limit), offset, filter, view).await {
+        Ok(address_books) => Ok(ApiResponse::JsonDataAddressBookCollection(
+            address_books,
+            fields,
+        )),
         Err(e) => Err(map_error(e)),
     }
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/addressbooks",
+    tag = "address books",
+    params(("X-User" = Option<String>, Header, description = "Caller recorded as the author of the write")),
+    request_body = NewAddressBook,
+    responses(
+        (status = 200, description = "The created address book", body = AddressBook),
+        (status = 400, description = "Malformed body", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all)]
 pub async fn create_address_book(
     State(state): State<AppState>,
+    Actor(actor): Actor,
     payload: Result<Json<NewAddressBook>, JsonRejection>,
 ) -> Result<ApiResponse, ApiError> {
     match payload {
@@ -30,7 +64,7 @@ pub async fn create_address_book(
             let address_book = payload.0;
             let repo = AddressBookRepository::new(state.pool);
 
-            match AddressBookService::add_address_book(repo, address_book).await {
+            match AddressBookService::add_address_book(repo, address_book, actor).await {
                 Ok(address_book) => Ok(ApiResponse::JsonDataAddressBook(address_book)),
                 Err(e) => Err(map_error(e)),
             }
@@ -39,47 +73,156 @@ pub async fn create_address_book(
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/addressbooks/{id}",
+    tag = "address books",
+    params(
+        ("id" = i32, Path, description = "Address book id"),
+        ViewParams,
+        ("If-None-Match" = Option<String>, Header, description = "Entity tags already held"),
+    ),
+    responses(
+        (status = 200, description = "The address book", body = AddressBook),
+        (status = 304, description = "The book still has one of the given entity tags"),
+        (status = 404, description = "No such address book", body = ErrorBody),
+        (status = 422, description = "Invalid sort key, locale or fields", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%address_book_id))]
 pub async fn show(
     Path(address_book_id): Path<i32>,
     State(state): State<AppState>,
+    Query(view): Query<ViewParams>,
+    if_none_match: IfNoneMatch,
 ) -> Result<ApiResponse, ApiError> {
+    let view = view.view().map_err(map_error)?;
+    let fields = view.fields.clone();
     let repo = AddressBookRepository::new(state.pool);
 
-    match AddressBookService::get_address_book_by_id(repo, address_book_id).await {
-        Ok(address_book) => Ok(ApiResponse::JsonDataAddressBook(address_book)),
+    match AddressBookService::get_address_book_by_id(repo, address_book_id, view).await {
+        Ok(address_book) if if_none_match.matches(&address_book.etag()) => {
+            Ok(ApiResponse::NotModified(address_book.etag()))
+        }
+        Ok(address_book) => Ok(ApiResponse::JsonDataAddressBookView(address_book, fields)),
         Err(e) => Err(map_error(e)),
     }
 }
 
+#[utoipa::path(
+    delete,
+    path = "/api/addressbooks/{id}",
+    tag = "address books",
+    params(
+        ("id" = i32, Path, description = "Address book id"),
+        ("If-Match" = Option<String>, Header, description = "Entity tag the book must still have"),
+        ("X-User" = Option<String>, Header, description = "Caller recorded as the author of the write"),
+    ),
+    responses(
+        (status = 204, description = "The book and its contacts were moved to the trash"),
+        (status = 404, description = "No such address book", body = ErrorBody),
+        (status = 412, description = "The book has changed", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%address_book_id))]
 pub async fn delete_address_book(
     Path(address_book_id): Path<i32>,
     State(state): State<AppState>,
+    Actor(actor): Actor,
+    if_match: IfMatch,
 ) -> Result<ApiResponse, ApiError> {
     let repo = AddressBookRepository::new(state.pool);
 
-    match AddressBookService::delete_address_book(repo, address_book_id).await {
+    match AddressBookService::delete_address_book(repo, address_book_id, actor, if_match).await {
         Ok(_) => Ok(ApiResponse::NoContent),
         Err(e) => Err(map_error(e)),
     }
 }
 
-pub async fn update(
-    Path(id): Path<i32>,
-    Json(address_book): Json<NewAddressBook>,
+#[utoipa::path(
+    post,
+    path = "/api/addressbooks/{id}/restore",
+    tag = "address books",
+    params(("id" = i32, Path, description = "Address book id"), ("X-User" = Option<String>, Header, description = "Caller recorded as the author of the write")),
+    responses(
+        (status = 200, description = "The restored address book", body = AddressBook),
+        (status = 404, description = "No such address book in the trash", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%address_book_id))]
+pub async fn restore(
+    Path(address_book_id): Path<i32>,
     State(state): State<AppState>,
+    Actor(actor): Actor,
 ) -> Result<ApiResponse, ApiError> {
     let repo = AddressBookRepository::new(state.pool);
 
-    match AddressBookService::update_address_book(repo, id, address_book).await {
+    match AddressBookService::restore_address_book(repo, address_book_id, actor).await {
         Ok(address_book) => Ok(ApiResponse::JsonDataAddressBook(address_book)),
         Err(e) => Err(map_error(e)),
     }
 }
 
-fn map_error(error: Error) -> ApiError {
-    match error {
-        Error::DatabaseQueryError(_) => ApiError::DataBaseError,
-        Error::AddressBookNotFound => ApiError::AddressBookNotFound,
-        Error::JsonDeserilizationError(_) => ApiError::JsonDeserilize,
+#[utoipa::path(
+    put,
+    path = "/api/addressbooks/{id}",
+    tag = "address books",
+    params(
+        ("id" = i32, Path, description = "Address book id"),
+        ("If-Match" = Option<String>, Header, description = "Entity tag the book must still have"),
+        ("X-User" = Option<String>, Header, description = "Caller recorded as the author of the write"),
+    ),
+    request_body = NewAddressBook,
+    responses(
+        (status = 200, description = "The updated address book", body = AddressBook),
+        (status = 400, description = "Malformed body", body = ErrorBody),
+        (status = 404, description = "No such address book", body = ErrorBody),
+        (status = 412, description = "The book has changed", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(address_book_id = %id))]
+pub async fn update(
+    Path(id): Path<i32>,
+    State(state): State<AppState>,
+    Actor(actor): Actor,
+    if_match: IfMatch,
+    payload: Result<Json<NewAddressBook>, JsonRejection>,
+) -> Result<ApiResponse, ApiError> {
+    match payload {
+        Ok(payload) => {
+            let address_book = payload.0;
+            let repo = AddressBookRepository::new(state.pool);
+
+            match AddressBookService::update_address_book(repo, id, address_book, actor, if_match)
+                .await
+            {
+                Ok(address_book) => Ok(ApiResponse::JsonDataAddressBook(address_book)),
+                Err(e) => Err(map_error(e)),
+            }
+        }
+        Err(e) => Err(map_error(Error::JsonDeserilizationError(e))),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/addressbooks/{id}/stats",
+    tag = "address books",
+    params(("id" = i32, Path, description = "Address book id")),
+    responses(
+        (status = 200, description = "Figures over the book's live contacts", body = AddressBookStats),
+        (status = 404, description = "No such address book", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%address_book_id))]
+pub async fn stats(
+    Path(address_book_id): Path<i32>,
+    State(state): State<AppState>,
+) -> Result<ApiResponse, ApiError> {
+    let repo = AddressBookRepository::new(state.pool);
+
+    match AddressBookService::get_address_book_stats(repo, address_book_id).await {
+        Ok(stats) => Ok(ApiResponse::JsonDataAddressBookStats(stats)),
+        Err(e) => Err(map_error(e)),
     }
 }
@@ -0,0 +1,69 @@
+use axum::extract::{Path, Query, State};
+
+use crate::repositories::audit_repo::AuditRepository;
+use crate::services::audit_service::AuditService;
+use crate::types::audit::AuditEvent;
+use crate::types::{ApiError, ApiResponse, AppState, ErrorBody, Pagination};
+
+use super::map_error;
+
+#[utoipa::path(
+    get,
+    path = "/api/addressbooks/{id}/history",
+    tag = "history",
+    params(
+        ("id" = i32, Path, description = "Address book id"),
+        Pagination,
+    ),
+    responses(
+        (status = 200, description = "Changes to the book and its contents, newest first", body = [AuditEvent]),
+        (status = 404, description = "No such address book", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%address_book_id))]
+pub async fn address_book_history(
+    Path(address_book_id): Path<i32>,
+    State(state): State<AppState>,
+    Query(params): Query<Pagination>,
+) -> Result<ApiResponse, ApiError> {
+    let limit = params.limit.unwrap_or(50);
+    let offset = params.offset.unwrap_or(0);
+    let repo = AuditRepository::new(state.pool);
+
+    match AuditService::get_address_book_history(repo, address_book_id, Some(limit), offset).await {
+        Ok(events) => Ok(ApiResponse::JsonDataAuditEventCollection(events)),
+        Err(e) => Err(map_error(e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/addressbooks/{id}/contacts/{contact_id}/history",
+    tag = "history",
+    params(
+        ("id" = i32, Path, description = "Address book id"),
+        ("contact_id" = i32, Path, description = "Contact id"),
+        Pagination,
+    ),
+    responses(
+        (status = 200, description = "Changes to the contact, newest first", body = [AuditEvent]),
+        (status = 404, description = "No such address book or contact", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%address_book_id, %contact_id))]
+pub async fn contact_history(
+    Path((address_book_id, contact_id)): Path<(i32, i32)>,
+    State(state): State<AppState>,
+    Query(params): Query<Pagination>,
+) -> Result<ApiResponse, ApiError> {
+    let limit = params.limit.unwrap_or(50);
+    let offset = params.offset.unwrap_or(0);
+    let repo = AuditRepository::new(state.pool);
+
+    match AuditService::get_contact_history(repo, address_book_id, contact_id, Some(limit), offset)
+        .await
+    {
+        Ok(events) => Ok(ApiResponse::JsonDataAuditEventCollection(events)),
+        Err(e) => Err(map_error(e)),
+    }
+}
@@ -0,0 +1,621 @@
+use axum::body::Bytes;
+use axum::extract::rejection::{BytesRejection, JsonRejection, StringRejection};
+use axum::extract::{Json, Path, Query, State};
+use axum::http::{HeaderMap, StatusCode};
+
+use crate::repositories::contact_repo::ContactRepository;
+use crate::repositories::field_repo::FieldRepository;
+use crate::services::contact_service::ContactService;
+use crate::services::export_service::ExportService;
+use crate::types::bulk::{BulkOptions, BulkRequest, BulkResult};
+use crate::types::changes::{ChangesQuery, ContactChanges};
+use crate::types::contact::{
+    BulkMoveRequest, Contact, ContactFilter, ContactPatch, NewContact, TransferRequest,
+};
+use crate::types::precondition::{IfMatch, IfNoneMatch};
+use crate::types::{Actor, ApiError, ApiResponse, AppState, ErrorBody, ListFilter, Pagination};
+
+use super::{has_content_type, map_error};
+use handle_errors::Error;
+
+#[utoipa::path(
+    get,
+    path = "/api/addressbooks/{id}/contacts",
+    tag = "contacts",
+    params(
+        ("id" = i32, Path, description = "Address book id"),
+        Pagination,
+        ListFilter,
+        ContactFilter,
+    ),
+    responses(
+        (status = 200, description = "A page of the book's live contacts", body = [Contact]),
+        (status = 422, description = "Invalid sort key or locale", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%address_book_id))]
+pub async fn index(
+    Path(address_book_id): Path<i32>,
+    State(state): State<AppState>,
+    Query(params): Query<Pagination>,
+    Query(filter): Query<ListFilter>,
+    Query(contact_filter): Query<ContactFilter>,
+) -> Result<ApiResponse, ApiError> {
+    let limit = params.limit.unwrap_or(1);
+    let offset = params.offset.unwrap_or(0);
+    let repo = ContactRepository::new(state.pool);
+
+    match ContactService::get_address_book_contacts(
+        repo,
+        address_book_id,
+        Some(limit),
+        offset,
+        filter,
+        contact_filter,
+    )
+    .await
+    {
+        Ok(contacts) => Ok(ApiResponse::JsonDataContactCollection(contacts)),
+        Err(e) => Err(map_error(e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/addressbooks/{id}/contacts",
+    tag = "contacts",
+    params(
+        ("id" = i32, Path, description = "Address book id"),
+        ("X-User" = Option<String>, Header, description = "Caller recorded as the author of the write"),
+    ),
+    request_body = NewContact,
+    responses(
+        (status = 200, description = "The created contact", body = Contact),
+        (status = 400, description = "Malformed body", body = ErrorBody),
+        (status = 404, description = "No such address book", body = ErrorBody),
+        (status = 422, description = "Invalid custom field values", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%address_book_id))]
+pub async fn create_contact(
+    Path(address_book_id): Path<i32>,
+    State(state): State<AppState>,
+    Actor(actor): Actor,
+    payload: Result<Json<NewContact>, JsonRejection>,
+) -> Result<ApiResponse, ApiError> {
+    match payload {
+        Ok(payload) => {
+            let contact = payload.0;
+            let repo = ContactRepository::new(state.pool);
+
+            match ContactService::add_contact(repo, address_book_id, contact, actor).await {
+                Ok(contact) => Ok(ApiResponse::JsonDataContact(Box::new(contact))),
+                Err(e) => Err(map_error(e)),
+            }
+        }
+        Err(e) => Err(map_error(Error::JsonDeserilizationError(e))),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/addressbooks/{id}/contacts/bulk",
+    tag = "contacts",
+    params(
+        ("id" = i32, Path, description = "Address book id"),
+        BulkOptions,
+        ("X-User" = Option<String>, Header, description = "Caller recorded as the author of the write"),
+    ),
+    request_body = BulkRequest,
+    responses(
+        (status = 200, description = "The outcome of each operation, in request order", body = BulkResult),
+        (status = 400, description = "Malformed body", body = ErrorBody),
+        (status = 404, description = "No such address book", body = ErrorBody),
+        (status = 413, description = "Body too large", body = ErrorBody),
+        (status = 422, description = "Too many operations, or one is invalid in an atomic request", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%address_book_id))]
+pub async fn bulk(
+    Path(address_book_id): Path<i32>,
+    State(state): State<AppState>,
+    Actor(actor): Actor,
+    Query(options): Query<BulkOptions>,
+    payload: Result<Json<BulkRequest>, JsonRejection>,
+) -> Result<ApiResponse, ApiError> {
+    match payload {
+        Ok(payload) => {
+            let atomic = options.atomic.unwrap_or(true);
+            let repo = ContactRepository::new(state.pool);
+
+            match ContactService::bulk_contacts(
+                repo,
+                address_book_id,
+                payload.0.operations,
+                actor,
+                atomic,
+            )
+            .await
+            {
+                Ok(results) => Ok(ApiResponse::JsonDataBulkResult(BulkResult {
+                    atomic,
+                    results,
+                })),
+                Err(e) => Err(map_error(e)),
+            }
+        }
+        Err(e) => Err(map_error(Error::JsonDeserilizationError(e))),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/addressbooks/{id}/contacts/export",
+    tag = "contacts",
+    params(
+        ("id" = i32, Path, description = "Address book id"),
+    ),
+    responses(
+        (status = 200, description = "The book's live contacts, one per row", body = String, content_type = "text/csv"),
+        (status = 404, description = "No such address book", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%address_book_id))]
+pub async fn export(
+    Path(address_book_id): Path<i32>,
+    State(state): State<AppState>,
+) -> Result<ApiResponse, ApiError> {
+    let contact_repo = ContactRepository::new(state.pool.clone());
+    let field_repo = FieldRepository::new(state.pool);
+
+    match ExportService::export_contacts(contact_repo, field_repo, address_book_id).await {
+        Ok(csv) => Ok(ApiResponse::Csv(csv)),
+        Err(e) => Err(map_error(e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/addressbooks/{id}/changes",
+    tag = "contacts",
+    params(
+        ("id" = i32, Path, description = "Address book id"),
+        ChangesQuery,
+    ),
+    responses(
+        (status = 200, description = "Contacts changed since the token", body = ContactChanges),
+        (status = 404, description = "No such address book", body = ErrorBody),
+        (status = 422, description = "Malformed change token", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%address_book_id))]
+pub async fn changes(
+    Path(address_book_id): Path<i32>,
+    Query(query): Query<ChangesQuery>,
+    State(state): State<AppState>,
+) -> Result<ApiResponse, ApiError> {
+    let repo = ContactRepository::new(state.pool);
+
+    match ContactService::get_changes(repo, address_book_id, query).await {
+        Ok(changes) => Ok(ApiResponse::JsonDataContactChanges(changes)),
+        Err(e) => Err(map_error(e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/addressbooks/{id}/contacts/import",
+    tag = "contacts",
+    params(
+        ("id" = i32, Path, description = "Address book id"),
+        ("X-User" = Option<String>, Header, description = "Caller recorded as the author of the write"),
+    ),
+    request_body(content = String, content_type = "text/csv"),
+    responses(
+        (status = 200, description = "The imported contacts", body = [Contact]),
+        (status = 400, description = "Unreadable body", body = ErrorBody),
+        (status = 404, description = "No such address book", body = ErrorBody),
+        (status = 413, description = "Body too large", body = ErrorBody),
+        (status = 415, description = "Not sent as text/csv", body = ErrorBody),
+        (status = 422, description = "Invalid rows, none imported", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%address_book_id))]
+pub async fn import(
+    Path(address_book_id): Path<i32>,
+    State(state): State<AppState>,
+    Actor(actor): Actor,
+    headers: HeaderMap,
+    body: Result<String, StringRejection>,
+) -> Result<ApiResponse, ApiError> {
+    if !has_content_type(&headers, "text/csv") {
+        return Err(map_error(Error::UnsupportedMediaType));
+    }
+    let body = match body {
+        Ok(body) => body,
+        Err(e) if e.status() == StatusCode::PAYLOAD_TOO_LARGE => {
+            return Err(map_error(Error::PayloadTooLarge(format!(
+                "import cannot be larger than {} bytes",
+                state.limits.import_body_bytes
+            ))))
+        }
+        Err(e) => return Err(map_error(Error::BadRequest(e.body_text()))),
+    };
+    let contact_repo = ContactRepository::new(state.pool.clone());
+    let field_repo = FieldRepository::new(state.pool);
+
+    match ExportService::import_contacts(contact_repo, field_repo, address_book_id, body, actor)
+        .await
+    {
+        Ok(contacts) => Ok(ApiResponse::JsonDataContactCollection(contacts)),
+        Err(e) => Err(map_error(e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/contacts/{contact_id}/move",
+    tag = "contacts",
+    params(
+        ("contact_id" = i32, Path, description = "Contact id"),
+        ("If-Match" = Option<String>, Header, description = "Entity tag the contact must still have"),
+        ("X-User" = Option<String>, Header, description = "Caller recorded as the author of the write"),
+    ),
+    request_body = TransferRequest,
+    responses(
+        (status = 200, description = "The moved contact", body = Contact),
+        (status = 400, description = "Malformed body", body = ErrorBody),
+        (status = 404, description = "No such contact or target address book", body = ErrorBody),
+        (status = 409, description = "The target book has a contact with the same name", body = ErrorBody),
+        (status = 412, description = "The contact has changed", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%contact_id))]
+pub async fn move_contact(
+    Path(contact_id): Path<i32>,
+    State(state): State<AppState>,
+    Actor(actor): Actor,
+    if_match: IfMatch,
+    payload: Result<Json<TransferRequest>, JsonRejection>,
+) -> Result<ApiResponse, ApiError> {
+    match payload {
+        Ok(payload) => {
+            let repo = ContactRepository::new(state.pool);
+
+            match ContactService::move_contact(repo, contact_id, payload.0, actor, if_match).await {
+                Ok(contact) => Ok(ApiResponse::JsonDataContact(Box::new(contact))),
+                Err(e) => Err(map_error(e)),
+            }
+        }
+        Err(e) => Err(map_error(Error::JsonDeserilizationError(e))),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/contacts/move",
+    tag = "contacts",
+    params(
+        ("X-User" = Option<String>, Header, description = "Caller recorded as the author of the write"),
+    ),
+    request_body = BulkMoveRequest,
+    responses(
+        (status = 200, description = "The moved contacts", body = [Contact]),
+        (status = 400, description = "Malformed body", body = ErrorBody),
+        (status = 404, description = "No such contact or target address book", body = ErrorBody),
+        (status = 409, description = "The target book has a contact with the same name", body = ErrorBody),
+        (status = 422, description = "Too many contacts", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn move_contacts(
+    State(state): State<AppState>,
+    Actor(actor): Actor,
+    payload: Result<Json<BulkMoveRequest>, JsonRejection>,
+) -> Result<ApiResponse, ApiError> {
+    match payload {
+        Ok(payload) => {
+            let repo = ContactRepository::new(state.pool);
+
+            match ContactService::move_contacts(repo, payload.0, actor).await {
+                Ok(contacts) => Ok(ApiResponse::JsonDataContactCollection(contacts)),
+                Err(e) => Err(map_error(e)),
+            }
+        }
+        Err(e) => Err(map_error(Error::JsonDeserilizationError(e))),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/contacts/{contact_id}/copy",
+    tag = "contacts",
+    params(
+        ("contact_id" = i32, Path, description = "Contact id"),
+        ("X-User" = Option<String>, Header, description = "Caller recorded as the author of the write"),
+    ),
+    request_body = TransferRequest,
+    responses(
+        (status = 200, description = "The copy", body = Contact),
+        (status = 400, description = "Malformed body", body = ErrorBody),
+        (status = 404, description = "No such contact or target address book", body = ErrorBody),
+        (status = 409, description = "The target book has a contact with the same name", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%contact_id))]
+pub async fn copy_contact(
+    Path(contact_id): Path<i32>,
+    State(state): State<AppState>,
+    Actor(actor): Actor,
+    payload: Result<Json<TransferRequest>, JsonRejection>,
+) -> Result<ApiResponse, ApiError> {
+    match payload {
+        Ok(payload) => {
+            let repo = ContactRepository::new(state.pool);
+
+            match ContactService::copy_contact(repo, contact_id, payload.0, actor).await {
+                Ok(contact) => Ok(ApiResponse::JsonDataContact(Box::new(contact))),
+                Err(e) => Err(map_error(e)),
+            }
+        }
+        Err(e) => Err(map_error(Error::JsonDeserilizationError(e))),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/addressbooks/{id}/contacts/{contact_id}",
+    tag = "contacts",
+    params(
+        ("id" = i32, Path, description = "Address book id"),
+        ("contact_id" = i32, Path, description = "Contact id"),
+        ("If-None-Match" = Option<String>, Header, description = "Entity tags already held"),
+    ),
+    responses(
+        (status = 200, description = "The contact", body = Contact),
+        (status = 304, description = "The contact still has one of the given entity tags"),
+        (status = 404, description = "No such address book or contact", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%address_book_id, %contact_id))]
+pub async fn show(
+    Path((address_book_id, contact_id)): Path<(i32, i32)>,
+    State(state): State<AppState>,
+    if_none_match: IfNoneMatch,
+) -> Result<ApiResponse, ApiError> {
+    let repo = ContactRepository::new(state.pool);
+
+    match ContactService::get_contact(repo, contact_id, address_book_id).await {
+        Ok(contact) if if_none_match.matches(&contact.etag()) => {
+            Ok(ApiResponse::NotModified(contact.etag()))
+        }
+        Ok(contact) => Ok(ApiResponse::JsonDataContact(Box::new(contact))),
+        Err(e) => Err(map_error(e)),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/addressbooks/{id}/contacts/{contact_id}",
+    tag = "contacts",
+    params(
+        ("id" = i32, Path, description = "Address book id"),
+        ("contact_id" = i32, Path, description = "Contact id"),
+        ("If-Match" = Option<String>, Header, description = "Entity tag the contact must still have"),
+        ("X-User" = Option<String>, Header, description = "Caller recorded as the author of the write"),
+    ),
+    request_body = NewContact,
+    responses(
+        (status = 200, description = "The updated contact", body = Contact),
+        (status = 400, description = "Malformed body", body = ErrorBody),
+        (status = 404, description = "No such address book or contact", body = ErrorBody),
+        (status = 412, description = "The contact has changed", body = ErrorBody),
+        (status = 422, description = "Invalid custom field values", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%address_book_id, %contact_id))]
+pub async fn update(
+    Path((address_book_id, contact_id)): Path<(i32, i32)>,
+    State(state): State<AppState>,
+    Actor(actor): Actor,
+    if_match: IfMatch,
+    payload: Result<Json<NewContact>, JsonRejection>,
+) -> Result<ApiResponse, ApiError> {
+    match payload {
+        Ok(payload) => {
+            let contact = payload.0;
+            let repo = ContactRepository::new(state.pool);
+
+            match ContactService::update_contact(
+                repo,
+                contact_id,
+                address_book_id,
+                contact,
+                actor,
+                if_match,
+            )
+            .await
+            {
+                Ok(contact) => Ok(ApiResponse::JsonDataContact(Box::new(contact))),
+                Err(e) => Err(map_error(e)),
+            }
+        }
+        Err(e) => Err(map_error(Error::JsonDeserilizationError(e))),
+    }
+}
+
+#[utoipa::path(
+    patch,
+    path = "/api/addressbooks/{id}/contacts/{contact_id}",
+    tag = "contacts",
+    params(
+        ("id" = i32, Path, description = "Address book id"),
+        ("contact_id" = i32, Path, description = "Contact id"),
+        ("If-Match" = Option<String>, Header, description = "Entity tag the contact must still have"),
+        ("X-User" = Option<String>, Header, description = "Caller recorded as the author of the write"),
+    ),
+    request_body(content = ContactPatch, content_type = "application/merge-patch+json"),
+    responses(
+        (status = 200, description = "The patched contact", body = Contact),
+        (status = 400, description = "Malformed patch", body = ErrorBody),
+        (status = 404, description = "No such address book or contact", body = ErrorBody),
+        (status = 412, description = "The contact has changed", body = ErrorBody),
+        (status = 415, description = "Not sent as application/merge-patch+json", body = ErrorBody),
+        (status = 422, description = "A required field set to null", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%address_book_id, %contact_id))]
+pub async fn patch(
+    Path((address_book_id, contact_id)): Path<(i32, i32)>,
+    State(state): State<AppState>,
+    Actor(actor): Actor,
+    if_match: IfMatch,
+    headers: HeaderMap,
+    body: Result<Bytes, BytesRejection>,
+) -> Result<ApiResponse, ApiError> {
+    if !has_content_type(&headers, "application/merge-patch+json") {
+        return Err(map_error(Error::UnsupportedMediaType));
+    }
+    let body = body.map_err(|e| map_error(Error::BodyRejection(e)))?;
+    let patch: ContactPatch = match serde_json::from_slice(&body) {
+        Ok(patch) => patch,
+        Err(e) => return Err(map_error(Error::InvalidBody(e.to_string()))),
+    };
+    let repo = ContactRepository::new(state.pool);
+
+    match ContactService::patch_contact(repo, contact_id, address_book_id, patch, actor, if_match)
+        .await
+    {
+        Ok(contact) => Ok(ApiResponse::JsonDataContact(Box::new(contact))),
+        Err(e) => Err(map_error(e)),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/addressbooks/{id}/contacts/{contact_id}",
+    tag = "contacts",
+    params(
+        ("id" = i32, Path, description = "Address book id"),
+        ("contact_id" = i32, Path, description = "Contact id"),
+        ("If-Match" = Option<String>, Header, description = "Entity tag the contact must still have"),
+        ("X-User" = Option<String>, Header, description = "Caller recorded as the author of the write"),
+    ),
+    responses(
+        (status = 204, description = "The contact was moved to the trash"),
+        (status = 404, description = "No such address book or contact", body = ErrorBody),
+        (status = 412, description = "The contact has changed", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%address_book_id, %contact_id))]
+pub async fn delete_contact(
+    Path((address_book_id, contact_id)): Path<(i32, i32)>,
+    State(state): State<AppState>,
+    Actor(actor): Actor,
+    if_match: IfMatch,
+) -> Result<ApiResponse, ApiError> {
+    let repo = ContactRepository::new(state.pool);
+
+    match ContactService::delete_contact(repo, contact_id, address_book_id, actor, if_match).await {
+        Ok(_) => Ok(ApiResponse::NoContent),
+        Err(e) => Err(map_error(e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/addressbooks/{id}/contacts/{contact_id}/restore",
+    tag = "contacts",
+    params(
+        ("id" = i32, Path, description = "Address book id"),
+        ("contact_id" = i32, Path, description = "Contact id"),
+        ("X-User" = Option<String>, Header, description = "Caller recorded as the author of the write"),
+    ),
+    responses(
+        (status = 200, description = "The restored contact", body = Contact),
+        (status = 404, description = "No such contact in the trash", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%address_book_id, %contact_id))]
+pub async fn restore(
+    Path((address_book_id, contact_id)): Path<(i32, i32)>,
+    State(state): State<AppState>,
+    Actor(actor): Actor,
+) -> Result<ApiResponse, ApiError> {
+    let repo = ContactRepository::new(state.pool);
+
+    match ContactService::restore_contact(repo, contact_id, address_book_id, actor).await {
+        Ok(contact) => Ok(ApiResponse::JsonDataContact(Box::new(contact))),
+        Err(e) => Err(map_error(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::{to_bytes, Body};
+    use axum::http::{Request, StatusCode};
+    use serde_json::{json, Value};
+    use sqlx::PgPool;
+    use tower::ServiceExt;
+
+    async fn send(app: &axum::Router, method: &str, uri: &str, body: Value) -> (StatusCode, Value) {
+        let content_type = if method == "PATCH" {
+            "application/merge-patch+json"
+        } else {
+            "application/json"
+        };
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", content_type)
+            .body(match body {
+                Value::Null => Body::empty(),
+                body => Body::from(body.to_string()),
+            })
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    // Needs a database server: DATABASE_URL=postgres://... cargo test -- --ignored
+    #[sqlx::test]
+    #[ignore]
+    async fn test_contacts_of_trashed_books_are_not_found(pool: PgPool) {
+        let app = crate::app(crate::tests::state(pool));
+        let ann = json!({"name": "Ann", "address": "1 Main St"});
+        let (_, book) = send(
+            &app,
+            "POST",
+            "/api/addressbooks",
+            json!({"address_book_name": "Friends"}),
+        )
+        .await;
+        let contacts = format!("/api/addressbooks/{}/contacts", book["id"]);
+        let (_, live) = send(&app, "POST", &contacts, ann.clone()).await;
+        let bob = json!({"name": "Bob", "address": "2 Main St"});
+        let (_, trashed) = send(&app, "POST", &contacts, bob).await;
+        let live = format!("{contacts}/{}", live["id"]);
+        let trashed = format!("{contacts}/{}", trashed["id"]);
+        let (status, _) = send(&app, "DELETE", &trashed, Value::Null).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let (status, _) = send(&app, "GET", &live, Value::Null).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let book = format!("/api/addressbooks/{}", book["id"]);
+        let (status, _) = send(&app, "DELETE", &book, Value::Null).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+
+        for (method, uri, body) in [
+            ("GET", live.clone(), Value::Null),
+            ("PUT", live.clone(), ann.clone()),
+            ("PATCH", live.clone(), json!({"phone_number": "555-0100"})),
+            ("DELETE", live.clone(), Value::Null),
+            ("POST", format!("{trashed}/restore"), Value::Null),
+        ] {
+            let (status, _) = send(&app, method, &uri, body).await;
+            assert_eq!(status, StatusCode::NOT_FOUND, "{method} {uri}");
+        }
+    }
+}
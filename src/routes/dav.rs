@@ -0,0 +1,173 @@
+use axum::body::Bytes;
+use axum::extract::{rejection::BytesRejection, Path, State};
+use axum::http::{HeaderMap, Method};
+use axum::response::Redirect;
+
+use crate::repositories::dav_repo::DavRepository;
+use crate::services::dav_service::DavService;
+use crate::types::dav::{parse_propfind, parse_report, DavPath, PRINCIPAL_HREF};
+use crate::types::precondition::Preconditions;
+use crate::types::{Actor, ApiError, ApiResponse, AppState};
+
+use super::{has_content_type, map_error};
+use handle_errors::Error;
+
+/// `/.well-known/carddav` (RFC 6764) points clients at the principal.
+#[tracing::instrument(skip_all)]
+pub async fn well_known() -> Redirect {
+    Redirect::permanent(PRINCIPAL_HREF)
+}
+
+/// `Depth: 0` asks for the resource alone; `1`, `infinity` or no header also lists its
+/// members, one level deep.
+fn depth_one(headers: &HeaderMap) -> bool {
+    headers
+        .get("depth")
+        .and_then(|value| value.to_str().ok())
+        .is_none_or(|depth| depth.trim() != "0")
+}
+
+fn body_text(body: &Bytes) -> Result<&str, ApiError> {
+    std::str::from_utf8(body)
+        .map_err(|_| map_error(Error::BadRequest(String::from("body must be UTF-8"))))
+}
+
+async fn propfind(
+    state: AppState,
+    path: DavPath,
+    headers: &HeaderMap,
+    body: &Bytes,
+) -> Result<ApiResponse, ApiError> {
+    let props = parse_propfind(body_text(body)?).map_err(|e| map_error(Error::BadRequest(e)))?;
+    let repo = DavRepository::new(state.pool);
+
+    match DavService::propfind(repo, state.blob_store, path, depth_one(headers), props).await {
+        Ok(multistatus) => Ok(ApiResponse::Multistatus(multistatus.to_xml())),
+        Err(e) => Err(map_error(e)),
+    }
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn principal(
+    method: Method,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Result<Bytes, BytesRejection>,
+) -> Result<ApiResponse, ApiError> {
+    let body = body.map_err(|e| map_error(Error::BodyRejection(e)))?;
+    match method.as_str() {
+        "OPTIONS" => Ok(ApiResponse::DavOptions),
+        "PROPFIND" => propfind(state, DavPath::Principal, &headers, &body).await,
+        _ => Err(map_error(Error::MethodNotAllowed)),
+    }
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn home(
+    method: Method,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Result<Bytes, BytesRejection>,
+) -> Result<ApiResponse, ApiError> {
+    let body = body.map_err(|e| map_error(Error::BodyRejection(e)))?;
+    match method.as_str() {
+        "OPTIONS" => Ok(ApiResponse::DavOptions),
+        "PROPFIND" => propfind(state, DavPath::Home, &headers, &body).await,
+        _ => Err(map_error(Error::MethodNotAllowed)),
+    }
+}
+
+#[tracing::instrument(skip_all, fields(address_book_id = %id))]
+pub async fn address_book(
+    Path(id): Path<i32>,
+    method: Method,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Result<Bytes, BytesRejection>,
+) -> Result<ApiResponse, ApiError> {
+    let body = body.map_err(|e| map_error(Error::BodyRejection(e)))?;
+    match method.as_str() {
+        "OPTIONS" => Ok(ApiResponse::DavOptions),
+        "PROPFIND" => propfind(state, DavPath::AddressBook(id), &headers, &body).await,
+        "REPORT" => {
+            let report =
+                parse_report(body_text(&body)?).map_err(|e| map_error(Error::BadRequest(e)))?;
+            let repo = DavRepository::new(state.pool);
+
+            match DavService::report(repo, state.blob_store, id, report).await {
+                Ok(multistatus) => Ok(ApiResponse::Multistatus(multistatus.to_xml())),
+                Err(e) => Err(map_error(e)),
+            }
+        }
+        _ => Err(map_error(Error::MethodNotAllowed)),
+    }
+}
+
+#[tracing::instrument(skip_all, fields(address_book_id = %id, %resource_name))]
+pub async fn card(
+    Path((id, resource_name)): Path<(i32, String)>,
+    method: Method,
+    State(state): State<AppState>,
+    Actor(actor): Actor,
+    Preconditions {
+        if_match,
+        if_none_match,
+    }: Preconditions,
+    headers: HeaderMap,
+    body: Result<Bytes, BytesRejection>,
+) -> Result<ApiResponse, ApiError> {
+    let body = body.map_err(|e| map_error(Error::BodyRejection(e)))?;
+    match method.as_str() {
+        "OPTIONS" => Ok(ApiResponse::DavOptions),
+        "PROPFIND" => propfind(state, DavPath::Card(id, resource_name), &headers, &body).await,
+        "GET" | "HEAD" => {
+            let repo = DavRepository::new(state.pool);
+
+            match DavService::get_card(repo, state.blob_store, id, resource_name).await {
+                Ok((card, _)) if if_none_match.matches(&card.etag()) => {
+                    Ok(ApiResponse::NotModified(card.etag()))
+                }
+                Ok((card, data)) => Ok(ApiResponse::VCard {
+                    etag: card.etag(),
+                    data,
+                }),
+                Err(e) => Err(map_error(e)),
+            }
+        }
+        "PUT" => {
+            if !has_content_type(&headers, "text/vcard")
+                && !has_content_type(&headers, "text/x-vcard")
+            {
+                return Err(map_error(Error::UnsupportedMediaType));
+            }
+            let repo = DavRepository::new(state.pool);
+
+            match DavService::put_card(
+                repo,
+                id,
+                resource_name,
+                body_text(&body)?,
+                actor,
+                if_match,
+                if_none_match,
+            )
+            .await
+            {
+                Ok((card, created)) => Ok(ApiResponse::VCardStored {
+                    created,
+                    etag: card.etag(),
+                }),
+                Err(e) => Err(map_error(e)),
+            }
+        }
+        "DELETE" => {
+            let repo = DavRepository::new(state.pool);
+
+            match DavService::delete_card(repo, id, resource_name, actor, if_match).await {
+                Ok(_) => Ok(ApiResponse::NoContent),
+                Err(e) => Err(map_error(e)),
+            }
+        }
+        _ => Err(map_error(Error::MethodNotAllowed)),
+    }
+}
@@ -0,0 +1,113 @@
+use axum::extract::{Path, State};
+use axum::http::HeaderMap;
+use axum::response::sse::Event;
+use std::time::Duration;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::mpsc;
+
+use crate::repositories::contact_repo::ContactRepository;
+use crate::services::event_service::EventService;
+use crate::types::changes::EventCursor;
+use crate::types::{ApiError, ApiResponse, AppState, ErrorBody};
+
+use super::map_error;
+
+/// How often a stream looks for changes without being notified, in case a notice was lost
+/// while the listener reconnected.
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Events buffered for a client that reads slowly.
+const STREAM_BUFFER: usize = 64;
+
+/// `GET /api/addressbooks/:id/events`: a Server-Sent Events stream of the book's contact
+/// changes. A reconnecting client resumes after its `Last-Event-ID`.
+#[utoipa::path(
+    get,
+    path = "/api/addressbooks/{id}/events",
+    tag = "contacts",
+    params(
+        ("id" = i32, Path, description = "Address book id"),
+        ("Last-Event-ID" = Option<String>, Header, description = "Id of the last event received, to resume after it"),
+    ),
+    responses(
+        (status = 200, description = "A stream of the book's contact changes", body = String, content_type = "text/event-stream"),
+        (status = 404, description = "No such address book", body = ErrorBody),
+        (status = 422, description = "Malformed Last-Event-ID", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%address_book_id))]
+pub async fn stream(
+    Path(address_book_id): Path<i32>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<ApiResponse, ApiError> {
+    let last_event_id = headers
+        .get("last-event-id")
+        .and_then(|value| value.to_str().ok());
+    // Subscribe before reading the position, so a change in between still wakes the stream.
+    let notices = state.change_feed.subscribe();
+    let repo = ContactRepository::new(state.pool);
+
+    let cursor = EventService::open(&repo, address_book_id, last_event_id)
+        .await
+        .map_err(map_error)?;
+    let (sender, receiver) = mpsc::channel(STREAM_BUFFER);
+    tokio::spawn(pump(repo, cursor, notices, sender));
+
+    Ok(ApiResponse::EventStream(receiver))
+}
+
+/// Feeds the client's stream until it disconnects or the book can no longer be read.
+async fn pump(
+    repo: ContactRepository,
+    mut cursor: EventCursor,
+    mut notices: broadcast::Receiver<i32>,
+    sender: mpsc::Sender<Event>,
+) {
+    if cursor.resync {
+        let event = Event::default()
+            .event("resync")
+            .id(cursor.position.to_string())
+            .data("{}");
+        if sender.send(event).await.is_err() {
+            return;
+        }
+    }
+
+    loop {
+        // On an error the stream ends and the client reconnects with its Last-Event-ID.
+        let Ok(events) = EventService::next_events(&repo, &mut cursor).await else {
+            return;
+        };
+        if !events.is_empty() {
+            for event in events {
+                let sse = Event::default()
+                    .event(event.name())
+                    .id(event.seq.to_string())
+                    .data(event.data(cursor.address_book_id).to_string());
+                if sender.send(sse).await.is_err() {
+                    return;
+                }
+            }
+            continue;
+        }
+
+        tokio::select! {
+            _ = wait_for_notice(&mut notices, cursor.address_book_id) => {}
+            _ = tokio::time::sleep(POLL_INTERVAL) => {}
+            _ = sender.closed() => return,
+        }
+    }
+}
+
+async fn wait_for_notice(notices: &mut broadcast::Receiver<i32>, address_book_id: i32) {
+    loop {
+        match notices.recv().await {
+            Ok(id) if id != address_book_id => continue,
+            // Lagging means notices were dropped, possibly one for this book.
+            Ok(_) | Err(RecvError::Lagged(_)) => return,
+            // Without a feed, polling is all that is left.
+            Err(RecvError::Closed) => std::future::pending().await,
+        }
+    }
+}
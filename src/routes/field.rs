@@ -0,0 +1,185 @@
+use axum::extract::{rejection::JsonRejection, Json, Path, State};
+
+use crate::repositories::field_repo::FieldRepository;
+use crate::services::field_service::FieldService;
+use crate::types::custom_field::{CustomField, NewCustomField};
+use crate::types::precondition::{IfMatch, IfNoneMatch};
+use crate::types::{Actor, ApiError, ApiResponse, AppState, ErrorBody};
+
+use super::map_error;
+use handle_errors::Error;
+
+#[utoipa::path(
+    get,
+    path = "/api/addressbooks/{id}/fields",
+    tag = "fields",
+    params(
+        ("id" = i32, Path, description = "Address book id"),
+    ),
+    responses(
+        (status = 200, description = "The book's custom fields", body = [CustomField]),
+        (status = 404, description = "No such address book", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%address_book_id))]
+pub async fn index(
+    Path(address_book_id): Path<i32>,
+    State(state): State<AppState>,
+) -> Result<ApiResponse, ApiError> {
+    let repo = FieldRepository::new(state.pool);
+
+    match FieldService::get_fields(repo, address_book_id).await {
+        Ok(fields) => Ok(ApiResponse::JsonDataCustomFieldCollection(fields)),
+        Err(e) => Err(map_error(e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/addressbooks/{id}/fields",
+    tag = "fields",
+    params(
+        ("id" = i32, Path, description = "Address book id"),
+        ("X-User" = Option<String>, Header, description = "Caller recorded as the author of the write"),
+    ),
+    request_body = NewCustomField,
+    responses(
+        (status = 200, description = "The created field", body = CustomField),
+        (status = 400, description = "Malformed body", body = ErrorBody),
+        (status = 404, description = "No such address book", body = ErrorBody),
+        (status = 409, description = "A field with the same name, or one existing contacts lack", body = ErrorBody),
+        (status = 422, description = "Invalid definition", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%address_book_id))]
+pub async fn create_field(
+    Path(address_book_id): Path<i32>,
+    State(state): State<AppState>,
+    Actor(actor): Actor,
+    payload: Result<Json<NewCustomField>, JsonRejection>,
+) -> Result<ApiResponse, ApiError> {
+    match payload {
+        Ok(payload) => {
+            let repo = FieldRepository::new(state.pool);
+
+            match FieldService::create_field(repo, address_book_id, payload.0, actor).await {
+                Ok(field) => Ok(ApiResponse::JsonDataCustomField(field)),
+                Err(e) => Err(map_error(e)),
+            }
+        }
+        Err(e) => Err(map_error(Error::JsonDeserilizationError(e))),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/addressbooks/{id}/fields/{field_id}",
+    tag = "fields",
+    params(
+        ("id" = i32, Path, description = "Address book id"),
+        ("field_id" = i32, Path, description = "Custom field id"),
+        ("If-None-Match" = Option<String>, Header, description = "Entity tags already held"),
+    ),
+    responses(
+        (status = 200, description = "The field", body = CustomField),
+        (status = 304, description = "The field still has one of the given entity tags"),
+        (status = 404, description = "No such address book or field", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%address_book_id, %field_id))]
+pub async fn show(
+    Path((address_book_id, field_id)): Path<(i32, i32)>,
+    State(state): State<AppState>,
+    if_none_match: IfNoneMatch,
+) -> Result<ApiResponse, ApiError> {
+    let repo = FieldRepository::new(state.pool);
+
+    match FieldService::get_field(repo, address_book_id, field_id).await {
+        Ok(field) if if_none_match.matches(&field.etag()) => {
+            Ok(ApiResponse::NotModified(field.etag()))
+        }
+        Ok(field) => Ok(ApiResponse::JsonDataCustomField(field)),
+        Err(e) => Err(map_error(e)),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/addressbooks/{id}/fields/{field_id}",
+    tag = "fields",
+    params(
+        ("id" = i32, Path, description = "Address book id"),
+        ("field_id" = i32, Path, description = "Custom field id"),
+        ("If-Match" = Option<String>, Header, description = "Entity tag the field must still have"),
+        ("X-User" = Option<String>, Header, description = "Caller recorded as the author of the write"),
+    ),
+    request_body = NewCustomField,
+    responses(
+        (status = 200, description = "The updated field", body = CustomField),
+        (status = 400, description = "Malformed body", body = ErrorBody),
+        (status = 404, description = "No such address book or field", body = ErrorBody),
+        (status = 409, description = "A field with the same name, or a change existing contacts violate", body = ErrorBody),
+        (status = 412, description = "The field has changed", body = ErrorBody),
+        (status = 422, description = "Invalid definition", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%address_book_id, %field_id))]
+pub async fn update(
+    Path((address_book_id, field_id)): Path<(i32, i32)>,
+    State(state): State<AppState>,
+    Actor(actor): Actor,
+    if_match: IfMatch,
+    payload: Result<Json<NewCustomField>, JsonRejection>,
+) -> Result<ApiResponse, ApiError> {
+    match payload {
+        Ok(payload) => {
+            let repo = FieldRepository::new(state.pool);
+
+            match FieldService::update_field(
+                repo,
+                address_book_id,
+                field_id,
+                payload.0,
+                actor,
+                if_match,
+            )
+            .await
+            {
+                Ok(field) => Ok(ApiResponse::JsonDataCustomField(field)),
+                Err(e) => Err(map_error(e)),
+            }
+        }
+        Err(e) => Err(map_error(Error::JsonDeserilizationError(e))),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/addressbooks/{id}/fields/{field_id}",
+    tag = "fields",
+    params(
+        ("id" = i32, Path, description = "Address book id"),
+        ("field_id" = i32, Path, description = "Custom field id"),
+        ("If-Match" = Option<String>, Header, description = "Entity tag the field must still have"),
+        ("X-User" = Option<String>, Header, description = "Caller recorded as the author of the write"),
+    ),
+    responses(
+        (status = 204, description = "The field and its values were removed"),
+        (status = 404, description = "No such address book or field", body = ErrorBody),
+        (status = 412, description = "The field has changed", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%address_book_id, %field_id))]
+pub async fn delete_field(
+    Path((address_book_id, field_id)): Path<(i32, i32)>,
+    State(state): State<AppState>,
+    Actor(actor): Actor,
+    if_match: IfMatch,
+) -> Result<ApiResponse, ApiError> {
+    let repo = FieldRepository::new(state.pool);
+
+    match FieldService::delete_field(repo, address_book_id, field_id, actor, if_match).await {
+        Ok(_) => Ok(ApiResponse::NoContent),
+        Err(e) => Err(map_error(e)),
+    }
+}
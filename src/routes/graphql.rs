@@ -0,0 +1,1036 @@
+use async_graphql::dataloader::{DataLoader, HashMapCache, Loader};
+use async_graphql::{
+    Context, EmptySubscription, Enum, ErrorExtensions, InputObject, MaybeUndefined, Object, Schema,
+};
+use axum::extract::{rejection::JsonRejection, Json, State};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde_json::{Map, Value};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::repositories::address_book_repo::AddressBookRepository;
+use crate::repositories::contact_repo::ContactRepository;
+use crate::services::address_book_service::AddressBookService;
+use crate::services::contact_service::ContactService;
+use crate::types::address_book::{AddressBook, AddressBookView, NewAddressBook};
+use crate::types::contact::{
+    BulkMoveRequest, Contact, ContactDetails, ContactFilter, ContactPatch, NewContact, OnConflict,
+    TransferRequest,
+};
+use crate::types::precondition::{EntityTags, IfMatch};
+use crate::types::{parse_sort_keys, Actor, ApiError, ApiResponse, AppState, ListFilter};
+
+use super::map_error;
+use handle_errors::Error;
+
+type GraphQlResult<T> = async_graphql::Result<T>;
+pub type AddressBookSchema = Schema<Query, Mutation, EmptySubscription>;
+
+const DEFAULT_PAGE_SIZE: i32 = 20;
+/// Largest page a listing returns. Contact lists nest under every book, so this also bounds
+/// how many contacts one query can fetch.
+const MAX_PAGE_SIZE: i32 = 100;
+const MAX_QUERY_DEPTH: usize = 10;
+
+static SCHEMA: OnceLock<AddressBookSchema> = OnceLock::new();
+
+/// The schema is built once; the pool, caller and loaders are attached to each request.
+pub fn schema() -> &'static AddressBookSchema {
+    SCHEMA.get_or_init(|| {
+        Schema::build(Query, Mutation, EmptySubscription)
+            .limit_depth(MAX_QUERY_DEPTH)
+            .finish()
+    })
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn execute(
+    State(state): State<AppState>,
+    actor: Actor,
+    payload: Result<Json<async_graphql::Request>, JsonRejection>,
+) -> Result<ApiResponse, ApiError> {
+    match payload {
+        Ok(payload) => {
+            let request = with_context(payload.0, state.pool, actor);
+            Ok(ApiResponse::GraphQl(schema().execute(request).await))
+        }
+        Err(e) => Err(map_error(Error::JsonDeserilizationError(e))),
+    }
+}
+
+/// Attaches the pool, the caller and fresh loaders, so batching never outlives a request.
+fn with_context(
+    request: async_graphql::Request,
+    pool: PgPool,
+    actor: Actor,
+) -> async_graphql::Request {
+    request
+        .data(pool.clone())
+        .data(actor)
+        .data(DataLoader::with_cache(
+            AddressBookLoader(pool.clone()),
+            tokio::spawn,
+            HashMapCache::default(),
+        ))
+        .data(DataLoader::with_cache(
+            ContactPageLoader(pool),
+            tokio::spawn,
+            HashMapCache::default(),
+        ))
+}
+
+/// A GraphQL error carrying the message and status the REST API would answer with.
+fn graphql_error(error: Error) -> async_graphql::Error {
+    let error = map_error(error);
+    let (status, message) = error.status_and_message();
+    let code = status
+        .canonical_reason()
+        .unwrap_or("Error")
+        .to_uppercase()
+        .replace(' ', "_");
+    async_graphql::Error::new(message).extend_with(|_, extensions| {
+        extensions.set("code", code);
+        extensions.set("status", status.as_u16());
+    })
+}
+
+/// `None` for a missing book or contact, which GraphQL reports as `null`.
+fn found<T>(result: Result<T, Error>) -> GraphQlResult<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(Error::AddressBookNotFound | Error::ContactNotFound) => Ok(None),
+        Err(e) => Err(graphql_error(e)),
+    }
+}
+
+/// The `If-Match` a write with `ifVersion` carries.
+fn if_match(version: Option<i32>) -> IfMatch {
+    match version {
+        Some(version) => IfMatch(EntityTags::Tags(vec![format!("\"{version}\"")])),
+        None => IfMatch::default(),
+    }
+}
+
+fn page_size(limit: Option<i32>) -> GraphQlResult<i32> {
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+        return Err(graphql_error(Error::ValidationError(format!(
+            "limit must be between 1 and {MAX_PAGE_SIZE}"
+        ))));
+    }
+    Ok(limit)
+}
+
+fn page_offset(offset: Option<i32>) -> GraphQlResult<i32> {
+    let offset = offset.unwrap_or(0);
+    if offset < 0 {
+        return Err(graphql_error(Error::ValidationError(String::from(
+            "offset cannot be negative",
+        ))));
+    }
+    Ok(offset)
+}
+
+/// Books as GraphQL reads them: without embedded contacts, which have their own field.
+fn book_view() -> AddressBookView {
+    AddressBookView {
+        fields: None,
+        contacts: false,
+        contact_count: true,
+    }
+}
+
+/// Loads books by id, batching the `addressBook` of many contacts into one query.
+pub struct AddressBookLoader(PgPool);
+
+impl Loader<i32> for AddressBookLoader {
+    type Value = AddressBook;
+    type Error = async_graphql::Error;
+
+    async fn load(&self, keys: &[i32]) -> Result<HashMap<i32, AddressBook>, Self::Error> {
+        let repo = AddressBookRepository::new(self.0.clone());
+        let address_books =
+            AddressBookService::get_address_books_by_ids(repo, keys.to_vec(), book_view())
+                .await
+                .map_err(graphql_error)?;
+        Ok(address_books
+            .into_iter()
+            .map(|address_book| (address_book.id.0, address_book))
+            .collect())
+    }
+}
+
+/// The arguments of a book's `contacts` field.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ContactPage {
+    limit: i32,
+    offset: i32,
+    search: Option<String>,
+    sort: Option<String>,
+    locale: Option<String>,
+    group: Option<String>,
+    updated_since: Option<DateTime<Utc>>,
+}
+
+impl ContactPage {
+    fn filters(&self) -> (ListFilter, ContactFilter) {
+        let filter = ListFilter {
+            updated_since: self.updated_since,
+            sort: self
+                .sort
+                .as_deref()
+                .map(parse_sort_keys)
+                .unwrap_or_default(),
+            locale: self.locale.clone(),
+        };
+        let contact_filter = ContactFilter {
+            group: self.group.clone(),
+            ..ContactFilter::default()
+        };
+        (filter, contact_filter)
+    }
+}
+
+/// Loads a page of contacts per book. Books asking for the same page share one query.
+pub struct ContactPageLoader(PgPool);
+
+impl Loader<(i32, ContactPage)> for ContactPageLoader {
+    type Value = Vec<Contact>;
+    type Error = async_graphql::Error;
+
+    async fn load(
+        &self,
+        keys: &[(i32, ContactPage)],
+    ) -> Result<HashMap<(i32, ContactPage), Vec<Contact>>, Self::Error> {
+        let mut books_by_page: HashMap<&ContactPage, Vec<i32>> = HashMap::new();
+        for (address_book_id, page) in keys {
+            books_by_page
+                .entry(page)
+                .or_default()
+                .push(*address_book_id);
+        }
+
+        let mut pages = HashMap::new();
+        for (page, address_book_ids) in books_by_page {
+            let (filter, contact_filter) = page.filters();
+            let repo = ContactRepository::new(self.0.clone());
+            let contacts = ContactService::get_contacts_of_address_books(
+                repo,
+                address_book_ids,
+                Some(page.limit),
+                page.offset,
+                filter,
+                contact_filter,
+                page.search.clone(),
+            )
+            .await
+            .map_err(graphql_error)?;
+            for (address_book_id, contacts) in contacts {
+                pages.insert((address_book_id, page.clone()), contacts);
+            }
+        }
+        Ok(pages)
+    }
+}
+
+pub struct AddressBookObject(AddressBook);
+
+#[Object(name = "AddressBook")]
+impl AddressBookObject {
+    async fn id(&self) -> i32 {
+        self.0.id.0
+    }
+
+    async fn address_book_name(&self) -> &str {
+        &self.0.address_book_name
+    }
+
+    /// Number of live contacts in the book.
+    async fn contact_count(&self, ctx: &Context<'_>) -> GraphQlResult<i64> {
+        if let Some(count) = self.0.contact_count {
+            return Ok(count);
+        }
+        let loader = ctx.data::<DataLoader<AddressBookLoader, HashMapCache>>()?;
+        let address_book = loader.load_one(self.0.id.0).await?;
+        Ok(address_book
+            .and_then(|address_book| address_book.contact_count)
+            .unwrap_or(0))
+    }
+
+    /// A page of the book's live contacts. `search` matches names, email, phone number and
+    /// organization; `sort` takes the same keys as the REST listing.
+    #[allow(clippy::too_many_arguments)]
+    async fn contacts(
+        &self,
+        ctx: &Context<'_>,
+        limit: Option<i32>,
+        offset: Option<i32>,
+        search: Option<String>,
+        sort: Option<String>,
+        locale: Option<String>,
+        group: Option<String>,
+        updated_since: Option<DateTime<Utc>>,
+    ) -> GraphQlResult<Vec<ContactObject>> {
+        let page = ContactPage {
+            limit: page_size(limit)?,
+            offset: page_offset(offset)?,
+            search,
+            sort,
+            locale,
+            group,
+            updated_since,
+        };
+        let loader = ctx.data::<DataLoader<ContactPageLoader, HashMapCache>>()?;
+        let contacts = loader.load_one((self.0.id.0, page)).await?;
+        Ok(contacts
+            .unwrap_or_default()
+            .into_iter()
+            .map(ContactObject)
+            .collect())
+    }
+
+    async fn created_at(&self) -> DateTime<Utc> {
+        self.0.created_at
+    }
+
+    async fn updated_at(&self) -> DateTime<Utc> {
+        self.0.updated_at
+    }
+
+    async fn created_by(&self) -> Option<&str> {
+        self.0.created_by.as_deref()
+    }
+
+    async fn updated_by(&self) -> Option<&str> {
+        self.0.updated_by.as_deref()
+    }
+
+    /// Pass as `ifVersion` to make a write fail if the book changed since.
+    async fn version(&self) -> i32 {
+        self.0.version
+    }
+}
+
+pub struct ContactObject(Contact);
+
+#[Object(name = "Contact")]
+impl ContactObject {
+    async fn id(&self) -> i32 {
+        self.0.id.0
+    }
+
+    async fn address_book_id(&self) -> i32 {
+        self.0.address_book_id.0
+    }
+
+    async fn address_book(&self, ctx: &Context<'_>) -> GraphQlResult<Option<AddressBookObject>> {
+        let loader = ctx.data::<DataLoader<AddressBookLoader, HashMapCache>>()?;
+        let address_book = loader.load_one(self.0.address_book_id.0).await?;
+        Ok(address_book.map(AddressBookObject))
+    }
+
+    async fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    async fn address(&self) -> &str {
+        &self.0.address
+    }
+
+    async fn phone_number(&self) -> Option<&str> {
+        self.0.phone_number.as_deref()
+    }
+
+    async fn email(&self) -> Option<&str> {
+        self.0.email.as_deref()
+    }
+
+    async fn name_prefix(&self) -> Option<&str> {
+        self.0.details.name_prefix.as_deref()
+    }
+
+    async fn given_name(&self) -> Option<&str> {
+        self.0.details.given_name.as_deref()
+    }
+
+    async fn middle_name(&self) -> Option<&str> {
+        self.0.details.middle_name.as_deref()
+    }
+
+    async fn family_name(&self) -> Option<&str> {
+        self.0.details.family_name.as_deref()
+    }
+
+    async fn name_suffix(&self) -> Option<&str> {
+        self.0.details.name_suffix.as_deref()
+    }
+
+    async fn nickname(&self) -> Option<&str> {
+        self.0.details.nickname.as_deref()
+    }
+
+    async fn organization(&self) -> Option<&str> {
+        self.0.details.organization.as_deref()
+    }
+
+    async fn job_title(&self) -> Option<&str> {
+        self.0.details.job_title.as_deref()
+    }
+
+    async fn birthday(&self) -> Option<NaiveDate> {
+        self.0.details.birthday
+    }
+
+    async fn anniversary(&self) -> Option<NaiveDate> {
+        self.0.details.anniversary
+    }
+
+    async fn notes(&self) -> Option<&str> {
+        self.0.details.notes.as_deref()
+    }
+
+    async fn display_name(&self) -> &str {
+        &self.0.display_name
+    }
+
+    async fn sort_key(&self) -> &str {
+        &self.0.sort_key
+    }
+
+    async fn groups(&self) -> &[String] {
+        &self.0.groups
+    }
+
+    async fn has_photo(&self) -> bool {
+        self.0.has_photo
+    }
+
+    /// Values of the book's custom fields, keyed by field name.
+    async fn custom_fields(&self) -> async_graphql::Json<&Map<String, Value>> {
+        async_graphql::Json(&self.0.custom_fields)
+    }
+
+    async fn created_at(&self) -> DateTime<Utc> {
+        self.0.created_at
+    }
+
+    async fn updated_at(&self) -> DateTime<Utc> {
+        self.0.updated_at
+    }
+
+    async fn created_by(&self) -> Option<&str> {
+        self.0.created_by.as_deref()
+    }
+
+    async fn updated_by(&self) -> Option<&str> {
+        self.0.updated_by.as_deref()
+    }
+
+    /// Pass as `ifVersion` to make a write fail if the contact changed since.
+    async fn version(&self) -> i32 {
+        self.0.version
+    }
+}
+
+#[derive(Enum, Clone, Copy, PartialEq, Eq)]
+#[graphql(name = "OnConflict", remote = "OnConflict")]
+pub enum OnConflictValue {
+    Fail,
+    Skip,
+    Rename,
+    Allow,
+}
+
+#[derive(InputObject)]
+pub struct ContactInput {
+    name: String,
+    address: String,
+    phone_number: Option<String>,
+    email: Option<String>,
+    name_prefix: Option<String>,
+    given_name: Option<String>,
+    middle_name: Option<String>,
+    family_name: Option<String>,
+    name_suffix: Option<String>,
+    nickname: Option<String>,
+    organization: Option<String>,
+    job_title: Option<String>,
+    birthday: Option<NaiveDate>,
+    anniversary: Option<NaiveDate>,
+    notes: Option<String>,
+    custom_fields: Option<async_graphql::Json<Map<String, Value>>>,
+}
+
+impl From<ContactInput> for NewContact {
+    fn from(input: ContactInput) -> Self {
+        NewContact {
+            name: input.name,
+            address: input.address,
+            phone_number: input.phone_number,
+            email: input.email,
+            details: ContactDetails {
+                name_prefix: input.name_prefix,
+                given_name: input.given_name,
+                middle_name: input.middle_name,
+                family_name: input.family_name,
+                name_suffix: input.name_suffix,
+                nickname: input.nickname,
+                organization: input.organization,
+                job_title: input.job_title,
+                birthday: input.birthday,
+                anniversary: input.anniversary,
+                notes: input.notes,
+            },
+            custom_fields: input
+                .custom_fields
+                .map(|fields| fields.0)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// A partial update with JSON Merge Patch semantics: omitted fields are kept and `null`
+/// clears a field.
+#[derive(InputObject)]
+pub struct ContactPatchInput {
+    name: MaybeUndefined<String>,
+    address: MaybeUndefined<String>,
+    phone_number: MaybeUndefined<String>,
+    email: MaybeUndefined<String>,
+    name_prefix: MaybeUndefined<String>,
+    given_name: MaybeUndefined<String>,
+    middle_name: MaybeUndefined<String>,
+    family_name: MaybeUndefined<String>,
+    name_suffix: MaybeUndefined<String>,
+    nickname: MaybeUndefined<String>,
+    organization: MaybeUndefined<String>,
+    job_title: MaybeUndefined<String>,
+    birthday: MaybeUndefined<NaiveDate>,
+    anniversary: MaybeUndefined<NaiveDate>,
+    notes: MaybeUndefined<String>,
+    /// Merged key by key: a `null` value removes that field, a `null` object clears all.
+    custom_fields: MaybeUndefined<async_graphql::Json<Map<String, Value>>>,
+}
+
+impl From<ContactPatchInput> for ContactPatch {
+    fn from(input: ContactPatchInput) -> Self {
+        ContactPatch {
+            name: input.name.into(),
+            address: input.address.into(),
+            phone_number: input.phone_number.into(),
+            email: input.email.into(),
+            name_prefix: input.name_prefix.into(),
+            given_name: input.given_name.into(),
+            middle_name: input.middle_name.into(),
+            family_name: input.family_name.into(),
+            name_suffix: input.name_suffix.into(),
+            nickname: input.nickname.into(),
+            organization: input.organization.into(),
+            job_title: input.job_title.into(),
+            birthday: input.birthday.into(),
+            anniversary: input.anniversary.into(),
+            notes: input.notes.into(),
+            custom_fields: input.custom_fields.map_value(|fields| fields.0).into(),
+        }
+    }
+}
+
+pub struct Query;
+
+#[Object]
+impl Query {
+    async fn address_books(
+        &self,
+        ctx: &Context<'_>,
+        limit: Option<i32>,
+        offset: Option<i32>,
+        sort: Option<String>,
+        locale: Option<String>,
+        updated_since: Option<DateTime<Utc>>,
+    ) -> GraphQlResult<Vec<AddressBookObject>> {
+        let filter = ListFilter {
+            updated_since,
+            sort: sort.as_deref().map(parse_sort_keys).unwrap_or_default(),
+            locale,
+        };
+        let repo = AddressBookRepository::new(ctx.data::<PgPool>()?.clone());
+
+        let address_books = AddressBookService::get_all_address_books(
+            repo,
+            Some(page_size(limit)?),
+            page_offset(offset)?,
+            filter,
+            book_view(),
+        )
+        .await
+        .map_err(graphql_error)?;
+        Ok(address_books.into_iter().map(AddressBookObject).collect())
+    }
+
+    async fn address_book(
+        &self,
+        ctx: &Context<'_>,
+        id: i32,
+    ) -> GraphQlResult<Option<AddressBookObject>> {
+        let repo = AddressBookRepository::new(ctx.data::<PgPool>()?.clone());
+
+        let address_book =
+            found(AddressBookService::get_address_book_by_id(repo, id, book_view()).await)?;
+        Ok(address_book.map(AddressBookObject))
+    }
+
+    async fn contact(
+        &self,
+        ctx: &Context<'_>,
+        address_book_id: i32,
+        id: i32,
+    ) -> GraphQlResult<Option<ContactObject>> {
+        let repo = ContactRepository::new(ctx.data::<PgPool>()?.clone());
+
+        let contact = found(ContactService::get_contact(repo, id, address_book_id).await)?;
+        Ok(contact.map(ContactObject))
+    }
+}
+
+pub struct Mutation;
+
+#[Object]
+impl Mutation {
+    async fn create_address_book(
+        &self,
+        ctx: &Context<'_>,
+        address_book_name: String,
+    ) -> GraphQlResult<AddressBookObject> {
+        let repo = AddressBookRepository::new(ctx.data::<PgPool>()?.clone());
+        let Actor(actor) = ctx.data::<Actor>()?.clone();
+
+        AddressBookService::add_address_book(repo, NewAddressBook { address_book_name }, actor)
+            .await
+            .map(AddressBookObject)
+            .map_err(graphql_error)
+    }
+
+    async fn update_address_book(
+        &self,
+        ctx: &Context<'_>,
+        id: i32,
+        address_book_name: String,
+        if_version: Option<i32>,
+    ) -> GraphQlResult<AddressBookObject> {
+        let repo = AddressBookRepository::new(ctx.data::<PgPool>()?.clone());
+        let Actor(actor) = ctx.data::<Actor>()?.clone();
+
+        AddressBookService::update_address_book(
+            repo,
+            id,
+            NewAddressBook { address_book_name },
+            actor,
+            if_match(if_version),
+        )
+        .await
+        .map(AddressBookObject)
+        .map_err(graphql_error)
+    }
+
+    /// Moves the book and its contacts to the trash.
+    async fn delete_address_book(
+        &self,
+        ctx: &Context<'_>,
+        id: i32,
+        if_version: Option<i32>,
+    ) -> GraphQlResult<bool> {
+        let repo = AddressBookRepository::new(ctx.data::<PgPool>()?.clone());
+        let Actor(actor) = ctx.data::<Actor>()?.clone();
+
+        AddressBookService::delete_address_book(repo, id, actor, if_match(if_version))
+            .await
+            .map(|_| true)
+            .map_err(graphql_error)
+    }
+
+    async fn restore_address_book(
+        &self,
+        ctx: &Context<'_>,
+        id: i32,
+    ) -> GraphQlResult<AddressBookObject> {
+        let repo = AddressBookRepository::new(ctx.data::<PgPool>()?.clone());
+        let Actor(actor) = ctx.data::<Actor>()?.clone();
+
+        AddressBookService::restore_address_book(repo, id, actor)
+            .await
+            .map(AddressBookObject)
+            .map_err(graphql_error)
+    }
+
+    async fn create_contact(
+        &self,
+        ctx: &Context<'_>,
+        address_book_id: i32,
+        contact: ContactInput,
+    ) -> GraphQlResult<ContactObject> {
+        let repo = ContactRepository::new(ctx.data::<PgPool>()?.clone());
+        let Actor(actor) = ctx.data::<Actor>()?.clone();
+
+        ContactService::add_contact(repo, address_book_id, contact.into(), actor)
+            .await
+            .map(ContactObject)
+            .map_err(graphql_error)
+    }
+
+    async fn update_contact(
+        &self,
+        ctx: &Context<'_>,
+        address_book_id: i32,
+        id: i32,
+        contact: ContactInput,
+        if_version: Option<i32>,
+    ) -> GraphQlResult<ContactObject> {
+        let repo = ContactRepository::new(ctx.data::<PgPool>()?.clone());
+        let Actor(actor) = ctx.data::<Actor>()?.clone();
+
+        ContactService::update_contact(
+            repo,
+            id,
+            address_book_id,
+            contact.into(),
+            actor,
+            if_match(if_version),
+        )
+        .await
+        .map(ContactObject)
+        .map_err(graphql_error)
+    }
+
+    async fn patch_contact(
+        &self,
+        ctx: &Context<'_>,
+        address_book_id: i32,
+        id: i32,
+        patch: ContactPatchInput,
+        if_version: Option<i32>,
+    ) -> GraphQlResult<ContactObject> {
+        let repo = ContactRepository::new(ctx.data::<PgPool>()?.clone());
+        let Actor(actor) = ctx.data::<Actor>()?.clone();
+
+        ContactService::patch_contact(
+            repo,
+            id,
+            address_book_id,
+            patch.into(),
+            actor,
+            if_match(if_version),
+        )
+        .await
+        .map(ContactObject)
+        .map_err(graphql_error)
+    }
+
+    /// Moves the contact to the trash.
+    async fn delete_contact(
+        &self,
+        ctx: &Context<'_>,
+        address_book_id: i32,
+        id: i32,
+        if_version: Option<i32>,
+    ) -> GraphQlResult<bool> {
+        let repo = ContactRepository::new(ctx.data::<PgPool>()?.clone());
+        let Actor(actor) = ctx.data::<Actor>()?.clone();
+
+        ContactService::delete_contact(repo, id, address_book_id, actor, if_match(if_version))
+            .await
+            .map(|_| true)
+            .map_err(graphql_error)
+    }
+
+    async fn restore_contact(
+        &self,
+        ctx: &Context<'_>,
+        address_book_id: i32,
+        id: i32,
+    ) -> GraphQlResult<ContactObject> {
+        let repo = ContactRepository::new(ctx.data::<PgPool>()?.clone());
+        let Actor(actor) = ctx.data::<Actor>()?.clone();
+
+        ContactService::restore_contact(repo, id, address_book_id, actor)
+            .await
+            .map(ContactObject)
+            .map_err(graphql_error)
+    }
+
+    async fn move_contacts(
+        &self,
+        ctx: &Context<'_>,
+        contact_ids: Vec<i32>,
+        target_address_book_id: i32,
+        #[graphql(default_with = "OnConflictValue::Fail")] on_conflict: OnConflictValue,
+    ) -> GraphQlResult<Vec<ContactObject>> {
+        let repo = ContactRepository::new(ctx.data::<PgPool>()?.clone());
+        let Actor(actor) = ctx.data::<Actor>()?.clone();
+        let request = BulkMoveRequest {
+            contact_ids,
+            target_address_book_id,
+            on_conflict: on_conflict.into(),
+        };
+
+        let contacts = ContactService::move_contacts(repo, request, actor)
+            .await
+            .map_err(graphql_error)?;
+        Ok(contacts.into_iter().map(ContactObject).collect())
+    }
+
+    async fn copy_contact(
+        &self,
+        ctx: &Context<'_>,
+        id: i32,
+        target_address_book_id: i32,
+        #[graphql(default_with = "OnConflictValue::Fail")] on_conflict: OnConflictValue,
+    ) -> GraphQlResult<ContactObject> {
+        let repo = ContactRepository::new(ctx.data::<PgPool>()?.clone());
+        let Actor(actor) = ctx.data::<Actor>()?.clone();
+        let request = TransferRequest {
+            target_address_book_id,
+            on_conflict: on_conflict.into(),
+        };
+
+        ContactService::copy_contact(repo, id, request, actor)
+            .await
+            .map(ContactObject)
+            .map_err(graphql_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    async fn run(pool: &PgPool, query: &str) -> Value {
+        let request = with_context(query.into(), pool.clone(), Actor(None));
+        serde_json::to_value(schema().execute(request).await).unwrap()
+    }
+
+    /// The query's errors as `(code, status)` pairs.
+    fn error_codes(response: &Value) -> Vec<(&str, u64)> {
+        response["errors"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| {
+                let extensions = &e["extensions"];
+                (
+                    extensions["code"].as_str().unwrap(),
+                    extensions["status"].as_u64().unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_schema_refuses_queries_nested_too_deep() {
+        let pool = PgPool::connect_lazy("postgres://localhost/unused").unwrap();
+        let mut selection = String::from("id");
+        for _ in 0..5 {
+            selection = format!("contacts {{ addressBook {{ {selection} }} }}");
+        }
+        let response = run(&pool, &format!("{{ addressBooks {{ {selection} }} }}")).await;
+
+        assert_eq!(response["data"], Value::Null);
+        assert_eq!(
+            response["errors"][0]["message"],
+            "Query is nested too deep."
+        );
+    }
+
+    #[tokio::test]
+    async fn test_schema_validates_queries() {
+        let pool = PgPool::connect_lazy("postgres://localhost/unused").unwrap();
+        for query in [
+            "{ addressBooks { unknownField } }",
+            "{ addressBook(id: \"one\") { id } }",
+            "{ contact(id: 1) { id } }",
+        ] {
+            let response = run(&pool, query).await;
+            assert_eq!(response["data"], Value::Null, "{query}");
+            assert_eq!(response["errors"].as_array().unwrap().len(), 1, "{query}");
+        }
+    }
+
+    // Needs a database server: DATABASE_URL=postgres://... cargo test -- --ignored
+    #[sqlx::test]
+    #[ignore]
+    async fn test_schema_reports_errors_as_rest_would(pool: PgPool) {
+        let response = run(&pool, "{ addressBooks(limit: 500) { id } }").await;
+        assert_eq!(error_codes(&response), [("UNPROCESSABLE_ENTITY", 422)]);
+        assert_eq!(
+            response["errors"][0]["message"],
+            format!("limit must be between 1 and {MAX_PAGE_SIZE}")
+        );
+
+        let response = run(&pool, "{ addressBooks(offset: -1) { id } }").await;
+        assert_eq!(error_codes(&response), [("UNPROCESSABLE_ENTITY", 422)]);
+
+        let response = run(
+            &pool,
+            "mutation { createAddressBook(addressBookName: \"Friends\") { id version } }",
+        )
+        .await;
+        let id = response["data"]["createAddressBook"]["id"]
+            .as_i64()
+            .unwrap();
+        let version = response["data"]["createAddressBook"]["version"]
+            .as_i64()
+            .unwrap();
+
+        let response = run(
+            &pool,
+            &format!(
+                "mutation {{ updateAddressBook(id: {id}, addressBookName: \"Family\", \
+                 ifVersion: {}) {{ id }} }}",
+                version + 1
+            ),
+        )
+        .await;
+        assert_eq!(error_codes(&response), [("PRECONDITION_FAILED", 412)]);
+
+        let response = run(
+            &pool,
+            &format!("{{ addressBook(id: {id}) {{ contacts(limit: 0) {{ id }} }} }}"),
+        )
+        .await;
+        assert_eq!(error_codes(&response), [("UNPROCESSABLE_ENTITY", 422)]);
+
+        let response = run(
+            &pool,
+            &format!(
+                "{{ addressBook(id: {}) {{ id }} contact(addressBookId: {id}, id: 1) {{ id }} }}",
+                id + 1
+            ),
+        )
+        .await;
+        assert_eq!(
+            response,
+            json!({ "data": { "addressBook": null, "contact": null } })
+        );
+    }
+
+    #[sqlx::test]
+    #[ignore]
+    async fn test_schema_loads_nested_fields_per_book(pool: PgPool) {
+        for (book, names) in [
+            ("Friends", ["Ann Smith", "Bob"]),
+            ("Work", ["Cy Smithers", "Di"]),
+        ] {
+            let response = run(
+                &pool,
+                &format!("mutation {{ createAddressBook(addressBookName: \"{book}\") {{ id }} }}"),
+            )
+            .await;
+            let id = &response["data"]["createAddressBook"]["id"];
+            for name in names {
+                let response = run(
+                    &pool,
+                    &format!(
+                        "mutation {{ createContact(addressBookId: {id}, \
+                         contact: {{ name: \"{name}\", address: \"1 Main St\" }}) {{ id }} }}"
+                    ),
+                )
+                .await;
+                assert_eq!(response["errors"], Value::Null);
+            }
+        }
+
+        // Two pages of each book, and every contact's book, through the loaders.
+        let response = run(
+            &pool,
+            "{ addressBooks(sort: \"name\") {
+                 addressBookName
+                 contactCount
+                 first: contacts(sort: \"name\", limit: 1) { name }
+                 found: contacts(search: \"smith\") {
+                   name
+                   addressBook { addressBookName }
+                 }
+               } }",
+        )
+        .await;
+        assert_eq!(
+            response,
+            json!({ "data": { "addressBooks": [
+                {
+                    "addressBookName": "Friends",
+                    "contactCount": 2,
+                    "first": [{ "name": "Ann Smith" }],
+                    "found": [{
+                        "name": "Ann Smith",
+                        "addressBook": { "addressBookName": "Friends" },
+                    }],
+                },
+                {
+                    "addressBookName": "Work",
+                    "contactCount": 2,
+                    "first": [{ "name": "Cy Smithers" }],
+                    "found": [{
+                        "name": "Cy Smithers",
+                        "addressBook": { "addressBookName": "Work" },
+                    }],
+                },
+            ] } })
+        );
+    }
+
+    #[sqlx::test]
+    #[ignore]
+    async fn test_contact_page_loader_answers_each_book_and_page(pool: PgPool) {
+        let mut books = Vec::new();
+        for book in ["Friends", "Work", "Empty"] {
+            let response = run(
+                &pool,
+                &format!("mutation {{ createAddressBook(addressBookName: \"{book}\") {{ id }} }}"),
+            )
+            .await;
+            books.push(
+                response["data"]["createAddressBook"]["id"]
+                    .as_i64()
+                    .unwrap() as i32,
+            );
+        }
+        for (book, name) in [(books[0], "Ann"), (books[0], "Bob"), (books[1], "Cy")] {
+            run(
+                &pool,
+                &format!(
+                    "mutation {{ createContact(addressBookId: {book}, \
+                     contact: {{ name: \"{name}\", address: \"1 Main St\" }}) {{ id }} }}"
+                ),
+            )
+            .await;
+        }
+        let page = |offset| ContactPage {
+            limit: 1,
+            offset,
+            search: None,
+            sort: Some(String::from("name")),
+            locale: None,
+            group: None,
+            updated_since: None,
+        };
+        let keys = [
+            (books[0], page(0)),
+            (books[1], page(0)),
+            (books[2], page(0)),
+            (books[0], page(1)),
+        ];
+
+        let pages = ContactPageLoader(pool).load(&keys).await.unwrap();
+        let names = |key: &(i32, ContactPage)| -> Vec<String> {
+            pages
+                .get(key)
+                .map(|contacts| contacts.iter().map(|c| c.name.clone()).collect())
+                .unwrap_or_default()
+        };
+        assert_eq!(names(&keys[0]), ["Ann"]);
+        assert_eq!(names(&keys[1]), ["Cy"]);
+        assert!(names(&keys[2]).is_empty());
+        assert_eq!(names(&keys[3]), ["Bob"]);
+    }
+}
@@ -0,0 +1,244 @@
+use axum::extract::{rejection::JsonRejection, Json, Path, State};
+
+use crate::repositories::group_repo::GroupRepository;
+use crate::services::group_service::GroupService;
+use crate::types::contact::Contact;
+use crate::types::group::{ContactGroup, NewContactGroup};
+use crate::types::precondition::{IfMatch, IfNoneMatch};
+use crate::types::{Actor, ApiError, ApiResponse, AppState, ErrorBody};
+
+use super::map_error;
+use handle_errors::Error;
+
+#[utoipa::path(
+    get,
+    path = "/api/addressbooks/{id}/groups",
+    tag = "groups",
+    params(
+        ("id" = i32, Path, description = "Address book id"),
+    ),
+    responses(
+        (status = 200, description = "The book's groups", body = [ContactGroup]),
+        (status = 404, description = "No such address book", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%address_book_id))]
+pub async fn index(
+    Path(address_book_id): Path<i32>,
+    State(state): State<AppState>,
+) -> Result<ApiResponse, ApiError> {
+    let repo = GroupRepository::new(state.pool);
+
+    match GroupService::get_groups(repo, address_book_id).await {
+        Ok(groups) => Ok(ApiResponse::JsonDataGroupCollection(groups)),
+        Err(e) => Err(map_error(e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/addressbooks/{id}/groups",
+    tag = "groups",
+    params(
+        ("id" = i32, Path, description = "Address book id"),
+        ("X-User" = Option<String>, Header, description = "Caller recorded as the author of the write"),
+    ),
+    request_body = NewContactGroup,
+    responses(
+        (status = 200, description = "The created group", body = ContactGroup),
+        (status = 400, description = "Malformed body", body = ErrorBody),
+        (status = 404, description = "No such address book", body = ErrorBody),
+        (status = 409, description = "A group with the same name", body = ErrorBody),
+        (status = 422, description = "Invalid name", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%address_book_id))]
+pub async fn create_group(
+    Path(address_book_id): Path<i32>,
+    State(state): State<AppState>,
+    Actor(actor): Actor,
+    payload: Result<Json<NewContactGroup>, JsonRejection>,
+) -> Result<ApiResponse, ApiError> {
+    match payload {
+        Ok(payload) => {
+            let repo = GroupRepository::new(state.pool);
+
+            match GroupService::create_group(repo, address_book_id, payload.0, actor).await {
+                Ok(group) => Ok(ApiResponse::JsonDataGroup(group)),
+                Err(e) => Err(map_error(e)),
+            }
+        }
+        Err(e) => Err(map_error(Error::JsonDeserilizationError(e))),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/addressbooks/{id}/groups/{group_id}",
+    tag = "groups",
+    params(
+        ("id" = i32, Path, description = "Address book id"),
+        ("group_id" = i32, Path, description = "Group id"),
+        ("If-None-Match" = Option<String>, Header, description = "Entity tags already held"),
+    ),
+    responses(
+        (status = 200, description = "The group", body = ContactGroup),
+        (status = 304, description = "The group still has one of the given entity tags"),
+        (status = 404, description = "No such address book or group", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%address_book_id, %group_id))]
+pub async fn show(
+    Path((address_book_id, group_id)): Path<(i32, i32)>,
+    State(state): State<AppState>,
+    if_none_match: IfNoneMatch,
+) -> Result<ApiResponse, ApiError> {
+    let repo = GroupRepository::new(state.pool);
+
+    match GroupService::get_group(repo, address_book_id, group_id).await {
+        Ok(group) if if_none_match.matches(&group.etag()) => {
+            Ok(ApiResponse::NotModified(group.etag()))
+        }
+        Ok(group) => Ok(ApiResponse::JsonDataGroup(group)),
+        Err(e) => Err(map_error(e)),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/addressbooks/{id}/groups/{group_id}",
+    tag = "groups",
+    params(
+        ("id" = i32, Path, description = "Address book id"),
+        ("group_id" = i32, Path, description = "Group id"),
+        ("If-Match" = Option<String>, Header, description = "Entity tag the group must still have"),
+        ("X-User" = Option<String>, Header, description = "Caller recorded as the author of the write"),
+    ),
+    request_body = NewContactGroup,
+    responses(
+        (status = 200, description = "The renamed group", body = ContactGroup),
+        (status = 400, description = "Malformed body", body = ErrorBody),
+        (status = 404, description = "No such address book or group", body = ErrorBody),
+        (status = 409, description = "A group with the same name", body = ErrorBody),
+        (status = 412, description = "The group has changed", body = ErrorBody),
+        (status = 422, description = "Invalid name", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%address_book_id, %group_id))]
+pub async fn update(
+    Path((address_book_id, group_id)): Path<(i32, i32)>,
+    State(state): State<AppState>,
+    Actor(actor): Actor,
+    if_match: IfMatch,
+    payload: Result<Json<NewContactGroup>, JsonRejection>,
+) -> Result<ApiResponse, ApiError> {
+    match payload {
+        Ok(payload) => {
+            let repo = GroupRepository::new(state.pool);
+
+            match GroupService::update_group(
+                repo,
+                address_book_id,
+                group_id,
+                payload.0,
+                actor,
+                if_match,
+            )
+            .await
+            {
+                Ok(group) => Ok(ApiResponse::JsonDataGroup(group)),
+                Err(e) => Err(map_error(e)),
+            }
+        }
+        Err(e) => Err(map_error(Error::JsonDeserilizationError(e))),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/addressbooks/{id}/groups/{group_id}",
+    tag = "groups",
+    params(
+        ("id" = i32, Path, description = "Address book id"),
+        ("group_id" = i32, Path, description = "Group id"),
+        ("If-Match" = Option<String>, Header, description = "Entity tag the group must still have"),
+        ("X-User" = Option<String>, Header, description = "Caller recorded as the author of the write"),
+    ),
+    responses(
+        (status = 204, description = "The group was removed"),
+        (status = 404, description = "No such address book or group", body = ErrorBody),
+        (status = 412, description = "The group has changed", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%address_book_id, %group_id))]
+pub async fn delete_group(
+    Path((address_book_id, group_id)): Path<(i32, i32)>,
+    State(state): State<AppState>,
+    Actor(actor): Actor,
+    if_match: IfMatch,
+) -> Result<ApiResponse, ApiError> {
+    let repo = GroupRepository::new(state.pool);
+
+    match GroupService::delete_group(repo, address_book_id, group_id, actor, if_match).await {
+        Ok(_) => Ok(ApiResponse::NoContent),
+        Err(e) => Err(map_error(e)),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/addressbooks/{id}/groups/{group_id}/contacts/{contact_id}",
+    tag = "groups",
+    params(
+        ("id" = i32, Path, description = "Address book id"),
+        ("group_id" = i32, Path, description = "Group id"),
+        ("contact_id" = i32, Path, description = "Contact id"),
+        ("X-User" = Option<String>, Header, description = "Caller recorded as the author of the write"),
+    ),
+    responses(
+        (status = 200, description = "The contact, now a member", body = Contact),
+        (status = 404, description = "No such address book, group or contact", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%address_book_id, %contact_id, %group_id))]
+pub async fn add_member(
+    Path((address_book_id, group_id, contact_id)): Path<(i32, i32, i32)>,
+    State(state): State<AppState>,
+    Actor(actor): Actor,
+) -> Result<ApiResponse, ApiError> {
+    let repo = GroupRepository::new(state.pool);
+
+    match GroupService::add_member(repo, address_book_id, group_id, contact_id, actor).await {
+        Ok(contact) => Ok(ApiResponse::JsonDataContact(Box::new(contact))),
+        Err(e) => Err(map_error(e)),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/addressbooks/{id}/groups/{group_id}/contacts/{contact_id}",
+    tag = "groups",
+    params(
+        ("id" = i32, Path, description = "Address book id"),
+        ("group_id" = i32, Path, description = "Group id"),
+        ("contact_id" = i32, Path, description = "Contact id"),
+        ("X-User" = Option<String>, Header, description = "Caller recorded as the author of the write"),
+    ),
+    responses(
+        (status = 200, description = "The contact, no longer a member", body = Contact),
+        (status = 404, description = "No such address book, group or contact", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%address_book_id, %contact_id, %group_id))]
+pub async fn remove_member(
+    Path((address_book_id, group_id, contact_id)): Path<(i32, i32, i32)>,
+    State(state): State<AppState>,
+    Actor(actor): Actor,
+) -> Result<ApiResponse, ApiError> {
+    let repo = GroupRepository::new(state.pool);
+
+    match GroupService::remove_member(repo, address_book_id, group_id, contact_id, actor).await {
+        Ok(contact) => Ok(ApiResponse::JsonDataContact(Box::new(contact))),
+        Err(e) => Err(map_error(e)),
+    }
+}
@@ -0,0 +1,94 @@
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::types::limits::RequestLimits;
+use crate::types::rate_limit::RouteClass;
+use crate::types::ApiError;
+
+/// Answers `504` when a request has not produced a response in time. The handler is dropped,
+/// which rolls back any transaction it had open. Only the time until the response starts
+/// counts, so event streams and exports are not cut off.
+pub async fn timeout(
+    State(limits): State<RequestLimits>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let class = RouteClass::of(request.method(), request.uri().path());
+    match tokio::time::timeout(limits.timeout(class), next.run(request)).await {
+        Ok(response) => response,
+        Err(_) => ApiError::Timeout.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::{to_bytes, Body};
+    use axum::http::{Request, StatusCode};
+    use serde_json::Value;
+    use sqlx::PgPool;
+    use std::time::Duration;
+    use tower::ServiceExt;
+
+    /// Sends a request through `app()` and returns its status and JSON body.
+    async fn send(app: &axum::Router, request: Request<Body>) -> (StatusCode, Value) {
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    #[tokio::test]
+    async fn test_router_refuses_large_bodies() {
+        let mut state =
+            crate::tests::state(PgPool::connect_lazy("postgres://localhost/unused").unwrap());
+        state.limits.body_bytes = 64;
+        let app = crate::app(state);
+        let body = format!("{{\"name\": \"{}\"}}", "a".repeat(100));
+
+        for (method, uri, content_type) in [
+            ("POST", "/api/addressbooks/1/contacts", "application/json"),
+            (
+                "PATCH",
+                "/api/addressbooks/1/contacts/1",
+                "application/merge-patch+json",
+            ),
+            ("PUT", "/dav/addressbooks/1/ann.vcf", "text/vcard"),
+            ("PROPFIND", "/dav/addressbooks/1/", "application/xml"),
+        ] {
+            let request = Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("content-type", content_type)
+                .body(Body::from(body.clone()))
+                .unwrap();
+            let (status, body) = send(&app, request).await;
+            assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE, "{method} {uri}");
+            assert_eq!(
+                body["error"], "request body is too large",
+                "{method} {uri}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_router_times_out_slow_requests() {
+        // A database that accepts connections and never answers keeps the handler waiting.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("postgres://{}/unused", listener.local_addr().unwrap());
+        let mut state = crate::tests::state(PgPool::connect_lazy(&url).unwrap());
+        state.limits.timeout = Duration::from_millis(100);
+        let app = crate::app(state);
+
+        let request = Request::builder()
+            .uri("/api/addressbooks/1/contacts")
+            .body(Body::empty())
+            .unwrap();
+        let (status, body) = send(&app, request).await;
+        assert_eq!(status, StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(body["error"], "request timed out");
+        drop(listener);
+    }
+}
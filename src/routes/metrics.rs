@@ -0,0 +1,11 @@
+use axum::extract::State;
+
+use crate::monitoring;
+use crate::types::{ApiResponse, AppState};
+
+/// The Prometheus scrape page.
+#[tracing::instrument(skip_all)]
+pub async fn scrape(State(state): State<AppState>) -> ApiResponse {
+    monitoring::record_pool(&state.pool);
+    ApiResponse::Metrics(state.metrics.render())
+}
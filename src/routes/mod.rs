@@ -1 +1,96 @@
 pub mod address_book;
+pub mod audit;
+pub mod contact;
+pub mod dav;
+pub mod event;
+pub mod field;
+pub mod graphql;
+pub mod group;
+pub mod limits;
+pub mod metrics;
+pub mod openapi;
+pub mod photo;
+pub mod rate_limit;
+pub mod trash;
+pub mod webhook;
+
+use crate::types::ApiError;
+use axum::http::{header, HeaderMap, StatusCode};
+use handle_errors::Error;
+
+/// SQLSTATE of a statement cancelled by `statement_timeout`.
+const QUERY_CANCELED: &str = "57014";
+
+fn map_error(error: Error) -> ApiError {
+    // The client only learns the status, so server-side failures are logged with their cause.
+    match &error {
+        Error::DatabaseQueryError(_) | Error::StorageError(_) => {
+            tracing::error!(error = %error, "request failed")
+        }
+        _ => tracing::debug!(error = %error, "request rejected"),
+    }
+    match error {
+        // Every pooled connection stayed busy for the acquire timeout.
+        Error::DatabaseQueryError(sqlx::Error::PoolTimedOut) => ApiError::ServiceUnavailable,
+        Error::DatabaseQueryError(sqlx::Error::Database(e))
+            if e.code().as_deref() == Some(QUERY_CANCELED) =>
+        {
+            ApiError::Timeout
+        }
+        Error::DatabaseQueryError(_) => ApiError::DataBaseError,
+        Error::AddressBookNotFound => ApiError::AddressBookNotFound,
+        Error::ContactNotFound => ApiError::ContactNotFound,
+        Error::GroupNotFound => ApiError::GroupNotFound,
+        Error::FieldNotFound => ApiError::FieldNotFound,
+        Error::PhotoNotFound => ApiError::PhotoNotFound,
+        Error::WebhookNotFound => ApiError::WebhookNotFound,
+        Error::WebhookDeliveryNotFound => ApiError::WebhookDeliveryNotFound,
+        Error::PreconditionFailed => ApiError::PreconditionFailed,
+        Error::UnsupportedMediaType => ApiError::UnsupportedMediaType,
+        Error::PayloadTooLarge(message) => ApiError::PayloadTooLarge(message),
+        Error::InvalidBody(_) => ApiError::JsonDeserilize,
+        Error::BadRequest(message) => ApiError::BadRequest(message),
+        Error::MethodNotAllowed => ApiError::MethodNotAllowed,
+        Error::InvalidSyncToken => ApiError::InvalidSyncToken,
+        Error::ValidationError(message) => ApiError::ValidationError(message),
+        Error::Conflict(message) => ApiError::Conflict(message),
+        Error::StorageError(_) => ApiError::StorageError,
+        Error::JsonDeserilizationError(e) if e.status() == StatusCode::PAYLOAD_TOO_LARGE => {
+            ApiError::PayloadTooLarge(String::from("request body is too large"))
+        }
+        Error::JsonDeserilizationError(_) => ApiError::JsonDeserilize,
+        Error::BodyRejection(e) if e.status() == StatusCode::PAYLOAD_TOO_LARGE => {
+            ApiError::PayloadTooLarge(String::from("request body is too large"))
+        }
+        Error::BodyRejection(e) => ApiError::BadRequest(e.body_text()),
+    }
+}
+
+/// Whether the request's `Content-Type` is `expected`, ignoring parameters such as charset.
+fn has_content_type(headers: &HeaderMap, expected: &str) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .is_some_and(|mime| mime.trim().eq_ignore_ascii_case(expected))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_error_reports_exhausted_pool_as_unavailable() {
+        let error = map_error(Error::DatabaseQueryError(sqlx::Error::PoolTimedOut));
+        assert_eq!(
+            error.status_and_message().0,
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+
+        let error = map_error(Error::DatabaseQueryError(sqlx::Error::RowNotFound));
+        assert_eq!(
+            error.status_and_message().0,
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+}
@@ -0,0 +1,292 @@
+use utoipa::{Modify, OpenApi};
+use utoipa_swagger_ui::SwaggerUi;
+
+use crate::types::address_book::{AddressBook, AddressBookId, NewAddressBook};
+use crate::types::audit::AuditEvent;
+use crate::types::bulk::{BulkItemError, BulkItemResult, BulkOperation, BulkRequest, BulkResult};
+use crate::types::changes::ContactChanges;
+use crate::types::contact::{
+    BulkMoveRequest, Contact, ContactDetails, ContactId, ContactPatch, NewContact, OnConflict,
+    TransferRequest,
+};
+use crate::types::custom_field::{CustomField, FieldId, FieldType, NewCustomField};
+use crate::types::group::{ContactGroup, GroupId, NewContactGroup};
+use crate::types::photo::{Photo, PhotoSize};
+use crate::types::stats::{AddressBookStats, StatsBucket};
+use crate::types::trash::Trash;
+use crate::types::webhook::{DeliveryAttempt, NewWebhook, Webhook, WebhookDelivery, WebhookId};
+use crate::types::ErrorBody;
+
+use super::{address_book, audit, contact, event, field, group, photo, trash, webhook};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Address book API",
+        description = "Address books and their contacts. Errors are sent as `ErrorBody`."
+    ),
+    modifiers(&WithoutLicense),
+    paths(
+        address_book::index,
+        address_book::create_address_book,
+        address_book::show,
+        address_book::update,
+        address_book::delete_address_book,
+        address_book::restore,
+        address_book::stats,
+        contact::index,
+        contact::create_contact,
+        contact::show,
+        contact::update,
+        contact::patch,
+        contact::delete_contact,
+        contact::restore,
+        contact::changes,
+        event::stream,
+        contact::bulk,
+        contact::export,
+        contact::import,
+        contact::move_contact,
+        contact::move_contacts,
+        contact::copy_contact,
+        photo::show,
+        photo::update,
+        photo::delete_photo,
+        field::index,
+        field::create_field,
+        field::show,
+        field::update,
+        field::delete_field,
+        group::index,
+        group::create_group,
+        group::show,
+        group::update,
+        group::delete_group,
+        group::add_member,
+        group::remove_member,
+        webhook::index,
+        webhook::create_webhook,
+        webhook::show,
+        webhook::update,
+        webhook::delete_webhook,
+        webhook::deliveries,
+        webhook::delivery,
+        webhook::redeliver,
+        audit::address_book_history,
+        audit::contact_history,
+        trash::index,
+    ),
+    components(schemas(
+        AddressBook,
+        AddressBookId,
+        NewAddressBook,
+        AddressBookStats,
+        StatsBucket,
+        Contact,
+        ContactId,
+        ContactDetails,
+        NewContact,
+        ContactPatch,
+        ContactChanges,
+        BulkRequest,
+        BulkOperation,
+        BulkResult,
+        BulkItemResult,
+        BulkItemError,
+        OnConflict,
+        TransferRequest,
+        BulkMoveRequest,
+        Photo,
+        PhotoSize,
+        CustomField,
+        FieldId,
+        FieldType,
+        NewCustomField,
+        ContactGroup,
+        GroupId,
+        NewContactGroup,
+        Webhook,
+        WebhookId,
+        NewWebhook,
+        WebhookDelivery,
+        DeliveryAttempt,
+        AuditEvent,
+        Trash,
+        ErrorBody,
+    )),
+    tags(
+        (name = "address books", description = "Address books"),
+        (name = "contacts", description = "Contacts of an address book"),
+        (name = "photos", description = "Contact photos and their thumbnails"),
+        (name = "fields", description = "Custom fields defined on an address book"),
+        (name = "groups", description = "Contact groups of an address book"),
+        (name = "webhooks", description = "Webhook subscriptions and their deliveries"),
+        (name = "history", description = "Audit log of changes"),
+        (name = "trash", description = "Deleted address books and contacts"),
+    )
+)]
+pub struct ApiDoc;
+
+/// Cargo.toml names no license, which would otherwise be published as an empty one.
+struct WithoutLicense;
+
+impl Modify for WithoutLicense {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        openapi.info.license = None;
+    }
+}
+
+/// Serves the spec at `/api/openapi.json` and Swagger UI at `/api/docs`. The UI's assets are
+/// built into the binary, so the page needs nothing from outside the service.
+pub fn docs() -> SwaggerUi {
+    SwaggerUi::new("/api/docs").url("/api/openapi.json", ApiDoc::openapi())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{to_bytes, Body, Bytes};
+    use axum::http::{Method, Request, StatusCode};
+    use sqlx::postgres::PgPoolOptions;
+    use std::time::Duration;
+    use tower::ServiceExt;
+
+    const METHODS: [Method; 5] = [
+        Method::GET,
+        Method::PUT,
+        Method::POST,
+        Method::PATCH,
+        Method::DELETE,
+    ];
+
+    /// Sends `method` to `path` through `app()`, without a database behind it.
+    async fn send(method: &Method, path: &str) -> (StatusCode, Bytes) {
+        // Handlers that reach the database fail fast instead of waiting for one.
+        let pool = PgPoolOptions::new()
+            .acquire_timeout(Duration::from_millis(100))
+            .connect_lazy("postgres://localhost:1/unused")
+            .unwrap();
+        let request = Request::builder()
+            .method(method)
+            .uri(path)
+            .header("content-type", "application/json")
+            .body(Body::from("{}"))
+            .unwrap();
+        let response = crate::app(crate::tests::state(pool))
+            .oneshot(request)
+            .await
+            .unwrap();
+        let status = response.status();
+        (
+            status,
+            to_bytes(response.into_body(), usize::MAX).await.unwrap(),
+        )
+    }
+
+    /// Whether `app()` has a handler for `method` on `path`. Unrouted requests get the
+    /// router's own empty 404 or 405; handlers always answer with a body.
+    async fn routed(method: &Method, path: &str) -> bool {
+        let (status, body) = send(method, path).await;
+        match status {
+            StatusCode::METHOD_NOT_ALLOWED => false,
+            StatusCode::NOT_FOUND => !body.is_empty(),
+            _ => true,
+        }
+    }
+
+    /// The `/api` routes `app()` registers, as `(method, path)` in the spec's notation, read
+    /// from its source since a built router cannot list them.
+    fn router_routes() -> Vec<(String, String)> {
+        include_str!("../main.rs")
+            .split(".route(")
+            .skip(1)
+            .filter_map(|call| {
+                let (path, rest) = call.trim_start().strip_prefix('"')?.split_once('"')?;
+                let method = rest
+                    .trim_start_matches([',', ' ', '\n'])
+                    .split('(')
+                    .next()?;
+                let path = path
+                    .split('/')
+                    .map(|segment| match segment.strip_prefix(':') {
+                        Some(param) => format!("{{{param}}}"),
+                        None => segment.to_string(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join("/");
+                Some((method.to_string(), path))
+            })
+            .filter(|(_, path)| path.starts_with("/api/"))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_spec_matches_router() {
+        let paths = ApiDoc::openapi().paths.paths;
+        assert!(paths.contains_key("/api/addressbooks"));
+
+        let routes = router_routes();
+        assert!(routes.contains(&(String::from("get"), String::from("/api/trash"))));
+        for (method, path) in routes {
+            let documented = paths.get(&path).is_some_and(|item| match method.as_str() {
+                "get" => item.get.is_some(),
+                "put" => item.put.is_some(),
+                "post" => item.post.is_some(),
+                "patch" => item.patch.is_some(),
+                "delete" => item.delete.is_some(),
+                _ => false,
+            });
+            assert!(
+                documented,
+                "{method} {path} is in the router but not the spec"
+            );
+        }
+
+        for (path, item) in paths {
+            // Path parameters are all numeric ids.
+            let uri: Vec<&str> = path
+                .split('/')
+                .map(|segment| {
+                    if segment.starts_with('{') {
+                        "1"
+                    } else {
+                        segment
+                    }
+                })
+                .collect();
+            let uri = uri.join("/");
+            for method in METHODS {
+                let documented = match method {
+                    Method::GET => item.get.is_some(),
+                    Method::PUT => item.put.is_some(),
+                    Method::POST => item.post.is_some(),
+                    Method::PATCH => item.patch.is_some(),
+                    _ => item.delete.is_some(),
+                };
+                assert_eq!(
+                    routed(&method, &uri).await,
+                    documented,
+                    "{method} {path} is {} the spec but {} the router",
+                    if documented { "in" } else { "not in" },
+                    if documented { "not in" } else { "in" },
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_docs_are_served_from_the_binary() {
+        let (status, spec) = send(&Method::GET, "/api/openapi.json").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&spec).unwrap(),
+            serde_json::to_value(ApiDoc::openapi()).unwrap()
+        );
+
+        let (status, page) = send(&Method::GET, "/api/docs/").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(String::from_utf8_lossy(&page).contains("swagger-ui"));
+        let (status, _) = send(&Method::GET, "/api/docs/swagger-ui-bundle.js").await;
+        assert_eq!(status, StatusCode::OK);
+    }
+}
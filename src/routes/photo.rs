@@ -0,0 +1,155 @@
+use axum::body::Bytes;
+use axum::extract::{rejection::BytesRejection, Path, Query, State};
+use axum::http::{HeaderMap, StatusCode};
+
+use crate::repositories::photo_repo::PhotoRepository;
+use crate::services::photo_service::PhotoService;
+use crate::types::photo::{Photo, PhotoQuery, MAX_PHOTO_BYTES, PHOTO_CONTENT_TYPES};
+use crate::types::precondition::IfNoneMatch;
+use crate::types::{Actor, ApiError, ApiResponse, AppState, ErrorBody};
+
+use super::{has_content_type, map_error};
+use handle_errors::Error;
+
+#[utoipa::path(
+    get,
+    path = "/api/addressbooks/{id}/contacts/{contact_id}/photo",
+    tag = "photos",
+    params(
+        ("id" = i32, Path, description = "Address book id"),
+        ("contact_id" = i32, Path, description = "Contact id"),
+        PhotoQuery,
+        ("If-None-Match" = Option<String>, Header, description = "Entity tags already held"),
+    ),
+    responses(
+        (status = 200, description = "The photo", content(
+            (Vec<u8> = "image/jpeg"),
+            (Vec<u8> = "image/png"),
+            (Vec<u8> = "image/webp"),
+        )),
+        (status = 304, description = "The photo still has one of the given entity tags"),
+        (status = 404, description = "No such address book, contact or photo", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%address_book_id, %contact_id))]
+pub async fn show(
+    Path((address_book_id, contact_id)): Path<(i32, i32)>,
+    State(state): State<AppState>,
+    Query(query): Query<PhotoQuery>,
+    if_none_match: IfNoneMatch,
+) -> Result<ApiResponse, ApiError> {
+    let repo = PhotoRepository::new(state.pool);
+
+    match PhotoService::get_photo(
+        repo,
+        state.blob_store,
+        address_book_id,
+        contact_id,
+        query.size,
+    )
+    .await
+    {
+        Ok((photo, _)) if if_none_match.matches(&photo.etag()) => {
+            Ok(ApiResponse::NotModified(photo.etag()))
+        }
+        Ok((photo, data)) => Ok(ApiResponse::Image {
+            content_type: photo.content_type_of(query.size).to_string(),
+            etag: photo.etag(),
+            data,
+        }),
+        Err(e) => Err(map_error(e)),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/addressbooks/{id}/contacts/{contact_id}/photo",
+    tag = "photos",
+    params(
+        ("id" = i32, Path, description = "Address book id"),
+        ("contact_id" = i32, Path, description = "Contact id"),
+        ("X-User" = Option<String>, Header, description = "Caller recorded as the author of the write"),
+    ),
+    request_body(content(
+        (Vec<u8> = "image/jpeg"),
+        (Vec<u8> = "image/png"),
+        (Vec<u8> = "image/webp"),
+    )),
+    responses(
+        (status = 200, description = "The stored photo", body = Photo),
+        (status = 404, description = "No such address book or contact", body = ErrorBody),
+        (status = 413, description = "Photo too large", body = ErrorBody),
+        (status = 415, description = "Not a JPEG, PNG or WebP upload", body = ErrorBody),
+        (status = 422, description = "Not a readable image", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%address_book_id, %contact_id))]
+pub async fn update(
+    Path((address_book_id, contact_id)): Path<(i32, i32)>,
+    State(state): State<AppState>,
+    Actor(actor): Actor,
+    headers: HeaderMap,
+    body: Result<Bytes, BytesRejection>,
+) -> Result<ApiResponse, ApiError> {
+    let Some(content_type) = PHOTO_CONTENT_TYPES
+        .iter()
+        .find(|content_type| has_content_type(&headers, content_type))
+    else {
+        return Err(map_error(Error::UnsupportedMediaType));
+    };
+    let body = match body {
+        Ok(body) => body,
+        Err(e) if e.status() == StatusCode::PAYLOAD_TOO_LARGE => {
+            return Err(map_error(Error::PayloadTooLarge(format!(
+                "photo cannot be larger than {MAX_PHOTO_BYTES} bytes"
+            ))))
+        }
+        Err(e) => return Err(map_error(Error::InvalidBody(e.body_text()))),
+    };
+    let repo = PhotoRepository::new(state.pool);
+
+    match PhotoService::put_photo(
+        repo,
+        state.blob_store,
+        address_book_id,
+        contact_id,
+        content_type.to_string(),
+        body.to_vec(),
+        actor,
+    )
+    .await
+    {
+        Ok(photo) => Ok(ApiResponse::JsonDataPhoto(photo)),
+        Err(e) => Err(map_error(e)),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/addressbooks/{id}/contacts/{contact_id}/photo",
+    tag = "photos",
+    params(
+        ("id" = i32, Path, description = "Address book id"),
+        ("contact_id" = i32, Path, description = "Contact id"),
+        ("X-User" = Option<String>, Header, description = "Caller recorded as the author of the write"),
+    ),
+    responses(
+        (status = 204, description = "The photo was removed"),
+        (status = 404, description = "No such address book, contact or photo", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%address_book_id, %contact_id))]
+pub async fn delete_photo(
+    Path((address_book_id, contact_id)): Path<(i32, i32)>,
+    State(state): State<AppState>,
+    Actor(actor): Actor,
+) -> Result<ApiResponse, ApiError> {
+    let repo = PhotoRepository::new(state.pool);
+
+    match PhotoService::delete_photo(repo, state.blob_store, address_book_id, contact_id, actor)
+        .await
+    {
+        Ok(_) => Ok(ApiResponse::NoContent),
+        Err(e) => Err(map_error(e)),
+    }
+}
@@ -0,0 +1,192 @@
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::HeaderMap,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::net::{IpAddr, SocketAddr};
+
+use crate::services::rate_limit_service::RateLimitService;
+use crate::types::rate_limit::{Client, Decision, RateLimiter, RouteClass};
+use crate::types::ApiError;
+
+/// Header naming the API key a request's quota is counted against.
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Counts each request against its client's bucket for the route class and answers `429`
+/// once the bucket is empty. Every limited response carries the `RateLimit-*` headers.
+pub async fn limit(State(limiter): State<RateLimiter>, request: Request, next: Next) -> Response {
+    let class = RouteClass::of(request.method(), request.uri().path());
+    let Some(limit) = limiter.limits.get(class) else {
+        return next.run(request).await;
+    };
+
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(address)| *address);
+    let decision = match client(&limiter, request.headers(), peer) {
+        Some(client) => {
+            RateLimitService::take(limiter.store.as_ref(), &client.bucket_key(class), limit).await
+        }
+        None => {
+            tracing::warn!("no peer address to count the request against; not limiting it");
+            Decision::unchecked(limit)
+        }
+    };
+
+    let mut response = if decision.allowed {
+        next.run(request).await
+    } else {
+        ApiError::TooManyRequests.into_response()
+    };
+    decision.write_headers(response.headers_mut());
+    response
+}
+
+/// A configured API key, else the client's address, or `None` when the peer is unknown.
+fn client(limiter: &RateLimiter, headers: &HeaderMap, peer: Option<SocketAddr>) -> Option<Client> {
+    let api_key = headers
+        .get(API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|key| limiter.api_keys.contains(*key));
+    if let Some(key) = api_key {
+        return Some(Client::ApiKey(key.to_string()));
+    }
+
+    let address = client_address(headers, peer?.ip(), limiter.trusted_proxy_hops);
+    Some(Client::Address(address.to_string()))
+}
+
+/// The address that connected to the outermost of `hops` trusted proxies. Each proxy appends
+/// the address it was connected from to `X-Forwarded-For`, so that is the entry `hops` places
+/// left of the peer. Entries further left come from the client and are not trusted.
+fn client_address(headers: &HeaderMap, peer: IpAddr, hops: usize) -> IpAddr {
+    if hops == 0 {
+        return peer;
+    }
+    let forwarded: Vec<&str> = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .collect();
+    // A request with fewer entries did not pass through every proxy; all we know is its peer.
+    forwarded
+        .len()
+        .checked_sub(hops)
+        .and_then(|index| forwarded[index].parse().ok())
+        .unwrap_or(peer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::rate_limit_store::MemoryRateLimitStore;
+    use crate::types::rate_limit::{RateLimit, RateLimits};
+    use axum::body::Body;
+    use axum::http::{HeaderValue, StatusCode};
+    use sqlx::PgPool;
+    use std::collections::HashSet;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    fn limiter(trusted_proxy_hops: usize) -> RateLimiter {
+        RateLimiter {
+            store: Arc::new(MemoryRateLimitStore::new()),
+            limits: RateLimits {
+                read: Some(RateLimit { per_minute: 1 }),
+                ..RateLimits::default()
+            },
+            api_keys: Arc::new(HashSet::from([String::from("k1")])),
+            trusted_proxy_hops,
+        }
+    }
+
+    fn forwarded_for(value: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", HeaderValue::from_static(value));
+        headers
+    }
+
+    #[test]
+    fn test_client_prefers_known_api_key() {
+        let limiter = limiter(1);
+        let peer = Some(SocketAddr::from(([10, 0, 0, 1], 443)));
+        let mut headers = forwarded_for("1.2.3.4, 203.0.113.7");
+
+        headers.insert(API_KEY_HEADER, HeaderValue::from_static("made-up"));
+        assert_eq!(
+            client(&limiter, &headers, peer),
+            Some(Client::Address(String::from("203.0.113.7")))
+        );
+
+        headers.insert(API_KEY_HEADER, HeaderValue::from_static("k1"));
+        assert_eq!(
+            client(&limiter, &headers, peer),
+            Some(Client::ApiKey(String::from("k1")))
+        );
+
+        assert_eq!(
+            client(&limiter, &HeaderMap::new(), peer),
+            Some(Client::Address(String::from("10.0.0.1")))
+        );
+        assert_eq!(client(&limiter, &HeaderMap::new(), None), None);
+    }
+
+    #[test]
+    fn test_client_address_believes_only_trusted_proxies() {
+        let peer = IpAddr::from([10, 0, 0, 2]);
+        let headers = forwarded_for("1.2.3.4, 203.0.113.7, 10.0.0.1");
+
+        assert_eq!(client_address(&headers, peer, 0), peer);
+        assert_eq!(
+            client_address(&headers, peer, 1),
+            IpAddr::from([10, 0, 0, 1])
+        );
+        assert_eq!(
+            client_address(&headers, peer, 2),
+            IpAddr::from([203, 0, 113, 7])
+        );
+        // More hops than entries: the request bypassed a proxy.
+        assert_eq!(client_address(&headers, peer, 4), peer);
+        assert_eq!(client_address(&forwarded_for("junk"), peer, 1), peer);
+    }
+
+    /// Whether `app()` lets a read from `peer` with the given `X-Forwarded-For` through.
+    async fn allowed(app: &axum::Router, peer: [u8; 4], forwarded: &'static str) -> bool {
+        let mut request = Request::builder()
+            .uri("/api/openapi.json")
+            .header("x-forwarded-for", forwarded)
+            .body(Body::empty())
+            .unwrap();
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(SocketAddr::from((peer, 40000))));
+        let response = app.clone().oneshot(request).await.unwrap();
+        response.status() != StatusCode::TOO_MANY_REQUESTS
+    }
+
+    #[tokio::test]
+    async fn test_router_ignores_spoofed_forwarded_for() {
+        let pool = PgPool::connect_lazy("postgres://localhost/unused").unwrap();
+
+        // Without trusted proxies, every address a client makes up shares its peer's quota.
+        let mut state = crate::tests::state(pool.clone());
+        state.rate_limiter = limiter(0);
+        let app = crate::app(state);
+        assert!(allowed(&app, [198, 51, 100, 1], "1.1.1.1").await);
+        assert!(!allowed(&app, [198, 51, 100, 1], "2.2.2.2").await);
+        assert!(allowed(&app, [198, 51, 100, 2], "2.2.2.2").await);
+
+        // Behind one proxy, only the entry it appended counts.
+        let mut state = crate::tests::state(pool);
+        state.rate_limiter = limiter(1);
+        let app = crate::app(state);
+        let proxy = [10, 0, 0, 1];
+        assert!(allowed(&app, proxy, "203.0.113.7").await);
+        assert!(!allowed(&app, proxy, "1.1.1.1, 203.0.113.7").await);
+        assert!(allowed(&app, proxy, "203.0.113.7, 203.0.113.8").await);
+    }
+}
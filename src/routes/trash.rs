@@ -0,0 +1,34 @@
+use axum::extract::{Query, State};
+
+use crate::repositories::trash_repo::TrashRepository;
+use crate::services::trash_service::TrashService;
+use crate::types::trash::Trash;
+use crate::types::{ApiError, ApiResponse, AppState, Pagination};
+
+use super::map_error;
+
+#[utoipa::path(
+    get,
+    path = "/api/trash",
+    tag = "trash",
+    params(
+        Pagination,
+    ),
+    responses(
+        (status = 200, description = "Deleted address books and contacts not yet purged", body = Trash),
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn index(
+    State(state): State<AppState>,
+    Query(params): Query<Pagination>,
+) -> Result<ApiResponse, ApiError> {
+    let limit = params.limit.unwrap_or(50);
+    let offset = params.offset.unwrap_or(0);
+    let repo = TrashRepository::new(state.pool);
+
+    match TrashService::get_trash(repo, Some(limit), offset).await {
+        Ok(trash) => Ok(ApiResponse::JsonDataTrash(trash)),
+        Err(e) => Err(map_error(e)),
+    }
+}
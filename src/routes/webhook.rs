@@ -0,0 +1,276 @@
+use axum::extract::{rejection::JsonRejection, Json, Path, Query, State};
+
+use crate::repositories::webhook_repo::WebhookRepository;
+use crate::services::webhook_service::WebhookService;
+use crate::types::precondition::{IfMatch, IfNoneMatch};
+use crate::types::webhook::{NewWebhook, Webhook, WebhookDelivery};
+use crate::types::{Actor, ApiError, ApiResponse, AppState, ErrorBody, Pagination};
+
+use super::map_error;
+use handle_errors::Error;
+
+#[utoipa::path(
+    get,
+    path = "/api/addressbooks/{id}/webhooks",
+    tag = "webhooks",
+    params(
+        ("id" = i32, Path, description = "Address book id"),
+    ),
+    responses(
+        (status = 200, description = "The book's webhooks", body = [Webhook]),
+        (status = 404, description = "No such address book", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%address_book_id))]
+pub async fn index(
+    Path(address_book_id): Path<i32>,
+    State(state): State<AppState>,
+) -> Result<ApiResponse, ApiError> {
+    let repo = WebhookRepository::new(state.pool);
+
+    match WebhookService::get_webhooks(repo, address_book_id).await {
+        Ok(webhooks) => Ok(ApiResponse::JsonDataWebhookCollection(webhooks)),
+        Err(e) => Err(map_error(e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/addressbooks/{id}/webhooks",
+    tag = "webhooks",
+    params(
+        ("id" = i32, Path, description = "Address book id"),
+        ("X-User" = Option<String>, Header, description = "Caller recorded as the author of the write"),
+    ),
+    request_body = NewWebhook,
+    responses(
+        (status = 200, description = "The created webhook", body = Webhook),
+        (status = 400, description = "Malformed body", body = ErrorBody),
+        (status = 404, description = "No such address book", body = ErrorBody),
+        (status = 422, description = "Invalid URL, secret or event types, or a target that is not public", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%address_book_id))]
+pub async fn create_webhook(
+    Path(address_book_id): Path<i32>,
+    State(state): State<AppState>,
+    Actor(actor): Actor,
+    payload: Result<Json<NewWebhook>, JsonRejection>,
+) -> Result<ApiResponse, ApiError> {
+    match payload {
+        Ok(payload) => {
+            let repo = WebhookRepository::new(state.pool);
+
+            match WebhookService::create_webhook(
+                repo,
+                &state.webhook_sender,
+                address_book_id,
+                payload.0,
+                actor,
+            )
+            .await
+            {
+                Ok(webhook) => Ok(ApiResponse::JsonDataWebhook(webhook)),
+                Err(e) => Err(map_error(e)),
+            }
+        }
+        Err(e) => Err(map_error(Error::JsonDeserilizationError(e))),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/addressbooks/{id}/webhooks/{webhook_id}",
+    tag = "webhooks",
+    params(
+        ("id" = i32, Path, description = "Address book id"),
+        ("webhook_id" = i32, Path, description = "Webhook id"),
+        ("If-None-Match" = Option<String>, Header, description = "Entity tags already held"),
+    ),
+    responses(
+        (status = 200, description = "The webhook", body = Webhook),
+        (status = 304, description = "The webhook still has one of the given entity tags"),
+        (status = 404, description = "No such address book or webhook", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%address_book_id, %webhook_id))]
+pub async fn show(
+    Path((address_book_id, webhook_id)): Path<(i32, i32)>,
+    State(state): State<AppState>,
+    if_none_match: IfNoneMatch,
+) -> Result<ApiResponse, ApiError> {
+    let repo = WebhookRepository::new(state.pool);
+
+    match WebhookService::get_webhook(repo, address_book_id, webhook_id).await {
+        Ok(webhook) if if_none_match.matches(&webhook.etag()) => {
+            Ok(ApiResponse::NotModified(webhook.etag()))
+        }
+        Ok(webhook) => Ok(ApiResponse::JsonDataWebhook(webhook)),
+        Err(e) => Err(map_error(e)),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/addressbooks/{id}/webhooks/{webhook_id}",
+    tag = "webhooks",
+    params(
+        ("id" = i32, Path, description = "Address book id"),
+        ("webhook_id" = i32, Path, description = "Webhook id"),
+        ("If-Match" = Option<String>, Header, description = "Entity tag the webhook must still have"),
+        ("X-User" = Option<String>, Header, description = "Caller recorded as the author of the write"),
+    ),
+    request_body = NewWebhook,
+    responses(
+        (status = 200, description = "The updated webhook", body = Webhook),
+        (status = 400, description = "Malformed body", body = ErrorBody),
+        (status = 404, description = "No such address book or webhook", body = ErrorBody),
+        (status = 412, description = "The webhook has changed", body = ErrorBody),
+        (status = 422, description = "Invalid URL, secret or event types, or a target that is not public", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%address_book_id, %webhook_id))]
+pub async fn update(
+    Path((address_book_id, webhook_id)): Path<(i32, i32)>,
+    State(state): State<AppState>,
+    Actor(actor): Actor,
+    if_match: IfMatch,
+    payload: Result<Json<NewWebhook>, JsonRejection>,
+) -> Result<ApiResponse, ApiError> {
+    match payload {
+        Ok(payload) => {
+            let repo = WebhookRepository::new(state.pool);
+
+            match WebhookService::update_webhook(
+                repo,
+                &state.webhook_sender,
+                address_book_id,
+                webhook_id,
+                payload.0,
+                actor,
+                if_match,
+            )
+            .await
+            {
+                Ok(webhook) => Ok(ApiResponse::JsonDataWebhook(webhook)),
+                Err(e) => Err(map_error(e)),
+            }
+        }
+        Err(e) => Err(map_error(Error::JsonDeserilizationError(e))),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/addressbooks/{id}/webhooks/{webhook_id}",
+    tag = "webhooks",
+    params(
+        ("id" = i32, Path, description = "Address book id"),
+        ("webhook_id" = i32, Path, description = "Webhook id"),
+        ("If-Match" = Option<String>, Header, description = "Entity tag the webhook must still have"),
+    ),
+    responses(
+        (status = 204, description = "The webhook and its deliveries were removed"),
+        (status = 404, description = "No such address book or webhook", body = ErrorBody),
+        (status = 412, description = "The webhook has changed", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%address_book_id, %webhook_id))]
+pub async fn delete_webhook(
+    Path((address_book_id, webhook_id)): Path<(i32, i32)>,
+    State(state): State<AppState>,
+    if_match: IfMatch,
+) -> Result<ApiResponse, ApiError> {
+    let repo = WebhookRepository::new(state.pool);
+
+    match WebhookService::delete_webhook(repo, address_book_id, webhook_id, if_match).await {
+        Ok(_) => Ok(ApiResponse::NoContent),
+        Err(e) => Err(map_error(e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/addressbooks/{id}/webhooks/{webhook_id}/deliveries",
+    tag = "webhooks",
+    params(
+        ("id" = i32, Path, description = "Address book id"),
+        ("webhook_id" = i32, Path, description = "Webhook id"),
+        Pagination,
+    ),
+    responses(
+        (status = 200, description = "The webhook's deliveries, newest first", body = [WebhookDelivery]),
+        (status = 404, description = "No such address book or webhook", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%address_book_id, %webhook_id))]
+pub async fn deliveries(
+    Path((address_book_id, webhook_id)): Path<(i32, i32)>,
+    State(state): State<AppState>,
+    Query(params): Query<Pagination>,
+) -> Result<ApiResponse, ApiError> {
+    let limit = params.limit.unwrap_or(50);
+    let offset = params.offset.unwrap_or(0);
+    let repo = WebhookRepository::new(state.pool);
+
+    match WebhookService::get_deliveries(repo, address_book_id, webhook_id, Some(limit), offset)
+        .await
+    {
+        Ok(deliveries) => Ok(ApiResponse::JsonDataWebhookDeliveryCollection(deliveries)),
+        Err(e) => Err(map_error(e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/addressbooks/{id}/webhooks/{webhook_id}/deliveries/{delivery_id}",
+    tag = "webhooks",
+    params(
+        ("id" = i32, Path, description = "Address book id"),
+        ("webhook_id" = i32, Path, description = "Webhook id"),
+        ("delivery_id" = i64, Path, description = "Delivery id"),
+    ),
+    responses(
+        (status = 200, description = "The delivery and its attempts", body = WebhookDelivery),
+        (status = 404, description = "No such address book, webhook or delivery", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%address_book_id, %webhook_id, %delivery_id))]
+pub async fn delivery(
+    Path((address_book_id, webhook_id, delivery_id)): Path<(i32, i32, i64)>,
+    State(state): State<AppState>,
+) -> Result<ApiResponse, ApiError> {
+    let repo = WebhookRepository::new(state.pool);
+
+    match WebhookService::get_delivery(repo, address_book_id, webhook_id, delivery_id).await {
+        Ok(delivery) => Ok(ApiResponse::JsonDataWebhookDelivery(delivery)),
+        Err(e) => Err(map_error(e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/addressbooks/{id}/webhooks/{webhook_id}/deliveries/{delivery_id}/redeliver",
+    tag = "webhooks",
+    params(
+        ("id" = i32, Path, description = "Address book id"),
+        ("webhook_id" = i32, Path, description = "Webhook id"),
+        ("delivery_id" = i64, Path, description = "Delivery id"),
+    ),
+    responses(
+        (status = 200, description = "The delivery, queued to be sent again", body = WebhookDelivery),
+        (status = 404, description = "No such address book, webhook or delivery", body = ErrorBody),
+    )
+)]
+#[tracing::instrument(skip_all, fields(%address_book_id, %webhook_id, %delivery_id))]
+pub async fn redeliver(
+    Path((address_book_id, webhook_id, delivery_id)): Path<(i32, i32, i64)>,
+    State(state): State<AppState>,
+) -> Result<ApiResponse, ApiError> {
+    let repo = WebhookRepository::new(state.pool);
+
+    match WebhookService::redeliver(repo, address_book_id, webhook_id, delivery_id).await {
+        Ok(delivery) => Ok(ApiResponse::JsonDataWebhookDelivery(delivery)),
+        Err(e) => Err(map_error(e)),
+    }
+}
@@ -0,0 +1,98 @@
+use axum::Router;
+use shuttle_runtime::{CustomError, Error};
+use std::net::SocketAddr;
+
+use crate::telemetry::Telemetry;
+
+/// Serves the router with each connection's peer address attached as `ConnectInfo`, which
+/// the rate limiter counts requests against. Shuttle's own axum service leaves it out.
+pub struct Server {
+    pub router: Router,
+    /// Flushes the spans still buffered once the server stops, whether it shuts down on a
+    /// signal or Shuttle aborts it.
+    pub telemetry: Telemetry,
+}
+
+#[shuttle_runtime::async_trait]
+impl shuttle_runtime::Service for Server {
+    async fn bind(self, addr: SocketAddr) -> Result<(), Error> {
+        let listener = tokio::net::TcpListener::bind(addr)
+            .await
+            .map_err(CustomError::new)?;
+        axum::serve(
+            listener,
+            self.router
+                .into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(shutdown_signal())
+        .await
+        .map_err(CustomError::new)?;
+        drop(self.telemetry);
+        Ok(())
+    }
+}
+
+/// Resolves on Ctrl-C or, on Unix, `SIGTERM`.
+async fn shutdown_signal() {
+    let interrupt = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(_) => std::future::pending().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = interrupt => {},
+        _ = terminate => {},
+    }
+    tracing::info!("shutting down");
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::rate_limit::{RateLimit, RateLimits};
+    use shuttle_runtime::Service;
+    use sqlx::PgPool;
+
+    #[tokio::test]
+    async fn test_peer_address_reaches_the_rate_limiter() {
+        let mut state =
+            crate::tests::state(PgPool::connect_lazy("postgres://localhost/unused").unwrap());
+        state.rate_limiter.limits = RateLimits {
+            read: Some(RateLimit { per_minute: 1 }),
+            ..RateLimits::default()
+        };
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let server = Server {
+            router: crate::app(state),
+            telemetry: Telemetry::default(),
+        };
+        tokio::spawn(server.bind(addr));
+
+        let url = format!("http://{addr}/api/openapi.json");
+        let client = reqwest::Client::new();
+        let mut statuses = Vec::new();
+        for _ in 0..50 {
+            match client.get(&url).send().await {
+                Ok(response) => statuses.push(response.status().as_u16()),
+                // Still starting up.
+                Err(_) => tokio::time::sleep(std::time::Duration::from_millis(20)).await,
+            }
+            if statuses.len() == 2 {
+                break;
+            }
+        }
+        assert_eq!(statuses, [200, 429]);
+    }
+}
@@ -1,22 +1,41 @@
 use crate::repositories::address_book_repo::IAddressBookRepository;
-use crate::types::address_book::{AddressBook, NewAddressBook};
+use crate::types::address_book::{AddressBook, AddressBookView, NewAddressBook};
+use crate::types::precondition::IfMatch;
+use crate::types::stats::AddressBookStats;
+use crate::types::ListFilter;
 pub struct AddressBookService {}
 
 impl AddressBookService {
+    #[tracing::instrument(skip_all)]
     pub async fn get_all_address_books<T: IAddressBookRepository>(
         repo: T,
         limit: Option<i32>,
         offset: i32,
+        filter: ListFilter,
+        view: AddressBookView,
     ) -> Result<Vec<AddressBook>, handle_errors::Error> {
-        repo.get_all_address_books(limit, offset).await
+        repo.get_all_address_books(limit, offset, filter, view).await
     }
 
+    #[tracing::instrument(skip_all, fields(address_book_id = %id))]
     pub async fn get_address_book_by_id<T: IAddressBookRepository>(
         repo: T,
         id: i32,
+        view: AddressBookView,
     ) -> Result<AddressBook, handle_errors::Error> {
-        repo.get_address_book_by_id(id).await
+        repo.get_address_book_by_id(id, view).await
     }
+
+    #[tracing::instrument(skip_all)]
+    pub async fn get_address_books_by_ids<T: IAddressBookRepository>(
+        repo: T,
+        ids: Vec<i32>,
+        view: AddressBookView,
+    ) -> Result<Vec<AddressBook>, handle_errors::Error> {
+        repo.get_address_books_by_ids(ids, view).await
+    }
+
+    #[tracing::instrument(skip_all)]
     pub async fn get_address_book_by_name<T: IAddressBookRepository>(
         repo: T,
         address_book_name: String,
@@ -24,29 +43,54 @@ impl AddressBookService {
         repo.find_address_book_by_name(address_book_name).await
     }
 
+    #[tracing::instrument(skip_all)]
     pub async fn add_address_book<T: IAddressBookRepository>(
         repo: T,
         address_book: NewAddressBook,
+        actor: Option<String>,
     ) -> Result<AddressBook, handle_errors::Error> {
-        repo.create_address_book(address_book.address_book_name)
+        repo.create_address_book(address_book.address_book_name, actor)
             .await
     }
 
+    #[tracing::instrument(skip_all, fields(address_book_id = %id))]
     pub async fn delete_address_book<T: IAddressBookRepository>(
         repo: T,
         id: i32,
+        actor: Option<String>,
+        if_match: IfMatch,
     ) -> Result<(), handle_errors::Error> {
-        repo.delete_address_book(id).await
+        repo.delete_address_book(id, actor, if_match).await
+    }
+
+    #[tracing::instrument(skip_all, fields(address_book_id = %id))]
+    pub async fn restore_address_book<T: IAddressBookRepository>(
+        repo: T,
+        id: i32,
+        actor: Option<String>,
+    ) -> Result<AddressBook, handle_errors::Error> {
+        repo.restore_address_book(id, actor).await
     }
 
+    #[tracing::instrument(skip_all, fields(address_book_id = %id))]
     pub async fn update_address_book<T: IAddressBookRepository>(
         repo: T,
         id: i32,
         address_book: NewAddressBook,
+        actor: Option<String>,
+        if_match: IfMatch,
     ) -> Result<AddressBook, handle_errors::Error> {
-        repo.update_address_book(id, &address_book.address_book_name)
+        repo.update_address_book(id, &address_book.address_book_name, actor, if_match)
             .await
     }
+
+    #[tracing::instrument(skip_all, fields(address_book_id = %id))]
+    pub async fn get_address_book_stats<T: IAddressBookRepository>(
+        repo: T,
+        id: i32,
+    ) -> Result<AddressBookStats, handle_errors::Error> {
+        repo.get_address_book_stats(id).await
+    }
 }
 
 #[cfg(test)]
@@ -54,6 +98,7 @@ mod tests {
     use super::*;
     use crate::repositories::address_book_repo::MockIAddressBookRepository;
     use crate::types::address_book::{AddressBook, AddressBookId};
+    use chrono::Utc;
     use mockall::predicate::eq;
 
     fn create_repo() -> MockIAddressBookRepository {
@@ -64,7 +109,14 @@ mod tests {
         AddressBook {
             id: AddressBookId(1),
             address_book_name: String::from("address_book_1"),
-            contacts: vec![],
+            contacts: None,
+            contact_count: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            created_by: None,
+            updated_by: None,
+            deleted_at: None,
+            version: 1,
         }
 
     }
@@ -77,25 +129,46 @@ mod tests {
             AddressBook {
                 id: AddressBookId(1),
                 address_book_name: String::from("address_book_1"),
-                contacts: vec![],
+                contacts: None,
+                contact_count: None,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+                created_by: None,
+                updated_by: None,
+                deleted_at: None,
+                version: 1,
             },
             AddressBook {
                 id: AddressBookId(2),
                 address_book_name: String::from("address_book_2"),
-                contacts: vec![],
+                contacts: None,
+                contact_count: None,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+                created_by: None,
+                updated_by: None,
+                deleted_at: None,
+                version: 1,
             },
         ];
         let limit = Some(2);
         let offset = 0;
+        let filter = ListFilter::default();
+        let view = AddressBookView {
+            contacts: false,
+            contact_count: true,
+            ..AddressBookView::default()
+        };
         repo.expect_get_all_address_books()
-            .with(eq(limit), eq(offset))
+            .with(eq(limit), eq(offset), eq(filter.clone()), eq(view.clone()))
             .once()
-            .returning(move |_, _| {
+            .returning(move |_, _, _, _| {
                 let address_books = address_books.clone();
                 Box::pin(async move { Ok(address_books) })
             });
 
-        let result = AddressBookService::get_all_address_books(repo, limit, offset).await;
+        let result =
+            AddressBookService::get_all_address_books(repo, limit, offset, filter, view).await;
         assert!(result.is_ok());
     }
 
@@ -106,15 +179,16 @@ mod tests {
         let id = 1;
 
         repo.expect_get_address_book_by_id()
-            .with(eq(id))
+            .with(eq(id), eq(AddressBookView::default()))
             .once()
-            .returning(move |_| {
+            .returning(move |_, _| {
                 let address_book = address_book.clone();
                 Box::pin(async move { Ok(address_book) })
                 
             });
 
-        let result = AddressBookService::get_address_book_by_id(repo, id).await;
+        let result =
+            AddressBookService::get_address_book_by_id(repo, id, AddressBookView::default()).await;
         assert!(result.is_ok());
 
     }
@@ -144,17 +218,47 @@ mod tests {
         let address_book = create_address_book();
         let mut repo = create_repo();
 
+        let actor = Some(String::from("alice"));
+
         repo.expect_create_address_book()
-           .with(eq(new_address_book.address_book_name.clone()))
+           .with(eq(new_address_book.address_book_name.clone()), eq(actor.clone()))
            .once()
-           .returning(move |_| {
+           .returning(move |_, _| {
                 let address_book = address_book.clone();
                 Box::pin(async move { Ok(address_book) })
             });
 
-            let result = AddressBookService::add_address_book(repo, new_address_book).await;
+            let result = AddressBookService::add_address_book(repo, new_address_book, actor).await;
             assert!(result.is_ok());
 
     }
 
+    #[tokio::test]
+    async fn test_get_address_book_stats() {
+        let mut repo = create_repo();
+        let stats = AddressBookStats {
+            address_book_id: AddressBookId(1),
+            contact_count: 3,
+            with_email: 2,
+            without_email: 1,
+            with_phone_number: 0,
+            without_phone_number: 3,
+            duplicates: 1,
+            last_updated_at: Utc::now(),
+            by_country: vec![],
+            by_group: vec![],
+        };
+        let expected = stats.clone();
+
+        repo.expect_get_address_book_stats()
+            .with(eq(1))
+            .once()
+            .returning(move |_| {
+                let stats = stats.clone();
+                Box::pin(async move { Ok(stats) })
+            });
+
+        let result = AddressBookService::get_address_book_stats(repo, 1).await;
+        assert_eq!(result.unwrap(), expected);
+    }
 }
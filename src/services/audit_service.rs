@@ -0,0 +1,48 @@
+use crate::repositories::audit_repo::IAuditRepository;
+use crate::types::audit::AuditEvent;
+pub struct AuditService {}
+
+impl AuditService {
+    #[tracing::instrument(skip_all, fields(%address_book_id))]
+    pub async fn get_address_book_history<T: IAuditRepository>(
+        repo: T,
+        address_book_id: i32,
+        limit: Option<i32>,
+        offset: i32,
+    ) -> Result<Vec<AuditEvent>, handle_errors::Error> {
+        repo.get_address_book_history(address_book_id, limit, offset)
+            .await
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, %contact_id))]
+    pub async fn get_contact_history<T: IAuditRepository>(
+        repo: T,
+        address_book_id: i32,
+        contact_id: i32,
+        limit: Option<i32>,
+        offset: i32,
+    ) -> Result<Vec<AuditEvent>, handle_errors::Error> {
+        repo.get_contact_history(address_book_id, contact_id, limit, offset)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::audit_repo::MockIAuditRepository;
+    use mockall::predicate::eq;
+
+    #[tokio::test]
+    async fn test_get_contact_history() {
+        let mut repo = MockIAuditRepository::new();
+
+        repo.expect_get_contact_history()
+            .with(eq(1), eq(7), eq(Some(20)), eq(0))
+            .once()
+            .returning(|_, _, _, _| Box::pin(async { Ok(vec![]) }));
+
+        let result = AuditService::get_contact_history(repo, 1, 7, Some(20), 0).await;
+        assert!(result.is_ok());
+    }
+}
@@ -1 +1,607 @@
+use crate::repositories::contact_repo::IContactRepository;
+use crate::types::bulk::{BulkItemResult, BulkOperation, MAX_BULK_OPERATIONS};
+use crate::types::changes::{ChangesQuery, ContactChanges};
+use crate::types::contact::{
+    BulkMoveRequest, Contact, ContactFilter, ContactPatch, NewContact, TransferRequest,
+};
+use crate::types::precondition::IfMatch;
+use crate::types::ListFilter;
+use chrono::{Duration, Utc};
+use std::collections::{HashMap, HashSet};
+pub struct ContactService {}
 
+impl ContactService {
+    #[tracing::instrument(skip_all, fields(%address_book_id))]
+    pub async fn get_address_book_contacts<T: IContactRepository>(
+        repo: T,
+        address_book_id: i32,
+        limit: Option<i32>,
+        offset: i32,
+        filter: ListFilter,
+        contact_filter: ContactFilter,
+    ) -> Result<Vec<Contact>, handle_errors::Error> {
+        repo.get_address_book_contacts(address_book_id, limit, offset, filter, contact_filter)
+            .await
+    }
+
+    /// The same page of contacts from each of several books, grouped by book id.
+    #[tracing::instrument(skip_all)]
+    pub async fn get_contacts_of_address_books<T: IContactRepository>(
+        repo: T,
+        address_book_ids: Vec<i32>,
+        limit: Option<i32>,
+        offset: i32,
+        filter: ListFilter,
+        contact_filter: ContactFilter,
+        search: Option<String>,
+    ) -> Result<HashMap<i32, Vec<Contact>>, handle_errors::Error> {
+        let contacts = repo
+            .get_contacts_of_address_books(
+                address_book_ids,
+                limit,
+                offset,
+                filter,
+                contact_filter,
+                search,
+            )
+            .await?;
+
+        let mut by_book: HashMap<i32, Vec<Contact>> = HashMap::new();
+        for contact in contacts {
+            by_book
+                .entry(contact.address_book_id.0)
+                .or_default()
+                .push(contact);
+        }
+        Ok(by_book)
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, contact_id = %id))]
+    pub async fn get_contact<T: IContactRepository>(
+        repo: T,
+        id: i32,
+        address_book_id: i32,
+    ) -> Result<Contact, handle_errors::Error> {
+        match repo.get_contact_by_id(id, address_book_id).await? {
+            Some(contact) => Ok(contact),
+            None => Err(handle_errors::Error::ContactNotFound),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id))]
+    pub async fn add_contact<T: IContactRepository>(
+        repo: T,
+        address_book_id: i32,
+        contact: NewContact,
+        actor: Option<String>,
+    ) -> Result<Contact, handle_errors::Error> {
+        repo.add_contact_to_address_book(address_book_id, contact, actor)
+            .await
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, contact_id = %id))]
+    pub async fn update_contact<T: IContactRepository>(
+        repo: T,
+        id: i32,
+        address_book_id: i32,
+        contact: NewContact,
+        actor: Option<String>,
+        if_match: IfMatch,
+    ) -> Result<Contact, handle_errors::Error> {
+        repo.update_contact(id, address_book_id, contact, actor, if_match)
+            .await
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, contact_id = %id))]
+    pub async fn patch_contact<T: IContactRepository>(
+        repo: T,
+        id: i32,
+        address_book_id: i32,
+        patch: ContactPatch,
+        actor: Option<String>,
+        if_match: IfMatch,
+    ) -> Result<Contact, handle_errors::Error> {
+        if patch.name == Some(None) {
+            return Err(handle_errors::Error::ValidationError(String::from(
+                "name cannot be null",
+            )));
+        }
+        if patch.address == Some(None) {
+            return Err(handle_errors::Error::ValidationError(String::from(
+                "address cannot be null",
+            )));
+        }
+
+        repo.patch_contact(id, address_book_id, patch, actor, if_match)
+            .await
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, contact_id = %id))]
+    pub async fn delete_contact<T: IContactRepository>(
+        repo: T,
+        id: i32,
+        address_book_id: i32,
+        actor: Option<String>,
+        if_match: IfMatch,
+    ) -> Result<(), handle_errors::Error> {
+        repo.delete_contact(id, address_book_id, actor, if_match)
+            .await
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, contact_id = %id))]
+    pub async fn restore_contact<T: IContactRepository>(
+        repo: T,
+        id: i32,
+        address_book_id: i32,
+        actor: Option<String>,
+    ) -> Result<Contact, handle_errors::Error> {
+        repo.restore_contact(id, address_book_id, actor).await
+    }
+
+    #[tracing::instrument(skip_all, fields(contact_id = %id))]
+    pub async fn move_contact<T: IContactRepository>(
+        repo: T,
+        id: i32,
+        request: TransferRequest,
+        actor: Option<String>,
+        if_match: IfMatch,
+    ) -> Result<Contact, handle_errors::Error> {
+        let mut contacts = repo
+            .move_contacts(
+                vec![id],
+                request.target_address_book_id,
+                request.on_conflict,
+                actor,
+                if_match,
+            )
+            .await?;
+        contacts.pop().ok_or(handle_errors::Error::ContactNotFound)
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub async fn move_contacts<T: IContactRepository>(
+        repo: T,
+        mut request: BulkMoveRequest,
+        actor: Option<String>,
+    ) -> Result<Vec<Contact>, handle_errors::Error> {
+        // Each contact is moved and returned once, however often it is listed.
+        let mut seen = HashSet::new();
+        request.contact_ids.retain(|id| seen.insert(*id));
+        if request.contact_ids.is_empty() {
+            return Err(handle_errors::Error::ValidationError(String::from(
+                "contact_ids cannot be empty",
+            )));
+        }
+        if request.contact_ids.len() > MAX_BULK_OPERATIONS {
+            return Err(handle_errors::Error::ValidationError(format!(
+                "at most {MAX_BULK_OPERATIONS} contacts can be moved at once"
+            )));
+        }
+
+        repo.move_contacts(
+            request.contact_ids,
+            request.target_address_book_id,
+            request.on_conflict,
+            actor,
+            IfMatch::default(),
+        )
+        .await
+    }
+
+    #[tracing::instrument(skip_all, fields(contact_id = %id))]
+    pub async fn copy_contact<T: IContactRepository>(
+        repo: T,
+        id: i32,
+        request: TransferRequest,
+        actor: Option<String>,
+    ) -> Result<Contact, handle_errors::Error> {
+        repo.copy_contact(
+            id,
+            request.target_address_book_id,
+            request.on_conflict,
+            actor,
+        )
+        .await
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id))]
+    pub async fn bulk_contacts<T: IContactRepository>(
+        repo: T,
+        address_book_id: i32,
+        operations: Vec<BulkOperation>,
+        actor: Option<String>,
+        atomic: bool,
+    ) -> Result<Vec<BulkItemResult>, handle_errors::Error> {
+        if operations.is_empty() {
+            return Err(handle_errors::Error::ValidationError(String::from(
+                "operations cannot be empty",
+            )));
+        }
+        if operations.len() > MAX_BULK_OPERATIONS {
+            return Err(handle_errors::Error::ValidationError(format!(
+                "at most {MAX_BULK_OPERATIONS} operations are allowed"
+            )));
+        }
+
+        repo.bulk_contacts(address_book_id, operations, actor, atomic)
+            .await
+    }
+
+    /// Contacts created, updated and deleted in a book since `query.since`, or the whole book
+    /// flagged for a full resync when there is no token or it has expired.
+    #[tracing::instrument(skip_all, fields(%address_book_id))]
+    pub async fn get_changes<T: IContactRepository>(
+        repo: T,
+        address_book_id: i32,
+        query: ChangesQuery,
+    ) -> Result<ContactChanges, handle_errors::Error> {
+        let since = query.position()?;
+        let log = repo.get_contact_changes(address_book_id, since).await?;
+        Ok(ContactChanges::from_log(address_book_id, since, log))
+    }
+
+    /// Prunes changes older than `retention`; tokens from before then get a full resync.
+    #[tracing::instrument(skip_all)]
+    pub async fn prune_changes<T: IContactRepository>(
+        repo: T,
+        retention: Duration,
+    ) -> Result<u64, handle_errors::Error> {
+        repo.prune_contact_changes(Utc::now() - retention).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::contact_repo::MockIContactRepository;
+    use crate::types::address_book::AddressBookId;
+    use crate::types::changes::ChangeLog;
+    use crate::types::contact::{ContactDetails, ContactId, OnConflict};
+    use crate::types::precondition::EntityTags;
+    use crate::types::SortKey;
+    use chrono::Utc;
+    use mockall::predicate::eq;
+    use serde_json::Map;
+
+    fn create_repo() -> MockIContactRepository {
+        MockIContactRepository::new()
+    }
+
+    fn create_contact() -> Contact {
+        Contact {
+            id: ContactId(1),
+            name: String::from("contact_1"),
+            address: String::from("1 Main Street"),
+            phone_number: None,
+            email: Some(String::from("contact_1@example.com")),
+            details: ContactDetails::default(),
+            display_name: String::from("contact_1"),
+            sort_key: String::from("contact_1"),
+            address_book_id: AddressBookId(1),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            created_by: None,
+            updated_by: None,
+            deleted_at: None,
+            version: 1,
+            groups: vec![],
+            has_photo: false,
+            custom_fields: Map::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_address_book_contacts() {
+        let mut repo = create_repo();
+        let contacts = vec![create_contact()];
+        let filter = ListFilter {
+            updated_since: Some(Utc::now()),
+            sort: vec![SortKey {
+                field: String::from("updated_at"),
+                descending: true,
+            }],
+            locale: None,
+        };
+        let contact_filter = ContactFilter {
+            group: Some(String::from("Family")),
+            ..ContactFilter::default()
+        };
+
+        repo.expect_get_address_book_contacts()
+            .with(
+                eq(1),
+                eq(Some(10)),
+                eq(0),
+                eq(filter.clone()),
+                eq(contact_filter.clone()),
+            )
+            .once()
+            .returning(move |_, _, _, _, _| {
+                let contacts = contacts.clone();
+                Box::pin(async move { Ok(contacts) })
+            });
+
+        let result =
+            ContactService::get_address_book_contacts(repo, 1, Some(10), 0, filter, contact_filter)
+                .await;
+        assert_eq!(result.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_get_contacts_of_address_books_groups_by_book() {
+        let mut repo = create_repo();
+        let mut other = create_contact();
+        other.id = ContactId(2);
+        other.address_book_id = AddressBookId(2);
+        let contacts = vec![create_contact(), other];
+
+        repo.expect_get_contacts_of_address_books()
+            .with(
+                eq(vec![1, 2, 3]),
+                eq(Some(5)),
+                eq(0),
+                eq(ListFilter::default()),
+                eq(ContactFilter::default()),
+                eq(Some(String::from("smith"))),
+            )
+            .once()
+            .returning(move |_, _, _, _, _, _| {
+                let contacts = contacts.clone();
+                Box::pin(async move { Ok(contacts) })
+            });
+
+        let by_book = ContactService::get_contacts_of_address_books(
+            repo,
+            vec![1, 2, 3],
+            Some(5),
+            0,
+            ListFilter::default(),
+            ContactFilter::default(),
+            Some(String::from("smith")),
+        )
+        .await
+        .unwrap();
+        assert_eq!(by_book[&1].len(), 1);
+        assert_eq!(by_book[&2][0].id, ContactId(2));
+        assert!(!by_book.contains_key(&3));
+    }
+
+    #[tokio::test]
+    async fn test_get_contact_not_found() {
+        let mut repo = create_repo();
+
+        repo.expect_get_contact_by_id()
+            .with(eq(2), eq(1))
+            .once()
+            .returning(|_, _| Box::pin(async { Ok(None) }));
+
+        let result = ContactService::get_contact(repo, 2, 1).await;
+        assert!(matches!(result, Err(handle_errors::Error::ContactNotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_update_contact_passes_precondition() {
+        let mut repo = create_repo();
+        let contact = create_contact();
+        let if_match = IfMatch(EntityTags::Tags(vec![String::from("\"1\"")]));
+
+        repo.expect_update_contact()
+            .withf(|id, address_book_id, contact, _, if_match| {
+                *id == 1
+                    && *address_book_id == 1
+                    && contact.name == "contact_1"
+                    && if_match.matches("\"1\"")
+            })
+            .once()
+            .returning(move |_, _, _, _, _| {
+                let contact = contact.clone();
+                Box::pin(async move { Ok(contact) })
+            });
+
+        let new_contact = NewContact {
+            name: String::from("contact_1"),
+            address: String::from("1 Main Street"),
+            phone_number: None,
+            email: None,
+            details: ContactDetails::default(),
+            custom_fields: Map::new(),
+        };
+        let result = ContactService::update_contact(repo, 1, 1, new_contact, None, if_match).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_patch_contact_rejects_null_name() {
+        let mut repo = create_repo();
+        repo.expect_patch_contact().never();
+
+        let patch = ContactPatch {
+            name: Some(None),
+            ..ContactPatch::default()
+        };
+        let result =
+            ContactService::patch_contact(repo, 1, 1, patch, None, IfMatch::default()).await;
+        assert!(matches!(
+            result,
+            Err(handle_errors::Error::ValidationError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_patch_contact_clears_nullable_field() {
+        let mut repo = create_repo();
+        let contact = create_contact();
+        let patch = ContactPatch {
+            email: Some(None),
+            ..ContactPatch::default()
+        };
+
+        repo.expect_patch_contact()
+            .with(
+                eq(1),
+                eq(1),
+                eq(patch.clone()),
+                eq(None),
+                eq(IfMatch::default()),
+            )
+            .once()
+            .returning(move |_, _, _, _, _| {
+                let contact = contact.clone();
+                Box::pin(async move { Ok(contact) })
+            });
+
+        let result =
+            ContactService::patch_contact(repo, 1, 1, patch, None, IfMatch::default()).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_move_contact_passes_options() {
+        let mut repo = create_repo();
+        let mut contact = create_contact();
+        contact.address_book_id = AddressBookId(2);
+
+        repo.expect_move_contacts()
+            .with(
+                eq(vec![1]),
+                eq(2),
+                eq(OnConflict::Rename),
+                eq(Some(String::from("alice"))),
+                eq(IfMatch::default()),
+            )
+            .once()
+            .returning(move |_, _, _, _, _| {
+                let contact = contact.clone();
+                Box::pin(async move { Ok(vec![contact]) })
+            });
+
+        let request = TransferRequest {
+            target_address_book_id: 2,
+            on_conflict: OnConflict::Rename,
+        };
+        let result = ContactService::move_contact(
+            repo,
+            1,
+            request,
+            Some(String::from("alice")),
+            IfMatch::default(),
+        )
+        .await;
+        assert_eq!(result.unwrap().address_book_id, AddressBookId(2));
+    }
+
+    #[tokio::test]
+    async fn test_move_contacts_rejects_empty_list() {
+        let mut repo = create_repo();
+        repo.expect_move_contacts().never();
+
+        let request = BulkMoveRequest {
+            contact_ids: vec![],
+            target_address_book_id: 2,
+            on_conflict: OnConflict::Fail,
+        };
+        let result = ContactService::move_contacts(repo, request, None).await;
+        assert!(matches!(
+            result,
+            Err(handle_errors::Error::ValidationError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_move_contacts_moves_listed_contacts_once() {
+        let mut repo = create_repo();
+        repo.expect_move_contacts()
+            .withf(|ids, target, _, _, _| ids == &vec![3, 1, 2] && *target == 2)
+            .once()
+            .returning(|_, _, _, _, _| Box::pin(async { Ok(vec![]) }));
+
+        let request = BulkMoveRequest {
+            contact_ids: vec![3, 1, 3, 2, 1],
+            target_address_book_id: 2,
+            on_conflict: OnConflict::Fail,
+        };
+        let result = ContactService::move_contacts(repo, request, None).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_bulk_contacts_rejects_oversized_batch() {
+        let mut repo = create_repo();
+        repo.expect_bulk_contacts().never();
+
+        let operation = BulkOperation::Delete {
+            id: 1,
+            if_match: None,
+        };
+        let operations = vec![operation; MAX_BULK_OPERATIONS + 1];
+        let result = ContactService::bulk_contacts(repo, 1, operations, None, true).await;
+        assert!(matches!(
+            result,
+            Err(handle_errors::Error::ValidationError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_bulk_contacts_passes_atomic_flag() {
+        let mut repo = create_repo();
+        let operations = vec![BulkOperation::Delete {
+            id: 1,
+            if_match: None,
+        }];
+
+        repo.expect_bulk_contacts()
+            .with(eq(1), eq(operations.clone()), eq(None), eq(false))
+            .once()
+            .returning(|_, _, _, _| {
+                Box::pin(async {
+                    Ok(vec![BulkItemResult::failed(
+                        0,
+                        &handle_errors::Error::ContactNotFound,
+                    )])
+                })
+            });
+
+        let result = ContactService::bulk_contacts(repo, 1, operations, None, false).await;
+        assert!(!result.unwrap()[0].is_success());
+    }
+    #[tokio::test]
+    async fn test_get_changes_reports_expired_token_as_full_resync() {
+        let mut repo = create_repo();
+        let contact = create_contact();
+
+        repo.expect_get_contact_changes()
+            .with(eq(1), eq(Some(3)))
+            .once()
+            .returning(move |_, _| {
+                let log = ChangeLog {
+                    current: 42,
+                    pruned_through: 7,
+                    changes: vec![],
+                    contacts: vec![contact.clone()],
+                };
+                Box::pin(async move { Ok(log) })
+            });
+
+        let query = ChangesQuery {
+            since: Some(String::from("3")),
+        };
+        let changes = ContactService::get_changes(repo, 1, query).await.unwrap();
+        assert!(changes.full_resync);
+        assert_eq!(changes.next_token, "42");
+        assert_eq!(changes.created.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_get_changes_rejects_malformed_token() {
+        let repo = create_repo();
+        let query = ChangesQuery {
+            since: Some(String::from("abc")),
+        };
+
+        let result = ContactService::get_changes(repo, 1, query).await;
+        assert!(matches!(
+            result,
+            Err(handle_errors::Error::ValidationError(_))
+        ));
+    }
+}
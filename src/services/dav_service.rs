@@ -0,0 +1,497 @@
+use crate::repositories::blob_store::IBlobStore;
+use crate::repositories::dav_repo::IDavRepository;
+use crate::types::dav::{
+    card_href, is_id_resource_name, parse_sync_token, resource_name_of, Card, DavPath, DavResource,
+    DavResponse, Multistatus, Prop, PropSelection, Report,
+};
+use crate::types::photo::{blob_key, PhotoSize};
+use crate::types::precondition::{EntityTags, IfMatch, IfNoneMatch};
+use crate::types::vcard;
+use axum::http::StatusCode;
+pub struct DavService {}
+
+fn wants_address_data(props: &PropSelection) -> bool {
+    matches!(props, PropSelection::Named(props) if props.contains(&Prop::AddressData))
+}
+
+/// Renders a card as a vCard, embedding the medium thumbnail of its photo.
+async fn render<B: IBlobStore>(store: &B, card: &Card) -> Result<String, handle_errors::Error> {
+    let photo = match &card.photo_blob_prefix {
+        Some(prefix) => store.get(&blob_key(prefix, PhotoSize::Medium)).await?,
+        None => None,
+    };
+    Ok(vcard::render(&card.contact, &card.uid, photo.as_deref()))
+}
+
+/// Builds the responses for `cards`, rendering them only when the vCard was asked for.
+async fn card_responses<B: IBlobStore>(
+    store: &B,
+    cards: Vec<Card>,
+    props: &PropSelection,
+) -> Result<Vec<DavResponse>, handle_errors::Error> {
+    let mut responses = Vec::with_capacity(cards.len());
+    for card in cards {
+        let data = if wants_address_data(props) {
+            Some(render(store, &card).await?)
+        } else {
+            None
+        };
+        responses.push(DavResource::Card(Box::new(card), data).response(props));
+    }
+    Ok(responses)
+}
+
+impl DavService {
+    /// Answers a PROPFIND. `depth_one` adds the resource's members after the resource itself.
+    #[tracing::instrument(skip_all)]
+    pub async fn propfind<R: IDavRepository, B: IBlobStore>(
+        repo: R,
+        store: B,
+        path: DavPath,
+        depth_one: bool,
+        props: PropSelection,
+    ) -> Result<Multistatus, handle_errors::Error> {
+        let mut responses = Vec::new();
+        match path {
+            DavPath::Principal => {
+                responses.push(DavResource::Principal.response(&props));
+                if depth_one {
+                    responses.push(DavResource::Home.response(&props));
+                }
+            }
+            DavPath::Home => {
+                responses.push(DavResource::Home.response(&props));
+                if depth_one {
+                    for collection in repo.get_collections().await? {
+                        responses.push(DavResource::AddressBook(collection).response(&props));
+                    }
+                }
+            }
+            DavPath::AddressBook(id) => {
+                let collection = repo.get_collection(id).await?;
+                responses.push(DavResource::AddressBook(collection).response(&props));
+                if depth_one {
+                    let cards = repo.get_cards(id, None).await?;
+                    responses.extend(card_responses(&store, cards, &props).await?);
+                }
+            }
+            DavPath::Card(id, name) => {
+                let Some(card) = repo.get_cards(id, Some(vec![name])).await?.pop() else {
+                    return Err(handle_errors::Error::ContactNotFound);
+                };
+                responses.extend(card_responses(&store, vec![card], &props).await?);
+            }
+        }
+        Ok(Multistatus {
+            responses,
+            sync_token: None,
+        })
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id))]
+    pub async fn report<R: IDavRepository, B: IBlobStore>(
+        repo: R,
+        store: B,
+        address_book_id: i32,
+        report: Report,
+    ) -> Result<Multistatus, handle_errors::Error> {
+        match report {
+            Report::AddressbookMultiget { props, hrefs } => {
+                let names: Vec<Option<String>> = hrefs
+                    .iter()
+                    .map(|href| resource_name_of(href, address_book_id))
+                    .collect();
+                let cards = repo
+                    .get_cards(
+                        address_book_id,
+                        Some(names.iter().flatten().cloned().collect()),
+                    )
+                    .await?;
+
+                let mut responses = Vec::with_capacity(hrefs.len());
+                for (href, name) in hrefs.into_iter().zip(names) {
+                    match cards
+                        .iter()
+                        .find(|card| Some(&card.resource_name) == name.as_ref())
+                    {
+                        Some(card) => responses
+                            .extend(card_responses(&store, vec![card.clone()], &props).await?),
+                        None => responses.push(DavResponse::Status {
+                            href,
+                            status: StatusCode::NOT_FOUND,
+                        }),
+                    }
+                }
+                Ok(Multistatus {
+                    responses,
+                    sync_token: None,
+                })
+            }
+            Report::AddressbookQuery {
+                props,
+                filter,
+                limit,
+            } => {
+                let mut matching = Vec::new();
+                for card in repo.get_cards(address_book_id, None).await? {
+                    if limit.is_some_and(|limit| matching.len() >= limit) {
+                        break;
+                    }
+                    // Filters never look at the photo, so it is left out here.
+                    if filter.matches(&vcard::render(&card.contact, &card.uid, None)) {
+                        matching.push(card);
+                    }
+                }
+                Ok(Multistatus {
+                    responses: card_responses(&store, matching, &props).await?,
+                    sync_token: None,
+                })
+            }
+            Report::SyncCollection { sync_token, props } => {
+                let collection = repo.get_collection(address_book_id).await?;
+                let since = match sync_token {
+                    None => None,
+                    Some(token) => match parse_sync_token(&token) {
+                        Some(seq)
+                            if (collection.pruned_through..=collection.sync_seq).contains(&seq) =>
+                        {
+                            Some(seq)
+                        }
+                        _ => return Err(handle_errors::Error::InvalidSyncToken),
+                    },
+                };
+
+                let responses = match since {
+                    None => {
+                        let cards = repo.get_cards(address_book_id, None).await?;
+                        card_responses(&store, cards, &props).await?
+                    }
+                    Some(since) => {
+                        let names = repo
+                            .get_changed_resources(address_book_id, since, collection.sync_seq)
+                            .await?;
+                        let cards = repo.get_cards(address_book_id, Some(names.clone())).await?;
+                        let mut responses = Vec::with_capacity(names.len());
+                        for name in names {
+                            if !cards.iter().any(|card| card.resource_name == name) {
+                                responses.push(DavResponse::Status {
+                                    href: card_href(address_book_id, &name),
+                                    status: StatusCode::NOT_FOUND,
+                                });
+                            }
+                        }
+                        responses.extend(card_responses(&store, cards, &props).await?);
+                        responses
+                    }
+                };
+                Ok(Multistatus {
+                    responses,
+                    sync_token: Some(collection.sync_token()),
+                })
+            }
+        }
+    }
+
+    /// Returns the card together with its vCard.
+    #[tracing::instrument(skip_all, fields(%address_book_id, %resource_name))]
+    pub async fn get_card<R: IDavRepository, B: IBlobStore>(
+        repo: R,
+        store: B,
+        address_book_id: i32,
+        resource_name: String,
+    ) -> Result<(Card, String), handle_errors::Error> {
+        let Some(card) = repo
+            .get_cards(address_book_id, Some(vec![resource_name]))
+            .await?
+            .pop()
+        else {
+            return Err(handle_errors::Error::ContactNotFound);
+        };
+        let data = render(&store, &card).await?;
+        Ok((card, data))
+    }
+
+    /// Creates or replaces the card at `resource_name`, returning it and whether it is new.
+    #[tracing::instrument(skip_all, fields(%address_book_id, %resource_name))]
+    pub async fn put_card<R: IDavRepository>(
+        repo: R,
+        address_book_id: i32,
+        resource_name: String,
+        body: &str,
+        actor: Option<String>,
+        if_match: IfMatch,
+        if_none_match: IfNoneMatch,
+    ) -> Result<(Card, bool), handle_errors::Error> {
+        let parsed = vcard::parse(body).map_err(handle_errors::Error::BadRequest)?;
+        let existing = repo
+            .get_cards(address_book_id, Some(vec![resource_name.clone()]))
+            .await?
+            .pop();
+
+        match existing {
+            Some(card) if if_none_match.matches(&card.etag()) => {
+                Err(handle_errors::Error::PreconditionFailed)
+            }
+            Some(card) => {
+                let card = repo
+                    .update_card(
+                        address_book_id,
+                        card.contact.id.0,
+                        parsed.uid,
+                        parsed.contact,
+                        actor,
+                        if_match,
+                    )
+                    .await?;
+                Ok((card, false))
+            }
+            None if if_match.0 != EntityTags::Absent => {
+                Err(handle_errors::Error::PreconditionFailed)
+            }
+            None if is_id_resource_name(&resource_name) => Err(handle_errors::Error::Conflict(
+                format!("\"{resource_name}\" is reserved for the contact with that id"),
+            )),
+            None if resource_name.len() > 255 => Err(handle_errors::Error::ValidationError(
+                String::from("resource names cannot be longer than 255 bytes"),
+            )),
+            None => {
+                let card = repo
+                    .create_card(
+                        address_book_id,
+                        resource_name,
+                        parsed.uid,
+                        parsed.contact,
+                        actor,
+                    )
+                    .await?;
+                Ok((card, true))
+            }
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, %resource_name))]
+    pub async fn delete_card<R: IDavRepository>(
+        repo: R,
+        address_book_id: i32,
+        resource_name: String,
+        actor: Option<String>,
+        if_match: IfMatch,
+    ) -> Result<(), handle_errors::Error> {
+        let Some(card) = repo
+            .get_cards(address_book_id, Some(vec![resource_name]))
+            .await?
+            .pop()
+        else {
+            return Err(handle_errors::Error::ContactNotFound);
+        };
+        repo.delete_card(address_book_id, card.contact.id.0, actor, if_match)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::blob_store::MockIBlobStore;
+    use crate::repositories::dav_repo::MockIDavRepository;
+    use crate::types::address_book::{AddressBook, AddressBookId};
+    use crate::types::contact::{Contact, ContactDetails, ContactId};
+    use crate::types::dav::CardCollection;
+    use chrono::Utc;
+    use mockall::predicate::eq;
+    use serde_json::Map;
+
+    const CARD: &str = "BEGIN:VCARD\r\nVERSION:3.0\r\nUID:abc\r\nFN:Ann\r\nEND:VCARD\r\n";
+
+    fn card(id: i32, resource_name: &str) -> Card {
+        Card {
+            resource_name: String::from(resource_name),
+            uid: String::from("abc"),
+            photo_blob_prefix: None,
+            contact: Contact {
+                id: ContactId(id),
+                name: String::from("Ann"),
+                address: String::new(),
+                phone_number: None,
+                email: None,
+                details: ContactDetails::default(),
+                display_name: String::from("Ann"),
+                sort_key: String::from("ann"),
+                address_book_id: AddressBookId(1),
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+                created_by: None,
+                updated_by: None,
+                deleted_at: None,
+                version: 1,
+                groups: vec![],
+                has_photo: false,
+                custom_fields: Map::new(),
+            },
+        }
+    }
+
+    fn collection(sync_seq: i64) -> CardCollection {
+        CardCollection {
+            book: AddressBook {
+                id: AddressBookId(1),
+                address_book_name: String::from("Friends"),
+                contacts: None,
+                contact_count: None,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+                created_by: None,
+                updated_by: None,
+                deleted_at: None,
+                version: 1,
+            },
+            sync_seq,
+            pruned_through: 0,
+        }
+    }
+
+    fn repo_with_cards(cards: Vec<Card>) -> MockIDavRepository {
+        let mut repo = MockIDavRepository::new();
+        repo.expect_get_cards().returning(move |_, names| {
+            let cards: Vec<Card> = cards
+                .iter()
+                .filter(|card| {
+                    names
+                        .as_ref()
+                        .is_none_or(|n| n.contains(&card.resource_name))
+                })
+                .cloned()
+                .collect();
+            Box::pin(async move { Ok(cards) })
+        });
+        repo
+    }
+
+    #[tokio::test]
+    async fn test_put_card_creates_or_updates() {
+        let mut repo = repo_with_cards(vec![]);
+        repo.expect_create_card()
+            .withf(|id, name, uid, contact, _| {
+                *id == 1
+                    && name == "ann.vcf"
+                    && uid.as_deref() == Some("abc")
+                    && contact.name == "Ann"
+            })
+            .once()
+            .returning(|_, name, _, _, _| Box::pin(async move { Ok(card(5, &name)) }));
+        let (_, created) = DavService::put_card(
+            repo,
+            1,
+            String::from("ann.vcf"),
+            CARD,
+            None,
+            IfMatch::default(),
+            IfNoneMatch(EntityTags::Any),
+        )
+        .await
+        .unwrap();
+        assert!(created);
+
+        let mut repo = repo_with_cards(vec![card(5, "ann.vcf")]);
+        repo.expect_update_card()
+            .with(
+                eq(1),
+                eq(5),
+                eq(Some(String::from("abc"))),
+                mockall::predicate::always(),
+                eq(None),
+                eq(IfMatch::default()),
+            )
+            .once()
+            .returning(|_, id, _, _, _, _| Box::pin(async move { Ok(card(id, "ann.vcf")) }));
+        let (_, created) = DavService::put_card(
+            repo,
+            1,
+            String::from("ann.vcf"),
+            CARD,
+            None,
+            IfMatch::default(),
+            IfNoneMatch::default(),
+        )
+        .await
+        .unwrap();
+        assert!(!created);
+    }
+
+    #[tokio::test]
+    async fn test_put_card_checks_preconditions() {
+        let mut repo = repo_with_cards(vec![card(5, "ann.vcf")]);
+        repo.expect_update_card().never();
+        let result = DavService::put_card(
+            repo,
+            1,
+            String::from("ann.vcf"),
+            CARD,
+            None,
+            IfMatch::default(),
+            IfNoneMatch(EntityTags::Any),
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(handle_errors::Error::PreconditionFailed)
+        ));
+
+        let mut repo = repo_with_cards(vec![]);
+        repo.expect_create_card().never();
+        let result = DavService::put_card(
+            repo,
+            1,
+            String::from("12.vcf"),
+            CARD,
+            None,
+            IfMatch::default(),
+            IfNoneMatch::default(),
+        )
+        .await;
+        assert!(matches!(result, Err(handle_errors::Error::Conflict(_))));
+    }
+
+    #[tokio::test]
+    async fn test_sync_collection_reports_changes_since_token() {
+        let mut repo = repo_with_cards(vec![card(5, "ann.vcf")]);
+        repo.expect_get_collection()
+            .returning(|_| Box::pin(async { Ok(collection(9)) }));
+        repo.expect_get_changed_resources()
+            .with(eq(1), eq(4), eq(9))
+            .once()
+            .returning(|_, _, _| {
+                Box::pin(async { Ok(vec![String::from("ann.vcf"), String::from("6.vcf")]) })
+            });
+        let report = Report::SyncCollection {
+            sync_token: Some(String::from("http://addressbook-service/ns/sync/4")),
+            props: PropSelection::Named(vec![Prop::GetEtag]),
+        };
+
+        let multistatus = DavService::report(repo, MockIBlobStore::new(), 1, report)
+            .await
+            .unwrap();
+        assert_eq!(
+            multistatus.sync_token.as_deref(),
+            Some("http://addressbook-service/ns/sync/9")
+        );
+        assert_eq!(multistatus.responses.len(), 2);
+        assert!(multistatus.responses.contains(&DavResponse::Status {
+            href: String::from("/dav/addressbooks/1/6.vcf"),
+            status: StatusCode::NOT_FOUND,
+        }));
+
+        let mut repo = MockIDavRepository::new();
+        repo.expect_get_collection()
+            .returning(|_| Box::pin(async { Ok(collection(9)) }));
+        let report = Report::SyncCollection {
+            sync_token: Some(String::from("http://addressbook-service/ns/sync/10")),
+            props: PropSelection::All,
+        };
+        let result = DavService::report(repo, MockIBlobStore::new(), 1, report).await;
+        assert!(matches!(
+            result,
+            Err(handle_errors::Error::InvalidSyncToken)
+        ));
+    }
+}
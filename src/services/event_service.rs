@@ -0,0 +1,131 @@
+use crate::repositories::contact_repo::IContactRepository;
+use crate::types::changes::{ContactEvent, EventCursor};
+pub struct EventService {}
+
+/// Events read per query; a stream that is far behind catches up batch by batch.
+const EVENT_BATCH: i64 = 100;
+
+impl EventService {
+    /// Starts a book's event stream after `last_event_id`, or at the book's latest change
+    /// without one. An id the change log no longer covers starts at the latest change too,
+    /// flagged for a resync.
+    #[tracing::instrument(skip_all, fields(%address_book_id))]
+    pub async fn open<T: IContactRepository>(
+        repo: &T,
+        address_book_id: i32,
+        last_event_id: Option<&str>,
+    ) -> Result<EventCursor, handle_errors::Error> {
+        let last_event_id = match last_event_id {
+            None => None,
+            Some(id) => match id.trim().parse::<i64>() {
+                Ok(id) if id >= 0 => Some(id),
+                _ => {
+                    return Err(handle_errors::Error::ValidationError(format!(
+                        "\"{id}\" is not a valid Last-Event-ID"
+                    )))
+                }
+            },
+        };
+        let position = repo.get_change_position(address_book_id).await?;
+
+        Ok(match last_event_id {
+            Some(id) if position.covers(id) => EventCursor {
+                address_book_id,
+                position: id,
+                resync: false,
+            },
+            _ => EventCursor {
+                address_book_id,
+                position: position.current,
+                resync: last_event_id.is_some(),
+            },
+        })
+    }
+
+    /// The next events after the cursor, which moves past them. Empty once caught up.
+    #[tracing::instrument(skip_all)]
+    pub async fn next_events<T: IContactRepository>(
+        repo: &T,
+        cursor: &mut EventCursor,
+    ) -> Result<Vec<ContactEvent>, handle_errors::Error> {
+        let events = repo
+            .get_contact_events(cursor.address_book_id, cursor.position, EVENT_BATCH)
+            .await?;
+        if let Some(last) = events.last() {
+            cursor.position = last.seq;
+        }
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::contact_repo::MockIContactRepository;
+    use crate::types::changes::{ChangeKind, ChangePosition};
+    use crate::types::contact::ContactId;
+    use mockall::predicate::eq;
+
+    fn with_position(repo: &mut MockIContactRepository) {
+        repo.expect_get_change_position()
+            .with(eq(1))
+            .returning(|_| {
+                Box::pin(async {
+                    Ok(ChangePosition {
+                        current: 40,
+                        pruned_through: 10,
+                    })
+                })
+            });
+    }
+
+    #[tokio::test]
+    async fn test_open_resumes_or_resyncs() {
+        let mut repo = MockIContactRepository::new();
+        with_position(&mut repo);
+
+        let cursor = |position, resync| EventCursor {
+            address_book_id: 1,
+            position,
+            resync,
+        };
+        let open = |id| EventService::open(&repo, 1, id);
+        assert_eq!(open(None).await.unwrap(), cursor(40, false));
+        assert_eq!(open(Some("25")).await.unwrap(), cursor(25, false));
+        assert_eq!(open(Some("5")).await.unwrap(), cursor(40, true));
+        assert_eq!(open(Some("41")).await.unwrap(), cursor(40, true));
+        assert!(matches!(
+            open(Some("abc")).await,
+            Err(handle_errors::Error::ValidationError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_next_events_moves_cursor() {
+        let mut repo = MockIContactRepository::new();
+        repo.expect_get_contact_events()
+            .with(eq(1), eq(25), eq(EVENT_BATCH))
+            .once()
+            .returning(|_, _, _| {
+                let events = [26, 29]
+                    .into_iter()
+                    .map(|seq| ContactEvent {
+                        seq,
+                        contact_id: ContactId(3),
+                        kind: ChangeKind::Delete,
+                        contact: None,
+                    })
+                    .collect();
+                Box::pin(async move { Ok(events) })
+            });
+
+        let mut cursor = EventCursor {
+            address_book_id: 1,
+            position: 25,
+            resync: false,
+        };
+        let events = EventService::next_events(&repo, &mut cursor).await.unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(cursor.position, 29);
+    }
+}
@@ -0,0 +1,316 @@
+use crate::repositories::contact_repo::IContactRepository;
+use crate::repositories::field_repo::IFieldRepository;
+use crate::types::contact::{Contact, ContactFilter, NewContact};
+use crate::types::custom_field::{self, format_cell, CustomField};
+use crate::types::ListFilter;
+use chrono::NaiveDate;
+pub struct ExportService {}
+
+/// Columns every export has, ahead of one `cf.<name>` column per custom field.
+const COLUMNS: [&str; 16] = [
+    "id",
+    "name",
+    "address",
+    "phone_number",
+    "email",
+    "name_prefix",
+    "given_name",
+    "middle_name",
+    "family_name",
+    "name_suffix",
+    "nickname",
+    "organization",
+    "job_title",
+    "birthday",
+    "anniversary",
+    "notes",
+];
+
+fn invalid(message: String) -> handle_errors::Error {
+    handle_errors::Error::ValidationError(message)
+}
+
+/// Renders contacts as CSV with a header row. Absent values are left empty.
+fn write_csv(fields: &[CustomField], contacts: &[Contact]) -> Result<String, csv::Error> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    let header = COLUMNS
+        .iter()
+        .map(|column| column.to_string())
+        .chain(fields.iter().map(|field| format!("cf.{}", field.name)));
+    writer.write_record(header)?;
+
+    for contact in contacts {
+        let details = &contact.details;
+        let date = |date: Option<NaiveDate>| date.map(|d| d.to_string()).unwrap_or_default();
+        let record = [
+            contact.id.0.to_string(),
+            contact.name.clone(),
+            contact.address.clone(),
+            contact.phone_number.clone().unwrap_or_default(),
+            contact.email.clone().unwrap_or_default(),
+            details.name_prefix.clone().unwrap_or_default(),
+            details.given_name.clone().unwrap_or_default(),
+            details.middle_name.clone().unwrap_or_default(),
+            details.family_name.clone().unwrap_or_default(),
+            details.name_suffix.clone().unwrap_or_default(),
+            details.nickname.clone().unwrap_or_default(),
+            details.organization.clone().unwrap_or_default(),
+            details.job_title.clone().unwrap_or_default(),
+            date(details.birthday),
+            date(details.anniversary),
+            details.notes.clone().unwrap_or_default(),
+        ]
+        .into_iter()
+        .chain(
+            fields
+                .iter()
+                .map(|field| format_cell(contact.custom_fields.get(&field.name))),
+        );
+        writer.write_record(record)?;
+    }
+
+    let bytes = writer.into_inner().map_err(|e| e.into_error())?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Parses CSV in the export format into new contacts. The `id` column is ignored, so an
+/// export can be imported as-is; errors name the offending line.
+fn read_csv(fields: &[CustomField], body: &str) -> Result<Vec<NewContact>, handle_errors::Error> {
+    let mut reader = csv::Reader::from_reader(body.as_bytes());
+    let header = reader
+        .headers()
+        .map_err(|e| invalid(e.to_string()))?
+        .clone();
+
+    let mut columns = Vec::with_capacity(header.len());
+    for column in header.iter() {
+        let definition = match column.strip_prefix("cf.") {
+            Some(name) => match fields.iter().find(|field| field.name == name) {
+                Some(field) => Some(field),
+                None => return Err(invalid(format!("unknown custom field \"{name}\""))),
+            },
+            None if COLUMNS.contains(&column) => None,
+            None => return Err(invalid(format!("unknown column \"{column}\""))),
+        };
+        columns.push((column, definition));
+    }
+    for required in ["name", "address"] {
+        if !header.iter().any(|column| column == required) {
+            return Err(invalid(format!("missing column \"{required}\"")));
+        }
+    }
+
+    let mut contacts = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| invalid(e.to_string()))?;
+        let line = record.position().map_or(0, |position| position.line());
+        let at_line = |message: String| invalid(format!("line {line}: {message}"));
+
+        let mut contact = NewContact::default();
+        for ((column, definition), cell) in columns.iter().zip(record.iter()) {
+            let optional = (!cell.is_empty()).then(|| cell.to_string());
+            let date = || match cell {
+                "" => Ok(None),
+                _ => NaiveDate::parse_from_str(cell, "%Y-%m-%d")
+                    .map(Some)
+                    .map_err(|_| at_line(format!("{column} must be a date (YYYY-MM-DD)"))),
+            };
+            let details = &mut contact.details;
+            match (*column, definition) {
+                (_, Some(field)) => {
+                    if let Some(value) = field.parse_cell(cell).map_err(at_line)? {
+                        contact.custom_fields.insert(field.name.clone(), value);
+                    }
+                }
+                ("name", None) => contact.name = cell.to_string(),
+                ("address", None) => contact.address = cell.to_string(),
+                ("phone_number", None) => contact.phone_number = optional,
+                ("email", None) => contact.email = optional,
+                ("name_prefix", None) => details.name_prefix = optional,
+                ("given_name", None) => details.given_name = optional,
+                ("middle_name", None) => details.middle_name = optional,
+                ("family_name", None) => details.family_name = optional,
+                ("name_suffix", None) => details.name_suffix = optional,
+                ("nickname", None) => details.nickname = optional,
+                ("organization", None) => details.organization = optional,
+                ("job_title", None) => details.job_title = optional,
+                ("birthday", None) => details.birthday = date()?,
+                ("anniversary", None) => details.anniversary = date()?,
+                ("notes", None) => details.notes = optional,
+                _ => {}
+            }
+        }
+        custom_field::validate(fields, &contact.custom_fields).map_err(at_line)?;
+        contacts.push(contact);
+    }
+    Ok(contacts)
+}
+
+impl ExportService {
+    #[tracing::instrument(skip_all, fields(%address_book_id))]
+    pub async fn export_contacts<C: IContactRepository, F: IFieldRepository>(
+        contact_repo: C,
+        field_repo: F,
+        address_book_id: i32,
+    ) -> Result<String, handle_errors::Error> {
+        let fields = field_repo.get_fields(address_book_id).await?;
+        let contacts = contact_repo
+            .get_address_book_contacts(
+                address_book_id,
+                None,
+                0,
+                ListFilter::default(),
+                ContactFilter::default(),
+            )
+            .await?;
+
+        write_csv(&fields, &contacts).map_err(|e| invalid(e.to_string()))
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id))]
+    pub async fn import_contacts<C: IContactRepository, F: IFieldRepository>(
+        contact_repo: C,
+        field_repo: F,
+        address_book_id: i32,
+        body: String,
+        actor: Option<String>,
+    ) -> Result<Vec<Contact>, handle_errors::Error> {
+        let fields = field_repo.get_fields(address_book_id).await?;
+        let contacts = read_csv(&fields, &body)?;
+        if contacts.is_empty() {
+            return Err(invalid(String::from("the file has no contacts")));
+        }
+
+        contact_repo
+            .import_contacts(address_book_id, contacts, actor)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::contact_repo::MockIContactRepository;
+    use crate::repositories::field_repo::MockIFieldRepository;
+    use crate::types::address_book::AddressBookId;
+    use crate::types::contact::{ContactDetails, ContactId};
+    use crate::types::custom_field::{FieldId, FieldType};
+    use chrono::Utc;
+    use serde_json::json;
+
+    fn create_field(name: &str, field_type: FieldType) -> CustomField {
+        CustomField {
+            id: FieldId(1),
+            address_book_id: AddressBookId(1),
+            name: String::from(name),
+            field_type,
+            required: false,
+            options: vec![],
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            created_by: None,
+            updated_by: None,
+            version: 1,
+        }
+    }
+
+    fn field_repo(fields: Vec<CustomField>) -> MockIFieldRepository {
+        let mut repo = MockIFieldRepository::new();
+        repo.expect_get_fields().returning(move |_| {
+            let fields = fields.clone();
+            Box::pin(async move { Ok(fields) })
+        });
+        repo
+    }
+
+    fn from_new(contact: NewContact) -> Contact {
+        Contact {
+            id: ContactId(7),
+            display_name: contact.name.clone(),
+            sort_key: contact.name.to_lowercase(),
+            name: contact.name,
+            address: contact.address,
+            phone_number: contact.phone_number,
+            email: contact.email,
+            details: contact.details,
+            address_book_id: AddressBookId(1),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            created_by: None,
+            updated_by: None,
+            deleted_at: None,
+            version: 1,
+            groups: vec![],
+            has_photo: false,
+            custom_fields: contact.custom_fields,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_export_round_trips_through_import() {
+        let fields = vec![
+            create_field("customer_id", FieldType::Number),
+            create_field("active", FieldType::Bool),
+        ];
+        let contact = NewContact {
+            name: String::from("Doe, Jane"),
+            address: String::from("1 Main Street\nSpringfield"),
+            phone_number: None,
+            email: Some(String::from("jane@example.com")),
+            details: ContactDetails {
+                family_name: Some(String::from("Doe")),
+                birthday: NaiveDate::from_ymd_opt(1990, 4, 1),
+                notes: Some(String::from("Prefers \"email\"")),
+                ..ContactDetails::default()
+            },
+            custom_fields: json!({ "customer_id": 42, "active": true })
+                .as_object()
+                .unwrap()
+                .clone(),
+        };
+
+        let mut contact_repo = MockIContactRepository::new();
+        let stored = from_new(contact.clone());
+        contact_repo
+            .expect_get_address_book_contacts()
+            .once()
+            .returning(move |_, _, _, _, _| {
+                let stored = stored.clone();
+                Box::pin(async move { Ok(vec![stored]) })
+            });
+        let csv = ExportService::export_contacts(contact_repo, field_repo(fields.clone()), 1)
+            .await
+            .unwrap();
+        assert!(csv.starts_with("id,name,address,phone_number,email,name_prefix,"));
+        assert!(csv.contains(",cf.customer_id,cf.active\n"));
+
+        let mut contact_repo = MockIContactRepository::new();
+        contact_repo
+            .expect_import_contacts()
+            .withf(move |_, contacts, _| contacts == &vec![contact.clone()])
+            .once()
+            .returning(|_, contacts, _| {
+                Box::pin(async move { Ok(contacts.into_iter().map(from_new).collect()) })
+            });
+        let imported =
+            ExportService::import_contacts(contact_repo, field_repo(fields), 1, csv, None).await;
+        assert_eq!(imported.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_import_reports_line_of_invalid_value() {
+        let mut contact_repo = MockIContactRepository::new();
+        contact_repo.expect_import_contacts().never();
+
+        let body = String::from(
+            "name,address,cf.customer_id\nAnn,1 Main Street,7\nBob,2 Main Street,many\n",
+        );
+        let fields = vec![create_field("customer_id", FieldType::Number)];
+        let result =
+            ExportService::import_contacts(contact_repo, field_repo(fields), 1, body, None).await;
+        assert!(matches!(
+            result,
+            Err(handle_errors::Error::ValidationError(message)) if message.starts_with("line 3:")
+        ));
+    }
+}
@@ -0,0 +1,174 @@
+use crate::repositories::field_repo::IFieldRepository;
+use crate::types::custom_field::{CustomField, FieldType, NewCustomField};
+use crate::types::precondition::IfMatch;
+pub struct FieldService {}
+
+/// Field names double as JSON keys and `cf.<name>` query parameters, so they are kept to
+/// lowercase identifiers. Only enum fields take options, and those must be distinct.
+fn validate_field(field: NewCustomField) -> Result<NewCustomField, handle_errors::Error> {
+    let mut chars = field.name.chars();
+    let valid_name = chars.next().is_some_and(|c| c.is_ascii_lowercase())
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        && field.name.len() <= 64;
+    if !valid_name {
+        return Err(handle_errors::Error::ValidationError(String::from(
+            "name must start with a lowercase letter and contain at most 64 lowercase letters, digits or underscores",
+        )));
+    }
+
+    let options: Vec<String> = field
+        .options
+        .iter()
+        .map(|option| option.trim().to_string())
+        .collect();
+    match field.field_type {
+        FieldType::Enum if options.is_empty() => Err(handle_errors::Error::ValidationError(
+            String::from("an enum field needs at least one option"),
+        )),
+        FieldType::Enum if options.iter().any(String::is_empty) => Err(
+            handle_errors::Error::ValidationError(String::from("options cannot be empty")),
+        ),
+        FieldType::Enum
+            if options
+                .iter()
+                .enumerate()
+                .any(|(i, option)| options[..i].contains(option)) =>
+        {
+            Err(handle_errors::Error::ValidationError(String::from(
+                "options must be unique",
+            )))
+        }
+        FieldType::Enum => Ok(NewCustomField { options, ..field }),
+        _ if !options.is_empty() => Err(handle_errors::Error::ValidationError(String::from(
+            "only enum fields take options",
+        ))),
+        _ => Ok(field),
+    }
+}
+
+impl FieldService {
+    #[tracing::instrument(skip_all, fields(%address_book_id))]
+    pub async fn get_fields<T: IFieldRepository>(
+        repo: T,
+        address_book_id: i32,
+    ) -> Result<Vec<CustomField>, handle_errors::Error> {
+        repo.get_fields(address_book_id).await
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, field_id = %id))]
+    pub async fn get_field<T: IFieldRepository>(
+        repo: T,
+        address_book_id: i32,
+        id: i32,
+    ) -> Result<CustomField, handle_errors::Error> {
+        repo.get_field(address_book_id, id).await
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id))]
+    pub async fn create_field<T: IFieldRepository>(
+        repo: T,
+        address_book_id: i32,
+        field: NewCustomField,
+        actor: Option<String>,
+    ) -> Result<CustomField, handle_errors::Error> {
+        let field = validate_field(field)?;
+        repo.create_field(address_book_id, field, actor).await
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, field_id = %id))]
+    pub async fn update_field<T: IFieldRepository>(
+        repo: T,
+        address_book_id: i32,
+        id: i32,
+        field: NewCustomField,
+        actor: Option<String>,
+        if_match: IfMatch,
+    ) -> Result<CustomField, handle_errors::Error> {
+        let field = validate_field(field)?;
+        repo.update_field(address_book_id, id, field, actor, if_match)
+            .await
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, field_id = %id))]
+    pub async fn delete_field<T: IFieldRepository>(
+        repo: T,
+        address_book_id: i32,
+        id: i32,
+        actor: Option<String>,
+        if_match: IfMatch,
+    ) -> Result<(), handle_errors::Error> {
+        repo.delete_field(address_book_id, id, actor, if_match)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::field_repo::MockIFieldRepository;
+    use crate::types::address_book::AddressBookId;
+    use crate::types::custom_field::FieldId;
+    use chrono::Utc;
+    use mockall::predicate::eq;
+
+    fn new_field(name: &str, field_type: FieldType, options: &[&str]) -> NewCustomField {
+        NewCustomField {
+            name: String::from(name),
+            field_type,
+            required: false,
+            options: options.iter().map(|option| option.to_string()).collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_field_trims_options() {
+        let mut repo = MockIFieldRepository::new();
+        let expected = new_field("tier", FieldType::Enum, &["gold", "silver"]);
+
+        repo.expect_create_field()
+            .with(eq(1), eq(expected), eq(None))
+            .once()
+            .returning(|_, field, _| {
+                Box::pin(async move {
+                    Ok(CustomField {
+                        id: FieldId(1),
+                        address_book_id: AddressBookId(1),
+                        name: field.name,
+                        field_type: field.field_type,
+                        required: field.required,
+                        options: field.options,
+                        created_at: Utc::now(),
+                        updated_at: Utc::now(),
+                        created_by: None,
+                        updated_by: None,
+                        version: 1,
+                    })
+                })
+            });
+
+        let field = new_field("tier", FieldType::Enum, &[" gold", "silver "]);
+        let result = FieldService::create_field(repo, 1, field, None).await;
+        assert_eq!(result.unwrap().options, vec!["gold", "silver"]);
+    }
+
+    #[tokio::test]
+    async fn test_create_field_rejects_invalid_definitions() {
+        let invalid = [
+            new_field("Customer ID", FieldType::Number, &[]),
+            new_field("tier", FieldType::Enum, &[]),
+            new_field("tier", FieldType::Enum, &["gold", "gold"]),
+            new_field("manager", FieldType::Text, &["a"]),
+        ];
+
+        for field in invalid {
+            let mut repo = MockIFieldRepository::new();
+            repo.expect_create_field().never();
+
+            let result = FieldService::create_field(repo, 1, field, None).await;
+            assert!(matches!(
+                result,
+                Err(handle_errors::Error::ValidationError(_))
+            ));
+        }
+    }
+}
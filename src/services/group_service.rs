@@ -0,0 +1,160 @@
+use crate::repositories::group_repo::IGroupRepository;
+use crate::types::contact::Contact;
+use crate::types::group::{ContactGroup, NewContactGroup};
+use crate::types::precondition::IfMatch;
+pub struct GroupService {}
+
+/// Trims the name and rejects blank or overlong ones.
+fn validate_name(group: NewContactGroup) -> Result<String, handle_errors::Error> {
+    let name = group.name.trim();
+    if name.is_empty() {
+        return Err(handle_errors::Error::ValidationError(String::from(
+            "name cannot be empty",
+        )));
+    }
+    if name.chars().count() > 255 {
+        return Err(handle_errors::Error::ValidationError(String::from(
+            "name cannot be longer than 255 characters",
+        )));
+    }
+    Ok(name.to_string())
+}
+
+impl GroupService {
+    #[tracing::instrument(skip_all, fields(%address_book_id))]
+    pub async fn get_groups<T: IGroupRepository>(
+        repo: T,
+        address_book_id: i32,
+    ) -> Result<Vec<ContactGroup>, handle_errors::Error> {
+        repo.get_groups(address_book_id).await
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, group_id = %id))]
+    pub async fn get_group<T: IGroupRepository>(
+        repo: T,
+        address_book_id: i32,
+        id: i32,
+    ) -> Result<ContactGroup, handle_errors::Error> {
+        repo.get_group(address_book_id, id).await
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id))]
+    pub async fn create_group<T: IGroupRepository>(
+        repo: T,
+        address_book_id: i32,
+        group: NewContactGroup,
+        actor: Option<String>,
+    ) -> Result<ContactGroup, handle_errors::Error> {
+        let name = validate_name(group)?;
+        repo.create_group(address_book_id, name, actor).await
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, group_id = %id))]
+    pub async fn update_group<T: IGroupRepository>(
+        repo: T,
+        address_book_id: i32,
+        id: i32,
+        group: NewContactGroup,
+        actor: Option<String>,
+        if_match: IfMatch,
+    ) -> Result<ContactGroup, handle_errors::Error> {
+        let name = validate_name(group)?;
+        repo.update_group(address_book_id, id, name, actor, if_match)
+            .await
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, group_id = %id))]
+    pub async fn delete_group<T: IGroupRepository>(
+        repo: T,
+        address_book_id: i32,
+        id: i32,
+        actor: Option<String>,
+        if_match: IfMatch,
+    ) -> Result<(), handle_errors::Error> {
+        repo.delete_group(address_book_id, id, actor, if_match)
+            .await
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, %contact_id, group_id = %id))]
+    pub async fn add_member<T: IGroupRepository>(
+        repo: T,
+        address_book_id: i32,
+        id: i32,
+        contact_id: i32,
+        actor: Option<String>,
+    ) -> Result<Contact, handle_errors::Error> {
+        repo.add_member(address_book_id, id, contact_id, actor)
+            .await
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, %contact_id, group_id = %id))]
+    pub async fn remove_member<T: IGroupRepository>(
+        repo: T,
+        address_book_id: i32,
+        id: i32,
+        contact_id: i32,
+        actor: Option<String>,
+    ) -> Result<Contact, handle_errors::Error> {
+        repo.remove_member(address_book_id, id, contact_id, actor)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::group_repo::MockIGroupRepository;
+    use crate::types::address_book::AddressBookId;
+    use crate::types::group::GroupId;
+    use chrono::Utc;
+    use mockall::predicate::eq;
+
+    fn create_group() -> ContactGroup {
+        ContactGroup {
+            id: GroupId(1),
+            address_book_id: AddressBookId(1),
+            name: String::from("Family"),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            created_by: None,
+            updated_by: None,
+            version: 1,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_group_trims_name() {
+        let mut repo = MockIGroupRepository::new();
+        let group = create_group();
+
+        repo.expect_create_group()
+            .with(eq(1), eq(String::from("Family")), eq(None))
+            .once()
+            .returning(move |_, _, _| {
+                let group = group.clone();
+                Box::pin(async move { Ok(group) })
+            });
+
+        let new_group = NewContactGroup {
+            name: String::from("  Family "),
+        };
+        let result = GroupService::create_group(repo, 1, new_group, None).await;
+        assert_eq!(result.unwrap().name, "Family");
+    }
+
+    #[tokio::test]
+    async fn test_update_group_rejects_blank_name() {
+        let mut repo = MockIGroupRepository::new();
+        repo.expect_update_group().never();
+
+        let new_group = NewContactGroup {
+            name: String::from("   "),
+        };
+        let result =
+            GroupService::update_group(repo, 1, 1, new_group, None, IfMatch::default()).await;
+        assert!(matches!(
+            result,
+            Err(handle_errors::Error::ValidationError(_))
+        ));
+    }
+}
@@ -0,0 +1,38 @@
+use crate::monitoring;
+use crate::repositories::metrics_repo::IMetricsRepository;
+use crate::types::stats::EntityCounts;
+pub struct MetricsService {}
+
+impl MetricsService {
+    /// Counts books and contacts and publishes the figures as gauges.
+    #[tracing::instrument(skip_all)]
+    pub async fn refresh_counts<T: IMetricsRepository>(
+        repo: T,
+    ) -> Result<EntityCounts, handle_errors::Error> {
+        let counts = repo.count_entities().await?;
+        monitoring::record_counts(&counts);
+        Ok(counts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::metrics_repo::MockIMetricsRepository;
+
+    #[tokio::test]
+    async fn test_refresh_counts_returns_counts() {
+        let mut repo = MockIMetricsRepository::new();
+        repo.expect_count_entities().once().returning(|| {
+            Box::pin(async {
+                Ok(EntityCounts {
+                    address_books: 2,
+                    contacts: 7,
+                })
+            })
+        });
+
+        let counts = MetricsService::refresh_counts(repo).await.unwrap();
+        assert_eq!(counts.contacts, 7);
+    }
+}
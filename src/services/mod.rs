@@ -1,2 +1,13 @@
 pub mod address_book_service;
 pub mod contact_service;
+pub mod dav_service;
+pub mod event_service;
+pub mod export_service;
+pub mod field_service;
+pub mod group_service;
+pub mod metrics_service;
+pub mod photo_service;
+pub mod rate_limit_service;
+pub mod trash_service;
+pub mod audit_service;
+pub mod webhook_service;
@@ -0,0 +1,374 @@
+use crate::repositories::blob_store::IBlobStore;
+use crate::repositories::photo_repo::IPhotoRepository;
+use crate::types::photo::{self, NewPhoto, Photo, PhotoSize, MAX_PHOTO_BYTES, MAX_PHOTO_DIMENSION};
+use image::{DynamicImage, ImageDecoder, ImageError, ImageFormat, ImageReader, Limits};
+use std::io::Cursor;
+pub struct PhotoService {}
+
+fn invalid(message: &str) -> handle_errors::Error {
+    handle_errors::Error::ValidationError(String::from(message))
+}
+
+fn encode(image: &DynamicImage, format: ImageFormat) -> Result<Vec<u8>, ImageError> {
+    let mut data = Vec::new();
+    match format {
+        // JPEG has no alpha channel.
+        ImageFormat::Jpeg => DynamicImage::ImageRgb8(image.to_rgb8())
+            .write_to(&mut Cursor::new(&mut data), ImageFormat::Jpeg)?,
+        _ => image.write_to(&mut Cursor::new(&mut data), format)?,
+    }
+    Ok(data)
+}
+
+/// Decodes an upload declared as `declared_type` and renders its thumbnails. The original is
+/// kept byte for byte; thumbnails are upright, never enlarged, and JPEG for JPEG uploads or
+/// PNG otherwise.
+fn process(
+    data: Vec<u8>,
+    declared_type: &str,
+    blob_prefix: String,
+) -> Result<NewPhoto, handle_errors::Error> {
+    let mut reader = ImageReader::new(Cursor::new(&data))
+        .with_guessed_format()
+        .map_err(|_| invalid("photo could not be read"))?;
+    let (content_type, thumbnail_format) = match reader.format() {
+        Some(ImageFormat::Jpeg) => ("image/jpeg", ImageFormat::Jpeg),
+        Some(ImageFormat::Png) => ("image/png", ImageFormat::Png),
+        Some(ImageFormat::WebP) => ("image/webp", ImageFormat::Png),
+        _ => return Err(invalid("photo must be a JPEG, PNG or WebP image")),
+    };
+    if content_type != declared_type {
+        return Err(handle_errors::Error::UnsupportedMediaType);
+    }
+
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_PHOTO_DIMENSION);
+    limits.max_image_height = Some(MAX_PHOTO_DIMENSION);
+    reader.limits(limits);
+
+    let decoded = reader.into_decoder().and_then(|mut decoder| {
+        let orientation = decoder.orientation()?;
+        let mut image = DynamicImage::from_decoder(decoder)?;
+        image.apply_orientation(orientation);
+        Ok(image)
+    });
+    let image = match decoded {
+        Ok(image) => image,
+        Err(ImageError::Limits(_)) => {
+            return Err(handle_errors::Error::PayloadTooLarge(format!(
+                "photo cannot be wider or taller than {MAX_PHOTO_DIMENSION} pixels"
+            )))
+        }
+        Err(_) => return Err(invalid("photo could not be decoded")),
+    };
+
+    let mut renditions = Vec::with_capacity(PhotoSize::ALL.len());
+    for size in PhotoSize::ALL {
+        let Some(bound) = size.bound() else {
+            continue;
+        };
+        let thumbnail = if image.width() > bound || image.height() > bound {
+            image.thumbnail(bound, bound)
+        } else {
+            image.clone()
+        };
+        let encoded = encode(&thumbnail, thumbnail_format)
+            .map_err(|e| handle_errors::Error::StorageError(std::io::Error::other(e)))?;
+        renditions.push((size, encoded));
+    }
+    renditions.push((PhotoSize::Original, data));
+
+    Ok(NewPhoto {
+        blob_prefix,
+        content_type: String::from(content_type),
+        width: image.width() as i32,
+        height: image.height() as i32,
+        renditions,
+    })
+}
+
+/// Removes every rendition stored under `prefix`. The database no longer points at them, so a
+/// failure only leaves garbage behind and is logged rather than returned.
+pub(crate) async fn discard_renditions<B: IBlobStore + ?Sized>(store: &B, prefix: &str) {
+    for size in PhotoSize::ALL {
+        if let Err(e) = store.delete(&photo::blob_key(prefix, size)).await {
+            tracing::warn!(error = %e, prefix, "removing a photo rendition failed");
+        }
+    }
+}
+
+impl PhotoService {
+    /// Returns the photo's metadata together with the bytes of the requested size.
+    #[tracing::instrument(skip_all, fields(%address_book_id, %contact_id))]
+    pub async fn get_photo<R: IPhotoRepository, B: IBlobStore>(
+        repo: R,
+        store: B,
+        address_book_id: i32,
+        contact_id: i32,
+        size: PhotoSize,
+    ) -> Result<(Photo, Vec<u8>), handle_errors::Error> {
+        let photo = repo.get_photo(address_book_id, contact_id).await?;
+        match store.get(&photo.blob_key(size)).await? {
+            Some(data) => Ok((photo, data)),
+            None => Err(handle_errors::Error::PhotoNotFound),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, %contact_id))]
+    pub async fn put_photo<R: IPhotoRepository, B: IBlobStore>(
+        repo: R,
+        store: B,
+        address_book_id: i32,
+        contact_id: i32,
+        content_type: String,
+        data: Vec<u8>,
+        actor: Option<String>,
+    ) -> Result<Photo, handle_errors::Error> {
+        if data.len() > MAX_PHOTO_BYTES {
+            return Err(handle_errors::Error::PayloadTooLarge(format!(
+                "photo cannot be larger than {MAX_PHOTO_BYTES} bytes"
+            )));
+        }
+        if data.is_empty() {
+            return Err(invalid("photo cannot be empty"));
+        }
+        // Fail fast for unknown contacts before spending time on the image.
+        match repo.get_photo(address_book_id, contact_id).await {
+            Ok(_) | Err(handle_errors::Error::PhotoNotFound) => {}
+            Err(e) => return Err(e),
+        }
+
+        let blob_prefix = photo::upload_prefix(contact_id);
+        let prefix = blob_prefix.clone();
+        let photo = tokio::task::spawn_blocking(move || process(data, &content_type, prefix))
+            .await
+            .map_err(|e| handle_errors::Error::StorageError(std::io::Error::other(e)))??;
+        for (size, data) in &photo.renditions {
+            if let Err(e) = store
+                .put(&photo::blob_key(&blob_prefix, *size), data.clone())
+                .await
+            {
+                discard_renditions(&store, &blob_prefix).await;
+                return Err(e);
+            }
+        }
+
+        match repo
+            .save_photo(address_book_id, contact_id, photo, actor)
+            .await
+        {
+            Ok((saved, replaced)) => {
+                if let Some(replaced) = replaced {
+                    discard_renditions(&store, &replaced.blob_prefix).await;
+                }
+                Ok(saved)
+            }
+            Err(e) => {
+                discard_renditions(&store, &blob_prefix).await;
+                Err(e)
+            }
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, %contact_id))]
+    pub async fn delete_photo<R: IPhotoRepository, B: IBlobStore>(
+        repo: R,
+        store: B,
+        address_book_id: i32,
+        contact_id: i32,
+        actor: Option<String>,
+    ) -> Result<(), handle_errors::Error> {
+        let deleted = repo
+            .delete_photo(address_book_id, contact_id, actor)
+            .await?;
+        discard_renditions(&store, &deleted.blob_prefix).await;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::blob_store::MockIBlobStore;
+    use crate::repositories::photo_repo::MockIPhotoRepository;
+    use crate::types::contact::ContactId;
+    use chrono::Utc;
+    use image::RgbaImage;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let image = DynamicImage::ImageRgba8(RgbaImage::new(width, height));
+        encode(&image, ImageFormat::Png).unwrap()
+    }
+
+    fn repo_without_photo() -> MockIPhotoRepository {
+        let mut repo = MockIPhotoRepository::new();
+        repo.expect_get_photo()
+            .returning(|_, _| Box::pin(async { Err(handle_errors::Error::PhotoNotFound) }));
+        repo
+    }
+
+    fn png_type() -> String {
+        String::from("image/png")
+    }
+
+    fn saved(contact_id: i32, photo: NewPhoto) -> Photo {
+        Photo {
+            contact_id: ContactId(contact_id),
+            byte_size: photo.byte_size(),
+            content_type: photo.content_type,
+            width: photo.width,
+            height: photo.height,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            created_by: None,
+            updated_by: None,
+            version: 1,
+            blob_prefix: photo.blob_prefix,
+        }
+    }
+
+    fn dimensions(data: &[u8]) -> (u32, u32) {
+        let image = image::load_from_memory(data).unwrap();
+        (image.width(), image.height())
+    }
+
+    #[tokio::test]
+    async fn test_put_photo_stores_thumbnails() {
+        let mut repo = repo_without_photo();
+        repo.expect_save_photo()
+            .withf(|_, _, photo, _| {
+                let sizes: Vec<_> = photo.renditions.iter().map(|(size, _)| *size).collect();
+                photo.content_type == "image/png"
+                    && (photo.width, photo.height) == (600, 300)
+                    && sizes == PhotoSize::ALL
+                    && dimensions(&photo.renditions[0].1) == (64, 32)
+                    && dimensions(&photo.renditions[1].1) == (256, 128)
+            })
+            .once()
+            .returning(|_, contact_id, photo, _| {
+                Box::pin(async move { Ok((saved(contact_id, photo), None)) })
+            });
+        let mut store = MockIBlobStore::new();
+        store
+            .expect_put()
+            .withf(|key, _| key.starts_with("contacts/7/photos/"))
+            .times(3)
+            .returning(|_, _| Box::pin(async { Ok(()) }));
+        store.expect_delete().never();
+
+        let data = png(600, 300);
+        let photo = PhotoService::put_photo(repo, store, 1, 7, png_type(), data.clone(), None)
+            .await
+            .unwrap();
+
+        assert_eq!(photo.byte_size, data.len() as i32);
+        assert!(photo.blob_prefix.starts_with("contacts/7/photos/"));
+    }
+
+    #[tokio::test]
+    async fn test_put_photo_discards_replaced_renditions() {
+        let mut repo = repo_without_photo();
+        repo.expect_save_photo()
+            .once()
+            .returning(|_, contact_id, photo, _| {
+                Box::pin(async move {
+                    let mut replaced = saved(contact_id, photo.clone());
+                    replaced.blob_prefix = String::from("contacts/7/photos/old");
+                    Ok((saved(contact_id, photo), Some(replaced)))
+                })
+            });
+        let mut store = MockIBlobStore::new();
+        store
+            .expect_put()
+            .times(3)
+            .returning(|_, _| Box::pin(async { Ok(()) }));
+        store
+            .expect_delete()
+            .withf(|key| key.starts_with("contacts/7/photos/old/"))
+            .times(3)
+            .returning(|_| Box::pin(async { Ok(()) }));
+
+        PhotoService::put_photo(repo, store, 1, 7, png_type(), png(10, 10), None)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_put_photo_discards_renditions_when_save_fails() {
+        let mut repo = repo_without_photo();
+        repo.expect_save_photo()
+            .once()
+            .returning(|_, _, _, _| Box::pin(async { Err(handle_errors::Error::ContactNotFound) }));
+        let mut store = MockIBlobStore::new();
+        store
+            .expect_put()
+            .times(3)
+            .returning(|_, _| Box::pin(async { Ok(()) }));
+        store
+            .expect_delete()
+            .withf(|key| key.starts_with("contacts/7/photos/"))
+            .times(3)
+            .returning(|_| Box::pin(async { Ok(()) }));
+
+        let result =
+            PhotoService::put_photo(repo, store, 1, 7, png_type(), png(10, 10), None).await;
+        assert!(matches!(result, Err(handle_errors::Error::ContactNotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_put_photo_rejects_mismatched_content_type() {
+        let mut store = MockIBlobStore::new();
+        store.expect_put().never();
+        let mut repo = repo_without_photo();
+        repo.expect_save_photo().never();
+
+        let result = PhotoService::put_photo(
+            repo,
+            store,
+            1,
+            7,
+            String::from("image/jpeg"),
+            png(10, 10),
+            None,
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(handle_errors::Error::UnsupportedMediaType)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_put_photo_rejects_invalid_uploads() {
+        let mut store = MockIBlobStore::new();
+        store.expect_put().never();
+
+        let mut repo = repo_without_photo();
+        repo.expect_save_photo().never();
+        let result = PhotoService::put_photo(
+            repo,
+            store,
+            1,
+            7,
+            png_type(),
+            b"not an image".to_vec(),
+            None,
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(handle_errors::Error::ValidationError(_))
+        ));
+
+        let mut store = MockIBlobStore::new();
+        store.expect_put().never();
+        let mut repo = MockIPhotoRepository::new();
+        repo.expect_get_photo().never();
+        let oversized = vec![0; MAX_PHOTO_BYTES + 1];
+        let result = PhotoService::put_photo(repo, store, 1, 7, png_type(), oversized, None).await;
+        assert!(matches!(
+            result,
+            Err(handle_errors::Error::PayloadTooLarge(_))
+        ));
+    }
+}
@@ -0,0 +1,60 @@
+use crate::repositories::rate_limit_store::IRateLimitStore;
+use crate::types::rate_limit::{Decision, RateLimit, REFILL_WINDOW_SECS};
+use chrono::{Duration, Utc};
+pub struct RateLimitService {}
+
+impl RateLimitService {
+    /// Counts a request against the bucket named `key`. When the store fails the request is
+    /// let through: an unavailable limiter should not take the API down with it.
+    #[tracing::instrument(skip_all)]
+    pub async fn take<T: IRateLimitStore + ?Sized>(
+        store: &T,
+        key: &str,
+        limit: RateLimit,
+    ) -> Decision {
+        store
+            .take(key, limit, Utc::now())
+            .await
+            .unwrap_or_else(|e| {
+                tracing::warn!(error = %e, "rate limit not checked");
+                Decision::unchecked(limit)
+            })
+    }
+
+    /// Forgets buckets that have had time to fill up again.
+    #[tracing::instrument(skip_all)]
+    pub async fn prune_idle<T: IRateLimitStore + ?Sized>(
+        store: &T,
+    ) -> Result<u64, handle_errors::Error> {
+        store
+            .prune(Utc::now() - Duration::seconds(REFILL_WINDOW_SECS))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::rate_limit_store::MockIRateLimitStore;
+
+    #[tokio::test]
+    async fn test_take_lets_requests_through_when_store_fails() {
+        let mut store = MockIRateLimitStore::new();
+        store
+            .expect_take()
+            .withf(|key, limit, _| key == "write:ip:127.0.0.1" && limit.per_minute == 5)
+            .once()
+            .returning(|_, _, _| {
+                Box::pin(async {
+                    Err(handle_errors::Error::DatabaseQueryError(
+                        sqlx::Error::PoolTimedOut,
+                    ))
+                })
+            });
+
+        let decision =
+            RateLimitService::take(&store, "write:ip:127.0.0.1", RateLimit { per_minute: 5 }).await;
+        assert!(decision.allowed);
+        assert_eq!(decision.remaining, 5);
+    }
+}
@@ -0,0 +1,68 @@
+use crate::repositories::blob_store::IBlobStore;
+use crate::repositories::trash_repo::ITrashRepository;
+use crate::services::photo_service::discard_renditions;
+use crate::types::trash::Trash;
+use chrono::{Duration, Utc};
+pub struct TrashService {}
+
+impl TrashService {
+    #[tracing::instrument(skip_all)]
+    pub async fn get_trash<T: ITrashRepository>(
+        repo: T,
+        limit: Option<i32>,
+        offset: i32,
+    ) -> Result<Trash, handle_errors::Error> {
+        repo.get_trash(limit, offset).await
+    }
+
+    /// Purges everything that has been in the trash for longer than `retention`, along with
+    /// the stored photos of the purged contacts. Returns the number of rows removed.
+    #[tracing::instrument(skip_all)]
+    pub async fn purge_expired<T: ITrashRepository, B: IBlobStore>(
+        repo: T,
+        store: B,
+        retention: Duration,
+    ) -> Result<u64, handle_errors::Error> {
+        let purged = repo.purge_deleted(Utc::now() - retention).await?;
+        for prefix in &purged.photo_blob_prefixes {
+            discard_renditions(&store, prefix).await;
+        }
+        Ok(purged.rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::blob_store::MockIBlobStore;
+    use crate::repositories::trash_repo::MockITrashRepository;
+    use crate::types::trash::Purged;
+
+    #[tokio::test]
+    async fn test_purge_expired_uses_retention_cutoff() {
+        let mut repo = MockITrashRepository::new();
+        let retention = Duration::days(30);
+        let expected = Utc::now() - retention;
+
+        repo.expect_purge_deleted()
+            .withf(move |deleted_before| (*deleted_before - expected).num_seconds().abs() < 5)
+            .once()
+            .returning(|_| {
+                Box::pin(async {
+                    Ok(Purged {
+                        rows: 3,
+                        photo_blob_prefixes: vec![String::from("contacts/7/photos/a")],
+                    })
+                })
+            });
+        let mut store = MockIBlobStore::new();
+        store
+            .expect_delete()
+            .withf(|key| key.starts_with("contacts/7/photos/a/"))
+            .times(3)
+            .returning(|_| Box::pin(async { Ok(()) }));
+
+        let result = TrashService::purge_expired(repo, store, retention).await;
+        assert_eq!(result.unwrap(), 3);
+    }
+}
@@ -0,0 +1,327 @@
+use crate::repositories::webhook_repo::IWebhookRepository;
+use crate::repositories::webhook_sender::IWebhookSender;
+use crate::types::precondition::IfMatch;
+use crate::types::webhook::{
+    retry_delay, signature, DeliveryAttempt, DeliveryOutcome, NewWebhook, PendingDelivery, Webhook,
+    WebhookDelivery, MAX_ATTEMPTS, MAX_LOGGED_RESPONSE_CHARS,
+};
+use chrono::{Duration, Utc};
+use std::time::Instant;
+pub struct WebhookService {}
+
+/// Deliveries claimed per dispatcher run. They are sent one after the other, so together
+/// they must fit in the claim lease even when every receiver times out.
+const DISPATCH_BATCH: i64 = 20;
+const CLAIM_LEASE_SECS: i64 = 5 * 60;
+
+impl WebhookService {
+    #[tracing::instrument(skip_all, fields(%address_book_id))]
+    pub async fn get_webhooks<T: IWebhookRepository>(
+        repo: T,
+        address_book_id: i32,
+    ) -> Result<Vec<Webhook>, handle_errors::Error> {
+        repo.get_webhooks(address_book_id).await
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, webhook_id = %id))]
+    pub async fn get_webhook<T: IWebhookRepository>(
+        repo: T,
+        address_book_id: i32,
+        id: i32,
+    ) -> Result<Webhook, handle_errors::Error> {
+        repo.get_webhook(address_book_id, id).await
+    }
+
+    /// Subscribes `webhook`, which must point at an address `sender` may deliver to.
+    #[tracing::instrument(skip_all, fields(%address_book_id))]
+    pub async fn create_webhook<T: IWebhookRepository, S: IWebhookSender>(
+        repo: T,
+        sender: &S,
+        address_book_id: i32,
+        webhook: NewWebhook,
+        actor: Option<String>,
+    ) -> Result<Webhook, handle_errors::Error> {
+        let webhook = validate(sender, webhook).await?;
+        repo.create_webhook(address_book_id, webhook, actor).await
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, webhook_id = %id))]
+    pub async fn update_webhook<T: IWebhookRepository, S: IWebhookSender>(
+        repo: T,
+        sender: &S,
+        address_book_id: i32,
+        id: i32,
+        webhook: NewWebhook,
+        actor: Option<String>,
+        if_match: IfMatch,
+    ) -> Result<Webhook, handle_errors::Error> {
+        let webhook = validate(sender, webhook).await?;
+        repo.update_webhook(address_book_id, id, webhook, actor, if_match)
+            .await
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, webhook_id = %id))]
+    pub async fn delete_webhook<T: IWebhookRepository>(
+        repo: T,
+        address_book_id: i32,
+        id: i32,
+        if_match: IfMatch,
+    ) -> Result<(), handle_errors::Error> {
+        repo.delete_webhook(address_book_id, id, if_match).await
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, %webhook_id))]
+    pub async fn get_deliveries<T: IWebhookRepository>(
+        repo: T,
+        address_book_id: i32,
+        webhook_id: i32,
+        limit: Option<i32>,
+        offset: i32,
+    ) -> Result<Vec<WebhookDelivery>, handle_errors::Error> {
+        repo.get_deliveries(address_book_id, webhook_id, limit, offset)
+            .await
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, %webhook_id, delivery_id = %id))]
+    pub async fn get_delivery<T: IWebhookRepository>(
+        repo: T,
+        address_book_id: i32,
+        webhook_id: i32,
+        id: i64,
+    ) -> Result<WebhookDelivery, handle_errors::Error> {
+        repo.get_delivery(address_book_id, webhook_id, id).await
+    }
+
+    #[tracing::instrument(skip_all, fields(%address_book_id, %webhook_id, delivery_id = %id))]
+    pub async fn redeliver<T: IWebhookRepository>(
+        repo: T,
+        address_book_id: i32,
+        webhook_id: i32,
+        id: i64,
+    ) -> Result<WebhookDelivery, handle_errors::Error> {
+        repo.redeliver(address_book_id, webhook_id, id).await
+    }
+
+    /// Makes one attempt at every due delivery and returns how many were attempted.
+    #[tracing::instrument(skip_all)]
+    pub async fn dispatch_due<T: IWebhookRepository, S: IWebhookSender>(
+        repo: T,
+        sender: &S,
+    ) -> Result<usize, handle_errors::Error> {
+        let deliveries = repo
+            .claim_due_deliveries(DISPATCH_BATCH, Duration::seconds(CLAIM_LEASE_SECS))
+            .await?;
+        let attempted = deliveries.len();
+
+        for delivery in deliveries {
+            let (attempt, outcome) = send(sender, &delivery).await;
+            repo.record_attempt(delivery.id, attempt, outcome).await?;
+        }
+        Ok(attempted)
+    }
+}
+
+/// Validates `webhook` and checks that its receiver is one deliveries may go to.
+async fn validate<S: IWebhookSender>(
+    sender: &S,
+    webhook: NewWebhook,
+) -> Result<NewWebhook, handle_errors::Error> {
+    let webhook = webhook.validate()?;
+    match sender.check_target(&webhook.url).await {
+        Ok(()) => Ok(webhook),
+        Err(e) => Err(handle_errors::Error::ValidationError(format!(
+            "url cannot receive deliveries: {e}"
+        ))),
+    }
+}
+
+/// Posts a signed delivery. A 2xx answer delivers it; anything else is retried with
+/// exponential backoff until `MAX_ATTEMPTS` is reached.
+async fn send<S: IWebhookSender>(
+    sender: &S,
+    delivery: &PendingDelivery,
+) -> (DeliveryAttempt, DeliveryOutcome) {
+    let body = delivery.body();
+    let attempted_at = Utc::now();
+    let timestamp = attempted_at.timestamp();
+    let headers = vec![
+        (String::from("Webhook-Id"), delivery.id.to_string()),
+        (String::from("Webhook-Event"), delivery.event_type.clone()),
+        (String::from("Webhook-Timestamp"), timestamp.to_string()),
+        (
+            String::from("Webhook-Signature"),
+            format!("sha256={}", signature(&delivery.secret, timestamp, &body)),
+        ),
+    ];
+
+    let started = Instant::now();
+    let result = sender.post(&delivery.url, headers, body).await;
+    let duration_ms = started.elapsed().as_millis().min(i32::MAX as u128) as i32;
+
+    let number = delivery.attempts + 1;
+    let mut attempt = DeliveryAttempt {
+        attempt: number,
+        attempted_at,
+        status_code: None,
+        error: None,
+        response_body: None,
+        duration_ms,
+    };
+    let delivered = match result {
+        Ok(response) => {
+            attempt.status_code = Some(i32::from(response.status));
+            attempt.response_body = Some(response.body)
+                .filter(|body| !body.is_empty())
+                .map(|body| body.chars().take(MAX_LOGGED_RESPONSE_CHARS).collect());
+            (200..300).contains(&response.status)
+        }
+        Err(e) => {
+            attempt.error = Some(e);
+            false
+        }
+    };
+
+    let outcome = if delivered {
+        DeliveryOutcome::Delivered
+    } else if number >= MAX_ATTEMPTS {
+        DeliveryOutcome::Failed
+    } else {
+        DeliveryOutcome::Retry(Utc::now() + retry_delay(number))
+    };
+    (attempt, outcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::webhook_repo::MockIWebhookRepository;
+    use crate::repositories::webhook_sender::{MockIWebhookSender, WebhookResponse};
+    use serde_json::json;
+
+    fn pending(attempts: i32) -> PendingDelivery {
+        PendingDelivery {
+            id: 7,
+            url: String::from("http://localhost:9000/hook"),
+            secret: String::from("s3cret"),
+            event_type: String::from("contact.created"),
+            payload: json!({ "entity_id": 1 }),
+            created_at: Utc::now(),
+            attempts,
+        }
+    }
+
+    fn claiming(repo: &mut MockIWebhookRepository, delivery: PendingDelivery) {
+        repo.expect_claim_due_deliveries()
+            .once()
+            .returning(move |_, _| {
+                let deliveries = vec![delivery.clone()];
+                Box::pin(async move { Ok(deliveries) })
+            });
+    }
+
+    fn header<'a>(headers: &'a [(String, String)], name: &str) -> &'a str {
+        headers
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_due_signs_and_delivers() {
+        let mut repo = MockIWebhookRepository::new();
+        let mut sender = MockIWebhookSender::new();
+        claiming(&mut repo, pending(0));
+
+        sender
+            .expect_post()
+            .withf(|url, headers, body| {
+                let timestamp: i64 = header(headers, "Webhook-Timestamp").parse().unwrap();
+                let expected = format!("sha256={}", signature("s3cret", timestamp, body));
+                url == "http://localhost:9000/hook"
+                    && header(headers, "Webhook-Id") == "7"
+                    && header(headers, "Webhook-Signature") == expected
+            })
+            .once()
+            .returning(|_, _, _| {
+                Box::pin(async {
+                    Ok(WebhookResponse {
+                        status: 204,
+                        body: String::new(),
+                    })
+                })
+            });
+        repo.expect_record_attempt()
+            .withf(|id, attempt, outcome| {
+                *id == 7
+                    && attempt.attempt == 1
+                    && attempt.status_code == Some(204)
+                    && *outcome == DeliveryOutcome::Delivered
+            })
+            .once()
+            .returning(|_, _, _| Box::pin(async { Ok(()) }));
+
+        let attempted = WebhookService::dispatch_due(repo, &sender).await;
+        assert_eq!(attempted.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_due_backs_off_then_gives_up() {
+        for (attempts, retried) in [(2, true), (MAX_ATTEMPTS - 1, false)] {
+            let mut repo = MockIWebhookRepository::new();
+            let mut sender = MockIWebhookSender::new();
+            claiming(&mut repo, pending(attempts));
+
+            sender.expect_post().once().returning(|_, _, _| {
+                Box::pin(async {
+                    Ok(WebhookResponse {
+                        status: 500,
+                        body: String::from("boom"),
+                    })
+                })
+            });
+            repo.expect_record_attempt()
+                .withf(move |_, attempt, outcome| {
+                    let expected = Utc::now() + retry_delay(attempts + 1);
+                    let outcome_ok = match outcome {
+                        DeliveryOutcome::Retry(at) => {
+                            retried && (*at - expected).num_seconds().abs() < 5
+                        }
+                        DeliveryOutcome::Failed => !retried,
+                        DeliveryOutcome::Delivered => false,
+                    };
+                    attempt.attempt == attempts + 1
+                        && attempt.response_body.as_deref() == Some("boom")
+                        && outcome_ok
+                })
+                .once()
+                .returning(|_, _, _| Box::pin(async { Ok(()) }));
+
+            WebhookService::dispatch_due(repo, &sender).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_webhook_refuses_internal_receivers() {
+        let mut repo = MockIWebhookRepository::new();
+        repo.expect_create_webhook().never();
+        let mut sender = MockIWebhookSender::new();
+        sender
+            .expect_check_target()
+            .withf(|url| url == "http://localhost:9000/hook")
+            .once()
+            .returning(|_| Box::pin(async { Err(String::from("not a public address")) }));
+        let webhook = NewWebhook {
+            url: String::from("http://localhost:9000/hook"),
+            secret: String::from("s3cret"),
+            event_types: vec![String::from("contact.created")],
+            active: true,
+        };
+
+        let result = WebhookService::create_webhook(repo, &sender, 1, webhook, None).await;
+        assert!(matches!(
+            result,
+            Err(handle_errors::Error::ValidationError(_))
+        ));
+    }
+}
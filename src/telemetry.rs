@@ -0,0 +1,267 @@
+use axum::body::Body;
+use axum::extract::MatchedPath;
+use axum::http::{HeaderMap, Request, Response};
+use axum::middleware::Next;
+use opentelemetry::propagation::Extractor;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use std::time::Duration;
+use tracing::{field, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+use crate::config::{Config, LogFormat};
+use crate::monitoring::{repository_spans, QueryMetricsLayer};
+
+const SERVICE_NAME: &str = env!("CARGO_PKG_NAME");
+
+/// Header carrying the id that ties a request's log lines together. A client may send its own;
+/// otherwise one is generated. Either way it is echoed on the response.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longest request id taken from a client. Generated ids are UUIDs, 36 characters long.
+const MAX_REQUEST_ID_LEN: usize = 64;
+
+/// Keeps spans exported while it lives. Dropping it sends the spans still buffered and stops
+/// the export, so the last requests before a shutdown are not lost.
+#[derive(Debug, Default)]
+#[must_use]
+pub struct Telemetry(Option<SdkTracerProvider>);
+
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        if let Some(provider) = self.0.take() {
+            if let Err(e) = provider.shutdown() {
+                tracing::warn!(error = %e, "buffered spans could not be exported");
+            }
+        }
+    }
+}
+
+/// Installs the global subscriber: log lines on stdout, query latency metrics and, when an
+/// OTLP endpoint is configured, spans exported to it. The log filter applies to logs and
+/// exported spans but not to the metrics.
+pub fn init(config: &Config) -> Telemetry {
+    let filter =
+        || EnvFilter::try_new(&config.log_filter).unwrap_or_else(|_| EnvFilter::new("info"));
+    let logs = match config.log_format {
+        LogFormat::Json => tracing_subscriber::fmt::layer()
+            .json()
+            .flatten_event(true)
+            .with_current_span(false)
+            .with_span_list(true)
+            .boxed(),
+        LogFormat::Text => tracing_subscriber::fmt::layer().boxed(),
+    };
+
+    let mut export_error = None;
+    let mut telemetry = Telemetry::default();
+    let traces = match config.otlp_endpoint.as_deref().map(tracer_provider) {
+        Some(Ok(provider)) => {
+            let tracer = provider.tracer(SERVICE_NAME);
+            opentelemetry::global::set_tracer_provider(provider.clone());
+            telemetry = Telemetry(Some(provider));
+            Some(tracing_opentelemetry::layer().with_tracer(tracer))
+        }
+        Some(Err(e)) => {
+            export_error = Some(e);
+            None
+        }
+        None => None,
+    };
+    opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+
+    // Fails only when a subscriber is already installed, which then keeps logging.
+    let _ = tracing_subscriber::registry()
+        .with(logs.with_filter(filter()))
+        .with(traces.with_filter(filter()))
+        .with(QueryMetricsLayer.with_filter(repository_spans()))
+        .try_init();
+    if let Some(e) = export_error {
+        tracing::error!(error = %e, "traces are not exported");
+    }
+    telemetry
+}
+
+/// Exports spans in batches over OTLP/HTTP to `endpoint`, e.g. `http://localhost:4318`.
+fn tracer_provider(endpoint: &str) -> Result<SdkTracerProvider, String> {
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+        .build()
+        .map_err(|e| e.to_string())?;
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name(SERVICE_NAME).build())
+        .build())
+}
+
+/// The span a request is handled in. It continues the trace named in a W3C `traceparent`
+/// header, so the service shows up in its callers' traces.
+pub fn request_span<B>(request: &Request<B>) -> Span {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(MatchedPath::as_str)
+        .unwrap_or_default();
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let span = tracing::info_span!(
+        "request",
+        method = %request.method(),
+        route,
+        path = request.uri().path(),
+        request_id,
+        status = field::Empty,
+    );
+
+    let parent = opentelemetry::global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HeaderExtractor(request.headers()))
+    });
+    // Without a subscriber exporting traces there is nothing to attach the parent to.
+    let _ = span.set_parent(parent);
+    span
+}
+
+/// Drops a client's request id that is longer than `MAX_REQUEST_ID_LEN` or has characters
+/// other than ASCII letters, digits, `-`, `_` and `.`, so one is generated in its place. Ids
+/// end up in every log line of the request and in exported spans.
+pub async fn check_request_id(mut request: Request<Body>, next: Next) -> Response<Body> {
+    let valid = request
+        .headers()
+        .get_all(REQUEST_ID_HEADER)
+        .iter()
+        .all(|value| {
+            let id = value.as_bytes();
+            (1..=MAX_REQUEST_ID_LEN).contains(&id.len())
+                && id
+                    .iter()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, b'-' | b'_' | b'.'))
+        });
+    if !valid {
+        request.headers_mut().remove(REQUEST_ID_HEADER);
+    }
+    next.run(request).await
+}
+
+/// Logs the outcome of a request once its response starts.
+pub fn record_response<B>(response: &Response<B>, latency: Duration, span: &Span) {
+    let status = response.status().as_u16();
+    span.record("status", status);
+    tracing::info!(
+        status,
+        latency_ms = latency.as_millis() as u64,
+        "request finished"
+    );
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|name| name.as_str()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+    use opentelemetry::propagation::TextMapPropagator;
+    use opentelemetry::trace::{TraceContextExt, Tracer};
+    use opentelemetry_sdk::error::OTelSdkResult;
+    use opentelemetry_sdk::trace::{SpanData, SpanExporter};
+    use sqlx::PgPool;
+    use std::sync::{Arc, Mutex};
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_traceparent_is_extracted() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "traceparent",
+            HeaderValue::from_static("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"),
+        );
+
+        let context = TraceContextPropagator::new().extract(&HeaderExtractor(&headers));
+        let span = context.span();
+        assert_eq!(
+            span.span_context().trace_id().to_string(),
+            "4bf92f3577b34da6a3ce929d0e0e4736"
+        );
+        assert!(span.span_context().is_remote());
+    }
+
+    /// Keeps the names of the spans it is given.
+    #[derive(Debug, Clone, Default)]
+    struct RecordingExporter(Arc<Mutex<Vec<String>>>);
+
+    impl SpanExporter for RecordingExporter {
+        async fn export(&self, batch: Vec<SpanData>) -> OTelSdkResult {
+            let mut names = self.0.lock().unwrap();
+            names.extend(batch.into_iter().map(|span| span.name.into_owned()));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_dropping_telemetry_exports_buffered_spans() {
+        let exporter = RecordingExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_batch_exporter(exporter.clone())
+            .build();
+        provider.tracer(SERVICE_NAME).in_span("request", |_| {});
+        // The batch is only sent on a timer, which has not fired yet.
+        assert!(exporter.0.lock().unwrap().is_empty());
+
+        drop(Telemetry(Some(provider)));
+        assert_eq!(*exporter.0.lock().unwrap(), ["request"]);
+    }
+
+    /// The `X-Request-Id` of `app()`'s answer to a request carrying `request_id`, if any.
+    async fn echoed_request_id(request_id: Option<&str>) -> String {
+        let pool = PgPool::connect_lazy("postgres://localhost/unused").unwrap();
+        let mut request = Request::builder().uri("/api/openapi.json");
+        if let Some(request_id) = request_id {
+            request = request.header(REQUEST_ID_HEADER, request_id);
+        }
+        let response = crate::app(crate::tests::state(pool))
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        response.headers()[REQUEST_ID_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[tokio::test]
+    async fn test_request_id_is_echoed_or_generated() {
+        assert_eq!(echoed_request_id(Some("abc-123.x_y")).await, "abc-123.x_y");
+
+        let generated = echoed_request_id(None).await;
+        assert!(uuid::Uuid::parse_str(&generated).is_ok(), "{generated}");
+
+        let long = "a".repeat(MAX_REQUEST_ID_LEN + 1);
+        for invalid in [long.as_str(), "id with spaces", "id\"<x>", ""] {
+            let replaced = echoed_request_id(Some(invalid)).await;
+            assert!(uuid::Uuid::parse_str(&replaced).is_ok(), "{invalid} kept");
+        }
+        assert_eq!(
+            echoed_request_id(Some(&long[1..])).await.len(),
+            MAX_REQUEST_ID_LEN
+        );
+    }
+}
@@ -1,17 +1,164 @@
 use crate::types::contact::Contact;
+use crate::types::{split_list, SortField};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct AddressBook {
     pub id: AddressBookId,
     pub address_book_name: String,
-    pub contacts: Vec<Contact>,
+    /// The book's live contacts, when the read asked for them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contacts: Option<Vec<Contact>>,
+    /// Number of live contacts, when the read asked for it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contact_count: Option<i64>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub created_by: Option<String>,
+    pub updated_by: Option<String>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub version: i32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, Hash)]
+impl AddressBook {
+    /// Strong entity tag for the book; its version also moves when any of its contacts change.
+    pub fn etag(&self) -> String {
+        format!("\"{}\"", self.version)
+    }
+}
+
+/// Keys accepted by `?sort=` on the address book listing.
+pub const SORT_FIELDS: [SortField; 3] = [
+    SortField::text("name", "address_book_name"),
+    SortField::new("created_at", "created_at"),
+    SortField::new("updated_at", "updated_at"),
+];
+
+/// Book attributes that `?fields=` can select.
+pub const FIELDS: [&str; 8] = [
+    "id",
+    "address_book_name",
+    "created_at",
+    "updated_at",
+    "created_by",
+    "updated_by",
+    "deleted_at",
+    "version",
+];
+
+/// Raw `?fields=` and `?include=` options of a book read.
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ViewParams {
+    /// Comma-separated attributes to return; `id` is always included.
+    pub fields: Option<String>,
+    /// `contacts` and/or `contact_count`. Without it, contacts are embedded.
+    pub include: Option<String>,
+}
+
+/// What a book read selects and serializes. Without `?include=`, contacts are embedded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AddressBookView {
+    /// Attributes to serialize, or `None` for all of them. `id` is always kept.
+    pub fields: Option<Vec<String>>,
+    pub contacts: bool,
+    pub contact_count: bool,
+}
+
+impl Default for AddressBookView {
+    fn default() -> Self {
+        Self {
+            fields: None,
+            contacts: true,
+            contact_count: false,
+        }
+    }
+}
+
+impl ViewParams {
+    pub fn view(self) -> Result<AddressBookView, handle_errors::Error> {
+        let mut view = AddressBookView::default();
+
+        if let Some(fields) = self.fields {
+            let mut selected = vec![String::from("id")];
+            for field in split_list(&fields) {
+                if !FIELDS.contains(&field) {
+                    return Err(handle_errors::Error::ValidationError(format!(
+                        "unknown field \"{field}\", expected one of {}",
+                        FIELDS.join(", ")
+                    )));
+                }
+                if !selected.iter().any(|selected| selected == field) {
+                    selected.push(field.to_string());
+                }
+            }
+            view.fields = Some(selected);
+        }
+
+        if let Some(include) = self.include {
+            view.contacts = false;
+            for include in split_list(&include) {
+                match include {
+                    "contacts" => view.contacts = true,
+                    "contact_count" => view.contact_count = true,
+                    _ => {
+                        return Err(handle_errors::Error::ValidationError(format!(
+                            "cannot include \"{include}\", expected contacts or contact_count"
+                        )))
+                    }
+                }
+            }
+        }
+        Ok(view)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, Hash, ToSchema)]
 pub struct AddressBookId(pub i32);
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct NewAddressBook {
     pub address_book_name: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_view_params() {
+        let params = ViewParams {
+            fields: Some(String::from("address_book_name, version")),
+            include: Some(String::from("contact_count")),
+        };
+        assert_eq!(
+            params.view().unwrap(),
+            AddressBookView {
+                fields: Some(vec![
+                    String::from("id"),
+                    String::from("address_book_name"),
+                    String::from("version"),
+                ]),
+                contacts: false,
+                contact_count: true,
+            }
+        );
+        assert_eq!(
+            ViewParams::default().view().unwrap(),
+            AddressBookView::default()
+        );
+
+        let params = ViewParams {
+            fields: Some(String::from("contacts")),
+            include: None,
+        };
+        assert!(params.view().is_err());
+        let params = ViewParams {
+            fields: None,
+            include: Some(String::from("groups")),
+        };
+        assert!(params.view().is_err());
+    }
+}
@@ -0,0 +1,244 @@
+use crate::types::address_book::{AddressBook, AddressBookId};
+use crate::types::contact::Contact;
+use crate::types::custom_field::CustomField;
+use crate::types::group::ContactGroup;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use utoipa::ToSchema;
+
+/// Fields that change on every write and would only add noise to a diff.
+const IGNORED_FIELDS: [&str; 3] = ["updated_at", "version", "contacts"];
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AuditEvent {
+    pub id: i64,
+    pub entity_type: String,
+    pub entity_id: i32,
+    pub address_book_id: AddressBookId,
+    pub action: String,
+    pub actor: Option<String>,
+    #[schema(value_type = Object)]
+    pub changes: Value,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EntityType {
+    AddressBook,
+    Contact,
+    Group,
+    CustomField,
+}
+
+impl EntityType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EntityType::AddressBook => "address_book",
+            EntityType::Contact => "contact",
+            EntityType::Group => "group",
+            EntityType::CustomField => "custom_field",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AuditAction {
+    Create,
+    Update,
+    Delete,
+    Restore,
+    Move,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::Create => "create",
+            AuditAction::Update => "update",
+            AuditAction::Delete => "delete",
+            AuditAction::Restore => "restore",
+            AuditAction::Move => "move",
+        }
+    }
+}
+
+/// An audit event about to be written alongside the mutation it describes.
+#[derive(Debug, Clone)]
+pub struct NewAuditEvent {
+    pub entity_type: EntityType,
+    pub entity_id: i32,
+    pub address_book_id: i32,
+    /// The book a moved contact left, which keeps the move in its history.
+    pub source_address_book_id: Option<i32>,
+    pub action: AuditAction,
+    pub actor: Option<String>,
+    pub changes: Value,
+}
+
+impl NewAuditEvent {
+    pub fn address_book(
+        action: AuditAction,
+        actor: Option<String>,
+        before: Option<&AddressBook>,
+        after: &AddressBook,
+    ) -> Self {
+        Self {
+            entity_type: EntityType::AddressBook,
+            entity_id: after.id.0,
+            address_book_id: after.id.0,
+            source_address_book_id: None,
+            action,
+            actor,
+            changes: diff(before.map(to_value), to_value(after)),
+        }
+    }
+
+    pub fn contact(
+        action: AuditAction,
+        actor: Option<String>,
+        before: Option<&Contact>,
+        after: &Contact,
+    ) -> Self {
+        Self {
+            entity_type: EntityType::Contact,
+            entity_id: after.id.0,
+            address_book_id: after.address_book_id.0,
+            source_address_book_id: before
+                .map(|before| before.address_book_id.0)
+                .filter(|source| *source != after.address_book_id.0),
+            action,
+            actor,
+            changes: diff(before.map(to_value), to_value(after)),
+        }
+    }
+
+    /// A live contact removed for good together with its book, by no one in particular.
+    pub fn purged_contact(contact: &Contact) -> Self {
+        Self {
+            entity_type: EntityType::Contact,
+            entity_id: contact.id.0,
+            address_book_id: contact.address_book_id.0,
+            source_address_book_id: None,
+            action: AuditAction::Delete,
+            actor: None,
+            changes: hard_delete_diff(AuditAction::Delete, None, contact),
+        }
+    }
+
+    pub fn group(
+        action: AuditAction,
+        actor: Option<String>,
+        before: Option<&ContactGroup>,
+        after: &ContactGroup,
+    ) -> Self {
+        Self {
+            entity_type: EntityType::Group,
+            entity_id: after.id.0,
+            address_book_id: after.address_book_id.0,
+            source_address_book_id: None,
+            action,
+            actor,
+            changes: hard_delete_diff(action, before, after),
+        }
+    }
+
+    pub fn custom_field(
+        action: AuditAction,
+        actor: Option<String>,
+        before: Option<&CustomField>,
+        after: &CustomField,
+    ) -> Self {
+        Self {
+            entity_type: EntityType::CustomField,
+            entity_id: after.id.0,
+            address_book_id: after.address_book_id.0,
+            source_address_book_id: None,
+            action,
+            actor,
+            changes: hard_delete_diff(action, before, after),
+        }
+    }
+}
+
+/// For entities that are deleted outright, a delete records the full final state as `before`.
+fn hard_delete_diff<T: Serialize>(action: AuditAction, before: Option<&T>, after: &T) -> Value {
+    match action {
+        AuditAction::Delete => {
+            json!({ "before": diff(None, to_value(after))["after"], "after": null })
+        }
+        _ => diff(before.map(to_value), to_value(after)),
+    }
+}
+
+fn to_value<T: Serialize>(entity: &T) -> Value {
+    serde_json::to_value(entity).unwrap_or(Value::Null)
+}
+
+/// Builds `{"before": {...}, "after": {...}}` holding only the fields that changed.
+/// Without a `before` (a create) the full new state is recorded.
+fn diff(before: Option<Value>, after: Value) -> Value {
+    let after = match after {
+        Value::Object(after) => after,
+        other => return json!({ "before": before, "after": other }),
+    };
+    let before = match before {
+        Some(Value::Object(before)) => before,
+        _ => {
+            let after: Map<String, Value> = after
+                .into_iter()
+                .filter(|(key, _)| !IGNORED_FIELDS.contains(&key.as_str()))
+                .collect();
+            return json!({ "before": null, "after": after });
+        }
+    };
+
+    let mut old = Map::new();
+    let mut new = Map::new();
+    for (key, value) in after {
+        if IGNORED_FIELDS.contains(&key.as_str()) {
+            continue;
+        }
+        let previous = before.get(&key).cloned().unwrap_or(Value::Null);
+        if previous != value {
+            old.insert(key.clone(), previous);
+            new.insert(key, value);
+        }
+    }
+    json!({ "before": old, "after": new })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_keeps_only_changed_fields() {
+        let before =
+            json!({ "id": 1, "email": "old@example.com", "name": "Bob", "updated_at": "a" });
+        let after =
+            json!({ "id": 1, "email": "new@example.com", "name": "Bob", "updated_at": "b" });
+
+        let changes = diff(Some(before), after);
+
+        assert_eq!(
+            changes,
+            json!({
+                "before": { "email": "old@example.com" },
+                "after": { "email": "new@example.com" }
+            })
+        );
+    }
+
+    #[test]
+    fn test_diff_without_before_records_full_state() {
+        let after = json!({ "id": 1, "name": "Bob", "contacts": [], "updated_at": "b" });
+
+        let changes = diff(None, after);
+
+        assert_eq!(
+            changes,
+            json!({ "before": null, "after": { "id": 1, "name": "Bob" } })
+        );
+    }
+}
@@ -0,0 +1,193 @@
+use crate::types::contact::{Contact, NewContact};
+use crate::types::precondition::{EntityTags, IfMatch};
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+/// Upper bound on the number of operations accepted in one bulk request.
+pub const MAX_BULK_OPERATIONS: usize = 1000;
+
+/// One operation of a bulk request. Updates and deletes may carry the contact's ETag,
+/// checked the same way as an `If-Match` header.
+#[derive(Debug, Clone, PartialEq, Deserialize, ToSchema)]
+#[serde(tag = "op", rename_all = "lowercase", deny_unknown_fields)]
+pub enum BulkOperation {
+    Create {
+        contact: NewContact,
+    },
+    Update {
+        id: i32,
+        contact: NewContact,
+        #[serde(default)]
+        if_match: Option<String>,
+    },
+    Delete {
+        id: i32,
+        #[serde(default)]
+        if_match: Option<String>,
+    },
+}
+
+pub fn if_match(tag: Option<String>) -> IfMatch {
+    match tag {
+        Some(tag) if tag == "*" => IfMatch(EntityTags::Any),
+        Some(tag) => IfMatch(EntityTags::Tags(vec![tag])),
+        None => IfMatch(EntityTags::Absent),
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct BulkRequest {
+    pub operations: Vec<BulkOperation>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct BulkOptions {
+    /// Whether one failed operation rolls back the others. Defaults to true.
+    pub atomic: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct BulkItemError {
+    pub code: &'static str,
+    pub message: String,
+}
+
+/// Outcome of the operation at `index` in the request.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct BulkItemResult {
+    pub index: usize,
+    pub status: u16,
+    pub contact: Option<Contact>,
+    pub error: Option<BulkItemError>,
+}
+
+impl BulkItemResult {
+    pub fn succeeded(index: usize, status: StatusCode, contact: Option<Contact>) -> Self {
+        Self {
+            index,
+            status: status.as_u16(),
+            contact,
+            error: None,
+        }
+    }
+
+    pub fn failed(index: usize, error: &handle_errors::Error) -> Self {
+        let (status, code, message) = match error {
+            handle_errors::Error::ContactNotFound => (
+                StatusCode::NOT_FOUND,
+                "contact_not_found",
+                error.to_string(),
+            ),
+            handle_errors::Error::AddressBookNotFound => (
+                StatusCode::NOT_FOUND,
+                "address_book_not_found",
+                error.to_string(),
+            ),
+            handle_errors::Error::PreconditionFailed => (
+                StatusCode::PRECONDITION_FAILED,
+                "precondition_failed",
+                error.to_string(),
+            ),
+            handle_errors::Error::ValidationError(message) => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "validation_failed",
+                message.clone(),
+            ),
+            handle_errors::Error::Conflict(message) => {
+                (StatusCode::CONFLICT, "conflict", message.clone())
+            }
+            // SQLSTATE class 22: the row was rejected for its data, e.g. a value too long.
+            handle_errors::Error::DatabaseQueryError(sqlx::Error::Database(e))
+                if e.code().is_some_and(|code| code.starts_with("22")) =>
+            {
+                (
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    "invalid_value",
+                    e.message().to_string(),
+                )
+            }
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "database_error",
+                String::from("something went wrong"),
+            ),
+        };
+        Self {
+            index,
+            status: status.as_u16(),
+            contact: None,
+            error: Some(BulkItemError { code, message }),
+        }
+    }
+
+    /// Results for an atomic batch of `len` operations that was rolled back because the
+    /// operation at `failed` could not be applied.
+    pub fn rolled_back(len: usize, failed: usize, error: &handle_errors::Error) -> Vec<Self> {
+        (0..len)
+            .map(|index| {
+                if index == failed {
+                    return Self::failed(index, error);
+                }
+                Self {
+                    index,
+                    status: StatusCode::FAILED_DEPENDENCY.as_u16(),
+                    contact: None,
+                    error: Some(BulkItemError {
+                        code: "rolled_back",
+                        message: format!("rolled back because operation {failed} failed"),
+                    }),
+                }
+            })
+            .collect()
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct BulkResult {
+    pub atomic: bool,
+    pub results: Vec<BulkItemResult>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_deserialize_operations() {
+        let request: BulkRequest = serde_json::from_value(json!({
+            "operations": [
+                { "op": "create", "contact": { "name": "Ann", "address": "1 Main Street" } },
+                { "op": "delete", "id": 7, "if_match": "\"3\"" }
+            ]
+        }))
+        .unwrap();
+
+        assert!(matches!(
+            request.operations[0],
+            BulkOperation::Create { .. }
+        ));
+        assert_eq!(
+            request.operations[1],
+            BulkOperation::Delete {
+                id: 7,
+                if_match: Some(String::from("\"3\""))
+            }
+        );
+    }
+
+    #[test]
+    fn test_rolled_back_reports_failing_operation() {
+        let results = BulkItemResult::rolled_back(3, 1, &handle_errors::Error::ContactNotFound);
+
+        let statuses: Vec<u16> = results.iter().map(|result| result.status).collect();
+        assert_eq!(statuses, vec![424, 404, 424]);
+        assert_eq!(results[1].error.as_ref().unwrap().code, "contact_not_found");
+    }
+}
@@ -0,0 +1,234 @@
+use crate::types::address_book::AddressBookId;
+use crate::types::contact::{Contact, ContactId};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use utoipa::{IntoParams, ToSchema};
+
+/// What a change did to a contact, as seen from one address book.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// The contact appeared in the book: it was created, moved in or restored.
+    Create,
+    Update,
+    /// The contact left the book: it was deleted or moved out.
+    Delete,
+}
+
+impl ChangeKind {
+    pub fn parse(kind: &str) -> Self {
+        match kind {
+            "create" => ChangeKind::Create,
+            "delete" => ChangeKind::Delete,
+            _ => ChangeKind::Update,
+        }
+    }
+}
+
+/// The part of a book's change log a sync needs, read in one snapshot.
+#[derive(Debug, Clone)]
+pub struct ChangeLog {
+    /// Sequence number of the latest change to the book's contacts, 0 before the first.
+    pub current: i64,
+    /// Changes up to this sequence number have been pruned.
+    pub pruned_through: i64,
+    /// The first change to each contact after the requested position, by contact id. Empty
+    /// when the log does not cover that position.
+    pub changes: Vec<(ContactId, ChangeKind)>,
+    /// The live contacts among `changes`, or every live contact when the log does not cover
+    /// the requested position.
+    pub contacts: Vec<Contact>,
+}
+
+impl ChangeLog {
+    /// Whether every change after `since` is still in the log.
+    pub fn covers(&self, since: Option<i64>) -> bool {
+        since.is_some_and(|since| self.pruned_through <= since && since <= self.current)
+    }
+}
+
+/// Where a book's change log stands, without the changes themselves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChangePosition {
+    pub current: i64,
+    pub pruned_through: i64,
+}
+
+impl ChangePosition {
+    /// Whether every change after `since` is still in the log.
+    pub fn covers(&self, since: i64) -> bool {
+        self.pruned_through <= since && since <= self.current
+    }
+}
+
+/// A change to one contact, as pushed to the book's event stream. Its `seq` is the event id.
+#[derive(Debug, Clone)]
+pub struct ContactEvent {
+    pub seq: i64,
+    pub contact_id: ContactId,
+    pub kind: ChangeKind,
+    /// The contact as it is now, unless the event removed it from the book or it has been
+    /// deleted since.
+    pub contact: Option<Contact>,
+}
+
+impl ContactEvent {
+    pub fn name(&self) -> &'static str {
+        match self.kind {
+            ChangeKind::Create => "contact.created",
+            ChangeKind::Update => "contact.updated",
+            ChangeKind::Delete => "contact.deleted",
+        }
+    }
+
+    pub fn data(&self, address_book_id: i32) -> Value {
+        json!({
+            "contact_id": self.contact_id,
+            "address_book_id": address_book_id,
+            "contact": self.contact,
+        })
+    }
+}
+
+/// How far an event stream has read its book's change log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventCursor {
+    pub address_book_id: i32,
+    /// The id of the last event sent.
+    pub position: i64,
+    /// Whether the stream could not resume where the client asked, so the client should
+    /// reload the book.
+    pub resync: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ChangesQuery {
+    /// The `next_token` of an earlier response. Omit it for a full sync.
+    pub since: Option<String>,
+}
+
+impl ChangesQuery {
+    pub fn position(&self) -> Result<Option<i64>, handle_errors::Error> {
+        match &self.since {
+            None => Ok(None),
+            Some(token) => match token.parse::<i64>() {
+                Ok(position) if position >= 0 => Ok(Some(position)),
+                _ => Err(handle_errors::Error::ValidationError(format!(
+                    "\"{token}\" is not a valid change token"
+                ))),
+            },
+        }
+    }
+}
+
+/// Contacts that changed in a book since a sync token.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ContactChanges {
+    pub address_book_id: AddressBookId,
+    /// Pass as `since` on the next request.
+    pub next_token: String,
+    /// Whether the lists hold the whole book rather than changes, because no token was given
+    /// or it expired. The client should replace its copy of the book.
+    pub full_resync: bool,
+    pub created: Vec<Contact>,
+    pub updated: Vec<Contact>,
+    pub deleted: Vec<ContactId>,
+}
+
+impl ContactChanges {
+    /// Sorts the log's contacts into created, updated and deleted. A contact that appeared
+    /// and left again since `since` is not reported at all.
+    pub fn from_log(address_book_id: i32, since: Option<i64>, log: ChangeLog) -> Self {
+        let mut changes = ContactChanges {
+            address_book_id: AddressBookId(address_book_id),
+            next_token: log.current.to_string(),
+            full_resync: !log.covers(since),
+            created: Vec::new(),
+            updated: Vec::new(),
+            deleted: Vec::new(),
+        };
+        if changes.full_resync {
+            changes.created = log.contacts;
+            return changes;
+        }
+
+        let mut contacts = log.contacts;
+        for (id, first) in log.changes {
+            let live = contacts
+                .iter()
+                .position(|contact| contact.id == id)
+                .map(|index| contacts.swap_remove(index));
+            match (live, first) {
+                (Some(contact), ChangeKind::Create) => changes.created.push(contact),
+                (Some(contact), _) => changes.updated.push(contact),
+                (None, ChangeKind::Create) => {}
+                (None, _) => changes.deleted.push(id),
+            }
+        }
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::contact::ContactDetails;
+    use chrono::Utc;
+    use serde_json::Map;
+
+    fn contact(id: i32) -> Contact {
+        Contact {
+            id: ContactId(id),
+            name: format!("Contact {id}"),
+            address: String::new(),
+            phone_number: None,
+            email: None,
+            details: ContactDetails::default(),
+            display_name: format!("Contact {id}"),
+            sort_key: format!("contact {id}"),
+            address_book_id: AddressBookId(1),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            created_by: None,
+            updated_by: None,
+            deleted_at: None,
+            version: 1,
+            groups: vec![],
+            has_photo: false,
+            custom_fields: Map::new(),
+        }
+    }
+
+    fn ids(contacts: &[Contact]) -> Vec<i32> {
+        contacts.iter().map(|contact| contact.id.0).collect()
+    }
+
+    #[test]
+    fn test_from_log_sorts_changes() {
+        let log = ChangeLog {
+            current: 20,
+            pruned_through: 5,
+            changes: vec![
+                (ContactId(1), ChangeKind::Create),
+                (ContactId(2), ChangeKind::Update),
+                (ContactId(3), ChangeKind::Update),
+                (ContactId(4), ChangeKind::Create),
+            ],
+            contacts: vec![contact(1), contact(2)],
+        };
+
+        let changes = ContactChanges::from_log(1, Some(10), log.clone());
+        assert!(!changes.full_resync);
+        assert_eq!(changes.next_token, "20");
+        assert_eq!(ids(&changes.created), vec![1]);
+        assert_eq!(ids(&changes.updated), vec![2]);
+        assert_eq!(changes.deleted, vec![ContactId(3)]);
+
+        for since in [None, Some(4), Some(21)] {
+            let changes = ContactChanges::from_log(1, since, log.clone());
+            assert!(changes.full_resync);
+            assert_eq!(ids(&changes.created), vec![1, 2]);
+            assert!(changes.updated.is_empty() && changes.deleted.is_empty());
+        }
+    }
+}
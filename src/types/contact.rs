@@ -1,24 +1,337 @@
 use crate::types::address_book::AddressBookId;
+use crate::types::SortField;
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use utoipa::{IntoParams, ToSchema};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Contact {
     pub id: ContactId,
     pub name: String,
     pub address: String,
     pub phone_number: Option<String>,
     pub email: Option<String>,
+    #[serde(flatten)]
+    pub details: ContactDetails,
+    /// The structured name parts joined, or `name` when there are none.
+    pub display_name: String,
+    /// Lowercased "family given middle", or the display name when none of those is set.
+    pub sort_key: String,
     pub address_book_id: AddressBookId,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub created_by: Option<String>,
+    pub updated_by: Option<String>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub version: i32,
+    /// Names of the groups the contact belongs to, sorted.
+    pub groups: Vec<String>,
+    pub has_photo: bool,
+    /// Values of the address book's custom fields, keyed by field name.
+    #[schema(value_type = Object)]
+    pub custom_fields: Map<String, Value>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, Hash)]
+impl Contact {
+    pub fn etag(&self) -> String {
+        format!("\"{}\"", self.version)
+    }
+}
+
+/// Keys accepted by `?sort=` on the contact listing.
+pub const SORT_FIELDS: [SortField; 10] = [
+    SortField::text("name", "name"),
+    SortField::text("display_name", "display_name"),
+    SortField::text("sort_key", "sort_key"),
+    SortField::text("given_name", "given_name"),
+    SortField::text("family_name", "family_name"),
+    SortField::text("organization", "organization"),
+    SortField::text("email", "email"),
+    SortField::new("birthday", "birthday"),
+    SortField::new("created_at", "created_at"),
+    SortField::new("updated_at", "updated_at"),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, Hash, ToSchema)]
 pub struct ContactId(pub i32);
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Optional structured name parts and personal details, alongside the free-form `name`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct ContactDetails {
+    pub name_prefix: Option<String>,
+    pub given_name: Option<String>,
+    pub middle_name: Option<String>,
+    pub family_name: Option<String>,
+    pub name_suffix: Option<String>,
+    pub nickname: Option<String>,
+    pub organization: Option<String>,
+    pub job_title: Option<String>,
+    pub birthday: Option<NaiveDate>,
+    pub anniversary: Option<NaiveDate>,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, ToSchema)]
 pub struct NewContact {
     pub name: String,
     pub address: String,
     pub phone_number: Option<String>,
     pub email: Option<String>,
-    pub address_book_id: AddressBookId,
+    #[serde(flatten)]
+    pub details: ContactDetails,
+    #[serde(default)]
+    #[schema(value_type = Object)]
+    pub custom_fields: Map<String, Value>,
+}
+
+/// Query parameters narrowing a contact listing: `group` selects members of a group by name and
+/// each `cf.<name>=<value>` requires a custom field to have that value.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ContactFilter {
+    /// Name of a group the contacts must belong to.
+    pub group: Option<String>,
+    #[serde(flatten)]
+    #[param(ignore)]
+    pub params: HashMap<String, String>,
+}
+
+impl ContactFilter {
+    /// The requested custom field values, keyed by field name.
+    pub fn custom_fields(&self) -> Map<String, Value> {
+        self.params
+            .iter()
+            .filter_map(|(key, value)| {
+                let name = key.strip_prefix("cf.")?;
+                Some((name.to_string(), Value::String(value.clone())))
+            })
+            .collect()
+    }
+}
+
+/// What to do when the target book already has a live contact with the same name.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OnConflict {
+    /// Refuse with `409 Conflict`.
+    #[default]
+    Fail,
+    /// Leave the contact where it is.
+    Skip,
+    /// Append a " (2)", " (3)", ... suffix to the name.
+    Rename,
+    /// Keep both contacts under the same name.
+    Allow,
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct TransferRequest {
+    pub target_address_book_id: i32,
+    #[serde(default)]
+    pub on_conflict: OnConflict,
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct BulkMoveRequest {
+    pub contact_ids: Vec<i32>,
+    pub target_address_book_id: i32,
+    #[serde(default)]
+    pub on_conflict: OnConflict,
+}
+
+/// A JSON Merge Patch (RFC 7396) for a contact. Each field is `None` when absent from the
+/// patch, `Some(None)` when explicitly set to `null` and `Some(Some(_))` when given a value.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ContactPatch {
+    #[serde(default, deserialize_with = "present")]
+    pub name: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub address: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub phone_number: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub email: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub name_prefix: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub given_name: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub middle_name: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub family_name: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub name_suffix: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub nickname: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub organization: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub job_title: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub birthday: Option<Option<NaiveDate>>,
+    #[serde(default, deserialize_with = "present")]
+    pub anniversary: Option<Option<NaiveDate>>,
+    #[serde(default, deserialize_with = "present")]
+    pub notes: Option<Option<String>>,
+    /// Merged key by key: a `null` value removes that field, a `null` object clears all.
+    #[serde(default, deserialize_with = "present")]
+    #[schema(value_type = Option<Object>)]
+    pub custom_fields: Option<Option<Map<String, Value>>>,
+}
+
+impl ContactPatch {
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none()
+            && self.address.is_none()
+            && self.phone_number.is_none()
+            && self.email.is_none()
+            && self.name_prefix.is_none()
+            && self.given_name.is_none()
+            && self.middle_name.is_none()
+            && self.family_name.is_none()
+            && self.name_suffix.is_none()
+            && self.nickname.is_none()
+            && self.organization.is_none()
+            && self.job_title.is_none()
+            && self.birthday.is_none()
+            && self.anniversary.is_none()
+            && self.notes.is_none()
+            && self.custom_fields.is_none()
+    }
+}
+
+/// Applies the patch's `custom_fields` to the current values.
+pub fn merge_custom_fields(
+    current: &Map<String, Value>,
+    patch: Option<Option<Map<String, Value>>>,
+) -> Map<String, Value> {
+    match patch {
+        None => current.clone(),
+        Some(None) => Map::new(),
+        Some(Some(patch)) => {
+            let mut merged = current.clone();
+            for (name, value) in patch {
+                if value.is_null() {
+                    merged.remove(&name);
+                } else {
+                    merged.insert(name, value);
+                }
+            }
+            merged
+        }
+    }
+}
+
+/// Marks a field as present, keeping an explicit `null` distinct from a missing key.
+fn present<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ListFilter;
+    use axum::extract::Query;
+
+    #[test]
+    fn test_contact_patch_distinguishes_null_from_missing() {
+        let patch: ContactPatch =
+            serde_json::from_str(r#"{"email": "new@example.com", "phone_number": null}"#).unwrap();
+
+        assert_eq!(patch.email, Some(Some(String::from("new@example.com"))));
+        assert_eq!(patch.phone_number, Some(None));
+        assert_eq!(patch.name, None);
+        assert_eq!(patch.address, None);
+    }
+
+    #[test]
+    fn test_contact_filter_collects_custom_fields() {
+        let uri = "/contacts?group=Family&cf.tier=gold&limit=10"
+            .parse()
+            .unwrap();
+        let Query(filter) = Query::<ContactFilter>::try_from_uri(&uri).unwrap();
+
+        assert_eq!(filter.group, Some(String::from("Family")));
+        assert_eq!(
+            Value::Object(filter.custom_fields()),
+            serde_json::json!({ "tier": "gold" })
+        );
+    }
+
+    #[test]
+    fn test_list_filter_builds_whitelisted_order_by() {
+        let uri = "/contacts?sort=family_name,-created_at&locale=de"
+            .parse()
+            .unwrap();
+        let Query(filter) = Query::<ListFilter>::try_from_uri(&uri).unwrap();
+
+        assert_eq!(
+            filter.order_by("c", &SORT_FIELDS).unwrap(),
+            "c.family_name COLLATE \"de-x-icu\" ASC NULLS LAST, \
+             c.created_at DESC NULLS LAST, c.id"
+        );
+        assert_eq!(
+            ListFilter::default().order_by("c", &SORT_FIELDS).unwrap(),
+            "c.id"
+        );
+
+        let uri = "/contacts?sort=name;DROP%20TABLE%20contacts"
+            .parse()
+            .unwrap();
+        let Query(filter) = Query::<ListFilter>::try_from_uri(&uri).unwrap();
+        assert!(filter.order_by("c", &SORT_FIELDS).is_err());
+
+        let filter = ListFilter {
+            locale: Some(String::from("de\" x")),
+            ..ListFilter::default()
+        };
+        assert!(filter.order_by("c", &SORT_FIELDS).is_err());
+    }
+
+    #[test]
+    fn test_merge_custom_fields() {
+        let current = serde_json::json!({ "tier": "gold", "customer_id": 42 });
+        let current = current.as_object().unwrap();
+        let patch: ContactPatch =
+            serde_json::from_str(r#"{"custom_fields": {"tier": null, "manager": "Sam"}}"#).unwrap();
+
+        let merged = merge_custom_fields(current, patch.custom_fields);
+
+        assert_eq!(
+            Value::Object(merged),
+            serde_json::json!({ "customer_id": 42, "manager": "Sam" })
+        );
+        assert!(merge_custom_fields(current, Some(None)).is_empty());
+    }
+
+    #[test]
+    fn test_contact_patch_rejects_unknown_fields() {
+        assert!(serde_json::from_str::<ContactPatch>(r#"{"favorite_color": "red"}"#).is_err());
+    }
+
+    #[test]
+    fn test_new_contact_reads_details() {
+        let contact: NewContact = serde_json::from_str(
+            r#"{"name": "Ann", "address": "1 Main Street", "given_name": "Ann",
+                "family_name": "Smith", "birthday": "1990-04-01"}"#,
+        )
+        .unwrap();
+
+        assert_eq!(contact.details.family_name, Some(String::from("Smith")));
+        assert_eq!(
+            contact.details.birthday,
+            NaiveDate::from_ymd_opt(1990, 4, 1)
+        );
+        assert!(serde_json::from_str::<NewContact>(
+            r#"{"name": "Ann", "address": "1 Main Street", "birthday": "01/04/1990"}"#
+        )
+        .is_err());
+    }
 }
@@ -0,0 +1,269 @@
+use crate::types::address_book::AddressBookId;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Number, Value};
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldType {
+    Text,
+    Number,
+    /// An ISO 8601 calendar date, `YYYY-MM-DD`.
+    Date,
+    Bool,
+    /// One of the definition's `options`.
+    Enum,
+}
+
+impl FieldType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FieldType::Text => "text",
+            FieldType::Number => "number",
+            FieldType::Date => "date",
+            FieldType::Bool => "bool",
+            FieldType::Enum => "enum",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "text" => Some(FieldType::Text),
+            "number" => Some(FieldType::Number),
+            "date" => Some(FieldType::Date),
+            "bool" => Some(FieldType::Bool),
+            "enum" => Some(FieldType::Enum),
+            _ => None,
+        }
+    }
+}
+
+/// Definition of a custom field available to every contact of an address book.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CustomField {
+    pub id: FieldId,
+    pub address_book_id: AddressBookId,
+    pub name: String,
+    pub field_type: FieldType,
+    pub required: bool,
+    pub options: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub created_by: Option<String>,
+    pub updated_by: Option<String>,
+    pub version: i32,
+}
+
+impl CustomField {
+    pub fn etag(&self) -> String {
+        format!("\"{}\"", self.version)
+    }
+
+    /// Checks that `value` has this field's type.
+    fn check(&self, value: &Value) -> Result<(), String> {
+        let valid = match self.field_type {
+            FieldType::Text => value.is_string(),
+            FieldType::Number => value.is_number(),
+            FieldType::Date => value
+                .as_str()
+                .is_some_and(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok()),
+            FieldType::Bool => value.is_boolean(),
+            FieldType::Enum => value
+                .as_str()
+                .is_some_and(|option| self.options.iter().any(|o| o == option)),
+        };
+        if valid {
+            return Ok(());
+        }
+        match self.field_type {
+            FieldType::Enum => Err(format!(
+                "custom field \"{}\" must be one of: {}",
+                self.name,
+                self.options.join(", ")
+            )),
+            FieldType::Date => Err(format!(
+                "custom field \"{}\" must be a date (YYYY-MM-DD)",
+                self.name
+            )),
+            field_type => Err(format!(
+                "custom field \"{}\" must be a {}",
+                self.name,
+                field_type.as_str()
+            )),
+        }
+    }
+
+    /// Parses a CSV cell into a value of this field's type. Empty cells are absent.
+    pub fn parse_cell(&self, cell: &str) -> Result<Option<Value>, String> {
+        if cell.is_empty() {
+            return Ok(None);
+        }
+        let value = match self.field_type {
+            FieldType::Number => cell
+                .parse::<f64>()
+                .ok()
+                .and_then(Number::from_f64)
+                .map(|number| match cell.parse::<i64>() {
+                    Ok(integer) => Value::from(integer),
+                    Err(_) => Value::Number(number),
+                })
+                .ok_or_else(|| format!("custom field \"{}\" must be a number", self.name))?,
+            FieldType::Bool => match cell {
+                "true" => Value::Bool(true),
+                "false" => Value::Bool(false),
+                _ => return Err(format!("custom field \"{}\" must be a bool", self.name)),
+            },
+            _ => Value::String(cell.to_string()),
+        };
+        self.check(&value)?;
+        Ok(Some(value))
+    }
+}
+
+/// Renders a stored value as a CSV cell, the inverse of [`CustomField::parse_cell`].
+pub fn format_cell(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(value)) => value.clone(),
+        Some(value) => value.to_string(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq, Hash, ToSchema)]
+pub struct FieldId(pub i32);
+
+#[derive(Debug, Clone, PartialEq, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct NewCustomField {
+    pub name: String,
+    pub field_type: FieldType,
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default)]
+    pub options: Vec<String>,
+}
+
+/// Validates a contact's custom field values against the book's definitions, dropping
+/// `null`s. Unknown fields, wrongly typed values and missing required fields are rejected.
+pub fn validate(
+    definitions: &[CustomField],
+    values: &Map<String, Value>,
+) -> Result<Map<String, Value>, String> {
+    let mut validated = Map::new();
+    for (name, value) in values {
+        let Some(definition) = definitions.iter().find(|d| &d.name == name) else {
+            return Err(format!("unknown custom field \"{name}\""));
+        };
+        if value.is_null() {
+            continue;
+        }
+        definition.check(value)?;
+        validated.insert(name.clone(), value.clone());
+    }
+
+    if let Some(missing) = definitions
+        .iter()
+        .find(|d| d.required && !validated.contains_key(&d.name))
+    {
+        return Err(format!("custom field \"{}\" is required", missing.name));
+    }
+    Ok(validated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn definition(name: &str, field_type: FieldType, required: bool) -> CustomField {
+        CustomField {
+            id: FieldId(1),
+            address_book_id: AddressBookId(1),
+            name: String::from(name),
+            field_type,
+            required,
+            options: vec![String::from("gold"), String::from("silver")],
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            created_by: None,
+            updated_by: None,
+            version: 1,
+        }
+    }
+
+    fn values(value: Value) -> Map<String, Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn test_validate_checks_types_and_drops_nulls() {
+        let definitions = vec![
+            definition("customer_id", FieldType::Number, true),
+            definition("renewal", FieldType::Date, false),
+            definition("tier", FieldType::Enum, false),
+            definition("manager", FieldType::Text, false),
+        ];
+
+        let validated = validate(
+            &definitions,
+            &values(json!({ "customer_id": 42, "renewal": "2025-01-31", "tier": "gold", "manager": null })),
+        )
+        .unwrap();
+        assert_eq!(
+            Value::Object(validated),
+            json!({ "customer_id": 42, "renewal": "2025-01-31", "tier": "gold" })
+        );
+
+        assert!(validate(&definitions, &values(json!({ "customer_id": "42" }))).is_err());
+        assert!(validate(
+            &definitions,
+            &values(json!({ "customer_id": 1, "renewal": "31/01/2025" }))
+        )
+        .is_err());
+        assert!(validate(
+            &definitions,
+            &values(json!({ "customer_id": 1, "tier": "bronze" }))
+        )
+        .is_err());
+        assert!(validate(
+            &definitions,
+            &values(json!({ "customer_id": 1, "colour": "red" }))
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_validate_requires_required_fields() {
+        let definitions = vec![definition("customer_id", FieldType::Number, true)];
+
+        let error = validate(&definitions, &values(json!({ "customer_id": null }))).unwrap_err();
+        assert_eq!(error, "custom field \"customer_id\" is required");
+    }
+
+    #[test]
+    fn test_cells_round_trip() {
+        let definitions = [
+            (
+                definition("customer_id", FieldType::Number, false),
+                json!(42),
+            ),
+            (definition("score", FieldType::Number, false), json!(1.5)),
+            (definition("active", FieldType::Bool, false), json!(true)),
+            (
+                definition("renewal", FieldType::Date, false),
+                json!("2025-01-31"),
+            ),
+            (definition("tier", FieldType::Enum, false), json!("silver")),
+        ];
+
+        for (definition, value) in definitions {
+            let cell = format_cell(Some(&value));
+            assert_eq!(definition.parse_cell(&cell).unwrap(), Some(value));
+        }
+        assert_eq!(
+            definition("manager", FieldType::Text, false).parse_cell(""),
+            Ok(None)
+        );
+    }
+}
@@ -0,0 +1,781 @@
+use crate::types::address_book::AddressBook;
+use crate::types::contact::Contact;
+use crate::types::vcard::{self, VCARD_CONTENT_TYPE};
+use axum::http::StatusCode;
+use roxmltree::{Document, Node};
+
+pub const DAV: &str = "DAV:";
+pub const CARDDAV: &str = "urn:ietf:params:xml:ns:carddav";
+pub const CALENDARSERVER: &str = "http://calendarserver.org/ns/";
+
+/// Sync tokens are this prefix followed by the sequence number of the book's latest change.
+pub const SYNC_TOKEN_PREFIX: &str = "http://addressbook-service/ns/sync/";
+
+/// The `DAV` header: class 1 and 3 WebDAV with CardDAV.
+pub const DAV_COMPLIANCE: &str = "1, 3, addressbook";
+
+pub const DAV_METHODS: &str = "OPTIONS, GET, HEAD, PUT, DELETE, PROPFIND, REPORT";
+
+pub const PRINCIPAL_HREF: &str = "/dav/";
+pub const HOME_HREF: &str = "/dav/addressbooks/";
+
+/// An address book served as a CardDAV collection, with the sequence number of the latest
+/// change to its contacts and of the latest one pruned from the log.
+#[derive(Debug, Clone)]
+pub struct CardCollection {
+    pub book: AddressBook,
+    pub sync_seq: i64,
+    pub pruned_through: i64,
+}
+
+impl CardCollection {
+    pub fn sync_token(&self) -> String {
+        format!("{SYNC_TOKEN_PREFIX}{}", self.sync_seq)
+    }
+}
+
+/// A contact served as a vCard resource.
+#[derive(Debug, Clone)]
+pub struct Card {
+    /// Last segment of the card's URL, such as `7.vcf` or the name a client created it under.
+    pub resource_name: String,
+    pub uid: String,
+    /// Where the renditions of the contact's photo are stored, if it has one.
+    pub photo_blob_prefix: Option<String>,
+    pub contact: Contact,
+}
+
+impl Card {
+    pub fn etag(&self) -> String {
+        self.contact.etag()
+    }
+
+    pub fn href(&self) -> String {
+        card_href(self.contact.address_book_id.0, &self.resource_name)
+    }
+}
+
+pub fn address_book_href(address_book_id: i32) -> String {
+    format!("{HOME_HREF}{address_book_id}/")
+}
+
+pub fn card_href(address_book_id: i32, resource_name: &str) -> String {
+    format!(
+        "{}{}",
+        address_book_href(address_book_id),
+        encode_segment(resource_name)
+    )
+}
+
+/// Reads the sequence number out of a sync token.
+pub fn parse_sync_token(token: &str) -> Option<i64> {
+    token.strip_prefix(SYNC_TOKEN_PREFIX)?.parse().ok()
+}
+
+/// Whether `name` has the shape of the names contacts are served under by id, `<id>.vcf`.
+/// Such names are reserved and cannot be chosen by clients.
+pub fn is_id_resource_name(name: &str) -> bool {
+    name.strip_suffix(".vcf")
+        .is_some_and(|id| !id.is_empty() && id.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// The resource a request addresses.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DavPath {
+    Principal,
+    Home,
+    AddressBook(i32),
+    Card(i32, String),
+}
+
+/// A WebDAV property this server knows, or any other one a client asked for.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Prop {
+    ResourceType,
+    DisplayName,
+    GetEtag,
+    GetContentType,
+    GetCtag,
+    SyncToken,
+    CurrentUserPrincipal,
+    AddressbookHomeSet,
+    SupportedReportSet,
+    CurrentUserPrivilegeSet,
+    AddressData,
+    Unknown { namespace: String, name: String },
+}
+
+impl Prop {
+    fn from_node(node: Node) -> Prop {
+        let namespace = node.tag_name().namespace().unwrap_or_default();
+        let name = node.tag_name().name();
+        match (namespace, name) {
+            (DAV, "resourcetype") => Prop::ResourceType,
+            (DAV, "displayname") => Prop::DisplayName,
+            (DAV, "getetag") => Prop::GetEtag,
+            (DAV, "getcontenttype") => Prop::GetContentType,
+            (DAV, "sync-token") => Prop::SyncToken,
+            (DAV, "current-user-principal") => Prop::CurrentUserPrincipal,
+            (DAV, "supported-report-set") => Prop::SupportedReportSet,
+            (DAV, "current-user-privilege-set") => Prop::CurrentUserPrivilegeSet,
+            (CALENDARSERVER, "getctag") => Prop::GetCtag,
+            (CARDDAV, "addressbook-home-set") => Prop::AddressbookHomeSet,
+            (CARDDAV, "address-data") => Prop::AddressData,
+            _ => Prop::Unknown {
+                namespace: namespace.to_string(),
+                name: name.to_string(),
+            },
+        }
+    }
+
+    /// The element name, with the prefixes declared on the multistatus root, and any
+    /// namespace declaration an unknown property needs.
+    fn tag(&self) -> (String, String) {
+        let tag = match self {
+            Prop::ResourceType => "d:resourcetype",
+            Prop::DisplayName => "d:displayname",
+            Prop::GetEtag => "d:getetag",
+            Prop::GetContentType => "d:getcontenttype",
+            Prop::GetCtag => "cs:getctag",
+            Prop::SyncToken => "d:sync-token",
+            Prop::CurrentUserPrincipal => "d:current-user-principal",
+            Prop::AddressbookHomeSet => "card:addressbook-home-set",
+            Prop::SupportedReportSet => "d:supported-report-set",
+            Prop::CurrentUserPrivilegeSet => "d:current-user-privilege-set",
+            Prop::AddressData => "card:address-data",
+            Prop::Unknown { namespace, name } if namespace.is_empty() => {
+                return (name.clone(), String::from(" xmlns=\"\""))
+            }
+            Prop::Unknown { namespace, name } => {
+                return (
+                    format!("x:{name}"),
+                    format!(" xmlns:x=\"{}\"", escape_xml(namespace)),
+                )
+            }
+        };
+        (tag.to_string(), String::new())
+    }
+
+    fn element(&self, value: Option<&str>) -> String {
+        let (tag, declaration) = self.tag();
+        match value {
+            Some(value) => format!("<{tag}{declaration}>{value}</{tag}>"),
+            None => format!("<{tag}{declaration}/>"),
+        }
+    }
+}
+
+/// The properties a PROPFIND or REPORT asks for.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PropSelection {
+    /// `allprop`, `propname` or an empty body: every property the resource has, except
+    /// `address-data`.
+    All,
+    Named(Vec<Prop>),
+}
+
+/// A text-match element of an addressbook-query filter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextMatch {
+    pub text: String,
+    pub match_type: MatchType,
+    pub negate: bool,
+    /// `i;octet` compares exactly; the default `i;unicode-casemap` ignores case.
+    pub case_sensitive: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MatchType {
+    Equals,
+    Contains,
+    StartsWith,
+    EndsWith,
+}
+
+impl TextMatch {
+    fn matches(&self, value: &str) -> bool {
+        let (value, text) = if self.case_sensitive {
+            (value.to_string(), self.text.clone())
+        } else {
+            (value.to_lowercase(), self.text.to_lowercase())
+        };
+        let found = match self.match_type {
+            MatchType::Equals => value == text,
+            MatchType::Contains => value.contains(&text),
+            MatchType::StartsWith => value.starts_with(&text),
+            MatchType::EndsWith => value.ends_with(&text),
+        };
+        found != self.negate
+    }
+}
+
+/// A prop-filter of an addressbook-query: a test on every value of one vCard property.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PropFilter {
+    /// Uppercase vCard property name, such as `FN` or `EMAIL`.
+    pub name: String,
+    pub all_of: bool,
+    pub is_not_defined: bool,
+    pub text_matches: Vec<TextMatch>,
+}
+
+impl PropFilter {
+    fn matches(&self, properties: &[(String, String)]) -> bool {
+        let mut values = properties
+            .iter()
+            .filter(|(name, _)| *name == self.name)
+            .map(|(_, value)| vcard::unescape(value));
+        if self.is_not_defined {
+            return values.next().is_none();
+        }
+        values.any(|value| {
+            let mut results = self.text_matches.iter().map(|m| m.matches(&value));
+            if self.all_of {
+                results.all(|matched| matched)
+            } else {
+                self.text_matches.is_empty() || results.any(|matched| matched)
+            }
+        })
+    }
+}
+
+/// The filter of an addressbook-query. A filter without prop-filters matches every card.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CardFilter {
+    pub all_of: bool,
+    pub props: Vec<PropFilter>,
+}
+
+impl CardFilter {
+    /// Tests the filter against a card, given as rendered by `vcard::render`.
+    pub fn matches(&self, card: &str) -> bool {
+        let Ok(properties) = vcard::properties(card) else {
+            return false;
+        };
+        let mut results = self.props.iter().map(|prop| prop.matches(&properties));
+        if self.all_of {
+            results.all(|matched| matched)
+        } else {
+            self.props.is_empty() || results.any(|matched| matched)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Report {
+    AddressbookMultiget {
+        props: PropSelection,
+        hrefs: Vec<String>,
+    },
+    AddressbookQuery {
+        props: PropSelection,
+        filter: CardFilter,
+        limit: Option<usize>,
+    },
+    SyncCollection {
+        /// `None` for an initial sync.
+        sync_token: Option<String>,
+        props: PropSelection,
+    },
+}
+
+fn parse_xml(body: &str) -> Result<Document<'_>, String> {
+    Document::parse(body).map_err(|e| format!("invalid XML: {e}"))
+}
+
+fn is(node: &Node, namespace: &str, name: &str) -> bool {
+    node.is_element() && node.has_tag_name((namespace, name))
+}
+
+fn child<'a, 'input>(
+    node: &Node<'a, 'input>,
+    namespace: &str,
+    name: &str,
+) -> Option<Node<'a, 'input>> {
+    node.children().find(|child| is(child, namespace, name))
+}
+
+fn prop_selection(node: &Node) -> PropSelection {
+    match child(node, DAV, "prop") {
+        Some(prop) => PropSelection::Named(
+            prop.children()
+                .filter(Node::is_element)
+                .map(Prop::from_node)
+                .collect(),
+        ),
+        None => PropSelection::All,
+    }
+}
+
+/// Parses a PROPFIND body. An empty body asks for all properties.
+pub fn parse_propfind(body: &str) -> Result<PropSelection, String> {
+    if body.trim().is_empty() {
+        return Ok(PropSelection::All);
+    }
+    let document = parse_xml(body)?;
+    let root = document.root_element();
+    if !is(&root, DAV, "propfind") {
+        return Err(String::from("expected a DAV:propfind element"));
+    }
+    Ok(prop_selection(&root))
+}
+
+/// Parses the body of a REPORT on an address book.
+pub fn parse_report(body: &str) -> Result<Report, String> {
+    let document = parse_xml(body)?;
+    let root = document.root_element();
+    let props = prop_selection(&root);
+
+    if is(&root, CARDDAV, "addressbook-multiget") {
+        let hrefs = root
+            .children()
+            .filter(|node| is(node, DAV, "href"))
+            .map(|node| node.text().unwrap_or_default().trim().to_string())
+            .collect();
+        Ok(Report::AddressbookMultiget { props, hrefs })
+    } else if is(&root, CARDDAV, "addressbook-query") {
+        let filter = match child(&root, CARDDAV, "filter") {
+            Some(filter) => card_filter(&filter)?,
+            None => CardFilter::default(),
+        };
+        let limit = match child(&root, CARDDAV, "limit")
+            .and_then(|limit| child(&limit, CARDDAV, "nresults"))
+        {
+            Some(nresults) => Some(
+                nresults
+                    .text()
+                    .unwrap_or_default()
+                    .trim()
+                    .parse()
+                    .map_err(|_| String::from("nresults must be a number"))?,
+            ),
+            None => None,
+        };
+        Ok(Report::AddressbookQuery {
+            props,
+            filter,
+            limit,
+        })
+    } else if is(&root, DAV, "sync-collection") {
+        let sync_token = child(&root, DAV, "sync-token")
+            .and_then(|token| token.text())
+            .map(str::trim)
+            .filter(|token| !token.is_empty())
+            .map(String::from);
+        Ok(Report::SyncCollection { sync_token, props })
+    } else {
+        Err(format!("unsupported report \"{}\"", root.tag_name().name()))
+    }
+}
+
+fn all_of(node: &Node) -> bool {
+    node.attribute("test") == Some("allof")
+}
+
+fn card_filter(node: &Node) -> Result<CardFilter, String> {
+    let mut props = Vec::new();
+    for prop in node
+        .children()
+        .filter(|node| is(node, CARDDAV, "prop-filter"))
+    {
+        let Some(name) = prop.attribute("name") else {
+            return Err(String::from("prop-filter needs a name"));
+        };
+        if child(&prop, CARDDAV, "param-filter").is_some() {
+            return Err(String::from("param-filter is not supported"));
+        }
+        let text_matches = prop
+            .children()
+            .filter(|node| is(node, CARDDAV, "text-match"))
+            .map(|node| text_match(&node))
+            .collect::<Result<_, _>>()?;
+        props.push(PropFilter {
+            name: name.to_ascii_uppercase(),
+            all_of: all_of(&prop),
+            is_not_defined: child(&prop, CARDDAV, "is-not-defined").is_some(),
+            text_matches,
+        });
+    }
+    Ok(CardFilter {
+        all_of: all_of(node),
+        props,
+    })
+}
+
+fn text_match(node: &Node) -> Result<TextMatch, String> {
+    let match_type = match node.attribute("match-type").unwrap_or("contains") {
+        "equals" => MatchType::Equals,
+        "contains" => MatchType::Contains,
+        "starts-with" => MatchType::StartsWith,
+        "ends-with" => MatchType::EndsWith,
+        other => return Err(format!("unsupported match-type \"{other}\"")),
+    };
+    let case_sensitive = match node.attribute("collation").unwrap_or("i;unicode-casemap") {
+        "i;unicode-casemap" => false,
+        "i;octet" => true,
+        other => return Err(format!("unsupported collation \"{other}\"")),
+    };
+    Ok(TextMatch {
+        text: node.text().unwrap_or_default().to_string(),
+        match_type,
+        negate: node.attribute("negate-condition") == Some("yes"),
+        case_sensitive,
+    })
+}
+
+/// A resource as it appears in a multistatus response.
+#[derive(Debug, Clone)]
+pub enum DavResource {
+    /// `/dav/`: the one principal, where clients start discovery.
+    Principal,
+    /// `/dav/addressbooks/`: the collection holding every address book.
+    Home,
+    AddressBook(CardCollection),
+    /// A card, with its rendered vCard when `address-data` was asked for.
+    Card(Box<Card>, Option<String>),
+}
+
+impl DavResource {
+    pub fn href(&self) -> String {
+        match self {
+            DavResource::Principal => String::from(PRINCIPAL_HREF),
+            DavResource::Home => String::from(HOME_HREF),
+            DavResource::AddressBook(collection) => address_book_href(collection.book.id.0),
+            DavResource::Card(card, _) => card.href(),
+        }
+    }
+
+    /// The properties returned for `allprop`.
+    fn all_props(&self) -> Vec<Prop> {
+        match self {
+            DavResource::Principal => vec![
+                Prop::ResourceType,
+                Prop::DisplayName,
+                Prop::CurrentUserPrincipal,
+                Prop::AddressbookHomeSet,
+            ],
+            DavResource::Home => vec![Prop::ResourceType, Prop::DisplayName],
+            DavResource::AddressBook(_) => vec![
+                Prop::ResourceType,
+                Prop::DisplayName,
+                Prop::GetEtag,
+                Prop::GetCtag,
+                Prop::SyncToken,
+            ],
+            DavResource::Card(..) => vec![Prop::ResourceType, Prop::GetEtag, Prop::GetContentType],
+        }
+    }
+
+    /// The XML content of `prop`, or `None` when the resource does not have it.
+    fn value(&self, prop: &Prop) -> Option<String> {
+        let href = |href: &str| format!("<d:href>{href}</d:href>");
+        match (prop, self) {
+            (Prop::ResourceType, DavResource::Principal) => {
+                Some(String::from("<d:collection/><d:principal/>"))
+            }
+            (Prop::ResourceType, DavResource::Home) => Some(String::from("<d:collection/>")),
+            (Prop::ResourceType, DavResource::AddressBook(_)) => {
+                Some(String::from("<d:collection/><card:addressbook/>"))
+            }
+            (Prop::ResourceType, DavResource::Card(..)) => Some(String::new()),
+            (Prop::DisplayName, DavResource::Principal) => {
+                Some(String::from("addressbook-service"))
+            }
+            (Prop::DisplayName, DavResource::Home) => Some(String::from("Address books")),
+            (Prop::DisplayName, DavResource::AddressBook(collection)) => {
+                Some(escape_xml(&collection.book.address_book_name))
+            }
+            (Prop::GetEtag, DavResource::AddressBook(collection)) => {
+                Some(escape_xml(&collection.book.etag()))
+            }
+            (Prop::GetEtag, DavResource::Card(card, _)) => Some(escape_xml(&card.etag())),
+            (Prop::GetContentType, DavResource::Card(..)) => Some(String::from(VCARD_CONTENT_TYPE)),
+            (Prop::GetCtag | Prop::SyncToken, DavResource::AddressBook(collection)) => {
+                Some(escape_xml(&collection.sync_token()))
+            }
+            (Prop::CurrentUserPrincipal, _) => Some(href(PRINCIPAL_HREF)),
+            (Prop::AddressbookHomeSet, DavResource::Principal) => Some(href(HOME_HREF)),
+            (Prop::SupportedReportSet, DavResource::AddressBook(_)) => Some(
+                [
+                    "card:addressbook-multiget",
+                    "card:addressbook-query",
+                    "d:sync-collection",
+                ]
+                .map(|report| {
+                    format!(
+                        "<d:supported-report><d:report><{report}/></d:report></d:supported-report>"
+                    )
+                })
+                .concat(),
+            ),
+            (Prop::CurrentUserPrivilegeSet, resource) => {
+                let privileges: &[&str] = match resource {
+                    DavResource::AddressBook(_) => &["read", "write", "bind", "unbind"],
+                    DavResource::Card(..) => &["read", "write", "write-content"],
+                    _ => &["read"],
+                };
+                Some(
+                    privileges
+                        .iter()
+                        .map(|p| format!("<d:privilege><d:{p}/></d:privilege>"))
+                        .collect(),
+                )
+            }
+            (Prop::AddressData, DavResource::Card(_, Some(data))) => Some(escape_xml(data)),
+            _ => None,
+        }
+    }
+
+    /// Builds the resource's response with the values of the selected properties.
+    pub fn response(&self, selection: &PropSelection) -> DavResponse {
+        let props = match selection {
+            PropSelection::All => self.all_props(),
+            PropSelection::Named(props) => props.clone(),
+        };
+        let mut found = Vec::new();
+        let mut missing = Vec::new();
+        for prop in props {
+            match self.value(&prop) {
+                Some(value) => found.push(prop.element(Some(&value))),
+                None => missing.push(prop.element(None)),
+            }
+        }
+        DavResponse::Props {
+            href: self.href(),
+            found,
+            missing,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DavResponse {
+    /// Properties found and properties the resource does not have, as XML elements.
+    Props {
+        href: String,
+        found: Vec<String>,
+        missing: Vec<String>,
+    },
+    /// A resource reported by status alone, such as a card deleted since the last sync.
+    Status { href: String, status: StatusCode },
+}
+
+/// The body of a `207 Multi-Status` response.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Multistatus {
+    pub responses: Vec<DavResponse>,
+    pub sync_token: Option<String>,
+}
+
+fn status_line(status: StatusCode) -> String {
+    format!(
+        "<d:status>HTTP/1.1 {} {}</d:status>",
+        status.as_u16(),
+        status.canonical_reason().unwrap_or_default()
+    )
+}
+
+impl Multistatus {
+    pub fn to_xml(&self) -> String {
+        let mut xml = format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+             <d:multistatus xmlns:d=\"{DAV}\" xmlns:card=\"{CARDDAV}\" xmlns:cs=\"{CALENDARSERVER}\">"
+        );
+        for response in &self.responses {
+            xml.push_str("<d:response>");
+            match response {
+                DavResponse::Props {
+                    href,
+                    found,
+                    missing,
+                } => {
+                    xml.push_str(&format!("<d:href>{}</d:href>", escape_xml(href)));
+                    for (props, status) in
+                        [(found, StatusCode::OK), (missing, StatusCode::NOT_FOUND)]
+                    {
+                        if !props.is_empty() {
+                            xml.push_str(&format!(
+                                "<d:propstat><d:prop>{}</d:prop>{}</d:propstat>",
+                                props.concat(),
+                                status_line(status)
+                            ));
+                        }
+                    }
+                }
+                DavResponse::Status { href, status } => {
+                    xml.push_str(&format!("<d:href>{}</d:href>", escape_xml(href)));
+                    xml.push_str(&status_line(*status));
+                }
+            }
+            xml.push_str("</d:response>");
+        }
+        if let Some(token) = &self.sync_token {
+            xml.push_str(&format!(
+                "<d:sync-token>{}</d:sync-token>",
+                escape_xml(token)
+            ));
+        }
+        xml.push_str("</d:multistatus>\n");
+        xml
+    }
+}
+
+/// The body of a `403 Forbidden` naming the precondition that failed, such as
+/// `valid-sync-token`.
+pub fn error_xml(precondition: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <d:error xmlns:d=\"{DAV}\"><d:{precondition}/></d:error>\n"
+    )
+}
+
+pub fn escape_xml(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\r' => escaped.push_str("&#13;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Percent-encodes a path segment, leaving unreserved characters as they are.
+pub fn encode_segment(segment: &str) -> String {
+    let mut encoded = String::with_capacity(segment.len());
+    for byte in segment.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'@' => {
+                encoded.push(byte as char)
+            }
+            byte => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+/// Reverses percent-encoding. Returns `None` for invalid escapes or non-UTF-8 results.
+pub fn decode_segment(segment: &str) -> Option<String> {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'%' {
+            let hex = segment.get(index + 1..index + 3)?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            index += 3;
+        } else {
+            decoded.push(bytes[index]);
+            index += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+/// The resource name an href in a multiget points to, if it names a card in the book.
+pub fn resource_name_of(href: &str, address_book_id: i32) -> Option<String> {
+    // Clients may send absolute URLs; only the path matters.
+    let path = match href.split_once("://") {
+        Some((_, rest)) => &rest[rest.find('/')?..],
+        None => href,
+    };
+    let name = path.strip_prefix(&address_book_href(address_book_id))?;
+    if name.is_empty() || name.contains('/') {
+        return None;
+    }
+    decode_segment(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_report_reads_queries() {
+        let body = r#"<?xml version="1.0"?>
+            <C:addressbook-query xmlns:D="DAV:" xmlns:C="urn:ietf:params:xml:ns:carddav">
+              <D:prop><D:getetag/><C:address-data/><X:color xmlns:X="urn:example"/></D:prop>
+              <C:filter test="allof">
+                <C:prop-filter name="email">
+                  <C:text-match match-type="ends-with">@EXAMPLE.com</C:text-match>
+                </C:prop-filter>
+                <C:prop-filter name="NICKNAME"><C:is-not-defined/></C:prop-filter>
+              </C:filter>
+              <C:limit><C:nresults>10</C:nresults></C:limit>
+            </C:addressbook-query>"#;
+        let Report::AddressbookQuery {
+            props,
+            filter,
+            limit,
+        } = parse_report(body).unwrap()
+        else {
+            panic!("expected an addressbook-query");
+        };
+
+        assert_eq!(limit, Some(10));
+        assert_eq!(
+            props,
+            PropSelection::Named(vec![
+                Prop::GetEtag,
+                Prop::AddressData,
+                Prop::Unknown {
+                    namespace: String::from("urn:example"),
+                    name: String::from("color"),
+                },
+            ])
+        );
+        assert!(filter.matches("BEGIN:VCARD\r\nFN:Ann\r\nEMAIL:ann@example.com\r\nEND:VCARD\r\n"));
+        assert!(
+            !filter.matches("BEGIN:VCARD\r\nEMAIL:ann@example.com\r\nNICKNAME:A\r\nEND:VCARD\r\n")
+        );
+        assert!(!filter.matches("BEGIN:VCARD\r\nEMAIL:ann@example.org\r\nEND:VCARD\r\n"));
+
+        let body = r#"<d:sync-collection xmlns:d="DAV:">
+              <d:sync-token>http://addressbook-service/ns/sync/42</d:sync-token>
+              <d:sync-level>1</d:sync-level><d:prop><d:getetag/></d:prop>
+            </d:sync-collection>"#;
+        let Report::SyncCollection { sync_token, .. } = parse_report(body).unwrap() else {
+            panic!("expected a sync-collection");
+        };
+        assert_eq!(sync_token.as_deref().and_then(parse_sync_token), Some(42));
+    }
+
+    #[test]
+    fn test_multistatus_reports_found_and_missing_props() {
+        let multistatus = Multistatus {
+            responses: vec![
+                DavResource::Home.response(&PropSelection::Named(vec![
+                    Prop::DisplayName,
+                    Prop::GetCtag,
+                ])),
+                DavResponse::Status {
+                    href: card_href(1, "a b.vcf"),
+                    status: StatusCode::NOT_FOUND,
+                },
+            ],
+            sync_token: Some(String::from("http://addressbook-service/ns/sync/3")),
+        };
+        let xml = multistatus.to_xml();
+        let document = Document::parse(&xml).unwrap();
+
+        assert!(xml.contains(
+            "<d:propstat><d:prop><d:displayname>Address books</d:displayname></d:prop>\
+             <d:status>HTTP/1.1 200 OK</d:status></d:propstat>"
+        ));
+        assert!(xml.contains(
+            "<d:propstat><d:prop><cs:getctag/></d:prop>\
+             <d:status>HTTP/1.1 404 Not Found</d:status></d:propstat>"
+        ));
+        assert!(xml.contains("<d:href>/dav/addressbooks/1/a%20b.vcf</d:href>"));
+        assert_eq!(document.root_element().tag_name().namespace(), Some(DAV));
+        assert_eq!(
+            resource_name_of("https://example.com/dav/addressbooks/1/a%20b.vcf", 1),
+            Some(String::from("a b.vcf"))
+        );
+        assert_eq!(resource_name_of("/dav/addressbooks/2/a.vcf", 1), None);
+    }
+}
@@ -0,0 +1,30 @@
+use crate::types::address_book::AddressBookId;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ContactGroup {
+    pub id: GroupId,
+    pub address_book_id: AddressBookId,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub created_by: Option<String>,
+    pub updated_by: Option<String>,
+    pub version: i32,
+}
+
+impl ContactGroup {
+    pub fn etag(&self) -> String {
+        format!("\"{}\"", self.version)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, Hash, ToSchema)]
+pub struct GroupId(pub i32);
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct NewContactGroup {
+    pub name: String,
+}
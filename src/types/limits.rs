@@ -0,0 +1,22 @@
+use std::time::Duration;
+
+use super::rate_limit::RouteClass;
+
+/// Bounds on the size and duration of requests. Imports and bulk writes get their own, since
+/// they carry many contacts at once.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RequestLimits {
+    pub body_bytes: usize,
+    pub import_body_bytes: usize,
+    pub timeout: Duration,
+    pub import_timeout: Duration,
+}
+
+impl RequestLimits {
+    pub fn timeout(&self, class: RouteClass) -> Duration {
+        match class {
+            RouteClass::Import => self.import_timeout,
+            RouteClass::Read | RouteClass::Write => self.timeout,
+        }
+    }
+}
@@ -1,68 +1,532 @@
 pub mod address_book;
+pub mod audit;
+pub mod bulk;
+pub mod changes;
 pub mod contact;
+pub mod custom_field;
+pub mod dav;
+pub mod group;
+pub mod limits;
+pub mod photo;
+pub mod precondition;
+pub mod rate_limit;
+pub mod stats;
+pub mod trash;
+pub mod vcard;
+pub mod webhook;
 
 use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::request::Parts,
     response::{IntoResponse, Response},
     Json,
 };
 
-use axum::http::StatusCode;
-use serde_json::json;
+use axum::http::{header, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use chrono::{DateTime, Utc};
+use metrics_exporter_prometheus::PrometheusHandle;
+use serde::Serialize;
+use serde_json::Value;
+use std::convert::Infallible;
+use tokio::sync::mpsc;
+use tokio_stream::{wrappers::ReceiverStream, StreamExt};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::repositories::blob_store::LocalBlobStore;
+use crate::repositories::change_feed::ChangeFeed;
+use crate::repositories::webhook_sender::HttpWebhookSender;
 
 use self::address_book::AddressBook;
+use self::audit::AuditEvent;
+use self::bulk::BulkResult;
+use self::changes::ContactChanges;
+use self::contact::Contact;
+use self::custom_field::CustomField;
+use self::dav::{error_xml, DAV_COMPLIANCE, DAV_METHODS};
+use self::group::ContactGroup;
+use self::limits::RequestLimits;
+use self::photo::Photo;
+use self::rate_limit::RateLimiter;
+use self::stats::AddressBookStats;
+use self::trash::Trash;
+use self::vcard::VCARD_CONTENT_TYPE;
+use self::webhook::{Webhook, WebhookDelivery};
 
 #[derive(serde::Deserialize)]
 pub struct NameQueryParam {
     pub name: String,
 }
 
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct Pagination {
+    /// Page size. Defaults to 1.
     pub limit: Option<i32>,
+    /// Items to skip. Defaults to 0.
     pub offset: Option<i32>,
 }
 
+/// Filtering and ordering options shared by the listing endpoints.
+#[derive(serde::Deserialize, Debug, Clone, Default, PartialEq, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListFilter {
+    /// Only items updated at or after this RFC 3339 timestamp.
+    pub updated_since: Option<DateTime<Utc>>,
+    /// Comma-separated sort keys, each prefixed with `-` for descending order.
+    #[serde(default, deserialize_with = "sort_keys")]
+    #[param(value_type = Option<String>)]
+    pub sort: Vec<SortKey>,
+    /// BCP 47 language tag, such as `de` or `sv-FI`, whose collation orders text keys.
+    pub locale: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SortKey {
+    pub field: String,
+    pub descending: bool,
+}
+
+/// A key a listing can be sorted by and the column behind it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SortField {
+    pub name: &'static str,
+    pub column: &'static str,
+    /// Whether the column is text and so follows `locale`.
+    pub text: bool,
+}
+
+impl SortField {
+    pub const fn new(name: &'static str, column: &'static str) -> Self {
+        Self {
+            name,
+            column,
+            text: false,
+        }
+    }
+
+    pub const fn text(name: &'static str, column: &'static str) -> Self {
+        Self {
+            name,
+            column,
+            text: true,
+        }
+    }
+}
+
+/// Splits a comma-separated query option into its trimmed, non-empty items.
+pub fn split_list(value: &str) -> impl Iterator<Item = &str> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+}
+
+fn sort_keys<'de, D>(deserializer: D) -> Result<Vec<SortKey>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = <String as serde::Deserialize>::deserialize(deserializer)?;
+    Ok(parse_sort_keys(&value))
+}
+
+/// Parses comma-separated sort keys, each prefixed with `-` for descending order.
+pub fn parse_sort_keys(value: &str) -> Vec<SortKey> {
+    split_list(value)
+        .map(|key| match key.strip_prefix('-') {
+            Some(field) => SortKey {
+                field: field.to_string(),
+                descending: true,
+            },
+            None => SortKey {
+                field: key.to_string(),
+                descending: false,
+            },
+        })
+        .collect()
+}
+
+impl ListFilter {
+    /// Builds the ORDER BY clause for the given table alias from the whitelisted `fields`,
+    /// always ending with an id tiebreaker. Missing values sort last either way.
+    pub fn order_by(
+        &self,
+        alias: &str,
+        fields: &[SortField],
+    ) -> Result<String, handle_errors::Error> {
+        let collation = match &self.locale {
+            None => None,
+            Some(locale) if is_language_tag(locale) => Some(format!(" COLLATE \"{locale}-x-icu\"")),
+            Some(locale) => {
+                return Err(handle_errors::Error::ValidationError(format!(
+                    "\"{locale}\" is not a valid locale"
+                )))
+            }
+        };
+
+        let mut terms = Vec::with_capacity(self.sort.len() + 1);
+        for key in &self.sort {
+            let Some(field) = fields.iter().find(|field| field.name == key.field) else {
+                let names: Vec<&str> = fields.iter().map(|field| field.name).collect();
+                return Err(handle_errors::Error::ValidationError(format!(
+                    "cannot sort by \"{}\", expected one of {}",
+                    key.field,
+                    names.join(", ")
+                )));
+            };
+            let collate = match &collation {
+                Some(collation) if field.text => collation.as_str(),
+                _ => "",
+            };
+            let direction = if key.descending { "DESC" } else { "ASC" };
+            terms.push(format!(
+                "{alias}.{}{collate} {direction} NULLS LAST",
+                field.column
+            ));
+        }
+        terms.push(format!("{alias}.id"));
+        Ok(terms.join(", "))
+    }
+}
+
+/// Accepts the shape of a BCP 47 tag only, so the locale can be quoted into a collation name.
+fn is_language_tag(locale: &str) -> bool {
+    !locale.is_empty()
+        && locale.len() <= 35
+        && locale.split('-').all(|part| {
+            !part.is_empty() && part.len() <= 8 && part.chars().all(|c| c.is_ascii_alphanumeric())
+        })
+}
+
+/// The caller on whose behalf a request is made, taken from the `X-User` header.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Actor(pub Option<String>);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Actor {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let actor = parts
+            .headers
+            .get("x-user")
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(String::from);
+        Ok(Actor(actor))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct AppState {
     pub pool: sqlx::PgPool,
+    pub blob_store: LocalBlobStore,
+    pub change_feed: ChangeFeed,
+    pub rate_limiter: RateLimiter,
+    pub limits: RequestLimits,
+    pub metrics: PrometheusHandle,
+    pub webhook_sender: HttpWebhookSender,
 }
 
 pub enum ApiResponse {
     JsonDataAddressBook(AddressBook),
-    JsonDataAddressBookCollection(Vec<AddressBook>),
+    /// A book read narrowed to the given `?fields=`, if any.
+    JsonDataAddressBookView(AddressBook, Option<Vec<String>>),
+    JsonDataAddressBookCollection(Vec<AddressBook>, Option<Vec<String>>),
+    JsonDataAddressBookStats(AddressBookStats),
+    JsonDataContactChanges(ContactChanges),
+    EventStream(mpsc::Receiver<Event>),
+    JsonDataContact(Box<Contact>),
+    JsonDataContactCollection(Vec<Contact>),
+    JsonDataCustomField(CustomField),
+    JsonDataCustomFieldCollection(Vec<CustomField>),
+    JsonDataGroup(ContactGroup),
+    JsonDataGroupCollection(Vec<ContactGroup>),
+    JsonDataPhoto(Photo),
+    JsonDataWebhook(Webhook),
+    JsonDataWebhookCollection(Vec<Webhook>),
+    JsonDataWebhookDelivery(WebhookDelivery),
+    JsonDataWebhookDeliveryCollection(Vec<WebhookDelivery>),
+    JsonDataTrash(Trash),
+    JsonDataAuditEventCollection(Vec<AuditEvent>),
+    JsonDataBulkResult(BulkResult),
+    /// A GraphQL result, sent with `200 OK` even when it carries errors.
+    GraphQl(async_graphql::Response),
+    Csv(String),
+    /// A Prometheus scrape page in the text exposition format.
+    Metrics(String),
+    Image {
+        content_type: String,
+        etag: String,
+        data: Vec<u8>,
+    },
+    /// A WebDAV `207 Multi-Status` body.
+    Multistatus(String),
+    VCard {
+        etag: String,
+        data: String,
+    },
+    /// A card written over CardDAV: `201 Created` when new, `204 No Content` otherwise.
+    VCardStored {
+        created: bool,
+        etag: String,
+    },
+    DavOptions,
+    NotModified(String),
     NoContent,
 }
 
 impl IntoResponse for ApiResponse {
     fn into_response(self) -> Response {
         match self {
-            ApiResponse::JsonDataAddressBook(data) => (StatusCode::OK, Json(data)).into_response(),
-            ApiResponse::JsonDataAddressBookCollection(data) => {
+            ApiResponse::JsonDataAddressBook(data) => {
+                (StatusCode::OK, [(header::ETAG, data.etag())], Json(data)).into_response()
+            }
+            ApiResponse::JsonDataAddressBookView(data, None) => {
+                (StatusCode::OK, [(header::ETAG, data.etag())], Json(data)).into_response()
+            }
+            ApiResponse::JsonDataAddressBookView(data, Some(fields)) => {
+                let etag = data.etag();
+                (
+                    StatusCode::OK,
+                    [(header::ETAG, etag)],
+                    Json(sparse(&data, &fields)),
+                )
+                    .into_response()
+            }
+            ApiResponse::JsonDataAddressBookCollection(data, None) => {
+                (StatusCode::OK, Json(data)).into_response()
+            }
+            ApiResponse::JsonDataAddressBookCollection(data, Some(fields)) => {
+                let data: Vec<Value> = data.iter().map(|book| sparse(book, &fields)).collect();
+                (StatusCode::OK, Json(data)).into_response()
+            }
+            ApiResponse::JsonDataAddressBookStats(data) => {
+                (StatusCode::OK, Json(data)).into_response()
+            }
+            ApiResponse::JsonDataContactChanges(data) => {
                 (StatusCode::OK, Json(data)).into_response()
             }
+            ApiResponse::EventStream(receiver) => {
+                let events = ReceiverStream::new(receiver).map(Ok::<_, Infallible>);
+                Sse::new(events)
+                    .keep_alive(KeepAlive::default())
+                    .into_response()
+            }
+            ApiResponse::JsonDataContact(data) => {
+                (StatusCode::OK, [(header::ETAG, data.etag())], Json(data)).into_response()
+            }
+            ApiResponse::JsonDataContactCollection(data) => {
+                (StatusCode::OK, Json(data)).into_response()
+            }
+            ApiResponse::JsonDataCustomField(data) => {
+                (StatusCode::OK, [(header::ETAG, data.etag())], Json(data)).into_response()
+            }
+            ApiResponse::JsonDataCustomFieldCollection(data) => {
+                (StatusCode::OK, Json(data)).into_response()
+            }
+            ApiResponse::JsonDataGroup(data) => {
+                (StatusCode::OK, [(header::ETAG, data.etag())], Json(data)).into_response()
+            }
+            ApiResponse::JsonDataGroupCollection(data) => {
+                (StatusCode::OK, Json(data)).into_response()
+            }
+            ApiResponse::JsonDataWebhook(data) => {
+                (StatusCode::OK, [(header::ETAG, data.etag())], Json(data)).into_response()
+            }
+            ApiResponse::JsonDataWebhookCollection(data) => {
+                (StatusCode::OK, Json(data)).into_response()
+            }
+            ApiResponse::JsonDataWebhookDelivery(data) => {
+                (StatusCode::OK, Json(data)).into_response()
+            }
+            ApiResponse::JsonDataWebhookDeliveryCollection(data) => {
+                (StatusCode::OK, Json(data)).into_response()
+            }
+            ApiResponse::JsonDataPhoto(data) => {
+                (StatusCode::OK, [(header::ETAG, data.etag())], Json(data)).into_response()
+            }
+            ApiResponse::JsonDataTrash(data) => (StatusCode::OK, Json(data)).into_response(),
+            ApiResponse::JsonDataAuditEventCollection(data) => {
+                (StatusCode::OK, Json(data)).into_response()
+            }
+            ApiResponse::JsonDataBulkResult(data) => {
+                let status = if data.results.iter().all(|result| result.is_success()) {
+                    StatusCode::OK
+                } else {
+                    StatusCode::MULTI_STATUS
+                };
+                (status, Json(data)).into_response()
+            }
+            ApiResponse::GraphQl(data) => (StatusCode::OK, Json(data)).into_response(),
+            ApiResponse::Csv(data) => (
+                StatusCode::OK,
+                [(header::CONTENT_TYPE, "text/csv; charset=utf-8")],
+                data,
+            )
+                .into_response(),
+            ApiResponse::Metrics(data) => (
+                StatusCode::OK,
+                [(
+                    header::CONTENT_TYPE,
+                    "text/plain; version=0.0.4; charset=utf-8",
+                )],
+                data,
+            )
+                .into_response(),
+            ApiResponse::Image {
+                content_type,
+                etag,
+                data,
+            } => (
+                StatusCode::OK,
+                [(header::CONTENT_TYPE, content_type), (header::ETAG, etag)],
+                data,
+            )
+                .into_response(),
+            ApiResponse::Multistatus(data) => (
+                StatusCode::MULTI_STATUS,
+                [(header::CONTENT_TYPE, "application/xml; charset=utf-8")],
+                data,
+            )
+                .into_response(),
+            ApiResponse::VCard { etag, data } => (
+                StatusCode::OK,
+                [
+                    (header::CONTENT_TYPE, VCARD_CONTENT_TYPE.to_string()),
+                    (header::ETAG, etag),
+                ],
+                data,
+            )
+                .into_response(),
+            ApiResponse::VCardStored { created, etag } => {
+                let status = if created {
+                    StatusCode::CREATED
+                } else {
+                    StatusCode::NO_CONTENT
+                };
+                (status, [(header::ETAG, etag)]).into_response()
+            }
+            ApiResponse::DavOptions => (
+                StatusCode::OK,
+                [
+                    (header::HeaderName::from_static("dav"), DAV_COMPLIANCE),
+                    (header::ALLOW, DAV_METHODS),
+                ],
+            )
+                .into_response(),
+            ApiResponse::NotModified(etag) => {
+                (StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response()
+            }
             ApiResponse::NoContent => (StatusCode::NO_CONTENT).into_response(),
         }
     }
 }
 
+/// Serializes `data`, keeping only the listed attributes plus any embedded `contacts` and
+/// `contact_count`.
+fn sparse<T: Serialize>(data: &T, fields: &[String]) -> Value {
+    match serde_json::to_value(data) {
+        Ok(Value::Object(object)) => Value::Object(
+            object
+                .into_iter()
+                .filter(|(key, _)| {
+                    fields.contains(key) || key == "contacts" || key == "contact_count"
+                })
+                .collect(),
+        ),
+        Ok(value) => value,
+        Err(_) => Value::Null,
+    }
+}
+
+/// The JSON body of every error response.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ErrorBody {
+    pub error: String,
+}
+
 pub enum ApiError {
     DataBaseError,
     JsonDeserilize,
     AddressBookNotFound,
+    ContactNotFound,
+    GroupNotFound,
+    FieldNotFound,
+    PhotoNotFound,
+    WebhookNotFound,
+    WebhookDeliveryNotFound,
+    PreconditionFailed,
+    UnsupportedMediaType,
+    PayloadTooLarge(String),
+    ValidationError(String),
+    Conflict(String),
+    StorageError,
+    BadRequest(String),
+    MethodNotAllowed,
+    InvalidSyncToken,
+    TooManyRequests,
+    ServiceUnavailable,
+    Timeout,
 }
 
-impl IntoResponse for ApiError {
-    fn into_response(self) -> axum::response::Response {
-        let (status, error_msg) = match self {
+impl ApiError {
+    /// The HTTP status and client-facing message of the error.
+    pub fn status_and_message(&self) -> (StatusCode, &str) {
+        match self {
             ApiError::DataBaseError => (StatusCode::INTERNAL_SERVER_ERROR, "something went wrong"),
             ApiError::JsonDeserilize => (StatusCode::BAD_REQUEST, "Json deserialization error"),
             ApiError::AddressBookNotFound => (StatusCode::NOT_FOUND, "addressbook not found"),
-        };
+            ApiError::ContactNotFound => (StatusCode::NOT_FOUND, "contact not found"),
+            ApiError::GroupNotFound => (StatusCode::NOT_FOUND, "group not found"),
+            ApiError::FieldNotFound => (StatusCode::NOT_FOUND, "custom field not found"),
+            ApiError::PhotoNotFound => (StatusCode::NOT_FOUND, "photo not found"),
+            ApiError::WebhookNotFound => (StatusCode::NOT_FOUND, "webhook not found"),
+            ApiError::WebhookDeliveryNotFound => {
+                (StatusCode::NOT_FOUND, "webhook delivery not found")
+            }
+            ApiError::PreconditionFailed => {
+                (StatusCode::PRECONDITION_FAILED, "precondition failed")
+            }
+            ApiError::UnsupportedMediaType => {
+                (StatusCode::UNSUPPORTED_MEDIA_TYPE, "unsupported media type")
+            }
+            ApiError::PayloadTooLarge(message) => (StatusCode::PAYLOAD_TOO_LARGE, message.as_str()),
+            ApiError::ValidationError(message) => {
+                (StatusCode::UNPROCESSABLE_ENTITY, message.as_str())
+            }
+            ApiError::Conflict(message) => (StatusCode::CONFLICT, message.as_str()),
+            ApiError::StorageError => (StatusCode::INTERNAL_SERVER_ERROR, "something went wrong"),
+            ApiError::BadRequest(message) => (StatusCode::BAD_REQUEST, message.as_str()),
+            ApiError::MethodNotAllowed => (StatusCode::METHOD_NOT_ALLOWED, "method not allowed"),
+            ApiError::InvalidSyncToken => (StatusCode::FORBIDDEN, "invalid sync token"),
+            ApiError::TooManyRequests => (StatusCode::TOO_MANY_REQUESTS, "too many requests"),
+            ApiError::ServiceUnavailable => {
+                (StatusCode::SERVICE_UNAVAILABLE, "service unavailable")
+            }
+            ApiError::Timeout => (StatusCode::GATEWAY_TIMEOUT, "request timed out"),
+        }
+    }
+}
 
-        let body = Json(json!({
-            "error": error_msg
+impl IntoResponse for ApiError {
+    fn into_response(self) -> axum::response::Response {
+        if let ApiError::InvalidSyncToken = self {
+            // WebDAV clients look for the failed precondition to know they must resync.
+            return (
+                StatusCode::FORBIDDEN,
+                [(header::CONTENT_TYPE, "application/xml; charset=utf-8")],
+                error_xml("valid-sync-token"),
+            )
+                .into_response();
+        }
+        let (status, error_msg) = self.status_and_message();
 
-        }));
+        let body = Json(ErrorBody {
+            error: error_msg.to_string(),
+        });
         (status, body).into_response()
     }
 }
@@ -0,0 +1,123 @@
+use crate::types::contact::ContactId;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+/// Largest accepted upload.
+pub const MAX_PHOTO_BYTES: usize = 5 * 1024 * 1024;
+
+/// Largest accepted width or height, checked before the image is decoded.
+pub const MAX_PHOTO_DIMENSION: u32 = 8192;
+
+/// Content types accepted for uploads.
+pub const PHOTO_CONTENT_TYPES: [&str; 3] = ["image/jpeg", "image/png", "image/webp"];
+
+/// Metadata of a contact's photo. The image itself lives in the blob store.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Photo {
+    pub contact_id: ContactId,
+    /// Content type of the original upload.
+    pub content_type: String,
+    pub width: i32,
+    pub height: i32,
+    pub byte_size: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub created_by: Option<String>,
+    pub updated_by: Option<String>,
+    pub version: i32,
+    /// Where the renditions are stored, see [`upload_prefix`].
+    #[serde(skip)]
+    pub blob_prefix: String,
+}
+
+impl Photo {
+    pub fn etag(&self) -> String {
+        format!("\"{}\"", self.version)
+    }
+
+    /// Thumbnails of JPEG photos are JPEGs; everything else is resized to PNG to keep
+    /// transparency.
+    pub fn content_type_of(&self, size: PhotoSize) -> &str {
+        match size {
+            PhotoSize::Original => &self.content_type,
+            _ if self.content_type == "image/jpeg" => "image/jpeg",
+            _ => "image/png",
+        }
+    }
+
+    pub fn blob_key(&self, size: PhotoSize) -> String {
+        blob_key(&self.blob_prefix, size)
+    }
+}
+
+/// A fresh blob prefix for an upload. Uploads never share one, so a failed or concurrent
+/// upload cannot overwrite the renditions of the photo in use.
+pub fn upload_prefix(contact_id: i32) -> String {
+    format!("contacts/{contact_id}/photos/{}", Uuid::new_v4())
+}
+
+pub fn blob_key(prefix: &str, size: PhotoSize) -> String {
+    format!("{prefix}/{}", size.as_str())
+}
+
+/// The stored renditions of a photo. Thumbnails fit in a square of the given size.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PhotoSize {
+    /// 64×64.
+    Small,
+    /// 256×256.
+    Medium,
+    #[default]
+    Original,
+}
+
+impl PhotoSize {
+    pub const ALL: [PhotoSize; 3] = [PhotoSize::Small, PhotoSize::Medium, PhotoSize::Original];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PhotoSize::Small => "small",
+            PhotoSize::Medium => "medium",
+            PhotoSize::Original => "original",
+        }
+    }
+
+    /// Bounding box of the thumbnail, or `None` for the original.
+    pub fn bound(&self) -> Option<u32> {
+        match self {
+            PhotoSize::Small => Some(64),
+            PhotoSize::Medium => Some(256),
+            PhotoSize::Original => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PhotoQuery {
+    /// Which rendition to send.
+    #[serde(default)]
+    pub size: PhotoSize,
+}
+
+/// A validated upload ready to be stored: the original bytes plus every thumbnail.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewPhoto {
+    pub blob_prefix: String,
+    pub content_type: String,
+    pub width: i32,
+    pub height: i32,
+    pub renditions: Vec<(PhotoSize, Vec<u8>)>,
+}
+
+impl NewPhoto {
+    pub fn byte_size(&self) -> i32 {
+        self.renditions
+            .iter()
+            .find(|(size, _)| *size == PhotoSize::Original)
+            .map_or(0, |(_, data)| data.len() as i32)
+    }
+}
@@ -0,0 +1,129 @@
+use axum::{async_trait, extract::FromRequestParts, http::request::Parts};
+use std::convert::Infallible;
+
+/// Parsed value of an `If-Match` / `If-None-Match` header.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum EntityTags {
+    #[default]
+    Absent,
+    Any,
+    Tags(Vec<String>),
+}
+
+impl EntityTags {
+    fn from_header(parts: &Parts, name: &str) -> Self {
+        let values: Vec<&str> = parts
+            .headers
+            .get_all(name)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .collect();
+        if values.is_empty() {
+            return EntityTags::Absent;
+        }
+
+        let tags: Vec<String> = values
+            .iter()
+            .flat_map(|value| value.split(','))
+            .map(|tag| tag.trim().to_string())
+            .filter(|tag| !tag.is_empty())
+            .collect();
+        if tags.iter().any(|tag| tag == "*") {
+            EntityTags::Any
+        } else {
+            EntityTags::Tags(tags)
+        }
+    }
+}
+
+/// `If-Match` precondition for writes. Uses strong comparison, so weak tags never match.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IfMatch(pub EntityTags);
+
+impl IfMatch {
+    pub fn matches(&self, etag: &str) -> bool {
+        match &self.0 {
+            EntityTags::Absent | EntityTags::Any => true,
+            EntityTags::Tags(tags) => tags.iter().any(|tag| tag == etag),
+        }
+    }
+}
+
+/// `If-None-Match` precondition for reads. Uses weak comparison.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IfNoneMatch(pub EntityTags);
+
+impl IfNoneMatch {
+    pub fn matches(&self, etag: &str) -> bool {
+        match &self.0 {
+            EntityTags::Absent => false,
+            EntityTags::Any => true,
+            EntityTags::Tags(tags) => tags
+                .iter()
+                .any(|tag| tag.trim_start_matches("W/") == etag.trim_start_matches("W/")),
+        }
+    }
+}
+
+/// Both preconditions, for handlers that serve reads and writes alike.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Preconditions {
+    pub if_match: IfMatch,
+    pub if_none_match: IfNoneMatch,
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for IfMatch {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(IfMatch(EntityTags::from_header(parts, "if-match")))
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for IfNoneMatch {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(IfNoneMatch(EntityTags::from_header(parts, "if-none-match")))
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Preconditions {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Preconditions {
+            if_match: IfMatch(EntityTags::from_header(parts, "if-match")),
+            if_none_match: IfNoneMatch(EntityTags::from_header(parts, "if-none-match")),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(tags: &[&str]) -> EntityTags {
+        EntityTags::Tags(tags.iter().map(|tag| tag.to_string()).collect())
+    }
+
+    #[test]
+    fn test_if_match_uses_strong_comparison() {
+        assert!(IfMatch(EntityTags::Absent).matches("\"3\""));
+        assert!(IfMatch(EntityTags::Any).matches("\"3\""));
+        assert!(IfMatch(tags(&["\"2\"", "\"3\""])).matches("\"3\""));
+        assert!(!IfMatch(tags(&["\"2\""])).matches("\"3\""));
+        assert!(!IfMatch(tags(&["W/\"3\""])).matches("\"3\""));
+    }
+
+    #[test]
+    fn test_if_none_match_uses_weak_comparison() {
+        assert!(!IfNoneMatch(EntityTags::Absent).matches("\"3\""));
+        assert!(IfNoneMatch(EntityTags::Any).matches("\"3\""));
+        assert!(IfNoneMatch(tags(&["W/\"3\""])).matches("\"3\""));
+        assert!(!IfNoneMatch(tags(&["\"2\""])).matches("\"3\""));
+    }
+}
@@ -0,0 +1,268 @@
+use axum::http::{HeaderMap, HeaderValue, Method};
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::repositories::rate_limit_store::IRateLimitStore;
+
+/// Seconds an empty bucket takes to fill up again. A bucket untouched for this long is full,
+/// which is the same as having none.
+pub const REFILL_WINDOW_SECS: i64 = 60;
+
+/// The quota a request counts against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteClass {
+    Read,
+    Write,
+    Import,
+}
+
+impl RouteClass {
+    /// Imports and bulk writes have their own quota; other requests are reads or writes by
+    /// method. CardDAV's `PROPFIND` and `REPORT` are reads.
+    pub fn of(method: &Method, path: &str) -> Self {
+        if path.ends_with("/contacts/import") || path.ends_with("/contacts/bulk") {
+            RouteClass::Import
+        } else if matches!(
+            method.as_str(),
+            "GET" | "HEAD" | "OPTIONS" | "PROPFIND" | "REPORT"
+        ) {
+            RouteClass::Read
+        } else {
+            RouteClass::Write
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RouteClass::Read => "read",
+            RouteClass::Write => "write",
+            RouteClass::Import => "import",
+        }
+    }
+}
+
+/// A token bucket holding up to `per_minute` tokens and refilled at that rate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub per_minute: u32,
+}
+
+impl RateLimit {
+    pub fn capacity(self) -> f64 {
+        f64::from(self.per_minute)
+    }
+}
+
+/// The limit of each route class. `None` leaves the class unlimited.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RateLimits {
+    pub read: Option<RateLimit>,
+    pub write: Option<RateLimit>,
+    pub import: Option<RateLimit>,
+}
+
+impl RateLimits {
+    pub fn get(&self, class: RouteClass) -> Option<RateLimit> {
+        match class {
+            RouteClass::Read => self.read,
+            RouteClass::Write => self.write,
+            RouteClass::Import => self.import,
+        }
+    }
+}
+
+/// A bucket as kept by a store.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bucket {
+    pub tokens: f64,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Bucket {
+    pub fn full(limit: RateLimit, now: DateTime<Utc>) -> Self {
+        Self {
+            tokens: limit.capacity(),
+            updated_at: now,
+        }
+    }
+
+    /// Refills the bucket for the time since it was last used, then takes a token if a whole
+    /// one is left. Returns whether it took one.
+    pub fn take(&mut self, limit: RateLimit, now: DateTime<Utc>) -> bool {
+        let elapsed = (now - self.updated_at).num_milliseconds().max(0) as f64 / 1000.0;
+        let refilled = elapsed * limit.capacity() / REFILL_WINDOW_SECS as f64;
+        self.tokens = (self.tokens + refilled).min(limit.capacity());
+        self.updated_at = self.updated_at.max(now);
+
+        let granted = self.tokens >= 1.0;
+        if granted {
+            self.tokens -= 1.0;
+        }
+        granted
+    }
+}
+
+/// Whether a request may proceed, and the quota left as reported in the `RateLimit-*`
+/// headers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Decision {
+    pub allowed: bool,
+    pub limit: u32,
+    pub remaining: u32,
+    /// Seconds until the bucket is full again.
+    pub reset_secs: u64,
+    /// Seconds until the next token, for `Retry-After`. Zero when the request was allowed.
+    pub retry_after_secs: u64,
+}
+
+impl Decision {
+    pub fn new(limit: RateLimit, allowed: bool, tokens: f64) -> Self {
+        // Seconds until `missing` more tokens have been refilled.
+        let refill_secs = |missing: f64| {
+            (missing.max(0.0) * REFILL_WINDOW_SECS as f64 / limit.capacity()).ceil() as u64
+        };
+        Self {
+            allowed,
+            limit: limit.per_minute,
+            remaining: tokens.max(0.0).floor() as u32,
+            reset_secs: refill_secs(limit.capacity() - tokens),
+            retry_after_secs: if allowed {
+                0
+            } else {
+                refill_secs(1.0 - tokens).max(1)
+            },
+        }
+    }
+
+    /// A request let through without counting, because the store could not be reached.
+    pub fn unchecked(limit: RateLimit) -> Self {
+        Self {
+            allowed: true,
+            limit: limit.per_minute,
+            remaining: limit.per_minute,
+            reset_secs: 0,
+            retry_after_secs: 0,
+        }
+    }
+
+    pub fn write_headers(&self, headers: &mut HeaderMap) {
+        let numbers = [
+            ("ratelimit-limit", u64::from(self.limit)),
+            ("ratelimit-remaining", u64::from(self.remaining)),
+            ("ratelimit-reset", self.reset_secs),
+        ];
+        for (name, value) in numbers {
+            headers.insert(name, HeaderValue::from(value));
+        }
+        if let Ok(policy) = HeaderValue::from_str(&format!("{};w={REFILL_WINDOW_SECS}", self.limit))
+        {
+            headers.insert("ratelimit-policy", policy);
+        }
+        if !self.allowed {
+            headers.insert("retry-after", HeaderValue::from(self.retry_after_secs));
+        }
+    }
+}
+
+/// Who a request's quota belongs to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Client {
+    /// A configured API key.
+    ApiKey(String),
+    /// The client's IP address, for requests without a known key.
+    Address(String),
+}
+
+impl Client {
+    /// Name of the client's bucket for `class`. API keys are stored hashed.
+    pub fn bucket_key(&self, class: RouteClass) -> String {
+        match self {
+            Client::ApiKey(key) => {
+                let digest = hex::encode(Sha256::digest(key.as_bytes()));
+                format!("{}:key:{}", class.name(), &digest[..32])
+            }
+            Client::Address(address) => format!("{}:ip:{address}", class.name()),
+        }
+    }
+}
+
+/// Shared state of the rate limiting layer.
+#[derive(Clone)]
+pub struct RateLimiter {
+    pub store: Arc<dyn IRateLimitStore + Send + Sync>,
+    pub limits: RateLimits,
+    /// Keys that get a quota of their own. Any other `X-Api-Key` is ignored, so made-up keys
+    /// cannot be used to escape the per-address quota.
+    pub api_keys: Arc<HashSet<String>>,
+    /// Proxies whose `X-Forwarded-For` entries are believed. With none, a client's address is
+    /// the peer's.
+    pub trusted_proxy_hops: usize,
+}
+
+impl std::fmt::Debug for RateLimiter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RateLimiter")
+            .field("limits", &self.limits)
+            .field("api_keys", &self.api_keys.len())
+            .field("trusted_proxy_hops", &self.trusted_proxy_hops)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_bucket_refills_up_to_capacity() {
+        let limit = RateLimit { per_minute: 2 };
+        let start = Utc::now();
+        let mut bucket = Bucket::full(limit, start);
+
+        assert!(bucket.take(limit, start));
+        assert!(bucket.take(limit, start));
+        assert!(!bucket.take(limit, start));
+        assert_eq!(
+            Decision::new(limit, false, bucket.tokens).retry_after_secs,
+            30
+        );
+
+        // Half a minute refills one token; an hour refills no more than the capacity.
+        assert!(bucket.take(limit, start + Duration::seconds(30)));
+        assert!(!bucket.take(limit, start + Duration::seconds(30)));
+        let later = start + Duration::hours(1);
+        assert!(bucket.take(limit, later));
+        assert!(bucket.take(limit, later));
+        assert!(!bucket.take(limit, later));
+    }
+
+    #[test]
+    fn test_decision_reports_quota() {
+        let limit = RateLimit { per_minute: 60 };
+        let decision = Decision::new(limit, true, 57.5);
+        assert_eq!(decision.remaining, 57);
+        assert_eq!(decision.reset_secs, 3);
+
+        let mut headers = HeaderMap::new();
+        decision.write_headers(&mut headers);
+        assert_eq!(headers["ratelimit-limit"], "60");
+        assert_eq!(headers["ratelimit-remaining"], "57");
+        assert_eq!(headers["ratelimit-policy"], "60;w=60");
+        assert!(!headers.contains_key("retry-after"));
+    }
+
+    #[test]
+    fn test_route_class() {
+        let import = RouteClass::of(&Method::POST, "/api/addressbooks/1/contacts/import");
+        let propfind = Method::from_bytes(b"PROPFIND").unwrap();
+        assert_eq!(import, RouteClass::Import);
+        assert_eq!(RouteClass::of(&propfind, "/dav/"), RouteClass::Read);
+        assert_eq!(
+            RouteClass::of(&Method::DELETE, "/api/addressbooks/1"),
+            RouteClass::Write
+        );
+    }
+}
@@ -0,0 +1,37 @@
+use crate::types::address_book::AddressBookId;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Aggregate figures over an address book's live contacts.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct AddressBookStats {
+    pub address_book_id: AddressBookId,
+    pub contact_count: i64,
+    pub with_email: i64,
+    pub without_email: i64,
+    pub with_phone_number: i64,
+    pub without_phone_number: i64,
+    /// Contacts sharing a display name, email or phone number with an older contact.
+    pub duplicates: i64,
+    /// Latest change to the book or any of its live contacts.
+    pub last_updated_at: DateTime<Utc>,
+    /// Contacts per country, taken from the last part of the address.
+    pub by_country: Vec<StatsBucket>,
+    /// Live members per group, including empty groups.
+    pub by_group: Vec<StatsBucket>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct StatsBucket {
+    /// `None` collects contacts without a value.
+    pub name: Option<String>,
+    pub count: i64,
+}
+
+/// Books and contacts across the whole service, not counting the trash.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EntityCounts {
+    pub address_books: i64,
+    pub contacts: i64,
+}
@@ -0,0 +1,18 @@
+use crate::types::address_book::AddressBook;
+use crate::types::contact::Contact;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Trash {
+    pub address_books: Vec<AddressBook>,
+    pub contacts: Vec<Contact>,
+}
+
+/// What a purge removed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Purged {
+    pub rows: u64,
+    /// Where the photos of the removed contacts are stored, for their blobs to be deleted.
+    pub photo_blob_prefixes: Vec<String>,
+}
@@ -0,0 +1,359 @@
+use crate::types::contact::{Contact, ContactDetails, NewContact};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chrono::NaiveDate;
+use image::ImageFormat;
+
+pub const VCARD_CONTENT_TYPE: &str = "text/vcard; charset=utf-8";
+
+/// Longest line, in octets, before it is folded onto a continuation line.
+const LINE_LENGTH: usize = 75;
+
+/// A vCard read from a client: the contact it describes and the UID it carries, if any.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VCard {
+    pub uid: Option<String>,
+    pub contact: NewContact,
+}
+
+/// The UID served for contacts that were not created with one.
+pub fn default_uid(contact_id: i32) -> String {
+    format!("contact-{contact_id}@addressbook-service")
+}
+
+/// Renders a contact as a vCard 3.0 (RFC 2426), embedding `photo` when given.
+pub fn render(contact: &Contact, uid: &str, photo: Option<&[u8]>) -> String {
+    let details = &contact.details;
+    let mut card = String::new();
+    push_line(&mut card, "BEGIN:VCARD");
+    push_line(&mut card, "VERSION:3.0");
+    push_line(&mut card, "PRODID:-//addressbook-service//EN");
+    push_line(&mut card, &format!("UID:{}", escape(uid)));
+    push_line(&mut card, &format!("FN:{}", escape(&contact.name)));
+    let name = [
+        &details.family_name,
+        &details.given_name,
+        &details.middle_name,
+        &details.name_prefix,
+        &details.name_suffix,
+    ]
+    .map(|part| escape(part.as_deref().unwrap_or_default()));
+    push_line(&mut card, &format!("N:{}", name.join(";")));
+
+    let text = [
+        ("NICKNAME", &details.nickname),
+        ("ORG", &details.organization),
+        ("TITLE", &details.job_title),
+        ("EMAIL;TYPE=INTERNET", &contact.email),
+        ("TEL", &contact.phone_number),
+    ];
+    for (property, value) in text {
+        if let Some(value) = value {
+            push_line(&mut card, &format!("{property}:{}", escape(value)));
+        }
+    }
+    if !contact.address.is_empty() {
+        push_line(
+            &mut card,
+            &format!("ADR:;;{};;;;", escape(&contact.address)),
+        );
+    }
+    for (property, date) in [
+        ("BDAY", details.birthday),
+        ("X-ANNIVERSARY", details.anniversary),
+    ] {
+        if let Some(date) = date {
+            push_line(
+                &mut card,
+                &format!("{property}:{}", date.format("%Y-%m-%d")),
+            );
+        }
+    }
+    if let Some(notes) = &details.notes {
+        push_line(&mut card, &format!("NOTE:{}", escape(notes)));
+    }
+    if !contact.groups.is_empty() {
+        let groups: Vec<String> = contact.groups.iter().map(|group| escape(group)).collect();
+        push_line(&mut card, &format!("CATEGORIES:{}", groups.join(",")));
+    }
+    if let Some(photo) = photo {
+        let kind = match image::guess_format(photo) {
+            Ok(ImageFormat::Jpeg) => "JPEG",
+            _ => "PNG",
+        };
+        push_line(
+            &mut card,
+            &format!("PHOTO;ENCODING=b;TYPE={kind}:{}", BASE64.encode(photo)),
+        );
+    }
+    push_line(
+        &mut card,
+        &format!("REV:{}", contact.updated_at.format("%Y-%m-%dT%H:%M:%SZ")),
+    );
+    push_line(&mut card, "END:VCARD");
+    card
+}
+
+/// Parses a single vCard (3.0 or 4.0). Properties without a contact field are dropped.
+pub fn parse(text: &str) -> Result<VCard, String> {
+    let properties = properties(text)?;
+    let mut uid = None;
+    let mut name = None;
+    let mut address = None;
+    let mut contact = NewContact::default();
+    let mut parts: Vec<String> = Vec::new();
+
+    for (property, value) in &properties {
+        let details = &mut contact.details;
+        match property.as_str() {
+            "UID" => uid = non_empty(value),
+            "FN" => name = non_empty(&unescape(value)),
+            "N" => parts = split_unescaped(value, ';'),
+            "NICKNAME" => details.nickname = non_empty(&unescape(value)),
+            "ORG" => {
+                let units: Vec<String> = split_unescaped(value, ';')
+                    .into_iter()
+                    .filter(|unit| !unit.is_empty())
+                    .collect();
+                details.organization = non_empty(&units.join(", "));
+            }
+            "TITLE" => details.job_title = non_empty(&unescape(value)),
+            "NOTE" => details.notes = non_empty(&unescape(value)),
+            "BDAY" => details.birthday = date(value),
+            "ANNIVERSARY" | "X-ANNIVERSARY" => details.anniversary = date(value),
+            "EMAIL" if contact.email.is_none() => contact.email = non_empty(&unescape(value)),
+            "TEL" if contact.phone_number.is_none() => {
+                contact.phone_number = non_empty(&unescape(value))
+            }
+            "ADR" if address.is_none() => {
+                let lines: Vec<String> = split_unescaped(value, ';')
+                    .into_iter()
+                    .filter(|line| !line.is_empty())
+                    .collect();
+                address = non_empty(&lines.join("\n"));
+            }
+            _ => {}
+        }
+    }
+
+    let part = |index: usize| parts.get(index).and_then(|part| non_empty(part));
+    contact.details = ContactDetails {
+        family_name: part(0),
+        given_name: part(1),
+        middle_name: part(2),
+        name_prefix: part(3),
+        name_suffix: part(4),
+        ..contact.details
+    };
+    let name = name.or_else(|| {
+        let joined: Vec<String> = [3, 1, 2, 0, 4].into_iter().filter_map(part).collect();
+        non_empty(&joined.join(" "))
+    });
+    contact.name = name.ok_or("vCard must have an FN or N property")?;
+    contact.address = address.unwrap_or_default();
+    Ok(VCard { uid, contact })
+}
+
+/// The unfolded content lines of a single vCard as (uppercase name, raw value) pairs, without
+/// parameters, property groups or the BEGIN/END lines.
+pub fn properties(text: &str) -> Result<Vec<(String, String)>, String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.split('\n') {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ if line.trim().is_empty() => {}
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    let mut lines = lines.into_iter();
+    if !lines
+        .next()
+        .is_some_and(|line| line.eq_ignore_ascii_case("BEGIN:VCARD"))
+    {
+        return Err(String::from("body must start with BEGIN:VCARD"));
+    }
+    let mut properties = Vec::new();
+    for line in lines.by_ref() {
+        if line.eq_ignore_ascii_case("END:VCARD") {
+            if lines.next().is_some() {
+                return Err(String::from("body must hold exactly one vCard"));
+            }
+            return Ok(properties);
+        }
+        let Some((head, value)) = split_content_line(&line) else {
+            return Err(format!("invalid vCard line \"{line}\""));
+        };
+        let name = head.split(';').next().unwrap_or_default();
+        let name = name.rsplit('.').next().unwrap_or_default();
+        properties.push((name.to_ascii_uppercase(), value.to_string()));
+    }
+    Err(String::from("vCard is missing END:VCARD"))
+}
+
+/// Splits a content line at the first colon outside a quoted parameter value.
+fn split_content_line(line: &str) -> Option<(&str, &str)> {
+    let mut quoted = false;
+    for (index, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ':' if !quoted => return Some((&line[..index], &line[index + 1..])),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Appends a content line, folding it so no physical line exceeds `LINE_LENGTH` octets.
+fn push_line(card: &mut String, line: &str) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > LINE_LENGTH {
+            card.push_str("\r\n ");
+            width = 1;
+        }
+        card.push(c);
+        width += c.len_utf8();
+    }
+    card.push_str("\r\n");
+}
+
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            ',' => escaped.push_str("\\,"),
+            ';' => escaped.push_str("\\;"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+pub fn unescape(value: &str) -> String {
+    components(value, None).concat()
+}
+
+/// Splits a structured value at unescaped `separator`s and unescapes each component.
+fn split_unescaped(value: &str, separator: char) -> Vec<String> {
+    components(value, Some(separator))
+}
+
+fn components(value: &str, separator: Option<char>) -> Vec<String> {
+    let mut components = vec![String::new()];
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        let current = components.last_mut().expect("components is never empty");
+        match c {
+            '\\' => match chars.next() {
+                Some('n' | 'N') => current.push('\n'),
+                Some(escaped) => current.push(escaped),
+                None => current.push('\\'),
+            },
+            c if Some(c) == separator => components.push(String::new()),
+            c => current.push(c),
+        }
+    }
+    components
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+/// Reads `YYYY-MM-DD` or `YYYYMMDD`, ignoring any time part. Dates without a year are dropped.
+fn date(value: &str) -> Option<NaiveDate> {
+    let value = value.trim();
+    let day = value.split('T').next().unwrap_or_default();
+    NaiveDate::parse_from_str(day, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(day, "%Y%m%d"))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::address_book::AddressBookId;
+    use crate::types::contact::ContactId;
+    use chrono::Utc;
+    use serde_json::Map;
+
+    fn contact() -> Contact {
+        Contact {
+            id: ContactId(7),
+            name: String::from("Dr. Ann Smith"),
+            address: String::from("1 Main Street, Springfield; Oregon"),
+            phone_number: Some(String::from("+1 555 0100")),
+            email: Some(String::from("ann@example.com")),
+            details: ContactDetails {
+                name_prefix: Some(String::from("Dr.")),
+                given_name: Some(String::from("Ann")),
+                family_name: Some(String::from("Smith")),
+                organization: Some(String::from("Acme")),
+                birthday: NaiveDate::from_ymd_opt(1990, 4, 1),
+                notes: Some(format!(
+                    "Line one\nLine two, {}",
+                    "long ".repeat(20).trim_end()
+                )),
+                ..ContactDetails::default()
+            },
+            display_name: String::from("Dr. Ann Smith"),
+            sort_key: String::from("smith ann"),
+            address_book_id: AddressBookId(1),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            created_by: None,
+            updated_by: None,
+            deleted_at: None,
+            version: 1,
+            groups: vec![String::from("Family")],
+            has_photo: false,
+            custom_fields: Map::new(),
+        }
+    }
+
+    #[test]
+    fn test_render_round_trips_through_parse() {
+        let contact = contact();
+        let card = render(&contact, &default_uid(7), None);
+
+        assert!(card.lines().all(|line| line.len() <= LINE_LENGTH + 1));
+        assert!(card.contains("N:Smith;Ann;;Dr.;\r\n"));
+        assert!(card.contains("CATEGORIES:Family\r\n"));
+
+        let parsed = parse(&card).unwrap();
+        assert_eq!(parsed.uid.as_deref(), Some("contact-7@addressbook-service"));
+        assert_eq!(parsed.contact.name, contact.name);
+        assert_eq!(parsed.contact.address, contact.address);
+        assert_eq!(parsed.contact.phone_number, contact.phone_number);
+        assert_eq!(parsed.contact.email, contact.email);
+        assert_eq!(parsed.contact.details, contact.details);
+    }
+
+    #[test]
+    fn test_parse_reads_client_cards() {
+        let card = "BEGIN:VCARD\r\nVERSION:4.0\r\nUID:urn:uuid:1234\r\n\
+                    N:Doe;John;;;\r\nitem1.EMAIL;TYPE=\"work,pref\":john@\r\n example.com\r\n\
+                    EMAIL:second@example.com\r\nADR;TYPE=home:;;2 High St;Leeds;;LS1;UK\r\n\
+                    BDAY:--0415\r\nANNIVERSARY:20100601\r\nEND:VCARD\r\n";
+        let parsed = parse(card).unwrap();
+
+        assert_eq!(parsed.uid.as_deref(), Some("urn:uuid:1234"));
+        assert_eq!(parsed.contact.name, "John Doe");
+        assert_eq!(parsed.contact.email.as_deref(), Some("john@example.com"));
+        assert_eq!(parsed.contact.address, "2 High St\nLeeds\nLS1\nUK");
+        assert_eq!(parsed.contact.details.birthday, None);
+        assert_eq!(
+            parsed.contact.details.anniversary,
+            NaiveDate::from_ymd_opt(2010, 6, 1)
+        );
+
+        assert!(parse("BEGIN:VCARD\r\nVERSION:3.0\r\nEND:VCARD\r\n").is_err());
+        assert!(parse("BEGIN:VCALENDAR\r\nEND:VCALENDAR\r\n").is_err());
+    }
+}
@@ -0,0 +1,362 @@
+use crate::types::address_book::AddressBookId;
+use crate::types::audit::{AuditAction, EntityType, NewAuditEvent};
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use utoipa::ToSchema;
+
+/// Every event a subscription can ask for.
+pub const EVENT_TYPES: [&str; 9] = [
+    "address_book.created",
+    "address_book.updated",
+    "address_book.deleted",
+    "address_book.restored",
+    "contact.created",
+    "contact.updated",
+    "contact.deleted",
+    "contact.restored",
+    "contact.moved",
+];
+
+/// A delivery is given up after this many failed attempts.
+pub const MAX_ATTEMPTS: i32 = 10;
+
+const FIRST_RETRY_DELAY_SECS: i64 = 30;
+
+/// Longest response body kept in the delivery log, in characters. Enough to see why a
+/// receiver refused an event, too little to read documents back through the log.
+pub const MAX_LOGGED_RESPONSE_CHARS: usize = 128;
+
+/// Addresses deliveries may be sent to. Public addresses always are; loopback, private,
+/// link-local and other internal ones only when they fall in one of the `allowed` networks.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WebhookTargets {
+    pub allowed: Vec<IpNet>,
+}
+
+impl WebhookTargets {
+    pub fn permits(&self, ip: IpAddr) -> bool {
+        // An IPv6 address that carries an IPv4 one reaches that host, so it is judged as it.
+        let reached = match ip {
+            IpAddr::V6(v6) => embedded_ipv4(v6).map_or(ip, IpAddr::V4),
+            IpAddr::V4(_) => ip,
+        };
+        is_public(reached)
+            || self
+                .allowed
+                .iter()
+                .any(|network| network.contains(&reached) || network.contains(&ip))
+    }
+}
+
+/// The IPv4 address inside an IPv4-mapped (`::ffff:a.b.c.d`), IPv4-compatible (`::a.b.c.d`),
+/// NAT64 (`64:ff9b::a.b.c.d`) or 6to4 (`2002:aabb:ccdd::/48`) address.
+fn embedded_ipv4(v6: Ipv6Addr) -> Option<Ipv4Addr> {
+    let join = |high: u16, low: u16| Ipv4Addr::from((u32::from(high) << 16) | u32::from(low));
+    match v6.segments() {
+        [0x64, 0xff9b, 0, 0, 0, 0, high, low] => Some(join(high, low)),
+        [0x2002, high, low, ..] => Some(join(high, low)),
+        _ => v6.to_ipv4(),
+    }
+}
+
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [first, second, ..] = v4.octets();
+            !(v4.is_unspecified()
+                || v4.is_loopback()
+                || v4.is_private()
+                || v4.is_link_local()
+                || v4.is_broadcast()
+                || v4.is_multicast()
+                || v4.is_documentation()
+                || first == 0
+                // Carrier-grade NAT, 100.64.0.0/10.
+                || (first == 100 && second & 0xc0 == 64))
+        }
+        IpAddr::V6(v6) => {
+            let [first, second, third, ..] = v6.segments();
+            !(v6.is_unspecified()
+                || v6.is_loopback()
+                || v6.is_multicast()
+                // Unique local, fc00::/7, link-local, fe80::/10, and site-local, fec0::/10.
+                || first & 0xfe00 == 0xfc00
+                || first & 0xffc0 == 0xfe80
+                || first & 0xffc0 == 0xfec0
+                // Local-use NAT64, 64:ff9b:1::/48, whose IPv4 address may be anywhere in it.
+                || (first, second, third) == (0x64, 0xff9b, 1))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Webhook {
+    pub id: WebhookId,
+    pub address_book_id: AddressBookId,
+    pub url: String,
+    pub event_types: Vec<String>,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub created_by: Option<String>,
+    pub updated_by: Option<String>,
+    pub version: i32,
+}
+
+impl Webhook {
+    pub fn etag(&self) -> String {
+        format!("\"{}\"", self.version)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, Hash, ToSchema)]
+pub struct WebhookId(pub i32);
+
+fn default_active() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct NewWebhook {
+    pub url: String,
+    /// Signs every delivery. It is write-only and never sent back.
+    pub secret: String,
+    pub event_types: Vec<String>,
+    #[serde(default = "default_active")]
+    pub active: bool,
+}
+
+impl NewWebhook {
+    /// Requires an http(s) URL, a secret and at least one known event type. Duplicate event
+    /// types are dropped.
+    pub fn validate(mut self) -> Result<Self, handle_errors::Error> {
+        self.url = self.url.trim().to_string();
+        let scheme_ok = ["http://", "https://"].iter().any(|scheme| {
+            self.url.len() > scheme.len()
+                && self
+                    .url
+                    .get(..scheme.len())
+                    .is_some_and(|prefix| prefix.eq_ignore_ascii_case(scheme))
+        });
+        if !scheme_ok {
+            return Err(handle_errors::Error::ValidationError(String::from(
+                "url must be an http or https URL",
+            )));
+        }
+        if self.secret.is_empty() {
+            return Err(handle_errors::Error::ValidationError(String::from(
+                "secret cannot be empty",
+            )));
+        }
+        if self.event_types.is_empty() {
+            return Err(handle_errors::Error::ValidationError(String::from(
+                "event_types cannot be empty",
+            )));
+        }
+        if let Some(unknown) = self
+            .event_types
+            .iter()
+            .find(|event_type| !EVENT_TYPES.contains(&event_type.as_str()))
+        {
+            return Err(handle_errors::Error::ValidationError(format!(
+                "\"{unknown}\" is not an event type; expected one of {}",
+                EVENT_TYPES.join(", ")
+            )));
+        }
+        let mut seen = Vec::with_capacity(self.event_types.len());
+        self.event_types.retain(|event_type| {
+            let first = !seen.contains(event_type);
+            seen.push(event_type.clone());
+            first
+        });
+        Ok(self)
+    }
+}
+
+/// The webhook event an audit event is delivered as, if any. Groups and custom fields have no
+/// events of their own.
+pub fn event_type(event: &NewAuditEvent) -> Option<String> {
+    let entity = match event.entity_type {
+        EntityType::AddressBook => "address_book",
+        EntityType::Contact => "contact",
+        EntityType::Group | EntityType::CustomField => return None,
+    };
+    let action = match event.action {
+        AuditAction::Create => "created",
+        AuditAction::Update => "updated",
+        AuditAction::Delete => "deleted",
+        AuditAction::Restore => "restored",
+        AuditAction::Move => "moved",
+    };
+    Some(format!("{entity}.{action}"))
+}
+
+/// An event queued for one subscription, with its delivery history.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct WebhookDelivery {
+    pub id: i64,
+    pub webhook_id: WebhookId,
+    pub event_type: String,
+    #[schema(value_type = Object)]
+    pub payload: Value,
+    /// `pending`, `delivered` or `failed`.
+    pub status: String,
+    pub attempts: i32,
+    /// When the next attempt is due, while `pending`.
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub attempt_log: Vec<DeliveryAttempt>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct DeliveryAttempt {
+    pub attempt: i32,
+    pub attempted_at: DateTime<Utc>,
+    /// The receiver's HTTP status, or none when no response arrived.
+    pub status_code: Option<i32>,
+    pub error: Option<String>,
+    pub response_body: Option<String>,
+    pub duration_ms: i32,
+}
+
+/// A due delivery claimed by the dispatcher, with what it needs to send it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingDelivery {
+    pub id: i64,
+    pub url: String,
+    pub secret: String,
+    pub event_type: String,
+    pub payload: Value,
+    pub created_at: DateTime<Utc>,
+    pub attempts: i32,
+}
+
+impl PendingDelivery {
+    /// The JSON document posted to the receiver.
+    pub fn body(&self) -> String {
+        json!({
+            "id": self.id,
+            "type": self.event_type,
+            "created_at": self.created_at,
+            "data": self.payload,
+        })
+        .to_string()
+    }
+}
+
+/// What became of one attempt, as recorded against the delivery.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeliveryOutcome {
+    Delivered,
+    Retry(DateTime<Utc>),
+    Failed,
+}
+
+/// Hex HMAC-SHA256 of `"<timestamp>.<body>"` under `secret`, sent as
+/// `Webhook-Signature: sha256=<hex>`.
+pub fn signature(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(format!("{timestamp}.{body}").as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// How long to wait after the `attempts`-th failed attempt: 30 seconds, doubling each time.
+pub fn retry_delay(attempts: i32) -> Duration {
+    let doublings = attempts.clamp(1, MAX_ATTEMPTS) - 1;
+    Duration::seconds(FIRST_RETRY_DELAY_SECS << doublings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_webhook(url: &str, event_types: &[&str]) -> NewWebhook {
+        NewWebhook {
+            url: url.to_string(),
+            secret: String::from("s3cret"),
+            event_types: event_types.iter().map(|t| t.to_string()).collect(),
+            active: true,
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        let webhook = new_webhook(" https://crm.example.com/hook ", &["contact.created"; 2])
+            .validate()
+            .unwrap();
+        assert_eq!(webhook.url, "https://crm.example.com/hook");
+        assert_eq!(webhook.event_types, vec!["contact.created"]);
+
+        assert!(new_webhook("ftp://example.com", &["contact.created"])
+            .validate()
+            .is_err());
+        assert!(new_webhook("https://example.com", &[]).validate().is_err());
+        assert!(new_webhook("https://example.com", &["group.created"])
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn test_targets_refuse_internal_addresses_unless_allowed() {
+        let ip = |ip: &str| ip.parse::<IpAddr>().unwrap();
+        let targets = WebhookTargets::default();
+        assert!(targets.permits(ip("93.184.216.34")));
+        assert!(targets.permits(ip("2606:2800:220:1::1")));
+        // The IPv4 host they carry is public.
+        assert!(targets.permits(ip("64:ff9b::5db8:d822")));
+        assert!(targets.permits(ip("2002:5db8:d822::1")));
+        for internal in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "::127.0.0.1",
+            "::10.1.2.3",
+            "64:ff9b::7f00:1",
+            "64:ff9b::a9fe:a9fe",
+            "64:ff9b:1::1",
+            "2002:7f00:1::1",
+            "2002:c0a8:101::",
+            "fec0::1",
+        ] {
+            assert!(!targets.permits(ip(internal)), "{internal} is permitted");
+        }
+
+        let targets = WebhookTargets {
+            allowed: vec!["10.0.0.0/8".parse().unwrap()],
+        };
+        assert!(targets.permits(ip("10.1.2.3")));
+        assert!(targets.permits(ip("64:ff9b::a01:203")));
+        assert!(!targets.permits(ip("127.0.0.1")));
+    }
+
+    #[test]
+    fn test_signature_matches_known_value() {
+        // printf '1700000000.{}' | openssl dgst -sha256 -hmac s3cret
+        assert_eq!(
+            signature("s3cret", 1_700_000_000, "{}"),
+            "97926816e98fbb41ccb1673225ff29a2f35369099990e1b1561651e7bd097ebf"
+        );
+    }
+
+    #[test]
+    fn test_retry_delay_doubles() {
+        assert_eq!(retry_delay(1), Duration::seconds(30));
+        assert_eq!(retry_delay(2), Duration::seconds(60));
+        assert_eq!(retry_delay(4), Duration::seconds(240));
+    }
+}